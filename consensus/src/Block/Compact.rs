@@ -0,0 +1,222 @@
+//Compact relay format of transaction blocks.
+//
+//Most extrinsics of a transaction block are already in the receiver's pool, so instead of the
+//content the sender relays the header and a 6 byte short id per extrinsic. Short ids are keyed
+//with the header hash and a per-relay nonce so nobody can grind extrinsics that collide for every
+//block. The header commits to the content through its sortition tree, so the block also carries
+//the content hash and its sortition proof, which a receiver checks before doing any work. It then
+//matches the ids against its pool, asks for the extrinsics it is missing and, if the rebuilt content
+//still does not match the content hash (a short id collision), falls back to the full block.
+use sp_core::H256;
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use std::{collections::btree_map::BTreeMap, vec::Vec};
+use super::Header::BlockHeader;
+use super::TransactionBlock::Content;
+use super::{commitsTo, BlockKind};
+use Argonaut_Runtime::UncheckedExtrinsic;
+
+//blake3 derive-key context of the short id key
+pub const SHORT_ID_CONTEXT: &str = "Argonaut 2023-10 compact relay short id v1";
+
+pub type ShortId = [u8; 6];
+
+pub fn shortIdKey(headerHash: &H256, nonce: u64) -> [u8; 32] {
+	blake3::derive_key(SHORT_ID_CONTEXT, &(headerHash, nonce).encode())
+}
+
+pub fn shortId(key: &[u8; 32], extrinsic: &UncheckedExtrinsic) -> ShortId {
+	let hash = extrinsic.using_encoded(|bytes| blake3::keyed_hash(key, bytes));
+	let mut id = [0u8; 6];
+	id.copy_from_slice(&hash.as_bytes()[..6]);
+	id
+}
+
+//a transaction block as relayed: its header, what the header commits to and the short ids of its
+//extrinsics, in block order
+#[derive(Clone, PartialEq, Eq, Debug, Encode, Decode, TypeInfo)]
+pub struct CompactTransactionBlock {
+	pub header: BlockHeader,
+	pub contentHash: H256,
+	pub sortitionProof: Vec<H256>,
+	pub nonce: u64,
+	pub shortIds: Vec<ShortId>,
+}
+
+impl CompactTransactionBlock {
+	pub fn new(header: BlockHeader, content: &Content, sortitionProof: Vec<H256>, nonce: u64) -> Self {
+		let key = shortIdKey(&header.hash(), nonce);
+		let shortIds = content.extrinsics.iter().map(|extrinsic| shortId(&key, extrinsic)).collect();
+		CompactTransactionBlock { header, contentHash: content.hash(), sortitionProof, nonce, shortIds }
+	}
+
+	pub fn key(&self) -> [u8; 32] {
+		shortIdKey(&self.header.hash(), self.nonce)
+	}
+
+	//whether the header commits to the content hash as its transaction content
+	pub fn commitsContent(&self, voterChains: u16) -> bool {
+		commitsTo(&self.header.contentRoot, BlockKind::Transaction, voterChains, &self.contentHash, &self.sortitionProof)
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CompactError {
+	//some extrinsics are still missing
+	Incomplete,
+	//the extrinsics sent for the missing slots are not the ones that were asked for
+	WrongTransactions,
+	//the rebuilt content does not match the content hash, a short id matched the wrong extrinsic
+	//and the full block has to be fetched
+	RootMismatch,
+}
+
+//a compact block being filled in from the pool and from requested extrinsics
+pub struct PartialTransactionBlock {
+	pub header: BlockHeader,
+	pub contentHash: H256,
+	pub sortitionProof: Vec<H256>,
+	key: [u8; 32],
+	shortIds: Vec<ShortId>,
+	slots: Vec<Option<UncheckedExtrinsic>>,
+}
+
+impl PartialTransactionBlock {
+	//fill every slot whose short id matches exactly one of `candidates`, usually the pool's
+	//ready extrinsics. Ids matched by several candidates are left missing rather than guessed.
+	pub fn new(compact: CompactTransactionBlock, candidates: impl IntoIterator<Item = UncheckedExtrinsic>) -> Self {
+		let key = compact.key();
+		let mut byId: BTreeMap<ShortId, Option<UncheckedExtrinsic>> = BTreeMap::new();
+		for candidate in candidates {
+			let id = shortId(&key, &candidate);
+			byId.entry(id).and_modify(|slot| *slot = None).or_insert(Some(candidate));
+		}
+		let slots = compact.shortIds.iter().map(|id| byId.get(id).cloned().flatten()).collect();
+		PartialTransactionBlock {
+			header: compact.header,
+			contentHash: compact.contentHash,
+			sortitionProof: compact.sortitionProof,
+			key,
+			shortIds: compact.shortIds,
+			slots,
+		}
+	}
+
+	//indexes of the extrinsics to request from the sender
+	pub fn missing(&self) -> Vec<u32> {
+		self.slots
+			.iter()
+			.enumerate()
+			.filter(|(_, slot)| slot.is_none())
+			.map(|(index, _)| index as u32)
+			.collect()
+	}
+
+	//fill the missing slots with `extrinsics`, given in the order of `missing`
+	pub fn fill(&mut self, extrinsics: Vec<UncheckedExtrinsic>) -> Result<(), CompactError> {
+		let missing = self.missing();
+		if extrinsics.len() != missing.len() {
+			return Err(CompactError::WrongTransactions);
+		}
+		if missing.iter().zip(&extrinsics).any(|(index, extrinsic)| shortId(&self.key, extrinsic) != self.shortIds[*index as usize]) {
+			return Err(CompactError::WrongTransactions);
+		}
+		for (index, extrinsic) in missing.into_iter().zip(extrinsics) {
+			self.slots[index as usize] = Some(extrinsic);
+		}
+		Ok(())
+	}
+
+	//the content, once complete and matching the content hash
+	pub fn finish(self) -> Result<Content, CompactError> {
+		let extrinsics: Option<Vec<UncheckedExtrinsic>> = self.slots.into_iter().collect();
+		let content = Content::new(extrinsics.ok_or(CompactError::Incomplete)?);
+		if content.hash() != self.contentHash {
+			return Err(CompactError::RootMismatch);
+		}
+		Ok(content)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::ExtraContent::ExtraContent;
+
+	fn extrinsic(i: u32) -> UncheckedExtrinsic {
+		UncheckedExtrinsic::new_unsigned(Argonaut_Runtime::SystemCall::remark { remark: i.encode() }.into())
+	}
+
+	fn block(count: u32) -> (BlockHeader, Content) {
+		let content = Content::new((0..count).map(extrinsic).collect());
+		let header = BlockHeader::new(H256::zero(), 1, 1, content.hash(), ExtraContent::default(), H256::zero());
+		(header, content)
+	}
+
+	#[test]
+	fn rebuildFromPool() {
+		let (header, content) = block(10);
+		let compact = CompactTransactionBlock::new(header, &content, Vec::new(), 7);
+		//the pool holds the block's extrinsics, in another order, and unrelated ones
+		let pool = (0..20).rev().map(extrinsic);
+		let partial = PartialTransactionBlock::new(compact, pool);
+		assert!(partial.missing().is_empty());
+		assert_eq!(partial.finish(), Ok(content));
+	}
+
+	#[test]
+	fn requestMissing() {
+		let (header, content) = block(10);
+		let compact = CompactTransactionBlock::new(header, &content, Vec::new(), 7);
+		let pool = (0..10).filter(|i| i % 3 != 0).map(extrinsic);
+		let mut partial = PartialTransactionBlock::new(compact, pool);
+		assert_eq!(partial.missing(), vec![0, 3, 6, 9]);
+		assert_eq!(partial.fill(vec![extrinsic(3)]), Err(CompactError::WrongTransactions));
+		assert_eq!(
+			partial.fill(vec![extrinsic(0), extrinsic(3), extrinsic(9), extrinsic(6)]),
+			Err(CompactError::WrongTransactions)
+		);
+		assert_eq!(partial.fill(vec![extrinsic(0), extrinsic(3), extrinsic(6), extrinsic(9)]), Ok(()));
+		assert_eq!(partial.finish(), Ok(content));
+	}
+
+	#[test]
+	fn incompleteBlock() {
+		let (header, content) = block(4);
+		let partial = PartialTransactionBlock::new(CompactTransactionBlock::new(header, &content, Vec::new(), 0), Vec::new());
+		assert_eq!(partial.finish(), Err(CompactError::Incomplete));
+	}
+
+	#[test]
+	fn wrongContentFallsBack() {
+		let (header, content) = block(4);
+		let mut compact = CompactTransactionBlock::new(header, &content, Vec::new(), 0);
+		compact.contentHash = H256::repeat_byte(1);
+		let partial = PartialTransactionBlock::new(compact, (0..4).map(extrinsic));
+		assert_eq!(partial.finish(), Err(CompactError::RootMismatch));
+	}
+
+	#[test]
+	fn contentHashCommittedByTheHeader() {
+		let (header, content) = block(4);
+		let mut compact = CompactTransactionBlock::new(header, &content, Vec::new(), 0);
+		assert!(compact.commitsContent(2));
+		compact.contentHash = H256::repeat_byte(1);
+		assert!(!compact.commitsContent(2));
+	}
+
+	#[test]
+	fn idsDependOnNonce() {
+		let (header, content) = block(4);
+		let first = CompactTransactionBlock::new(header.clone(), &content, Vec::new(), 0);
+		let second = CompactTransactionBlock::new(header, &content, Vec::new(), 1);
+		assert_ne!(first.shortIds, second.shortIds);
+	}
+
+	#[test]
+	fn compactRoundTrip() {
+		let (header, content) = block(4);
+		let compact = CompactTransactionBlock::new(header, &content, Vec::new(), 3);
+		assert_eq!(CompactTransactionBlock::decode(&mut &compact.encode()[..]).ok(), Some(compact));
+	}
+}
@@ -0,0 +1,366 @@
+//RaptorQ erasure coding of transaction block content for propagation.
+//
+//The SCALE encoded content is split into RaptorQ symbols, source symbols plus a fixed share of
+//repair symbols, and a peer rebuilds the content from any large enough subset of them. Everything
+//about the coding is derived from the content alone, so the symbols of a block are deterministic.
+//The content hash of a transaction block, its leaf of the header's contentRoot, is the hash of
+//(version, extrinsicRoot, contentLength, symbolRoot) in the transaction content context, with the
+//content's own version and the Merkle root of the symbols of its coding. Each gossiped symbol
+//carries those four and its proof against the symbol root, so a symbol of any other coding does not
+//verify and only the producer's coding is ever decoded. The RaptorQ parameters and the number of
+//symbols are derived locally from the committed length, never taken from a peer.
+use sp_core::H256;
+use codec::{Decode, DecodeAll, Encode};
+use scale_info::TypeInfo;
+use std::{collections::btree_set::BTreeSet, vec::Vec};
+use raptorq::{Decoder, Encoder, EncodingPacket, ObjectTransmissionInformation};
+use super::TransactionBlock::Content;
+use super::FormatVersion;
+use crate::Hashing::{hashWithContext, TRANSACTION_CONTENT_CONTEXT};
+use crate::MerkleTree::{MerkleProof, MerkleTree};
+
+//payload bytes of one symbol, small enough for a symbol and its proof to fit one gossip message
+pub const SYMBOL_SIZE: u16 = 1024;
+
+//encoded content a symbol may claim, twice the default transaction block payload limit. The real
+//limit is checked on the rebuilt content, this only bounds what a decoder allocates.
+pub const MAX_CONTENT_LENGTH: u64 = 8 * 1024 * 1024;
+
+//length of the RaptorQ payload id in front of a packet's symbol, its first byte is the source block
+const PAYLOAD_ID_SIZE: usize = 4;
+
+//serialized ObjectTransmissionInformation, the RaptorQ parameters of a block's coding
+pub type Oti = [u8; 12];
+
+//the RaptorQ parameters of the coding of `contentLength` encoded bytes
+fn transmissionInformation(contentLength: u64) -> ObjectTransmissionInformation {
+	ObjectTransmissionInformation::with_defaults(contentLength, SYMBOL_SIZE)
+}
+
+//repair symbols generated per source block, half the source symbols so that a peer can still
+//rebuild the content after losing a quarter of all symbols
+fn repairSymbols(oti: &ObjectTransmissionInformation) -> u32 {
+	let sourceSymbols = oti.transfer_length().div_ceil(SYMBOL_SIZE as u64);
+	let perBlock = sourceSymbols / oti.source_blocks().max(1) as u64;
+	(perBlock / 2 + 1) as u32
+}
+
+//the number of packets in the coding, the leaf count of its symbol tree. Like the parameters it is
+//derived from the committed length, a symbol proof is never checked against a count a peer claims.
+fn symbolCount(oti: &ObjectTransmissionInformation) -> u32 {
+	let sourceSymbols = oti.transfer_length().div_ceil(oti.symbol_size() as u64) as u32;
+	sourceSymbols + repairSymbols(oti) * oti.source_blocks() as u32
+}
+
+//all roots the symbols and the header commit to
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Coding {
+	pub version: FormatVersion,
+	pub oti: Oti,
+	pub extrinsicRoot: H256,
+	pub contentLength: u64,
+	pub symbolRoot: H256,
+	//serialized RaptorQ packets, the leaves of the symbol tree
+	pub packets: Vec<Vec<u8>>,
+}
+
+impl Coding {
+	pub fn new(content: &Content) -> Self {
+		let data = content.encode();
+		let config = transmissionInformation(data.len() as u64);
+		let packets: Vec<Vec<u8>> = Encoder::new(&data, config)
+			.get_encoded_packets(repairSymbols(&config))
+			.iter()
+			.map(EncodingPacket::serialize)
+			.collect();
+		Coding {
+			version: content.version,
+			oti: config.serialize(),
+			extrinsicRoot: content.extrinsicRoot(),
+			contentLength: data.len() as u64,
+			symbolRoot: MerkleTree::new(&packets).root(),
+			packets,
+		}
+	}
+
+	pub fn contentRoot(&self) -> H256 {
+		contentRoot(self.version, &self.extrinsicRoot, self.contentLength, &self.symbolRoot)
+	}
+
+	//every symbol of the block with hash `blockHash`, ready to gossip
+	pub fn symbols(&self, blockHash: H256) -> Vec<Symbol> {
+		let tree = MerkleTree::new(&self.packets);
+		self.packets
+			.iter()
+			.enumerate()
+			.map(|(index, packet)| Symbol {
+				blockHash,
+				version: self.version,
+				extrinsicRoot: self.extrinsicRoot,
+				contentLength: self.contentLength,
+				symbolRoot: self.symbolRoot,
+				packet: packet.clone(),
+				proof: tree.proof(index).expect("index is within the packets; qed"),
+			})
+			.collect()
+	}
+}
+
+pub fn contentRoot(version: FormatVersion, extrinsicRoot: &H256, contentLength: u64, symbolRoot: &H256) -> H256 {
+	hashWithContext(TRANSACTION_CONTENT_CONTEXT, &(version, extrinsicRoot, contentLength, symbolRoot))
+}
+
+//one RaptorQ symbol of a transaction block, as gossiped
+#[derive(Clone, PartialEq, Eq, Debug, Encode, Decode, TypeInfo)]
+pub struct Symbol {
+	pub blockHash: H256,
+	//format version of the content
+	pub version: FormatVersion,
+	pub extrinsicRoot: H256,
+	//encoded length of the content, the RaptorQ parameters are derived from it
+	pub contentLength: u64,
+	pub symbolRoot: H256,
+	pub packet: Vec<u8>,
+	//proof of `packet` against `symbolRoot`
+	pub proof: MerkleProof,
+}
+
+impl Symbol {
+	//whether the symbol is a well formed packet of the coding the transaction content with
+	//`contentHash` commits to
+	pub fn verify(&self, contentHash: &H256) -> bool {
+		if self.contentLength == 0 || self.contentLength > MAX_CONTENT_LENGTH {
+			return false;
+		}
+		let oti = transmissionInformation(self.contentLength);
+		contentRoot(self.version, &self.extrinsicRoot, self.contentLength, &self.symbolRoot) == *contentHash &&
+			self.fits(&oti) &&
+			self.proof.verify(&self.symbolRoot, symbolCount(&oti), &self.packet)
+	}
+
+	//raptorq panics on a packet of another symbol size or of a source block past the last one
+	fn fits(&self, oti: &ObjectTransmissionInformation) -> bool {
+		self.packet.len() == PAYLOAD_ID_SIZE + oti.symbol_size() as usize &&
+			u32::from(self.packet[0]) < u32::from(oti.source_blocks())
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SymbolError {
+	//the symbol is not one of the coding the content hash commits to
+	Forged,
+	//the symbol was already received
+	Duplicate,
+	//the committed coding rebuilds content that does not hash to the committed content hash. Only
+	//the producer could commit to it, the block cannot be rebuilt and is not valid either.
+	Inconsistent,
+}
+
+//collects the symbols of one block until its content can be rebuilt
+pub struct SymbolCollector {
+	contentHash: H256,
+	//opened with the first verified symbol, every verified symbol claims the same length
+	decoder: Option<Decoder>,
+	received: BTreeSet<u32>,
+	inconsistent: bool,
+}
+
+impl SymbolCollector {
+	//collector for the transaction content with `contentHash`
+	pub fn new(contentHash: H256) -> Self {
+		SymbolCollector { contentHash, decoder: None, received: BTreeSet::new(), inconsistent: false }
+	}
+
+	pub fn received(&self) -> usize {
+		self.received.len()
+	}
+
+	//add a symbol, returns the content once enough symbols arrived
+	pub fn push(&mut self, symbol: Symbol) -> Result<Option<Content>, SymbolError> {
+		if self.inconsistent {
+			return Err(SymbolError::Inconsistent);
+		}
+		if !symbol.verify(&self.contentHash) {
+			return Err(SymbolError::Forged);
+		}
+		if !self.received.insert(symbol.proof.index) {
+			return Err(SymbolError::Duplicate);
+		}
+		let decoder = self.decoder.get_or_insert_with(|| Decoder::new(transmissionInformation(symbol.contentLength)));
+		let data = match decoder.decode(EncodingPacket::deserialize(&symbol.packet)) {
+			Some(data) => data,
+			None => return Ok(None),
+		};
+		match Content::decode_all(&mut &data[..]) {
+			Ok(content) if content.hash() == self.contentHash => Ok(Some(content)),
+			_ => {
+				self.inconsistent = true;
+				Err(SymbolError::Inconsistent)
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn content(count: usize) -> Content {
+		Content::new((0..count)
+			.map(|i| Argonaut_Runtime::UncheckedExtrinsic::new_unsigned(Argonaut_Runtime::SystemCall::remark { remark: vec![i as u8; 100] }.into()))
+			.collect())
+	}
+
+	#[test]
+	fn rootMatchesContentHash() {
+		let content = content(50);
+		assert_eq!(Coding::new(&content).contentRoot(), content.hash());
+	}
+
+	#[test]
+	fn rootCommitsToTheContentVersion() {
+		let content = content(5);
+		let coding = Coding::new(&content);
+		let newer = super::super::FormatVersion(super::super::BLOCK_FORMAT_VERSION + 1);
+		assert_ne!(contentRoot(newer, &coding.extrinsicRoot, coding.contentLength, &coding.symbolRoot), coding.contentRoot());
+		let mut symbol = coding.symbols(H256::zero()).remove(0);
+		assert!(symbol.verify(&content.hash()));
+		symbol.version = newer;
+		assert!(!symbol.verify(&content.hash()));
+	}
+
+	#[test]
+	fn rebuildFromAllSymbols() {
+		let content = content(200);
+		let coding = Coding::new(&content);
+		let mut collector = SymbolCollector::new(coding.contentRoot());
+		let mut rebuilt = None;
+		for symbol in coding.symbols(H256::zero()) {
+			if let Some(done) = collector.push(symbol).unwrap() {
+				rebuilt = Some(done);
+				break;
+			}
+		}
+		assert_eq!(rebuilt, Some(content));
+	}
+
+	#[test]
+	fn rebuildWithMissingSymbols() {
+		let content = content(200);
+		let coding = Coding::new(&content);
+		let symbols = coding.symbols(H256::zero());
+		let mut collector = SymbolCollector::new(coding.contentRoot());
+		//lose every fourth symbol, source symbols included
+		let rebuilt = symbols
+			.into_iter()
+			.enumerate()
+			.filter(|(index, _)| index % 4 != 0)
+			.find_map(|(_, symbol)| collector.push(symbol).unwrap());
+		assert_eq!(rebuilt, Some(content));
+	}
+
+	#[test]
+	fn forgedSymbolRejected() {
+		let coding = Coding::new(&content(20));
+		let mut collector = SymbolCollector::new(coding.contentRoot());
+		let mut symbol = coding.symbols(H256::zero()).remove(1);
+		symbol.packet[6] ^= 1;
+		assert_eq!(collector.push(symbol), Err(SymbolError::Forged));
+
+		//a valid symbol of another block
+		let other = Coding::new(&content(21)).symbols(H256::zero()).remove(0);
+		assert_eq!(collector.push(other), Err(SymbolError::Forged));
+	}
+
+	//a coding of other packets under the honest block's extrinsicRoot and length, its symbols verify
+	fn forgedCoding(coding: &Coding, forge: impl Fn(&mut Vec<u8>)) -> Coding {
+		let mut packets = coding.packets.clone();
+		packets.iter_mut().for_each(forge);
+		Coding { symbolRoot: MerkleTree::new(&packets).root(), packets, ..coding.clone() }
+	}
+
+	#[test]
+	fn truncatedPacketRejected() {
+		let coding = Coding::new(&content(1));
+		let mut symbol = coding.symbols(H256::zero()).remove(0);
+		symbol.packet.truncate(3);
+		assert!(!symbol.verify(&coding.contentRoot()));
+
+		let short = forgedCoding(&coding, |packet| packet.truncate(100));
+		assert!(short.symbols(H256::zero()).iter().all(|symbol| !symbol.verify(&coding.contentRoot())));
+	}
+
+	#[test]
+	fn sourceBlockOutOfRangeRejected() {
+		let coding = Coding::new(&content(20));
+		let forged = forgedCoding(&coding, |packet| packet[0] = 5);
+		let symbol = forged.symbols(H256::zero()).remove(0);
+		assert!(!symbol.verify(&coding.contentRoot()));
+		assert_eq!(SymbolCollector::new(coding.contentRoot()).push(symbol), Err(SymbolError::Forged));
+	}
+
+	#[test]
+	fn oversizedLengthRejected() {
+		let coding = Coding { contentLength: MAX_CONTENT_LENGTH + 1, ..Coding::new(&content(1)) };
+		let symbol = coding.symbols(H256::zero()).remove(0);
+		assert!(!symbol.verify(&coding.contentRoot()));
+	}
+
+	#[test]
+	fn otherCodingRejected() {
+		let content = content(200);
+		let coding = Coding::new(&content);
+		let forged = forgedCoding(&coding, |packet| packet[PAYLOAD_ID_SIZE] ^= 1);
+		let mut collector = SymbolCollector::new(coding.contentRoot());
+		assert!(forged.symbols(H256::zero()).into_iter().all(|symbol| collector.push(symbol) == Err(SymbolError::Forged)));
+		assert_eq!(collector.received(), 0);
+		let rebuilt = coding.symbols(H256::zero()).into_iter().find_map(|symbol| collector.push(symbol).unwrap());
+		assert_eq!(rebuilt, Some(content));
+	}
+
+	#[test]
+	fn committedCodingOfOtherContent() {
+		let coding = Coding::new(&content(200));
+		let forged = forgedCoding(&coding, |packet| packet[PAYLOAD_ID_SIZE] ^= 1);
+		//a header committing to the forged coding, its symbols verify but never rebuild its content
+		let mut collector = SymbolCollector::new(forged.contentRoot());
+		let results: Vec<_> = forged.symbols(H256::zero()).into_iter().map(|symbol| collector.push(symbol)).collect();
+		assert!(results.contains(&Err(SymbolError::Inconsistent)));
+		assert!(results.iter().all(|result| result.as_ref().map_or(true, Option::is_none)));
+		assert_eq!(collector.push(forged.symbols(H256::zero()).remove(0)), Err(SymbolError::Inconsistent));
+	}
+
+	#[test]
+	fn duplicateSymbolRejected() {
+		let coding = Coding::new(&content(200));
+		let mut collector = SymbolCollector::new(coding.contentRoot());
+		let symbol = coding.symbols(H256::zero()).remove(0);
+		assert_eq!(collector.push(symbol.clone()), Ok(None));
+		assert_eq!(collector.push(symbol), Err(SymbolError::Duplicate));
+	}
+
+	#[test]
+	fn symbolCountIsDerivedFromTheLength() {
+		for count in [1, 20, 200] {
+			let coding = Coding::new(&content(count));
+			assert_eq!(symbolCount(&transmissionInformation(coding.contentLength)) as usize, coding.packets.len());
+		}
+	}
+
+	#[test]
+	fn symbolAtAnotherIndexRejected() {
+		let coding = Coding::new(&content(200));
+		let mut collector = SymbolCollector::new(coding.contentRoot());
+		let symbols = coding.symbols(H256::zero());
+		assert_eq!(collector.push(symbols[1].clone()), Ok(None));
+		//the same packet claimed at an index no proof of it can reach, it cannot pass as a new symbol
+		let mut moved = symbols[1].clone();
+		moved.proof.index = coding.packets.len() as u32;
+		assert_eq!(collector.push(moved), Err(SymbolError::Forged));
+	}
+
+	#[test]
+	fn symbolsAreDeterministic() {
+		assert_eq!(Coding::new(&content(30)), Coding::new(&content(30)));
+	}
+}
@@ -0,0 +1,238 @@
+//Structured extraContent of a block header.
+//
+//The field stays 32 bytes in the header and in the block hash, what changes is how they are read:
+//  byte 0       layout version
+//  bytes 1..5   feature signalling bits, little endian, one bit per pending soft upgrade
+//  byte 5       length of the miner tag
+//  bytes 6..32  miner tag, zero padded
+//All zero bytes are version 0, the layout of headers from before the field was structured, and
+//signal nothing. A version above the current one is from a newer node: by default it is accepted
+//and counted as signalling nothing, since only a node that knows the layout can read its bits.
+//A chain that wants to force an upgrade can reject unknown versions instead.
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+use std::vec::Vec;
+use super::Header::BlockHeader;
+
+pub const EXTRA_CONTENT_VERSION: u8 = 1;
+pub const MAX_MINER_TAG: usize = 26;
+
+const SIGNALS: core::ops::Range<usize> = 1..5;
+const TAG_LENGTH: usize = 5;
+const TAG: core::ops::Range<usize> = 6..32;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Encode, Decode, TypeInfo, Serialize, Deserialize)]
+pub struct ExtraContent([u8; 32]);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExtraContentError {
+	TagTooLong,
+	//version 0 with anything but zero bytes
+	NonZeroLegacy,
+	//the tag length byte points past the field or the padding after the tag is not zero
+	MalformedTag,
+	UnknownVersion(u8),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum UnknownVersionPolicy {
+	//accept the header, its signals are not counted
+	#[default]
+	Accept,
+	Reject,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParsedExtraContent {
+	Legacy,
+	Current { signals: u32, minerTag: Vec<u8> },
+	//a newer layout this node cannot read
+	Unknown(u8),
+}
+
+impl ParsedExtraContent {
+	//signals this node can count, none for legacy and unknown layouts
+	pub fn signals(&self) -> u32 {
+		match self {
+			ParsedExtraContent::Current { signals, .. } => *signals,
+			_ => 0,
+		}
+	}
+}
+
+impl Default for ExtraContent {
+	fn default() -> Self {
+		ExtraContent::new(0, &[]).expect("an empty tag fits; qed")
+	}
+}
+
+impl ExtraContent {
+	pub fn new(signals: u32, minerTag: &[u8]) -> Result<Self, ExtraContentError> {
+		if minerTag.len() > MAX_MINER_TAG {
+			return Err(ExtraContentError::TagTooLong);
+		}
+		let mut bytes = [0u8; 32];
+		bytes[0] = EXTRA_CONTENT_VERSION;
+		bytes[SIGNALS].copy_from_slice(&signals.to_le_bytes());
+		bytes[TAG_LENGTH] = minerTag.len() as u8;
+		bytes[TAG.start..TAG.start + minerTag.len()].copy_from_slice(minerTag);
+		Ok(ExtraContent(bytes))
+	}
+
+	//the raw bytes as they are in a header, checked only by `parse`
+	pub fn fromBytes(bytes: [u8; 32]) -> Self {
+		ExtraContent(bytes)
+	}
+
+	pub fn asBytes(&self) -> &[u8; 32] {
+		&self.0
+	}
+
+	pub fn version(&self) -> u8 {
+		self.0[0]
+	}
+
+	pub fn parse(&self) -> Result<ParsedExtraContent, ExtraContentError> {
+		match self.version() {
+			0 if self.0.iter().all(|byte| *byte == 0) => Ok(ParsedExtraContent::Legacy),
+			0 => Err(ExtraContentError::NonZeroLegacy),
+			EXTRA_CONTENT_VERSION => {
+				let length = self.0[TAG_LENGTH] as usize;
+				if length > MAX_MINER_TAG || self.0[TAG.start + length..].iter().any(|byte| *byte != 0) {
+					return Err(ExtraContentError::MalformedTag);
+				}
+				let mut signals = [0u8; 4];
+				signals.copy_from_slice(&self.0[SIGNALS]);
+				Ok(ParsedExtraContent::Current {
+					signals: u32::from_le_bytes(signals),
+					minerTag: self.0[TAG.start..TAG.start + length].to_vec(),
+				})
+			},
+			version => Ok(ParsedExtraContent::Unknown(version)),
+		}
+	}
+
+	//parse on import, applying `policy` to layouts newer than this node's
+	pub fn check(&self, policy: UnknownVersionPolicy) -> Result<ParsedExtraContent, ExtraContentError> {
+		match self.parse()? {
+			ParsedExtraContent::Unknown(version) if policy == UnknownVersionPolicy::Reject =>
+				Err(ExtraContentError::UnknownVersion(version)),
+			parsed => Ok(parsed),
+		}
+	}
+}
+
+//a soft upgrade coordinated by miner signalling: it locks in once `threshold` of the last
+//`window` headers set `bit`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Deployment {
+	pub bit: u8,
+	pub window: u32,
+	pub threshold: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeploymentError {
+	//the signals are 32 bits wide
+	BitOutOfRange(u8),
+	//a threshold of zero is locked in from the start, one above the window never
+	BadThreshold,
+}
+
+impl Deployment {
+	pub fn new(bit: u8, window: u32, threshold: u32) -> Result<Self, DeploymentError> {
+		if bit >= 32 {
+			return Err(DeploymentError::BitOutOfRange(bit));
+		}
+		if threshold == 0 || threshold > window {
+			return Err(DeploymentError::BadThreshold);
+		}
+		Ok(Deployment { bit, window, threshold })
+	}
+
+	//a deployment built around `new` with a bit past the signals is never signalled
+	pub fn signalledBy(&self, header: &BlockHeader) -> bool {
+		match (header.extraContent.parse(), 1u32.checked_shl(self.bit as u32)) {
+			(Ok(parsed), Some(mask)) => parsed.signals() & mask != 0,
+			_ => false,
+		}
+	}
+
+	//headers among the first `window` of `headers`, newest first, that signal the deployment
+	pub fn count<'a>(&self, headers: impl IntoIterator<Item = &'a BlockHeader>) -> u32 {
+		headers.into_iter().take(self.window as usize).filter(|header| self.signalledBy(header)).count() as u32
+	}
+
+	pub fn lockedIn<'a>(&self, headers: impl IntoIterator<Item = &'a BlockHeader>) -> bool {
+		self.count(headers) >= self.threshold
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::H256;
+
+	#[test]
+	fn roundTrip() {
+		let extra = ExtraContent::new(0b101, b"pool.example").unwrap();
+		assert_eq!(extra.version(), EXTRA_CONTENT_VERSION);
+		assert_eq!(extra.parse(), Ok(ParsedExtraContent::Current { signals: 0b101, minerTag: b"pool.example".to_vec() }));
+		assert_eq!(ExtraContent::decode(&mut &extra.encode()[..]).ok(), Some(extra));
+		assert_eq!(extra.encode().len(), 32);
+		assert!(ExtraContent::new(0, &[1; MAX_MINER_TAG]).is_ok());
+		assert_eq!(ExtraContent::new(0, &[1; MAX_MINER_TAG + 1]), Err(ExtraContentError::TagTooLong));
+	}
+
+	#[test]
+	fn malformedFieldsRejected() {
+		assert_eq!(ExtraContent::fromBytes([0; 32]).parse(), Ok(ParsedExtraContent::Legacy));
+		let mut legacy = [0u8; 32];
+		legacy[31] = 1;
+		assert_eq!(ExtraContent::fromBytes(legacy).parse(), Err(ExtraContentError::NonZeroLegacy));
+
+		let mut bytes = *ExtraContent::new(0, b"tag").unwrap().asBytes();
+		bytes[TAG_LENGTH] = MAX_MINER_TAG as u8 + 1;
+		assert_eq!(ExtraContent::fromBytes(bytes).parse(), Err(ExtraContentError::MalformedTag));
+		let mut bytes = *ExtraContent::new(0, b"tag").unwrap().asBytes();
+		bytes[31] = 1;
+		assert_eq!(ExtraContent::fromBytes(bytes).parse(), Err(ExtraContentError::MalformedTag));
+	}
+
+	#[test]
+	fn unknownVersionPolicy() {
+		let mut bytes = [0xaa; 32];
+		bytes[0] = EXTRA_CONTENT_VERSION + 1;
+		let newer = ExtraContent::fromBytes(bytes);
+		let accepted = newer.check(UnknownVersionPolicy::Accept).unwrap();
+		assert_eq!(accepted, ParsedExtraContent::Unknown(EXTRA_CONTENT_VERSION + 1));
+		assert_eq!(accepted.signals(), 0);
+		assert_eq!(newer.check(UnknownVersionPolicy::Reject), Err(ExtraContentError::UnknownVersion(EXTRA_CONTENT_VERSION + 1)));
+	}
+
+	#[test]
+	fn deploymentBitChecked() {
+		assert_eq!(Deployment::new(32, 10, 7), Err(DeploymentError::BitOutOfRange(32)));
+		assert_eq!(Deployment::new(3, 10, 11), Err(DeploymentError::BadThreshold));
+		assert_eq!(Deployment::new(3, 10, 0), Err(DeploymentError::BadThreshold));
+		let header = BlockHeader::new(H256::zero(), 1, 0, H256::zero(), ExtraContent::new(u32::MAX, &[]).unwrap(), H256::zero());
+		assert!(Deployment::new(31, 1, 1).unwrap().signalledBy(&header));
+		assert!(!Deployment { bit: 40, window: 1, threshold: 1 }.signalledBy(&header));
+	}
+
+	#[test]
+	fn deploymentLocksInOnThreshold() {
+		let deployment = Deployment::new(3, 10, 7).unwrap();
+		let header = |signals: u32| {
+			BlockHeader::new(H256::zero(), 1, 0, H256::zero(), ExtraContent::new(signals, &[]).unwrap(), H256::zero())
+		};
+		//newest first, 6 of the last 10 signal and older ones do not count
+		let mut headers: Vec<BlockHeader> = (0..10).map(|i| header(if i < 6 { 1 << 3 } else { 1 << 2 })).collect();
+		headers.extend((0..5).map(|_| header(1 << 3)));
+		assert_eq!(deployment.count(&headers), 6);
+		assert!(!deployment.lockedIn(&headers));
+		headers[6] = header(1 << 3 | 1);
+		assert!(deployment.lockedIn(&headers));
+	}
+}
@@ -0,0 +1,182 @@
+use sp_core::{crypto::AccountId32 as AccountId, H256};
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+use super::FormatVersion;
+use super::ExtraContent::ExtraContent;
+use crate::Hashing::{hashWithContext, HEADER_CONTEXT};
+use sp_runtime::generic::Digest as HeaderDigest;
+use pqcrypto_falcon::falcon512::{PublicKey, SecretKey};
+use primitives::seal::{MinerSeal, MinerSealError, MINER_SEAL_ENGINE_ID};
+
+//SCALE encoded it is a valid `Parameter`, the block hash below commits to the fields rather than the encoding
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Encode, Decode, TypeInfo)]
+pub struct BlockHeader {
+	pub version: FormatVersion,
+	//Hash of the parent proposer Block
+	pub parent: H256,
+	//timestamp of the current Block
+	pub timestamp: u128,
+	//nonce for it
+	pub nonce: u32,
+	//merkle root of the content inside of a block
+	pub contentRoot: H256,
+	//protocol version, signalling bits and miner tag, see ExtraContent
+	pub extraContent: ExtraContent,
+	//Difficulty of the block
+	pub difficulty: H256,
+	//coinbase of the miner's address
+	pub coinbase: AccountId,
+	//digest items that are not part of the block hash, e.g. the miner seal signing that hash
+	pub digest: HeaderDigest
+}
+
+impl BlockHeader {
+	//create a new block header function
+	pub fn new(parent: H256, timestamp: u128, nonce: u32, contentRoot: H256, extraContent: ExtraContent, difficulty: H256) -> Self {
+		BlockHeader {
+			version: FormatVersion::CURRENT,
+			parent,
+			timestamp,
+			nonce,
+			contentRoot,
+			extraContent,
+			difficulty,
+			coinbase: AccountId::new([0; 32]),
+			digest: HeaderDigest::default()
+		}
+	}
+
+	pub fn with_coinbase(mut self, coinbase: AccountId) -> Self {
+		self.coinbase = coinbase;
+		self
+	}
+
+	//sign the block hash with the Falcon key behind the coinbase and push the seal into the digest,
+	//any seal from an earlier signing is replaced
+	pub fn seal_with_miner_key(&mut self, publicKey: &PublicKey, secretKey: &SecretKey) {
+		let seal = MinerSeal::sign(&self.hash().0, publicKey, secretKey);
+		self.digest.logs.retain(|item| !matches!(item, sp_runtime::DigestItem::Seal(id, _) if *id == MINER_SEAL_ENGINE_ID));
+		self.digest.push(seal.to_digest_item());
+	}
+
+	pub fn miner_seal(&self) -> Result<Option<MinerSeal>, MinerSealError> {
+		MinerSeal::from_digest(self.digest.logs())
+	}
+
+	//check the miner seal on import, every block but the genesis blocks has to carry one
+	pub fn verify_miner_seal(&self) -> Result<(), MinerSealError> {
+		match self.miner_seal()? {
+			Some(seal) => seal.verify(&self.hash().0, self.coinbase.as_ref()),
+			None => Err(MinerSealError::Missing),
+		}
+	}
+
+	//hashing function for the block header, see Hashing. It covers every field but the digest, which holds the miner seal over this hash. The coinbase has to be
+	//committed by the proof of work, otherwise a relayer could swap in its own account and seal the
+	//unchanged hash with its own key
+	pub fn hash(&self) -> H256 {
+		hashWithContext(HEADER_CONTEXT, &(
+			&self.version,
+			&self.parent,
+			&self.timestamp,
+			&self.nonce,
+			&self.contentRoot,
+			&self.extraContent,
+			&self.difficulty,
+			&self.coinbase,
+		))
+	}
+}
+
+//create test to see if the hashing function works
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_hash() {
+		let parent = H256::from_low_u64_be(1);
+		let timestamp = 1;
+		let nonce = 1;
+		let contentRoot = H256::from_low_u64_be(1);
+		let extraContent = ExtraContent::default();
+		let difficulty = H256::from_low_u64_be(1);
+		let header = BlockHeader::new(parent, timestamp, nonce, contentRoot, extraContent, difficulty);
+		let hash = header.hash();
+		assert_eq!(hash, header.clone().hash());
+		//every field is committed, the digest is not
+		let changed = [
+			BlockHeader { parent: H256::from_low_u64_be(2), ..header.clone() },
+			BlockHeader { timestamp: 2, ..header.clone() },
+			BlockHeader { nonce: 2, ..header.clone() },
+			BlockHeader { contentRoot: H256::from_low_u64_be(2), ..header.clone() },
+			BlockHeader { extraContent: ExtraContent::new(1, &[]).unwrap(), ..header.clone() },
+			BlockHeader { difficulty: H256::from_low_u64_be(2), ..header.clone() },
+			header.clone().with_coinbase(AccountId::new([1; 32])),
+		];
+		for other in changed {
+			assert_ne!(other.hash(), hash);
+		}
+		let mut sealed = header.clone();
+		sealed.digest.push(sp_runtime::DigestItem::Other(vec![1]));
+		assert_eq!(sealed.hash(), hash);
+	}
+
+	//create test to see if the new function works
+	#[test]
+	fn test_new() {
+		let parent = H256::from_low_u64_be(1);
+		let timestamp = 1;
+		let nonce = 1;
+		let contentRoot = H256::from_low_u64_be(1);
+		let extraContent = ExtraContent::default();
+		let difficulty = H256::from_low_u64_be(1);
+		let header = BlockHeader::new(parent, timestamp, nonce, contentRoot, extraContent, difficulty);
+		assert_eq!(header.parent, H256::from_low_u64_be(1));
+		assert_eq!(header.timestamp, 1);
+		assert_eq!(header.nonce, 1);
+		assert_eq!(header.contentRoot, H256::from_low_u64_be(1));
+		assert_eq!(header.extraContent, ExtraContent::default());
+		assert_eq!(header.difficulty, H256::from_low_u64_be(1));
+	}
+
+	fn sealed_header() -> (BlockHeader, PublicKey) {
+		let (pk, sk) = primitives::FalconHostFunctions::generate_keypair();
+		let coinbase = AccountId::new(primitives::FalconHostFunctions::hash_public_key(&pk));
+		let mut header = BlockHeader::new(H256::from_low_u64_be(1), 1, 1, H256::from_low_u64_be(1), ExtraContent::default(), H256::from_low_u64_be(1))
+			.with_coinbase(coinbase);
+		header.seal_with_miner_key(&pk, &sk);
+		(header, pk)
+	}
+
+	#[test]
+	fn test_miner_seal() {
+		let (header, _) = sealed_header();
+		assert!(header.miner_seal().unwrap().is_some());
+		assert_eq!(header.verify_miner_seal(), Ok(()));
+	}
+
+	#[test]
+	fn test_relayed_coinbase_rejected() {
+		let (header, _) = sealed_header();
+		//a relayer pays the block to itself and re-seals with its own key
+		let (relayerPk, relayerSk) = primitives::FalconHostFunctions::generate_keypair();
+		let mut relayed = header.clone().with_coinbase(AccountId::new(primitives::FalconHostFunctions::hash_public_key(&relayerPk)));
+		//the miner's seal does not sign for the new coinbase
+		assert_eq!(relayed.verify_miner_seal(), Err(MinerSealError::CoinbaseMismatch));
+		//re-sealed the seal verifies, but the coinbase is part of the hash the work was found for,
+		//so the relayed header is a new block that needs its own proof of work
+		relayed.seal_with_miner_key(&relayerPk, &relayerSk);
+		assert_eq!(relayed.verify_miner_seal(), Ok(()));
+		assert_ne!(relayed.hash(), header.hash());
+	}
+
+	#[test]
+	fn test_unsealed_header() {
+		let header = BlockHeader::new(H256::from_low_u64_be(1), 1, 1, H256::from_low_u64_be(1), ExtraContent::default(), H256::from_low_u64_be(1));
+		assert_eq!(header.verify_miner_seal(), Err(MinerSealError::Missing));
+	}
+
+}
+
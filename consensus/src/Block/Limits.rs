@@ -0,0 +1,218 @@
+//Per-kind payload limits of Prism blocks.
+//
+//The runtime's BlockLength bounds the substrate block as a whole and cannot tell the block kinds
+//apart, while a transaction block is bounded by its bytes and proposer and voter blocks by how
+//many hashes they reference. Builders take at most what the limits allow and import rejects
+//anything beyond them with the exact limit that was broken. The limits are part of the chain's
+//rules and come from its chain spec, a limit the spec leaves out keeps its default.
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+use sp_core::H256;
+use std::vec::Vec;
+use super::{Content, ProposerBlock, TransactionBlock, VoterBlock};
+use Argonaut_Runtime::UncheckedExtrinsic;
+
+//encoded size of the payload a block carries, what the limits are checked against
+pub trait PayloadSize {
+	fn payloadSize(&self) -> usize;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Encode, Decode, TypeInfo, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PayloadLimits {
+	//encoded bytes of the extrinsics of a transaction block
+	pub maxTransactionBlockBytes: u32,
+	//transaction blocks a proposer block may reference
+	pub maxTransactionReferences: u32,
+	//proposer blocks a proposer block may reference
+	pub maxProposerReferences: u32,
+	//votes of a voter block
+	pub maxVotes: u32,
+}
+
+impl Default for PayloadLimits {
+	fn default() -> Self {
+		PayloadLimits {
+			//the runtime's 5 MiB BlockLength minus room for the header and the SCALE framing
+			maxTransactionBlockBytes: 4 * 1024 * 1024,
+			maxTransactionReferences: 1024,
+			maxProposerReferences: 1024,
+			maxVotes: 1024,
+		}
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PayloadError {
+	TransactionBlockTooLarge { size: usize, limit: u32 },
+	TooManyTransactionReferences { count: usize, limit: u32 },
+	TooManyProposerReferences { count: usize, limit: u32 },
+	TooManyVotes { count: usize, limit: u32 },
+}
+
+impl core::fmt::Display for PayloadError {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		match self {
+			PayloadError::TransactionBlockTooLarge { size, limit } =>
+				write!(f, "transaction block payload of {} bytes exceeds the limit of {} bytes", size, limit),
+			PayloadError::TooManyTransactionReferences { count, limit } =>
+				write!(f, "proposer block references {} transaction blocks, the limit is {}", count, limit),
+			PayloadError::TooManyProposerReferences { count, limit } =>
+				write!(f, "proposer block references {} proposer blocks, the limit is {}", count, limit),
+			PayloadError::TooManyVotes { count, limit } =>
+				write!(f, "voter block casts {} votes, the limit is {}", count, limit),
+		}
+	}
+}
+
+impl PayloadLimits {
+	//check a block's content on import
+	pub fn check(&self, content: &Content) -> Result<(), PayloadError> {
+		match content {
+			Content::TransactionBlock(content) => self.checkTransactionBlock(content),
+			Content::ProposerBlock(content) => self.checkProposerBlock(content),
+			Content::VoterBlock(content) => self.checkVoterBlock(content),
+		}
+	}
+
+	pub fn checkTransactionBlock(&self, content: &TransactionBlock::Content) -> Result<(), PayloadError> {
+		let size = content.payloadSize();
+		if size > self.maxTransactionBlockBytes as usize {
+			return Err(PayloadError::TransactionBlockTooLarge { size, limit: self.maxTransactionBlockBytes });
+		}
+		Ok(())
+	}
+
+	pub fn checkProposerBlock(&self, content: &ProposerBlock::Content) -> Result<(), PayloadError> {
+		let count = content.transactionReferences.len();
+		if count > self.maxTransactionReferences as usize {
+			return Err(PayloadError::TooManyTransactionReferences { count, limit: self.maxTransactionReferences });
+		}
+		let count = content.proposerReferences.len();
+		if count > self.maxProposerReferences as usize {
+			return Err(PayloadError::TooManyProposerReferences { count, limit: self.maxProposerReferences });
+		}
+		Ok(())
+	}
+
+	pub fn checkVoterBlock(&self, content: &VoterBlock::Content) -> Result<(), PayloadError> {
+		let count = content.votes.len();
+		if count > self.maxVotes as usize {
+			return Err(PayloadError::TooManyVotes { count, limit: self.maxVotes });
+		}
+		Ok(())
+	}
+
+	//transaction block content from `candidates` in order, skipping any extrinsic that would push
+	//the payload over the limit so that smaller ones behind it still get in
+	pub fn buildTransactionBlock(&self, candidates: impl IntoIterator<Item = UncheckedExtrinsic>) -> TransactionBlock::Content {
+		let mut size = 0usize;
+		let mut extrinsics = Vec::new();
+		for extrinsic in candidates {
+			let extrinsicSize = extrinsic.encoded_size();
+			if size + extrinsicSize <= self.maxTransactionBlockBytes as usize {
+				size += extrinsicSize;
+				extrinsics.push(extrinsic);
+			}
+		}
+		TransactionBlock::Content::new(extrinsics)
+	}
+
+	//proposer block content referencing as many of the given blocks as allowed, oldest first so
+	//the rest is picked up by the next proposer block
+	pub fn buildProposerBlock(&self, mut transactionReferences: Vec<H256>, mut proposerReferences: Vec<H256>) -> ProposerBlock::Content {
+		transactionReferences.truncate(self.maxTransactionReferences as usize);
+		proposerReferences.truncate(self.maxProposerReferences as usize);
+		ProposerBlock::Content::new(transactionReferences, proposerReferences)
+	}
+
+	//voter block content with the votes of the lowest levels first, votes have to be cast level
+	//after level so the remaining ones go into the next voter block
+	pub fn buildVoterBlock(&self, chainNumber: u16, parent: H256, mut votes: Vec<H256>) -> VoterBlock::Content {
+		votes.truncate(self.maxVotes as usize);
+		VoterBlock::Content::new(chainNumber, parent, votes)
+	}
+}
+
+impl PayloadSize for Content {
+	fn payloadSize(&self) -> usize {
+		match self {
+			Content::TransactionBlock(content) => content.payloadSize(),
+			Content::ProposerBlock(content) => content.encoded_size(),
+			Content::VoterBlock(content) => content.encoded_size(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn extrinsic(size: usize) -> UncheckedExtrinsic {
+		UncheckedExtrinsic::new_unsigned(Argonaut_Runtime::SystemCall::remark { remark: vec![0; size] }.into())
+	}
+
+	fn hashes(count: u64) -> Vec<H256> {
+		(0..count).map(H256::from_low_u64_be).collect()
+	}
+
+	fn limits() -> PayloadLimits {
+		PayloadLimits { maxTransactionBlockBytes: 1000, maxTransactionReferences: 3, maxProposerReferences: 2, maxVotes: 4 }
+	}
+
+	#[test]
+	fn builtBlocksPassTheirOwnCheck() {
+		let limits = limits();
+		let transactions = limits.buildTransactionBlock((0..20).map(|_| extrinsic(90)));
+		assert!(transactions.payloadSize() <= 1000);
+		assert!(!transactions.extrinsics.is_empty());
+		assert_eq!(limits.check(&Content::TransactionBlock(transactions)), Ok(()));
+
+		let proposer = limits.buildProposerBlock(hashes(10), hashes(10));
+		assert_eq!(proposer.transactionReferences, hashes(3));
+		assert_eq!(proposer.proposerReferences, hashes(2));
+		assert_eq!(limits.check(&Content::ProposerBlock(proposer)), Ok(()));
+
+		let voter = limits.buildVoterBlock(0, H256::zero(), hashes(10));
+		assert_eq!(voter.votes, hashes(4));
+		assert_eq!(limits.check(&Content::VoterBlock(voter)), Ok(()));
+	}
+
+	#[test]
+	fn missingLimitsKeepTheirDefault() {
+		let limits: PayloadLimits = serde_json::from_str(r#"{"maxVotes": 4}"#).unwrap();
+		assert_eq!(limits, PayloadLimits { maxVotes: 4, ..PayloadLimits::default() });
+		assert_eq!(serde_json::from_str::<PayloadLimits>(&serde_json::to_string(&limits).unwrap()).unwrap(), limits);
+	}
+
+	#[test]
+	fn smallerExtrinsicsFillTheRest() {
+		let limits = limits();
+		let content = limits.buildTransactionBlock(vec![extrinsic(900), extrinsic(500), extrinsic(10)]);
+		assert_eq!(content.extrinsics, vec![extrinsic(900), extrinsic(10)]);
+	}
+
+	#[test]
+	fn oversizedBlocksRejected() {
+		let limits = limits();
+		let transactions = TransactionBlock::Content::new((0..20).map(|_| extrinsic(90)).collect());
+		let size = transactions.payloadSize();
+		assert_eq!(
+			limits.check(&Content::TransactionBlock(transactions)),
+			Err(PayloadError::TransactionBlockTooLarge { size, limit: 1000 })
+		);
+		assert_eq!(
+			limits.check(&Content::ProposerBlock(ProposerBlock::Content::new(hashes(4), hashes(1)))),
+			Err(PayloadError::TooManyTransactionReferences { count: 4, limit: 3 })
+		);
+		assert_eq!(
+			limits.check(&Content::ProposerBlock(ProposerBlock::Content::new(hashes(1), hashes(3)))),
+			Err(PayloadError::TooManyProposerReferences { count: 3, limit: 2 })
+		);
+		assert_eq!(
+			limits.check(&Content::VoterBlock(VoterBlock::Content::new(0, H256::zero(), hashes(5)))),
+			Err(PayloadError::TooManyVotes { count: 5, limit: 4 })
+		);
+	}
+}
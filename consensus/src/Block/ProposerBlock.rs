@@ -0,0 +1,39 @@
+use sp_core::H256;
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+use super::FormatVersion;
+use crate::MerkleTree::MerkleTree;
+use crate::Hashing::{hashWithContext, PROPOSER_CONTENT_CONTEXT};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default, Encode, Decode, TypeInfo)]
+pub struct Content{
+	pub version: FormatVersion,
+	//list of transaction blocks referred by this proposer block
+	pub transactionReferences: Vec<H256>,
+	//list of proposer blocks referred by this proposer block
+	pub proposerReferences: Vec<H256>,
+}
+
+impl Content{
+	pub fn new(transactionReferences: Vec<H256>, proposerReferences: Vec<H256>) -> Self{
+		Content{
+			version: FormatVersion::CURRENT,
+			transactionReferences,
+			proposerReferences,
+		}
+	}
+	//create function to return the transaction references and proposer references
+	pub fn getTransactionReferences(&self) -> &[H256]{
+		&self.transactionReferences
+	}
+	pub fn getProposerReferences(&self) -> &[H256]{
+		&self.proposerReferences
+	}
+	//commits to both reference lists, each as a merkle tree so a single reference can be proven
+	pub fn hash(&self) -> H256{
+		let transactionRoot = MerkleTree::new(&self.transactionReferences).root();
+		let proposerRoot = MerkleTree::new(&self.proposerReferences).root();
+		hashWithContext(PROPOSER_CONTENT_CONTEXT, &(&self.version, &transactionRoot, &proposerRoot))
+	}
+}
@@ -0,0 +1,49 @@
+use sp_core::H256;
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+use super::FormatVersion;
+use crate::MerkleTree::MerkleTree;
+use super::ErasureCode::Coding;
+use super::Limits::PayloadSize;
+use Argonaut_Runtime::UncheckedExtrinsic;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default, Encode, Decode, TypeInfo)]
+pub struct Content{
+	pub version: FormatVersion,
+	//a vector of extrinsics
+	pub extrinsics: Vec<UncheckedExtrinsic>,
+}
+//impl content
+impl Content{
+	//create a new content function
+	pub fn new(extrinsics: Vec<UncheckedExtrinsic>) -> Self {
+		Content {
+			version: FormatVersion::CURRENT,
+			extrinsics
+		}
+	}
+}
+
+impl PayloadSize for Content {
+	//encoded size of the extrinsics, without the version byte and the length prefix
+	fn payloadSize(&self) -> usize {
+		self.extrinsics.iter().map(|extrinsic| extrinsic.encoded_size()).sum()
+	}
+}
+
+
+impl Content{
+	//merkle root of the extrinsics, what inclusion proofs of a single extrinsic are checked against
+	pub fn extrinsicRoot(&self) -> H256{
+		let tree = MerkleTree::new(&self.extrinsics);
+		tree.root()
+	}
+
+	//the hash of a transaction content commits to the extrinsics, to the encoded length the RaptorQ
+	//symbols are derived from and to the symbols themselves, see ErasureCode. It takes coding the
+	//content.
+	pub fn hash(&self) -> H256{
+		Coding::new(self).contentRoot()
+	}
+}
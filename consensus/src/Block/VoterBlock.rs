@@ -0,0 +1,44 @@
+use super::Block;
+use sp_core::H256;
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+use super::FormatVersion;
+use crate::MerkleTree::MerkleTree;
+use crate::Hashing::{hashWithContext, VOTER_CONTENT_CONTEXT};
+use crate::Genesis::PrismGenesis;
+
+
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default, Encode, Decode, TypeInfo)]
+pub struct Content{
+	pub version: FormatVersion,
+	//ID of the voter chain
+	pub chainNumber: u16,
+	//Hash of the parent voter block
+	pub parent: H256,
+	//list of votes on the proposer blocks
+	pub votes: Vec<H256>,
+}
+
+impl Content {
+	//create a new content function
+	pub fn new(chainNumber: u16, parent: H256, votes: Vec<H256>) -> Self {
+		Content {
+			version: FormatVersion::CURRENT,
+			chainNumber,
+			parent,
+			votes
+		}
+	}
+
+	//the votes as a merkle tree so a single vote can be proven
+	pub fn hash(&self) -> H256{
+		let votesRoot = MerkleTree::new(&self.votes).root();
+		hashWithContext(VOTER_CONTENT_CONTEXT, &(&self.version, &self.chainNumber, &self.parent, &votesRoot))
+	}
+}
+//genesis block of voter chain `chainNumber`, a child of the proposer genesis, fixed by the chain spec
+pub fn Genesis(params: &PrismGenesis, chainNumber: u16) -> Block {
+	params.voterGenesis(chainNumber, params.proposerGenesis().hash())
+}
\ No newline at end of file
@@ -0,0 +1,571 @@
+use sp_runtime::traits::{BlakeTwo256, Hash as HashT, Header as HeaderT};
+use sp_runtime::{ConsensusEngineId, DigestItem, StateVersion};
+use sp_core::H256;
+use codec::{Decode, Encode, EncodeLike, Input, Output};
+use scale_info::{build::Fields, Path, Type, TypeInfo};
+use serde::{Deserialize, Serialize};
+use Limits::{PayloadError, PayloadLimits, PayloadSize};
+use crate::MerkleTree::{MerkleProof, MerkleTree};
+pub mod Compact;
+pub mod ErasureCode;
+pub mod ExtraContent;
+pub mod Header;
+pub mod Limits;
+pub mod ProposerBlock;
+pub mod TransactionBlock;
+pub mod VoterBlock;
+
+//format version of the SCALE encoding of every block type, bump it whenever a field changes
+pub const BLOCK_FORMAT_VERSION: u8 = 1;
+
+//leading byte of every encoded block type, decoding fails on a version this node does not know
+//instead of misreading the fields that follow
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, Encode, TypeInfo)]
+pub struct FormatVersion(u8);
+
+impl FormatVersion {
+	pub const CURRENT: FormatVersion = FormatVersion(BLOCK_FORMAT_VERSION);
+
+	pub fn get(&self) -> u8 {
+		self.0
+	}
+}
+
+impl Default for FormatVersion {
+	fn default() -> Self {
+		FormatVersion::CURRENT
+	}
+}
+
+impl Decode for FormatVersion {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		match input.read_byte()? {
+			BLOCK_FORMAT_VERSION => Ok(FormatVersion::CURRENT),
+			_ => Err("unsupported block format version".into()),
+		}
+	}
+}
+
+//engine id of the pre-runtime digest linking a Substrate header to the Prism block it came from
+pub const PRISM_ENGINE_ID: ConsensusEngineId = *b"prsm";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Ord, PartialOrd)]
+pub enum BlockKind {
+	Proposer,
+	Voter(u16),
+	Transaction,
+}
+
+impl BlockKind {
+	pub fn of(content: &Content) -> Self {
+		match content {
+			Content::ProposerBlock(_) => BlockKind::Proposer,
+			Content::VoterBlock(content) => BlockKind::Voter(content.chainNumber),
+			Content::TransactionBlock(_) => BlockKind::Transaction,
+		}
+	}
+
+	//leaf of the content of this kind in a sortition tree, none for a chain the tree has no leaf for
+	pub fn sortitionLeaf(&self, voterChains: u16) -> Option<u32> {
+		match *self {
+			BlockKind::Proposer => Some(0),
+			BlockKind::Voter(chain) if chain < voterChains => Some(1 + chain as u32),
+			BlockKind::Voter(_) => None,
+			BlockKind::Transaction => Some(1 + voterChains as u32),
+		}
+	}
+}
+
+//A miner mines a content of every kind at once: the header's contentRoot is the root of the Merkle
+//tree over their hashes, the proposer content first, then one voter content per chain and the
+//transaction content last. Whichever kind the sortition puts the header hash in, the block carries
+//that content and the proof of its leaf, and the header commits to the kind without a second hash.
+//`contentHashes` are in that order.
+pub fn sortitionTree(contentHashes: &[H256]) -> MerkleTree {
+	MerkleTree::new(contentHashes)
+}
+
+//whether `contentRoot` commits to the content with `contentHash` as the content of `kind`. Without a
+//proof the header commits to that one content, the root is its hash. A tree of two or more leaves
+//has a sibling on every path, so a proof is never empty.
+pub fn commitsTo(contentRoot: &H256, kind: BlockKind, voterChains: u16, contentHash: &H256, sortitionProof: &[H256]) -> bool {
+	if sortitionProof.is_empty() {
+		return contentRoot == contentHash;
+	}
+	match kind.sortitionLeaf(voterChains) {
+		Some(index) => MerkleProof { index, siblings: sortitionProof.to_vec() }.verify(contentRoot, voterChains as u32 + 2, contentHash),
+		None => false,
+	}
+}
+
+//a block of one kind, its header and the content the header's contentRoot commits to. The coinbase
+//and format version are the header's, a block has no second copy that could disagree with them.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize, Encode, Decode, TypeInfo)]
+pub struct BlockOf<C> {
+	pub header: Header::BlockHeader,
+	pub content: C,
+	//empty when the header commits to this content alone, otherwise the siblings of its leaf, see
+	//sortitionTree
+	pub sortitionProof: Vec<H256>,
+}
+
+//encoded as the format version, then the block kind as the variant index, like Content
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Block {
+	Transaction(BlockOf<TransactionBlock::Content>),
+	Proposer(BlockOf<ProposerBlock::Content>),
+	Voter(BlockOf<VoterBlock::Content>),
+}
+
+impl Block {
+	pub fn fromParts(header: Header::BlockHeader, content: Content, sortitionProof: Vec<H256>) -> Self {
+		match content {
+			Content::TransactionBlock(content) => Block::Transaction(BlockOf { header, content, sortitionProof }),
+			Content::ProposerBlock(content) => Block::Proposer(BlockOf { header, content, sortitionProof }),
+			Content::VoterBlock(content) => Block::Voter(BlockOf { header, content, sortitionProof }),
+		}
+	}
+
+	pub fn intoParts(self) -> (Header::BlockHeader, Content, Vec<H256>) {
+		match self {
+			Block::Transaction(block) => (block.header, Content::TransactionBlock(block.content), block.sortitionProof),
+			Block::Proposer(block) => (block.header, Content::ProposerBlock(block.content), block.sortitionProof),
+			Block::Voter(block) => (block.header, Content::VoterBlock(block.content), block.sortitionProof),
+		}
+	}
+
+	pub fn header(&self) -> &Header::BlockHeader {
+		match self {
+			Block::Transaction(block) => &block.header,
+			Block::Proposer(block) => &block.header,
+			Block::Voter(block) => &block.header,
+		}
+	}
+
+	pub fn hash(&self) -> H256 {
+		self.header().hash()
+	}
+
+	pub fn sortitionProof(&self) -> &[H256] {
+		match self {
+			Block::Transaction(block) => &block.sortitionProof,
+			Block::Proposer(block) => &block.sortitionProof,
+			Block::Voter(block) => &block.sortitionProof,
+		}
+	}
+
+	pub fn kind(&self) -> BlockKind {
+		match self {
+			Block::Transaction(_) => BlockKind::Transaction,
+			Block::Proposer(_) => BlockKind::Proposer,
+			Block::Voter(block) => BlockKind::Voter(block.content.chainNumber),
+		}
+	}
+
+	//whether the header's contentRoot commits to the content, see commitsTo
+	pub fn commitsContent(&self, voterChains: u16) -> bool {
+		commitsTo(&self.header().contentRoot, self.kind(), voterChains, &self.contentHash(), self.sortitionProof())
+	}
+
+	pub fn contentHash(&self) -> H256 {
+		match self {
+			Block::Transaction(block) => block.content.hash(),
+			Block::Proposer(block) => block.content.hash(),
+			Block::Voter(block) => block.content.hash(),
+		}
+	}
+
+	pub fn asTransaction(&self) -> Option<&BlockOf<TransactionBlock::Content>> {
+		match self {
+			Block::Transaction(block) => Some(block),
+			_ => None,
+		}
+	}
+
+	pub fn asProposer(&self) -> Option<&BlockOf<ProposerBlock::Content>> {
+		match self {
+			Block::Proposer(block) => Some(block),
+			_ => None,
+		}
+	}
+
+	pub fn asVoter(&self) -> Option<&BlockOf<VoterBlock::Content>> {
+		match self {
+			Block::Voter(block) => Some(block),
+			_ => None,
+		}
+	}
+
+	pub fn extrinsics(&self) -> Option<&[Argonaut_Runtime::UncheckedExtrinsic]> {
+		self.asTransaction().map(|block| &block.content.extrinsics[..])
+	}
+
+	pub fn transactionReferences(&self) -> Option<&[H256]> {
+		self.asProposer().map(|block| &block.content.transactionReferences[..])
+	}
+
+	pub fn proposerReferences(&self) -> Option<&[H256]> {
+		self.asProposer().map(|block| &block.content.proposerReferences[..])
+	}
+
+	pub fn votes(&self) -> Option<&[H256]> {
+		self.asVoter().map(|block| &block.content.votes[..])
+	}
+
+	//the Substrate block a transaction block's extrinsics are executed in, once the ledger puts it
+	//after the Substrate block `parentHash` at height `number`. Proposer and voter blocks carry no
+	//extrinsics and have none. The state root is left zero for the executor to fill in, as in a
+	//block template, and a pre-runtime digest links the header back to the Prism block.
+	pub fn toSubstrate(&self, number: Argonaut_Runtime::BlockNumber, parentHash: H256) -> Option<Argonaut_Runtime::Block> {
+		let block = self.asTransaction()?;
+		let extrinsics = block.content.extrinsics.clone();
+		let extrinsicsRoot = BlakeTwo256::ordered_trie_root(extrinsics.iter().map(Encode::encode).collect(), StateVersion::V0);
+		let mut digest = sp_runtime::generic::Digest::default();
+		digest.push(DigestItem::PreRuntime(PRISM_ENGINE_ID, self.hash().encode()));
+		let header = Argonaut_Runtime::Header::new(number, extrinsicsRoot, H256::zero(), parentHash, digest);
+		Some(Argonaut_Runtime::Block { header, extrinsics })
+	}
+
+	//reject a block whose content is over the limits of its kind
+	pub fn checkPayload(&self, limits: &PayloadLimits) -> Result<(), PayloadError> {
+		match self {
+			Block::Transaction(block) => limits.checkTransactionBlock(&block.content),
+			Block::Proposer(block) => limits.checkProposerBlock(&block.content),
+			Block::Voter(block) => limits.checkVoterBlock(&block.content),
+		}
+	}
+}
+
+impl Encode for Block {
+	fn size_hint(&self) -> usize {
+		2 + match self {
+			Block::Transaction(block) => block.size_hint(),
+			Block::Proposer(block) => block.size_hint(),
+			Block::Voter(block) => block.size_hint(),
+		}
+	}
+
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		FormatVersion::CURRENT.encode_to(dest);
+		match self {
+			Block::Transaction(block) => encodeVariant(dest, 0, block),
+			Block::Proposer(block) => encodeVariant(dest, 1, block),
+			Block::Voter(block) => encodeVariant(dest, 2, block),
+		}
+	}
+}
+
+impl EncodeLike for Block {}
+
+impl Decode for Block {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		FormatVersion::decode(input)?;
+		match input.read_byte()? {
+			0 => Ok(Block::Transaction(Decode::decode(input)?)),
+			1 => Ok(Block::Proposer(Decode::decode(input)?)),
+			2 => Ok(Block::Voter(Decode::decode(input)?)),
+			_ => Err("unknown block kind".into()),
+		}
+	}
+}
+
+fn encodeVariant<T: Output + ?Sized, V: Encode>(dest: &mut T, index: u8, value: &V) {
+	dest.push_byte(index);
+	value.encode_to(dest);
+}
+
+//the TypeInfo of Block and Content, which a derive would describe without the leading version:
+//a composite of the format version and a plain enum of the kinds, laid out as they are encoded
+fn versionedTypeInfo<K: TypeInfo + 'static>(name: &'static str, kindName: &'static str) -> Type {
+	Type::builder().path(Path::new(name, module_path!())).composite(
+		Fields::named()
+			.field(|f| f.ty::<FormatVersion>().name("version").type_name("FormatVersion"))
+			.field(|f| f.ty::<K>().name("kind").type_name(kindName)),
+	)
+}
+
+//only described, never built, the variant indices are those encodeVariant writes
+#[allow(dead_code)]
+#[derive(TypeInfo)]
+enum BlockKindLayout {
+	#[codec(index = 0)]
+	Transaction(BlockOf<TransactionBlock::Content>),
+	#[codec(index = 1)]
+	Proposer(BlockOf<ProposerBlock::Content>),
+	#[codec(index = 2)]
+	Voter(BlockOf<VoterBlock::Content>),
+}
+
+#[allow(dead_code, clippy::enum_variant_names)]
+#[derive(TypeInfo)]
+enum ContentKindLayout {
+	#[codec(index = 0)]
+	TransactionBlock(TransactionBlock::Content),
+	#[codec(index = 1)]
+	ProposerBlock(ProposerBlock::Content),
+	#[codec(index = 2)]
+	VoterBlock(VoterBlock::Content),
+}
+
+impl TypeInfo for Block {
+	type Identity = Self;
+
+	fn type_info() -> Type {
+		versionedTypeInfo::<BlockKindLayout>("Block", "BlockKindLayout")
+	}
+}
+
+impl TypeInfo for Content {
+	type Identity = Self;
+
+	fn type_info() -> Type {
+		versionedTypeInfo::<ContentKindLayout>("Content", "ContentKindLayout")
+	}
+}
+
+impl PayloadSize for Block {
+	fn payloadSize(&self) -> usize {
+		match self {
+			Block::Transaction(block) => block.content.payloadSize(),
+			Block::Proposer(block) => block.content.encoded_size(),
+			Block::Voter(block) => block.content.encoded_size(),
+		}
+	}
+}
+
+
+//encoded as the format version, then the block kind as the variant index, then the content with
+//its own version byte. The leading version lets a later format change the set of kinds.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Content{
+	TransactionBlock(TransactionBlock::Content),
+	ProposerBlock(ProposerBlock::Content),
+	VoterBlock(VoterBlock::Content)
+}
+
+impl Encode for Content {
+	fn size_hint(&self) -> usize {
+		2 + match self {
+			Content::TransactionBlock(content) => content.size_hint(),
+			Content::ProposerBlock(content) => content.size_hint(),
+			Content::VoterBlock(content) => content.size_hint(),
+		}
+	}
+
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		FormatVersion::CURRENT.encode_to(dest);
+		match self {
+			Content::TransactionBlock(content) => encodeVariant(dest, 0, content),
+			Content::ProposerBlock(content) => encodeVariant(dest, 1, content),
+			Content::VoterBlock(content) => encodeVariant(dest, 2, content),
+		}
+	}
+}
+
+impl EncodeLike for Content {}
+
+impl Decode for Content {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+		FormatVersion::decode(input)?;
+		match input.read_byte()? {
+			0 => Ok(Content::TransactionBlock(Decode::decode(input)?)),
+			1 => Ok(Content::ProposerBlock(Decode::decode(input)?)),
+			2 => Ok(Content::VoterBlock(Decode::decode(input)?)),
+			_ => Err("unknown block kind".into()),
+		}
+	}
+}
+
+impl Content{
+	//what the header's contentRoot commits to
+	pub fn hash(&self) -> H256{
+		match self {
+			Content::TransactionBlock(content) => content.hash(),
+			Content::ProposerBlock(content) => content.hash(),
+			Content::VoterBlock(content) => content.hash(),
+		}
+	}
+}
+
+//round trip every block type through its SCALE encoding
+#[cfg(test)]
+mod codecTests {
+	use super::*;
+	use codec::DecodeAll;
+	use proptest::prelude::*;
+	use sp_core::crypto::AccountId32 as AccountId;
+	use sp_runtime::{generic::Digest as HeaderDigest, DigestItem};
+
+	fn hash() -> impl Strategy<Value = H256> {
+		any::<[u8; 32]>().prop_map(H256::from)
+	}
+
+	fn hashes() -> impl Strategy<Value = Vec<H256>> {
+		prop::collection::vec(hash(), 0..16)
+	}
+
+	fn header() -> impl Strategy<Value = Header::BlockHeader> {
+		(hash(), any::<u128>(), any::<u32>(), hash(), any::<[u8; 32]>(), hash(), any::<[u8; 32]>(),
+			prop::collection::vec(prop::collection::vec(any::<u8>(), 0..64), 0..4))
+			.prop_map(|(parent, timestamp, nonce, contentRoot, extraContent, difficulty, coinbase, logs)| {
+				let mut header = Header::BlockHeader::new(parent, timestamp, nonce, contentRoot, ExtraContent::ExtraContent::fromBytes(extraContent), difficulty)
+					.with_coinbase(AccountId::new(coinbase));
+				header.digest = HeaderDigest { logs: logs.into_iter().map(DigestItem::Other).collect() };
+				header
+			})
+	}
+
+	fn transactionContent() -> impl Strategy<Value = TransactionBlock::Content> {
+		prop::collection::vec(prop::collection::vec(any::<u8>(), 0..64), 0..8).prop_map(|remarks| {
+			TransactionBlock::Content::new(remarks.into_iter()
+				.map(|remark| Argonaut_Runtime::UncheckedExtrinsic::new_unsigned(Argonaut_Runtime::SystemCall::remark { remark }.into()))
+				.collect())
+		})
+	}
+
+	fn proposerContent() -> impl Strategy<Value = ProposerBlock::Content> {
+		(hashes(), hashes()).prop_map(|(transactions, proposers)| ProposerBlock::Content::new(transactions, proposers))
+	}
+
+	fn voterContent() -> impl Strategy<Value = VoterBlock::Content> {
+		(any::<u16>(), hash(), hashes()).prop_map(|(chainNumber, parent, votes)| VoterBlock::Content::new(chainNumber, parent, votes))
+	}
+
+	fn content() -> impl Strategy<Value = Content> {
+		prop_oneof![
+			transactionContent().prop_map(Content::TransactionBlock),
+			proposerContent().prop_map(Content::ProposerBlock),
+			voterContent().prop_map(Content::VoterBlock),
+		]
+	}
+
+	fn block() -> impl Strategy<Value = Block> {
+		(header(), content(), hashes()).prop_map(|(header, content, sortitionProof)| Block::fromParts(header, content, sortitionProof))
+	}
+
+	fn roundTrip<T: Encode + DecodeAll + PartialEq + core::fmt::Debug>(value: &T) {
+		let encoded = value.encode();
+		assert_eq!(encoded[0], BLOCK_FORMAT_VERSION);
+		assert_eq!(T::decode_all(&mut &encoded[..]).ok().as_ref(), Some(value));
+	}
+
+	proptest! {
+		#[test]
+		fn headerRoundTrip(header in header()) {
+			roundTrip(&header);
+		}
+
+		#[test]
+		fn contentRoundTrip(transaction in transactionContent(), proposer in proposerContent(), voter in voterContent()) {
+			roundTrip(&transaction);
+			roundTrip(&proposer);
+			roundTrip(&voter);
+		}
+
+		#[test]
+		fn blockRoundTrip(block in block()) {
+			//the version comes first, then the kind, then the header with its own version
+			let encoded = block.encode();
+			prop_assert_eq!(encoded[0], BLOCK_FORMAT_VERSION);
+			let kind = match block.kind() { BlockKind::Transaction => 0, BlockKind::Proposer => 1, BlockKind::Voter(_) => 2 };
+			prop_assert_eq!(encoded[1], kind);
+			prop_assert_eq!(encoded[2], BLOCK_FORMAT_VERSION);
+			prop_assert_eq!(Block::decode_all(&mut &encoded[..]).ok(), Some(block.clone()));
+			let (header, content, sortitionProof) = block.clone().intoParts();
+			prop_assert_eq!(Block::fromParts(header, content, sortitionProof), block);
+		}
+
+		#[test]
+		fn contentEnumRoundTrip(content in content()) {
+			let encoded = content.encode();
+			prop_assert_eq!(encoded[0], BLOCK_FORMAT_VERSION);
+			prop_assert_eq!(encoded[2], BLOCK_FORMAT_VERSION);
+			prop_assert_eq!(Content::decode_all(&mut &encoded[..]).ok(), Some(content));
+		}
+
+		#[test]
+		fn unknownEnumVersionRejected(block in block(), version in 2u8..) {
+			let mut encoded = block.encode();
+			encoded[0] = version;
+			prop_assert!(Block::decode(&mut &encoded[..]).is_err());
+			let mut encoded = block.intoParts().1.encode();
+			encoded[0] = version;
+			prop_assert!(Content::decode(&mut &encoded[..]).is_err());
+		}
+
+		#[test]
+		fn unknownVersionRejected(header in header(), version in 2u8..) {
+			let mut encoded = header.encode();
+			encoded[0] = version;
+			prop_assert!(Header::BlockHeader::decode(&mut &encoded[..]).is_err());
+		}
+	}
+
+	#[test]
+	fn typedAccessors() {
+		let header = Header::BlockHeader::new(H256::zero(), 1, 0, H256::zero(), ExtraContent::ExtraContent::default(), H256::zero());
+		let references = vec![H256::repeat_byte(1)];
+		let proposer = Block::fromParts(header.clone(), Content::ProposerBlock(ProposerBlock::Content::new(references.clone(), Vec::new())), Vec::new());
+		assert_eq!(proposer.kind(), BlockKind::Proposer);
+		assert_eq!(proposer.transactionReferences(), Some(&references[..]));
+		assert_eq!(proposer.votes(), None);
+		assert_eq!(proposer.extrinsics(), None);
+		assert!(proposer.toSubstrate(1, H256::zero()).is_none());
+
+		let voter = Block::fromParts(header.clone(), Content::VoterBlock(VoterBlock::Content::new(3, H256::zero(), references.clone())), Vec::new());
+		assert_eq!(voter.kind(), BlockKind::Voter(3));
+		assert_eq!(voter.votes(), Some(&references[..]));
+		assert_eq!(voter.hash(), header.hash());
+	}
+
+	#[test]
+	fn transactionBlockToSubstrate() {
+		let extrinsics: Vec<Argonaut_Runtime::UncheckedExtrinsic> = (0..3u8)
+			.map(|i| Argonaut_Runtime::UncheckedExtrinsic::new_unsigned(Argonaut_Runtime::SystemCall::remark { remark: vec![i] }.into()))
+			.collect();
+		let content = TransactionBlock::Content::new(extrinsics.clone());
+		let header = Header::BlockHeader::new(H256::zero(), 1, 0, content.hash(), ExtraContent::ExtraContent::default(), H256::zero());
+		let block = Block::fromParts(header, Content::TransactionBlock(content), Vec::new());
+		assert_eq!(block.extrinsics(), Some(&extrinsics[..]));
+		let parent = H256::repeat_byte(7);
+		let substrate = block.toSubstrate(5, parent).unwrap();
+		assert_eq!(substrate.extrinsics, extrinsics);
+		assert_eq!(substrate.header.number, 5);
+		assert_eq!(substrate.header.parent_hash, parent);
+		assert_eq!(
+			substrate.header.extrinsics_root,
+			BlakeTwo256::ordered_trie_root(extrinsics.iter().map(Encode::encode).collect(), StateVersion::V0)
+		);
+		assert_eq!(substrate.header.digest.logs(), &[DigestItem::PreRuntime(PRISM_ENGINE_ID, block.hash().encode())]);
+	}
+
+	#[test]
+	fn typeInfoLeadsWithTheVersion() {
+		use scale_info::{MetaType, TypeDef};
+		for (info, kinds) in [
+			(Block::type_info(), BlockKindLayout::type_info()),
+			(Content::type_info(), ContentKindLayout::type_info()),
+		] {
+			let fields = match info.type_def {
+				TypeDef::Composite(composite) => composite.fields,
+				_ => panic!("not a composite"),
+			};
+			assert_eq!(fields.len(), 2);
+			assert_eq!(fields[0].ty, MetaType::new::<FormatVersion>());
+			let variants = match kinds.type_def {
+				TypeDef::Variant(variant) => variant.variants,
+				_ => panic!("not a variant"),
+			};
+			assert_eq!(variants.iter().map(|variant| variant.index).collect::<Vec<_>>(), vec![0, 1, 2]);
+		}
+		let encoded = Content::VoterBlock(VoterBlock::Content::default()).encode();
+		assert_eq!(encoded[..2], [BLOCK_FORMAT_VERSION, 2]);
+	}
+
+	#[test]
+	fn versionZeroRejected() {
+		let mut encoded = VoterBlock::Content::default().encode();
+		encoded[0] = 0;
+		assert!(VoterBlock::Content::decode(&mut &encoded[..]).is_err());
+	}
+}
@@ -0,0 +1,336 @@
+//Atomic writes of block imports.
+//
+//Everything an import changes in the database, the block itself, its levels and graph edges, the
+//unreferred sets and the votes, leaders and ledger it moves, is collected in one `ImportBatch` and
+//written as a single atomic write, see Store. The database never holds a block without its edges or
+//an edge to a block it does not hold.
+//
+//The batch also records the block as the last import, so each import is a single synced write and
+//the database is always at the end of some import.
+use codec::{Decode, Encode};
+use sp_core::H256;
+use crate::Block::Block;
+use super::Graph::Edge;
+use super::Index::{IndexedBlock, TreeIndex};
+use super::Ledger::{Ledger, LedgerChanges, LedgerUpdate};
+use super::Store::{ReadStore, Store};
+use super::*;
+
+const LAST_IMPORT_KEY: &[u8] = b"lastImport";
+pub const VOTER_LEDGER_TIPS_KEY: &[u8] = b"voterLedgerTips";
+pub const PROPOSER_LEDGER_TIP_KEY: &[u8] = b"proposerLedgerTip";
+
+//a put, or a delete when `value` is none
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct WriteOp {
+	pub cf: &'static str,
+	pub key: Vec<u8>,
+	pub value: Option<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ImportBatch {
+	ops: Vec<WriteOp>,
+}
+
+//levels are big endian so they sort in order
+pub fn levelKey(level: u64) -> [u8; 8] {
+	level.to_be_bytes()
+}
+
+//under the block pointed to, so the blocks pointing to one are a prefix scan, see Graph
+pub fn referrerKey(target: &H256, referrer: &H256, edge: Edge) -> [u8; 65] {
+	let mut key = [0u8; 65];
+	key[..32].copy_from_slice(target.as_bytes());
+	key[32..64].copy_from_slice(referrer.as_bytes());
+	key[64] = edge as u8;
+	key
+}
+
+pub fn voterLevelKey(chain: u16, level: u64) -> [u8; 10] {
+	let mut key = [0u8; 10];
+	key[..2].copy_from_slice(&chain.to_be_bytes());
+	key[2..].copy_from_slice(&level.to_be_bytes());
+	key
+}
+
+//`default` for a missing value, an error for one that does not decode
+fn decodeOr<T: Decode>(cf: &str, bytes: Option<Vec<u8>>, default: T) -> Result<T> {
+	match bytes {
+		Some(bytes) => decodeValue(cf, &bytes),
+		None => Ok(default),
+	}
+}
+
+impl ImportBatch {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn put<V: Encode>(&mut self, cf: &'static str, key: impl AsRef<[u8]>, value: &V) {
+		self.ops.push(WriteOp { cf, key: key.as_ref().to_vec(), value: Some(value.encode()) });
+	}
+
+	pub fn delete(&mut self, cf: &'static str, key: impl AsRef<[u8]>) {
+		self.ops.push(WriteOp { cf, key: key.as_ref().to_vec(), value: None });
+	}
+
+	pub fn ops(&self) -> &[WriteOp] {
+		&self.ops
+	}
+
+	//the ops of `other` after those of this batch
+	pub fn append(&mut self, other: ImportBatch) {
+		self.ops.extend(other.ops);
+	}
+
+	//what the batch leaves at `key`: Some(None) for a delete, None if the batch does not touch it
+	pub fn get(&self, cf: &str, key: impl AsRef<[u8]>) -> Option<Option<&[u8]>> {
+		self.ops.iter().rev().find(|op| op.cf == cf && op.key == key.as_ref()).map(|op| op.value.as_deref())
+	}
+
+	//the block and everything that hangs off it. `index` already holds the block and `previous`
+	//reads the database as it is before the batch
+	pub fn block(
+		&mut self,
+		block: &Block,
+		indexed: &IndexedBlock,
+		index: &TreeIndex,
+		previous: impl Fn(&'static str, &[u8]) -> Result<Option<Vec<u8>>>,
+	) -> Result<()> {
+		let hash = block.hash();
+		let level = indexed.level;
+		self.put(BLOCK_CF, hash, block);
+		self.put(PARENT_NEIGHBOR_CF, hash, &block.header().parent);
+		match block {
+			Block::Proposer(proposer) => {
+				self.put(PROPOSER_NODE_LEVEL_CF, hash, &level);
+				self.put(PROPOSER_TREE_LEVEL_CF, levelKey(level), &index.proposerLevel(level).to_vec());
+				self.put(TRANSACTION_REF_NEIGHBOR_CF, hash, &proposer.content.transactionReferences);
+				self.put(PROPOSER_REF_NEIGHBOR_CF, hash, &proposer.content.proposerReferences);
+				for reference in &proposer.content.transactionReferences {
+					self.delete(UNREFERRED_TRANSACTION_CF, reference);
+				}
+				for reference in &proposer.content.proposerReferences {
+					self.delete(UNREFERRED_PROPOSER_CF, reference);
+				}
+				self.put(UNREFERRED_PROPOSER_CF, hash, &proposer.header.timestamp);
+			},
+			Block::Voter(voter) => {
+				let chain = voter.content.chainNumber;
+				let countKey = voterLevelKey(chain, level);
+				let count: u64 = decodeOr(VOTER_TREE_LEVEL_COUNT_CF, previous(VOTER_TREE_LEVEL_COUNT_CF, &countKey)?, 0)?;
+				//a voter block votes on the levels after those its chain already voted on
+				let parentVoted: u64 = if voter.content.parent.is_zero() {
+					0
+				} else {
+					decodeOr(VOTER_NODE_VOTED_LEVEL_CF, previous(VOTER_NODE_VOTED_LEVEL_CF, voter.content.parent.as_bytes())?, 0)?
+				};
+				let voted = voter.content.votes.iter()
+					.filter_map(|vote| index.get(vote).map(|vote| vote.level))
+					.fold(parentVoted, u64::max);
+				self.put(VOTER_NODE_LEVEL_CF, hash, &level);
+				self.put(VOTER_NODE_CHAIN_CF, hash, &chain);
+				self.put(VOTER_TREE_LEVEL_COUNT_CF, countKey, &(count + 1));
+				self.put(VOTER_NODE_VOTED_LEVEL_CF, hash, &voted);
+				self.put(VOTER_PARENT_NEIGHBOR_CF, hash, &voter.content.parent);
+				self.put(VOTE_NEIGHBOR_CF, hash, &voter.content.votes);
+			},
+			Block::Transaction(transaction) => {
+				self.put(UNREFERRED_TRANSACTION_CF, hash, &transaction.header.timestamp);
+			},
+		}
+		self.referrers(block);
+		Ok(())
+	}
+
+	//the block's edges backward, under each block it points to, see Graph
+	fn referrers(&mut self, block: &Block) {
+		let hash = block.hash();
+		let mut edges = vec![(block.header().parent, Edge::Parent)];
+		match block {
+			Block::Proposer(proposer) => {
+				edges.extend(proposer.content.transactionReferences.iter().map(|reference| (*reference, Edge::TransactionReference)));
+				edges.extend(proposer.content.proposerReferences.iter().map(|reference| (*reference, Edge::ProposerReference)));
+			},
+			Block::Voter(voter) => {
+				edges.push((voter.content.parent, Edge::VoterParent));
+				edges.extend(voter.content.votes.iter().map(|vote| (*vote, Edge::Vote)));
+			},
+			Block::Transaction(_) => {},
+		}
+		for (target, edge) in edges {
+			//genesis blocks point to zero
+			if target.is_zero() {
+				continue;
+			}
+			self.put(REFERRER_NEIGHBOR_CF, referrerKey(&target, &hash, edge), &());
+		}
+	}
+
+	//the part of the ledger `changes` and `update` touched
+	pub fn ledger(&mut self, ledger: &Ledger, changes: &LedgerChanges, update: &LedgerUpdate) {
+		for proposer in &changes.proposers {
+			let votes = ledger.votesOn(proposer);
+			if votes.is_empty() {
+				self.delete(PROPOSER_NODE_VOTE_CF, proposer);
+				self.delete(PROPOSER_VOTE_COUNT_CF, proposer);
+			} else {
+				self.put(PROPOSER_NODE_VOTE_CF, proposer, &votes.to_vec());
+				self.put(PROPOSER_VOTE_COUNT_CF, proposer, &ledger.voteCount(proposer));
+			}
+		}
+		for level in &changes.leaderLevels {
+			match ledger.leader(*level) {
+				Some(leader) => self.put(PROPOSER_LEADER_SEQUENCE_CF, levelKey(*level), &leader),
+				None => self.delete(PROPOSER_LEADER_SEQUENCE_CF, levelKey(*level)),
+			}
+		}
+		//a level both removed and added ends up with the added entry
+		for (level, _) in &update.removed {
+			self.delete(PROPOSER_LEDGER_ORDER_CF, levelKey(*level));
+		}
+		for (level, blocks) in &update.added {
+			self.put(PROPOSER_LEDGER_ORDER_CF, levelKey(*level), blocks);
+		}
+		if !changes.voterChains.is_empty() {
+			self.put(META_CF, VOTER_LEDGER_TIPS_KEY, &ledger.voterTips().to_vec());
+		}
+		if !update.isEmpty() {
+			self.put(META_CF, PROPOSER_LEDGER_TIP_KEY, &ledger.proposerLedgerTip());
+		}
+	}
+
+	//record `hash` as the last import, written with the rest of the batch
+	pub fn imported(&mut self, hash: H256) {
+		self.put(META_CF, LAST_IMPORT_KEY, &hash);
+	}
+
+	//apply the batch in one write
+	pub fn write(self, store: &impl Store) -> Result<()> {
+		store.write(self.ops)
+	}
+}
+
+//the last block whose import batch was written
+pub fn lastImport(store: &impl ReadStore) -> Result<Option<H256>> {
+	store.get(META_CF, LAST_IMPORT_KEY)?.map(|bytes| decodeOr(META_CF, Some(bytes), H256::zero())).transpose()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::Fixtures::block;
+	use crate::Block::{Content, ProposerBlock, TransactionBlock, VoterBlock};
+	use super::super::Ledger::LedgerView;
+	use super::super::Store::MemoryStore;
+
+	fn insert(index: &mut TreeIndex, batch: &mut ImportBatch, block: &Block) -> H256 {
+		let indexed = index.insert(block).unwrap();
+		batch.block(block, &indexed, index, |_, _| Ok(None)).unwrap();
+		block.hash()
+	}
+
+	#[test]
+	fn blockWritesEdgesAndUnreferredSets() {
+		let mut index = TreeIndex::new();
+		let mut batch = ImportBatch::new();
+		let genesis = insert(&mut index, &mut batch, &block(H256::zero(), 0, Content::ProposerBlock(ProposerBlock::Content::new(Vec::new(), Vec::new()))));
+		let transaction = insert(&mut index, &mut batch, &block(genesis, 1, Content::TransactionBlock(TransactionBlock::Content::new(Vec::new()))));
+		assert_eq!(batch.get(UNREFERRED_TRANSACTION_CF, transaction), Some(Some(&1u128.encode()[..])));
+
+		let proposer = block(genesis, 2, Content::ProposerBlock(ProposerBlock::Content::new(vec![transaction], vec![genesis])));
+		let hash = insert(&mut index, &mut batch, &proposer);
+		assert_eq!(batch.get(UNREFERRED_TRANSACTION_CF, transaction), Some(None));
+		assert_eq!(batch.get(UNREFERRED_PROPOSER_CF, genesis), Some(None));
+		assert_eq!(batch.get(PROPOSER_NODE_LEVEL_CF, hash), Some(Some(&1u64.encode()[..])));
+		assert_eq!(batch.get(PROPOSER_TREE_LEVEL_CF, levelKey(1)), Some(Some(&vec![hash].encode()[..])));
+		assert_eq!(batch.get(PARENT_NEIGHBOR_CF, hash), Some(Some(&genesis.encode()[..])));
+		assert_eq!(batch.get(TRANSACTION_REF_NEIGHBOR_CF, hash), Some(Some(&vec![transaction].encode()[..])));
+		assert_eq!(batch.get(BLOCK_CF, hash), Some(Some(&proposer.encode()[..])));
+		for (target, referrer, edge) in [(genesis, transaction, Edge::Parent), (genesis, hash, Edge::Parent), (genesis, hash, Edge::ProposerReference), (transaction, hash, Edge::TransactionReference)] {
+			assert_eq!(batch.get(REFERRER_NEIGHBOR_CF, referrerKey(&target, &referrer, edge)), Some(Some(&[][..])));
+		}
+	}
+
+	#[test]
+	fn voterCountsBuildOnTheDatabase() {
+		let mut index = TreeIndex::new();
+		let mut batch = ImportBatch::new();
+		let genesis = insert(&mut index, &mut batch, &block(H256::zero(), 0, Content::ProposerBlock(ProposerBlock::Content::new(Vec::new(), Vec::new()))));
+		let proposer = insert(&mut index, &mut batch, &block(genesis, 1, Content::ProposerBlock(ProposerBlock::Content::new(Vec::new(), vec![genesis]))));
+		let voterGenesis = insert(&mut index, &mut batch, &block(genesis, 2, Content::VoterBlock(VoterBlock::Content::new(0, H256::zero(), Vec::new()))));
+
+		let voter = block(proposer, 3, Content::VoterBlock(VoterBlock::Content::new(0, voterGenesis, vec![proposer])));
+		let indexed = index.insert(&voter).unwrap();
+		let mut batch = ImportBatch::new();
+		batch.block(&voter, &indexed, &index, |cf, _| Ok((cf == VOTER_TREE_LEVEL_COUNT_CF).then(|| 2u64.encode()))).unwrap();
+		let hash = voter.hash();
+		assert_eq!(batch.get(VOTER_TREE_LEVEL_COUNT_CF, voterLevelKey(0, 1)), Some(Some(&3u64.encode()[..])));
+		assert_eq!(batch.get(VOTER_NODE_VOTED_LEVEL_CF, hash), Some(Some(&1u64.encode()[..])));
+		assert_eq!(batch.get(VOTER_PARENT_NEIGHBOR_CF, hash), Some(Some(&voterGenesis.encode()[..])));
+		assert_eq!(batch.get(VOTE_NEIGHBOR_CF, hash), Some(Some(&vec![proposer].encode()[..])));
+	}
+
+	struct View;
+
+	impl LedgerView for View {
+		fn level(&self, hash: &H256) -> Option<u64> {
+			Some(hash.as_bytes()[0] as u64 / 10)
+		}
+		fn voterParent(&self, _: &H256) -> Option<H256> {
+			Some(H256::repeat_byte(1))
+		}
+		fn votes(&self, _: &H256) -> Option<Vec<H256>> {
+			Some(vec![H256::repeat_byte(10)])
+		}
+		fn proposerReferences(&self, _: &H256) -> Option<Vec<H256>> {
+			Some(Vec::new())
+		}
+		fn proposerLevel(&self, level: u64) -> Vec<H256> {
+			vec![H256::repeat_byte(level as u8 * 10)]
+		}
+	}
+
+	#[test]
+	fn lastImportWrittenWithTheBatch() {
+		let store = MemoryStore::new();
+		let hash = H256::repeat_byte(1);
+		let mut batch = ImportBatch::new();
+		batch.put(BLOCK_CF, hash, &1u8);
+		batch.imported(hash);
+		assert_eq!(batch.ops().len(), 2);
+		assert_eq!(lastImport(&store), Ok(None));
+		batch.write(&store).unwrap();
+		assert_eq!(lastImport(&store), Ok(Some(hash)));
+	}
+
+	#[test]
+	fn corruptValuesRejected() {
+		let mut index = TreeIndex::new();
+		let mut batch = ImportBatch::new();
+		let genesis = insert(&mut index, &mut batch, &block(H256::zero(), 0, Content::ProposerBlock(ProposerBlock::Content::new(Vec::new(), Vec::new()))));
+		let voterGenesis = insert(&mut index, &mut batch, &block(genesis, 1, Content::VoterBlock(VoterBlock::Content::new(0, H256::zero(), Vec::new()))));
+		let voter = block(genesis, 2, Content::VoterBlock(VoterBlock::Content::new(0, voterGenesis, Vec::new())));
+		let indexed = index.insert(&voter).unwrap();
+		let corrupt = batch.block(&voter, &indexed, &index, |cf, _| Ok((cf == VOTER_TREE_LEVEL_COUNT_CF).then(|| vec![1u8])));
+		assert!(matches!(corrupt, Err(StoreError(message)) if message.starts_with(VOTER_TREE_LEVEL_COUNT_CF)));
+	}
+
+	#[test]
+	fn ledgerWritesWhatChanged() {
+		//one voter chain, its tip at level 1 votes for the only proposer block at level 1
+		let mut ledger = Ledger::new(H256::zero(), vec![H256::repeat_byte(1)]);
+		let update = ledger.setVoterTip(&View, 0, H256::repeat_byte(11)).unwrap();
+		let changes = ledger.takeChanges();
+		let mut batch = ImportBatch::new();
+		batch.ledger(&ledger, &changes, &update);
+		let leader = H256::repeat_byte(10);
+		assert_eq!(batch.get(PROPOSER_VOTE_COUNT_CF, leader), Some(Some(&1u64.encode()[..])));
+		assert_eq!(batch.get(PROPOSER_NODE_VOTE_CF, leader), Some(Some(&vec![(0u16, 1u64)].encode()[..])));
+		assert_eq!(batch.get(PROPOSER_LEADER_SEQUENCE_CF, levelKey(1)), Some(Some(&leader.encode()[..])));
+		assert_eq!(batch.get(PROPOSER_LEDGER_ORDER_CF, levelKey(1)), Some(Some(&vec![leader].encode()[..])));
+		assert_eq!(batch.get(META_CF, PROPOSER_LEDGER_TIP_KEY), Some(Some(&1u64.encode()[..])));
+		assert_eq!(batch.get(META_CF, VOTER_LEDGER_TIPS_KEY), Some(Some(&vec![H256::repeat_byte(11)].encode()[..])));
+	}
+}
@@ -0,0 +1,9 @@
+//Blocks for the tests of the tree.
+use sp_core::H256;
+use crate::Block::{Block, Content, ExtraContent::ExtraContent, Header::BlockHeader};
+
+//a block on `parent` holding `content`, nonce keeps siblings with the same parent and content apart
+pub fn block(parent: H256, nonce: u32, content: Content) -> Block {
+	let header = BlockHeader::new(parent, 1, nonce, content.hash(), ExtraContent::default(), H256::zero());
+	Block::fromParts(header, content, Vec::new())
+}
@@ -0,0 +1,336 @@
+//Queries over the block DAG.
+//
+//Every edge a block has to another block is stored twice: forward under the block, in the neighbor
+//column families, and backward under the block it points to, in REFERRER_NEIGHBOR_CF. The forward
+//edges give ancestors and reference closures, the backward ones give referrers, descendants and
+//the voters of a level. Edges of pruned blocks stay, so the walks go through them.
+//
+//A backward edge is a key of its own, the target, the referrer and the edge kind, see
+//Batch::referrerKey. Writing one never reads or rewrites the others, and the referrers of a block
+//are a prefix scan.
+//
+//Everything here reads one view of the store, see BlockTree for the consistent entry points.
+use codec::{Decode, Encode};
+use sp_core::H256;
+use std::collections::{HashSet, VecDeque};
+use super::Batch::{levelKey, referrerKey, ImportBatch};
+use super::Migration::{MigrationProgress, MigrationWrite};
+use super::Store::{ReadStore, StoreError};
+use super::*;
+
+//how one block points to another
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Encode, Decode)]
+pub enum Edge {
+	//the proposer parent in the header, every block has one
+	Parent,
+	//the previous block of a voter chain
+	VoterParent,
+	//a voter block's vote on a proposer block
+	Vote,
+	//a proposer block's reference to a transaction block
+	TransactionReference,
+	//a proposer block's reference to another proposer block
+	ProposerReference,
+}
+
+pub const ALL_EDGES: &[Edge] = &[Edge::Parent, Edge::VoterParent, Edge::Vote, Edge::TransactionReference, Edge::ProposerReference];
+
+impl Edge {
+	//where the forward edges of this kind are stored, with one target or a list of them
+	pub fn column(self) -> (&'static str, bool) {
+		match self {
+			Edge::Parent => (PARENT_NEIGHBOR_CF, false),
+			Edge::VoterParent => (VOTER_PARENT_NEIGHBOR_CF, false),
+			Edge::Vote => (VOTE_NEIGHBOR_CF, true),
+			Edge::TransactionReference => (TRANSACTION_REF_NEIGHBOR_CF, true),
+			Edge::ProposerReference => (PROPOSER_REF_NEIGHBOR_CF, true),
+		}
+	}
+}
+
+//a block pointing to the one it is stored under
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Encode, Decode)]
+pub struct Referrer {
+	pub block: H256,
+	pub edge: Edge,
+}
+
+//everything a proposer block confirms once it leads a level: the proposer blocks it reaches by
+//proposer references, itself first, and the transaction blocks they refer to
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ReferenceClosure {
+	pub proposers: Vec<H256>,
+	pub transactions: Vec<H256>,
+}
+
+fn decode<T: Decode>(cf: &'static str, bytes: &[u8]) -> Result<T> {
+	T::decode(&mut &bytes[..]).map_err(|e| StoreError(format!("{}: {}", cf, e)))
+}
+
+//the blocks `hash` points to by `edge`. Genesis blocks point to zero, which is left out
+pub fn targets(view: &dyn ReadStore, hash: &H256, edge: Edge) -> Result<Vec<H256>> {
+	let (cf, many) = edge.column();
+	let targets = match view.get(cf, hash.as_bytes())? {
+		Some(bytes) if many => decode(cf, &bytes)?,
+		Some(bytes) => vec![decode(cf, &bytes)?],
+		None => Vec::new(),
+	};
+	Ok(targets.into_iter().filter(|target: &H256| !target.is_zero()).collect())
+}
+
+//the blocks pointing to `hash`, by block hash and then edge kind
+pub fn referrers(view: &dyn ReadStore, hash: &H256) -> Result<Vec<Referrer>> {
+	view.scanPrefix(REFERRER_NEIGHBOR_CF, hash.as_bytes())?
+		.into_iter()
+		.map(|(key, _)| match key.len() {
+			65 => Ok(Referrer { block: H256::from_slice(&key[32..64]), edge: decode(REFERRER_NEIGHBOR_CF, &key[64..])? }),
+			_ => Err(StoreError(format!("{}: key {:02x?} is not a referrer", REFERRER_NEIGHBOR_CF, key))),
+		})
+		.collect()
+}
+
+//breadth first from `hash` along `next`, nearest first and each block once, `hash` left out
+fn walk(hash: &H256, depth: u64, mut next: impl FnMut(&H256) -> Result<Vec<H256>>) -> Result<Vec<H256>> {
+	let mut seen = HashSet::from([*hash]);
+	let mut queue = VecDeque::from([(*hash, 0)]);
+	let mut found = Vec::new();
+	while let Some((block, distance)) = queue.pop_front() {
+		if distance == depth {
+			continue;
+		}
+		for neighbor in next(&block)? {
+			if seen.insert(neighbor) {
+				found.push(neighbor);
+				queue.push_back((neighbor, distance + 1));
+			}
+		}
+	}
+	Ok(found)
+}
+
+//the blocks `hash` reaches by at most `depth` forward edges of the given kinds
+pub fn ancestors(view: &dyn ReadStore, hash: &H256, depth: u64, edges: &[Edge]) -> Result<Vec<H256>> {
+	walk(hash, depth, |block| {
+		let mut next = Vec::new();
+		for edge in edges {
+			next.extend(targets(view, block, *edge)?);
+		}
+		Ok(next)
+	})
+}
+
+//the blocks reaching `hash` by at most `depth` edges of the given kinds
+pub fn descendants(view: &dyn ReadStore, hash: &H256, depth: u64, edges: &[Edge]) -> Result<Vec<H256>> {
+	walk(hash, depth, |block| {
+		Ok(referrers(view, block)?.into_iter().filter(|referrer| edges.contains(&referrer.edge)).map(|referrer| referrer.block).collect())
+	})
+}
+
+pub fn referenceClosure(view: &dyn ReadStore, proposer: &H256) -> Result<ReferenceClosure> {
+	let mut proposers = vec![*proposer];
+	proposers.extend(ancestors(view, proposer, u64::MAX, &[Edge::ProposerReference])?);
+	let mut seen = HashSet::new();
+	let mut transactions = Vec::new();
+	for proposer in &proposers {
+		for transaction in targets(view, proposer, Edge::TransactionReference)? {
+			if seen.insert(transaction) {
+				transactions.push(transaction);
+			}
+		}
+	}
+	Ok(ReferenceClosure { proposers, transactions })
+}
+
+//every voter block voting on a proposer block of `level`, main chain or not, with the proposer
+//block it voted on
+pub fn levelVoters(view: &dyn ReadStore, level: u64) -> Result<Vec<(H256, H256)>> {
+	let proposers: Vec<H256> = match view.get(PROPOSER_TREE_LEVEL_CF, &levelKey(level))? {
+		Some(bytes) => decode(PROPOSER_TREE_LEVEL_CF, &bytes)?,
+		None => return Ok(Vec::new()),
+	};
+	let mut voters = Vec::new();
+	for proposer in proposers {
+		for referrer in referrers(view, &proposer)? {
+			if referrer.edge == Edge::Vote {
+				voters.push((referrer.block, proposer));
+			}
+		}
+	}
+	Ok(voters)
+}
+
+//blocks whose backward edges rebuildReferrers writes in one batch, what bounds the memory it takes
+pub const REBUILD_PAGE_BLOCKS: usize = 10_000;
+
+//REFERRER_NEIGHBOR_CF rebuilt from the forward edges, for stores written before it existed. Every
+//block has a parent edge, so the blocks are walked in pages of PARENT_NEIGHBOR_CF and the backward
+//edges of each page go to `write` in a batch of their own, with the blocks done out of the total
+pub fn rebuildReferrers(view: &dyn ReadStore, write: &mut MigrationWrite) -> Result<()> {
+	rebuildReferrersIn(view, write, REBUILD_PAGE_BLOCKS)
+}
+
+fn rebuildReferrersIn(view: &dyn ReadStore, write: &mut MigrationWrite, pageBlocks: usize) -> Result<()> {
+	let total = countKeys(view, PARENT_NEIGHBOR_CF, pageBlocks)?;
+	let mut done = 0;
+	write(ImportBatch::new(), MigrationProgress { done, total })?;
+	let mut from = Vec::new();
+	loop {
+		let page = view.scanPage(PARENT_NEIGHBOR_CF, &from, pageBlocks)?;
+		let next = match page.last() {
+			Some((last, _)) => after(last),
+			None => return Ok(()),
+		};
+		let mut batch = ImportBatch::new();
+		for (block, _) in &page {
+			let block = H256::from_slice(block);
+			for edge in ALL_EDGES {
+				for target in targets(view, &block, *edge)? {
+					batch.put(REFERRER_NEIGHBOR_CF, referrerKey(&target, &block, *edge), &());
+				}
+			}
+		}
+		done += page.len() as u64;
+		write(batch, MigrationProgress { done, total })?;
+		if page.len() < pageBlocks {
+			return Ok(());
+		}
+		from = next;
+	}
+}
+
+fn countKeys(view: &dyn ReadStore, cf: &'static str, pageSize: usize) -> Result<u64> {
+	let mut count = 0;
+	let mut from = Vec::new();
+	loop {
+		let page = view.scanPage(cf, &from, pageSize)?;
+		count += page.len() as u64;
+		match page.last() {
+			Some((last, _)) if page.len() == pageSize => from = after(last),
+			_ => return Ok(count),
+		}
+	}
+}
+
+//the first key after `key`
+fn after(key: &[u8]) -> Vec<u8> {
+	let mut next = key.to_vec();
+	next.push(0);
+	next
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::Store::{MemoryStore, Store};
+	use super::super::Prune::PruningMode;
+	use super::super::Fixtures::block;
+	use crate::Block::{Block, Content, ProposerBlock, TransactionBlock, VoterBlock};
+	use crate::Genesis::{GenesisBlocks, PrismGenesis};
+
+	fn insert(tree: &BlockTree<MemoryStore>, block: Block) -> H256 {
+		let hash = block.hash();
+		tree.insert(block).unwrap();
+		hash
+	}
+
+	//genesis <- a <- b on the proposer tree, b referring to a and two transaction blocks, and a
+	//voter block of each of the two chains voting on a and b
+	struct Dag {
+		tree: BlockTree<MemoryStore>,
+		genesis: GenesisBlocks,
+		transactions: [H256; 2],
+		proposers: [H256; 2],
+		voters: [H256; 2],
+	}
+
+	fn dag() -> Dag {
+		let genesis = PrismGenesis { voterChains: 2, timestamp: 1, difficulty: H256::repeat_byte(0xff) }.blocks().unwrap();
+		let tree = BlockTree::new(MemoryStore::new(), &genesis, PruningMode::Archive).unwrap();
+		let g = genesis.proposerHash();
+		let t0 = insert(&tree, block(g, 1, Content::TransactionBlock(TransactionBlock::Content::new(Vec::new()))));
+		let t1 = insert(&tree, block(g, 2, Content::TransactionBlock(TransactionBlock::Content::new(Vec::new()))));
+		let a = insert(&tree, block(g, 3, Content::ProposerBlock(ProposerBlock::Content::new(vec![t0], vec![g]))));
+		let b = insert(&tree, block(a, 4, Content::ProposerBlock(ProposerBlock::Content::new(vec![t0, t1], vec![a]))));
+		let v0 = insert(&tree, block(b, 5, Content::VoterBlock(VoterBlock::Content::new(0, genesis.voters[0].hash(), vec![a, b]))));
+		let v1 = insert(&tree, block(a, 6, Content::VoterBlock(VoterBlock::Content::new(1, genesis.voters[1].hash(), vec![a]))));
+		Dag { tree, genesis, transactions: [t0, t1], proposers: [a, b], voters: [v0, v1] }
+	}
+
+	#[test]
+	fn referrersCoverEveryEdge() {
+		let dag = dag();
+		let [a, b] = dag.proposers;
+		let [v0, v1] = dag.voters;
+		let mut expected = vec![
+			Referrer { block: b, edge: Edge::Parent },
+			Referrer { block: b, edge: Edge::ProposerReference },
+			Referrer { block: v0, edge: Edge::Vote },
+			Referrer { block: v1, edge: Edge::Parent },
+			Referrer { block: v1, edge: Edge::Vote },
+		];
+		expected.sort_by_key(|referrer| (referrer.block, referrer.edge as u8));
+		assert_eq!(dag.tree.referrers(&a), Ok(expected));
+		assert_eq!(dag.tree.referrers(&dag.transactions[1]), Ok(vec![Referrer { block: b, edge: Edge::TransactionReference }]));
+		assert_eq!(dag.tree.referrers(&v0), Ok(Vec::new()));
+	}
+
+	#[test]
+	fn walksStopAtTheDepth() {
+		let dag = dag();
+		let g = dag.genesis.proposerHash();
+		let [a, b] = dag.proposers;
+		let [v0, _] = dag.voters;
+		assert_eq!(dag.tree.ancestors(&v0, 1, &[Edge::Parent]), Ok(vec![b]));
+		assert_eq!(dag.tree.ancestors(&v0, 10, &[Edge::Parent]), Ok(vec![b, a, g]));
+		assert_eq!(dag.tree.ancestors(&v0, 1, ALL_EDGES), Ok(vec![b, dag.genesis.voters[0].hash(), a]));
+		assert_eq!(dag.tree.descendants(&a, 1, &[Edge::Parent]), Ok(vec![b, dag.voters[1]]));
+		assert_eq!(dag.tree.descendants(&a, 2, &[Edge::Parent]), Ok(vec![b, dag.voters[1], v0]));
+		assert_eq!(dag.tree.ancestors(&a, 0, ALL_EDGES), Ok(Vec::new()));
+	}
+
+	#[test]
+	fn closureFollowsProposerReferences() {
+		let dag = dag();
+		let [a, b] = dag.proposers;
+		let [t0, t1] = dag.transactions;
+		assert_eq!(dag.tree.referenceClosure(&b), Ok(ReferenceClosure {
+			proposers: vec![b, a, dag.genesis.proposerHash()],
+			transactions: vec![t0, t1],
+		}));
+	}
+
+	#[test]
+	fn votersOfALevel() {
+		let dag = dag();
+		let [a, b] = dag.proposers;
+		let [v0, v1] = dag.voters;
+		assert_eq!(dag.tree.levelVoters(1), Ok(vec![(v0, a), (v1, a)]));
+		assert_eq!(dag.tree.levelVoters(2), Ok(vec![(v0, b)]));
+		assert_eq!(dag.tree.levelVoters(3), Ok(Vec::new()));
+	}
+
+	#[test]
+	fn referrersRebuiltInPages() {
+		let dag = dag();
+		let store = dag.tree.store();
+		let written = store.scan(REFERRER_NEIGHBOR_CF).unwrap();
+		let blocks = store.scan(PARENT_NEIGHBOR_CF).unwrap().len() as u64;
+		let mut cleared = ImportBatch::new();
+		for (key, _) in &written {
+			cleared.delete(REFERRER_NEIGHBOR_CF, key.clone());
+		}
+		store.write(cleared.ops().to_vec()).unwrap();
+
+		let mut reported = Vec::new();
+		store.consistent(|view| rebuildReferrersIn(view, &mut |batch: ImportBatch, progress| {
+			reported.push(progress);
+			store.write(batch.ops().to_vec())
+		}, 2)).unwrap();
+		let done: Vec<u64> = reported.iter().map(|progress| progress.done).collect();
+		let mut expected: Vec<u64> = (0..blocks).step_by(2).collect();
+		expected.push(blocks);
+		assert_eq!(done, expected);
+		assert!(reported.iter().all(|progress| progress.total == blocks));
+		assert_eq!(store.scan(REFERRER_NEIGHBOR_CF).unwrap(), written);
+	}
+}
@@ -0,0 +1,335 @@
+//In-memory indexes of the block tree, updated on every insert.
+//
+//Proposer blocks form one tree through their header's parent and every voter chain its own
+//through the content's voter parent. Transaction blocks are in no tree, they are only indexed by
+//level. A block's level is its depth in its tree, genesis blocks (parent zero) are at level 0, and
+//a transaction block is at the level after its proposer parent, the first level a proposer block
+//could refer to it from.
+use sp_core::H256;
+use std::collections::{HashMap, HashSet};
+use crate::Block::{Block, BlockKind};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Ord, PartialOrd)]
+pub enum TreeId {
+	Proposer,
+	Voter(u16),
+}
+
+impl TreeId {
+	pub fn of(kind: BlockKind) -> Option<Self> {
+		match kind {
+			BlockKind::Proposer => Some(TreeId::Proposer),
+			BlockKind::Voter(chain) => Some(TreeId::Voter(chain)),
+			BlockKind::Transaction => None,
+		}
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IndexError {
+	AlreadyKnown,
+	//the parent in the block's tree, or the proposer parent of a transaction block, is not indexed
+	UnknownParent(H256),
+	//the parent is indexed but not in the block's tree: a voter parent on another voter chain or
+	//not a voter block, or a proposer parent that is not a proposer block
+	WrongParent(H256),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct IndexedBlock {
+	pub kind: BlockKind,
+	pub level: u64,
+	//zero for genesis blocks and transaction blocks
+	pub treeParent: H256,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct TreeIndex {
+	blocks: HashMap<H256, IndexedBlock>,
+	//tree parent to its children, in insertion order
+	children: HashMap<H256, Vec<H256>>,
+	leaves: HashMap<TreeId, HashSet<H256>>,
+	//highest block of each tree, the first one seen wins a tie
+	best: HashMap<TreeId, (H256, u64)>,
+	//proposer blocks by level, in insertion order. Every import writes its level's list to
+	//PROPOSER_TREE_LEVEL_CF, see Batch, and BlockTree::open restores them from there
+	proposerLevels: Vec<Vec<H256>>,
+}
+
+//the parent a block hangs off in its tree
+fn treeParent(block: &Block) -> H256 {
+	match block {
+		Block::Proposer(block) => block.header.parent,
+		Block::Voter(block) => block.content.parent,
+		Block::Transaction(_) => H256::zero(),
+	}
+}
+
+impl TreeIndex {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	//where `block` would go, without indexing it. Every parent the block names has to be indexed and
+	//of the right kind: the tree parent in the block's own tree, and the proposer parent of voter and
+	//transaction blocks a proposer block. Genesis blocks name the zero hash.
+	pub fn place(&self, block: &Block) -> Result<IndexedBlock, IndexError> {
+		if self.blocks.contains_key(&block.hash()) {
+			return Err(IndexError::AlreadyKnown);
+		}
+		let kind = block.kind();
+		let parent = treeParent(block);
+		let proposerParent = block.header().parent;
+		self.expectParent(&proposerParent, BlockKind::Proposer)?;
+		//transaction blocks are placed by their proposer parent
+		let levelParent = match kind {
+			BlockKind::Transaction => proposerParent,
+			_ => {
+				self.expectParent(&parent, kind)?;
+				parent
+			},
+		};
+		let level = match self.blocks.get(&levelParent) {
+			Some(indexed) => indexed.level + 1,
+			None => 0,
+		};
+		Ok(IndexedBlock { kind, level, treeParent: parent })
+	}
+
+	//`parent` is zero or an indexed block of `kind`
+	fn expectParent(&self, parent: &H256, kind: BlockKind) -> Result<(), IndexError> {
+		if parent.is_zero() {
+			return Ok(());
+		}
+		match self.blocks.get(parent) {
+			None => Err(IndexError::UnknownParent(*parent)),
+			Some(indexed) if indexed.kind != kind => Err(IndexError::WrongParent(*parent)),
+			Some(_) => Ok(()),
+		}
+	}
+
+	pub fn insert(&mut self, block: &Block) -> Result<IndexedBlock, IndexError> {
+		let indexed = self.place(block)?;
+		self.record(block.hash(), indexed);
+		Ok(indexed)
+	}
+
+	//index `block` at `level` without its parent, the oldest block a snapshot has of a voter chain
+	pub fn insertAnchor(&mut self, block: &Block, level: u64) -> Result<IndexedBlock, IndexError> {
+		let hash = block.hash();
+		if self.blocks.contains_key(&hash) {
+			return Err(IndexError::AlreadyKnown);
+		}
+		let indexed = IndexedBlock { kind: block.kind(), level, treeParent: treeParent(block) };
+		self.record(hash, indexed);
+		Ok(indexed)
+	}
+
+	//index a block the database holds, as the database placed it. Parents have to be restored
+	//before their children, see BlockTree::open
+	pub fn restore(&mut self, hash: H256, indexed: IndexedBlock) {
+		if !self.blocks.contains_key(&hash) {
+			self.record(hash, indexed);
+		}
+	}
+
+	//make `hash` at `level` the best tip of `tree`, whatever block was seen first at that level
+	pub fn restoreBest(&mut self, tree: TreeId, hash: H256, level: u64) {
+		self.best.insert(tree, (hash, level));
+	}
+
+	fn record(&mut self, hash: H256, indexed: IndexedBlock) {
+		let (kind, level, parent) = (indexed.kind, indexed.level, indexed.treeParent);
+		self.blocks.insert(hash, indexed);
+		let tree = match TreeId::of(kind) {
+			Some(tree) => tree,
+			None => return,
+		};
+		if !parent.is_zero() {
+			self.children.entry(parent).or_default().push(hash);
+		}
+		let leaves = self.leaves.entry(tree).or_default();
+		leaves.remove(&parent);
+		leaves.insert(hash);
+		match self.best.get(&tree) {
+			Some((_, bestLevel)) if *bestLevel >= level => {},
+			_ => { self.best.insert(tree, (hash, level)); },
+		}
+		if tree == TreeId::Proposer {
+			if self.proposerLevels.len() <= level as usize {
+				self.proposerLevels.resize(level as usize + 1, Vec::new());
+			}
+			self.proposerLevels[level as usize].push(hash);
+		}
+	}
+
+	pub fn get(&self, hash: &H256) -> Option<&IndexedBlock> {
+		self.blocks.get(hash)
+	}
+
+	pub fn contains(&self, hash: &H256) -> bool {
+		self.blocks.contains_key(hash)
+	}
+
+	pub fn len(&self) -> usize {
+		self.blocks.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.blocks.is_empty()
+	}
+
+	pub fn children(&self, hash: &H256) -> &[H256] {
+		self.children.get(hash).map(|children| &children[..]).unwrap_or(&[])
+	}
+
+	pub fn leaves(&self, tree: TreeId) -> impl Iterator<Item = &H256> {
+		self.leaves.get(&tree).into_iter().flatten()
+	}
+
+	pub fn isLeaf(&self, tree: TreeId, hash: &H256) -> bool {
+		self.leaves.get(&tree).is_some_and(|leaves| leaves.contains(hash))
+	}
+
+	//best tip of `tree` and its level
+	pub fn best(&self, tree: TreeId) -> Option<(H256, u64)> {
+		self.best.get(&tree).copied()
+	}
+
+	pub fn proposerBestLevel(&self) -> Option<u64> {
+		self.best(TreeId::Proposer).map(|(_, level)| level)
+	}
+
+	pub fn proposerLevel(&self, level: u64) -> &[H256] {
+		self.proposerLevels.get(level as usize).map(|blocks| &blocks[..]).unwrap_or(&[])
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::Fixtures::block;
+	use crate::Block::{Content, ProposerBlock, TransactionBlock, VoterBlock};
+
+	fn proposer(parent: H256, nonce: u32) -> Block {
+		block(parent, nonce, Content::ProposerBlock(ProposerBlock::Content::new(Vec::new(), Vec::new())))
+	}
+
+	fn voter(proposerParent: H256, chain: u16, voterParent: H256, nonce: u32) -> Block {
+		block(proposerParent, nonce, Content::VoterBlock(VoterBlock::Content::new(chain, voterParent, Vec::new())))
+	}
+
+	fn insert(index: &mut TreeIndex, block: &Block) -> H256 {
+		index.insert(block).unwrap();
+		block.hash()
+	}
+
+	#[test]
+	fn proposerTree() {
+		let mut index = TreeIndex::new();
+		let genesis = insert(&mut index, &proposer(H256::zero(), 0));
+		let a = insert(&mut index, &proposer(genesis, 1));
+		let b = insert(&mut index, &proposer(genesis, 2));
+		let c = insert(&mut index, &proposer(a, 3));
+
+		assert_eq!(index.children(&genesis), &[a, b]);
+		assert_eq!(index.children(&a), &[c]);
+		assert!(index.children(&c).is_empty());
+		let mut leaves: Vec<H256> = index.leaves(TreeId::Proposer).copied().collect();
+		leaves.sort();
+		let mut expected = vec![b, c];
+		expected.sort();
+		assert_eq!(leaves, expected);
+		assert!(!index.isLeaf(TreeId::Proposer, &genesis));
+		assert_eq!(index.best(TreeId::Proposer), Some((c, 2)));
+		assert_eq!(index.proposerLevel(0), &[genesis]);
+		assert_eq!(index.proposerLevel(1), &[a, b]);
+		assert_eq!(index.proposerLevel(2), &[c]);
+		assert!(index.proposerLevel(3).is_empty());
+	}
+
+	#[test]
+	fn bestTipKeepsFirstOnTie() {
+		let mut index = TreeIndex::new();
+		let genesis = insert(&mut index, &proposer(H256::zero(), 0));
+		let first = insert(&mut index, &proposer(genesis, 1));
+		insert(&mut index, &proposer(genesis, 2));
+		assert_eq!(index.best(TreeId::Proposer), Some((first, 1)));
+	}
+
+	#[test]
+	fn voterChainsAreSeparateTrees() {
+		let mut index = TreeIndex::new();
+		let genesis = insert(&mut index, &proposer(H256::zero(), 0));
+		let chain0 = insert(&mut index, &voter(genesis, 0, H256::zero(), 1));
+		let chain1 = insert(&mut index, &voter(genesis, 1, H256::zero(), 2));
+		let next = insert(&mut index, &voter(genesis, 0, chain0, 3));
+
+		//voter blocks are not children of their proposer parent
+		assert!(index.children(&genesis).is_empty());
+		assert_eq!(index.children(&chain0), &[next]);
+		assert_eq!(index.best(TreeId::Voter(0)), Some((next, 1)));
+		assert_eq!(index.best(TreeId::Voter(1)), Some((chain1, 0)));
+		assert_eq!(index.leaves(TreeId::Voter(0)).collect::<Vec<_>>(), vec![&next]);
+		assert_eq!(index.best(TreeId::Proposer), Some((genesis, 0)));
+	}
+
+	#[test]
+	fn transactionBlocksOnlyIndexedByLevel() {
+		let mut index = TreeIndex::new();
+		let genesis = insert(&mut index, &proposer(H256::zero(), 0));
+		let transaction = block(genesis, 1, Content::TransactionBlock(TransactionBlock::Content::new(Vec::new())));
+		let indexed = index.insert(&transaction).unwrap();
+		assert_eq!(indexed.level, 1);
+		assert_eq!(indexed.kind, BlockKind::Transaction);
+		assert!(index.children(&genesis).is_empty());
+		assert!(index.isLeaf(TreeId::Proposer, &genesis));
+		assert!(index.contains(&transaction.hash()));
+	}
+
+	#[test]
+	fn anchorStartsATreeAtItsLevel() {
+		let mut index = TreeIndex::new();
+		let genesis = insert(&mut index, &proposer(H256::zero(), 0));
+		let anchor = voter(genesis, 0, H256::repeat_byte(7), 1);
+		assert_eq!(index.insert(&anchor), Err(IndexError::UnknownParent(H256::repeat_byte(7))));
+		assert_eq!(index.insertAnchor(&anchor, 40).unwrap().level, 40);
+		let next = insert(&mut index, &voter(genesis, 0, anchor.hash(), 2));
+		assert_eq!(index.best(TreeId::Voter(0)), Some((next, 41)));
+		assert_eq!(index.insertAnchor(&anchor, 40), Err(IndexError::AlreadyKnown));
+	}
+
+	#[test]
+	fn rejectsParentsOutsideTheTree() {
+		let mut index = TreeIndex::new();
+		let genesis = insert(&mut index, &proposer(H256::zero(), 0));
+		let chain0 = insert(&mut index, &voter(genesis, 0, H256::zero(), 1));
+		insert(&mut index, &voter(genesis, 1, H256::zero(), 2));
+		let transaction = insert(&mut index, &block(genesis, 3, Content::TransactionBlock(TransactionBlock::Content::new(Vec::new()))));
+
+		//a voter parent on another chain, or not a voter block at all
+		assert_eq!(index.insert(&voter(genesis, 1, chain0, 4)), Err(IndexError::WrongParent(chain0)));
+		assert_eq!(index.insert(&voter(genesis, 0, genesis, 5)), Err(IndexError::WrongParent(genesis)));
+		//proposer parents that are not proposer blocks
+		assert_eq!(index.insert(&voter(chain0, 0, chain0, 6)), Err(IndexError::WrongParent(chain0)));
+		assert_eq!(index.insert(&proposer(transaction, 7)), Err(IndexError::WrongParent(transaction)));
+		let onVoter = block(chain0, 8, Content::TransactionBlock(TransactionBlock::Content::new(Vec::new())));
+		assert_eq!(index.insert(&onVoter), Err(IndexError::WrongParent(chain0)));
+		assert_eq!(index.insert(&voter(H256::repeat_byte(9), 0, chain0, 9)), Err(IndexError::UnknownParent(H256::repeat_byte(9))));
+		assert_eq!(index.len(), 4);
+		assert_eq!(index.best(TreeId::Voter(1)).map(|(_, level)| level), Some(0));
+
+		assert_eq!(index.insert(&voter(genesis, 0, chain0, 10)).unwrap().level, 1);
+	}
+
+	#[test]
+	fn rejectsUnknownParentAndDuplicates() {
+		let mut index = TreeIndex::new();
+		let orphan = proposer(H256::repeat_byte(1), 0);
+		assert_eq!(index.insert(&orphan), Err(IndexError::UnknownParent(H256::repeat_byte(1))));
+		assert_eq!(index.len(), 0);
+		let genesis = proposer(H256::zero(), 0);
+		index.insert(&genesis).unwrap();
+		assert_eq!(index.insert(&genesis), Err(IndexError::AlreadyKnown));
+	}
+}
@@ -0,0 +1,547 @@
+//Votes, leaders and the confirmed ledger, and how they follow voter chain reorganisations.
+//
+//Only the votes on the main chain of each voter chain count. A proposer block becomes the leader of
+//its level once a majority of the voter chains vote for it, the lowest hash should several have
+//one, and the ledger confirms levels in order
+//as long as each has a leader: the proposer blocks the leader refers to that are not confirmed yet,
+//depth first in reference order, then the leader itself.
+//
+//When a voter chain's main tip moves, the votes of the abandoned branch are retracted and those of
+//the new branch applied, leaders of the touched levels recomputed and the ledger rolled back to the
+//first level whose leader changed before being extended again. Everything that can fail is read
+//before anything is changed, so a reorg is applied completely or not at all.
+use codec::{Decode, Encode};
+use sp_core::H256;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+//what the ledger reads from the block tree
+pub trait LedgerView {
+	//level of a block, see Index
+	fn level(&self, hash: &H256) -> Option<u64>;
+	//voter parent of a voter block
+	fn voterParent(&self, hash: &H256) -> Option<H256>;
+	//proposer blocks a voter block votes for
+	fn votes(&self, hash: &H256) -> Option<Vec<H256>>;
+	//proposer blocks a proposer block refers to
+	fn proposerReferences(&self, hash: &H256) -> Option<Vec<H256>>;
+	//proposer blocks at a level
+	fn proposerLevel(&self, level: u64) -> Vec<H256>;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReorgError {
+	UnknownChain(u16),
+	UnknownBlock(H256),
+	//the old and new tip do not share the chain's genesis
+	Disjoint,
+}
+
+//ledger levels with the proposer blocks they confirm, in ledger order
+pub type LedgerEntries = Vec<(u64, Vec<H256>)>;
+
+//what a change of voter tip did to the ledger, removed entries have to be undone by consumers
+//before the added ones are applied
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct LedgerUpdate {
+	pub removed: LedgerEntries,
+	pub added: LedgerEntries,
+}
+
+impl LedgerUpdate {
+	pub fn isEmpty(&self) -> bool {
+		self.removed.is_empty() && self.added.is_empty()
+	}
+}
+
+//everything a ledger holds, for snapshots. Sorted, so equal ledgers give equal states
+#[derive(Clone, PartialEq, Eq, Debug, Default, Encode, Decode)]
+pub struct LedgerState {
+	pub voterTips: Vec<H256>,
+	pub votes: Vec<(H256, Vec<(u16, u64)>)>,
+	pub leaders: Vec<(u64, H256)>,
+	pub ledger: LedgerEntries,
+}
+
+//what changed since the last `takeChanges`, for writing the ledger back to the database
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct LedgerChanges {
+	//proposer blocks whose votes changed
+	pub proposers: HashSet<H256>,
+	//levels whose leader changed
+	pub leaderLevels: BTreeSet<u64>,
+	//voter chains whose tip moved
+	pub voterChains: BTreeSet<u16>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Ledger {
+	voterChains: u16,
+	//main chain tip of every voter chain
+	voterTips: Vec<H256>,
+	//PROPOSER_NODE_VOTE_CF, the (chain, voter level) of every main chain vote on a proposer block
+	votes: HashMap<H256, Vec<(u16, u64)>>,
+	//PROPOSER_VOTE_COUNT_CF
+	voteCount: HashMap<H256, u64>,
+	//PROPOSER_LEADER_SEQUENCE_CF
+	leaders: BTreeMap<u64, H256>,
+	//PROPOSER_LEDGER_ORDER_CF
+	ledger: BTreeMap<u64, Vec<H256>>,
+	confirmed: HashSet<H256>,
+	changes: LedgerChanges,
+}
+
+impl Ledger {
+	//a ledger holding only the genesis proposer block, every voter chain at its genesis block
+	pub fn new(proposerGenesis: H256, voterGenesis: Vec<H256>) -> Self {
+		Ledger {
+			voterChains: voterGenesis.len() as u16,
+			voterTips: voterGenesis,
+			votes: HashMap::new(),
+			voteCount: HashMap::new(),
+			leaders: BTreeMap::from([(0, proposerGenesis)]),
+			ledger: BTreeMap::from([(0, vec![proposerGenesis])]),
+			confirmed: HashSet::from([proposerGenesis]),
+			changes: LedgerChanges::default(),
+		}
+	}
+
+	pub fn state(&self) -> LedgerState {
+		let mut votes: Vec<(H256, Vec<(u16, u64)>)> = self.votes.iter()
+			.filter(|(_, votes)| !votes.is_empty())
+			.map(|(proposer, votes)| {
+				let mut votes = votes.clone();
+				votes.sort();
+				(*proposer, votes)
+			})
+			.collect();
+		votes.sort();
+		LedgerState {
+			voterTips: self.voterTips.clone(),
+			votes,
+			leaders: self.leaders.iter().map(|(level, leader)| (*level, *leader)).collect(),
+			ledger: self.ledger.iter().map(|(level, blocks)| (*level, blocks.clone())).collect(),
+		}
+	}
+
+	//a ledger restored from a snapshot, all of it reported as changed. None without the genesis level
+	pub fn fromState(state: LedgerState) -> Option<Self> {
+		if state.ledger.first().map(|(level, _)| *level) != Some(0) {
+			return None;
+		}
+		let changes = LedgerChanges {
+			proposers: state.votes.iter().map(|(proposer, _)| *proposer).collect(),
+			leaderLevels: state.leaders.iter().map(|(level, _)| *level).collect(),
+			voterChains: (0..state.voterTips.len() as u16).collect(),
+		};
+		Some(Ledger {
+			voterChains: state.voterTips.len() as u16,
+			voterTips: state.voterTips,
+			voteCount: state.votes.iter().map(|(proposer, votes)| (*proposer, votes.len() as u64)).collect(),
+			votes: state.votes.into_iter().collect(),
+			leaders: state.leaders.into_iter().collect(),
+			confirmed: state.ledger.iter().flat_map(|(_, blocks)| blocks.iter().copied()).collect(),
+			ledger: state.ledger.into_iter().collect(),
+			changes,
+		})
+	}
+
+	pub fn voterTips(&self) -> &[H256] {
+		&self.voterTips
+	}
+
+	//highest confirmed level
+	pub fn proposerLedgerTip(&self) -> u64 {
+		*self.ledger.keys().next_back().expect("the genesis level is never rolled back; qed")
+	}
+
+	pub fn voteCount(&self, proposer: &H256) -> u64 {
+		self.voteCount.get(proposer).copied().unwrap_or(0)
+	}
+
+	pub fn votesOn(&self, proposer: &H256) -> &[(u16, u64)] {
+		self.votes.get(proposer).map(|votes| &votes[..]).unwrap_or(&[])
+	}
+
+	pub fn leader(&self, level: u64) -> Option<H256> {
+		self.leaders.get(&level).copied()
+	}
+
+	pub fn entries(&self) -> impl Iterator<Item = (&u64, &Vec<H256>)> {
+		self.ledger.iter()
+	}
+
+	//the proposer blocks ledger level `level` confirms
+	pub fn entry(&self, level: u64) -> Option<&[H256]> {
+		self.ledger.get(&level).map(|blocks| &blocks[..])
+	}
+
+	pub fn isConfirmed(&self, proposer: &H256) -> bool {
+		self.confirmed.contains(proposer)
+	}
+
+	pub fn takeChanges(&mut self) -> LedgerChanges {
+		std::mem::take(&mut self.changes)
+	}
+
+	//voter blocks leaving and joining the main chain when its tip moves from `old` to `new`, both
+	//in ascending order
+	fn route(view: &impl LedgerView, old: H256, new: H256) -> Result<(Vec<H256>, Vec<H256>), ReorgError> {
+		let level = |hash: &H256| view.level(hash).ok_or(ReorgError::UnknownBlock(*hash));
+		let parent = |hash: &H256| match view.voterParent(hash) {
+			Some(parent) if !parent.is_zero() => Ok(parent),
+			Some(_) => Err(ReorgError::Disjoint),
+			None => Err(ReorgError::UnknownBlock(*hash)),
+		};
+		let (mut retracted, mut applied) = (Vec::new(), Vec::new());
+		let (mut a, mut b) = (old, new);
+		let (mut levelA, mut levelB) = (level(&a)?, level(&b)?);
+		while levelB > levelA {
+			applied.push(b);
+			b = parent(&b)?;
+			levelB -= 1;
+		}
+		while levelA > levelB {
+			retracted.push(a);
+			a = parent(&a)?;
+			levelA -= 1;
+		}
+		while a != b {
+			retracted.push(a);
+			applied.push(b);
+			a = parent(&a)?;
+			b = parent(&b)?;
+		}
+		retracted.reverse();
+		applied.reverse();
+		Ok((retracted, applied))
+	}
+
+	//votes a move of `chain`'s tip to `newTip` retracts and applies, by voter level. Every read that
+	//can fail, nothing is changed
+	fn stageVoterTip(&self, view: &impl LedgerView, chain: u16, newTip: H256) -> Result<Option<(LedgerEntries, LedgerEntries)>, ReorgError> {
+		let oldTip = *self.voterTips.get(chain as usize).ok_or(ReorgError::UnknownChain(chain))?;
+		if oldTip == newTip {
+			return Ok(None);
+		}
+		let (retracted, applied) = Self::route(view, oldTip, newTip)?;
+		let votesOf = |blocks: &[H256]| -> Result<LedgerEntries, ReorgError> {
+			blocks
+				.iter()
+				.map(|hash| Ok((view.level(hash).ok_or(ReorgError::UnknownBlock(*hash))?, view.votes(hash).ok_or(ReorgError::UnknownBlock(*hash))?)))
+				.collect()
+		};
+		Ok(Some((votesOf(&retracted)?, votesOf(&applied)?)))
+	}
+
+	//whether setVoterTip would succeed, without changing the ledger. The lowest proposer level whose
+	//votes it changes, the lowest level whose leader can change, is returned
+	pub fn checkVoterTip(&self, view: &impl LedgerView, chain: u16, newTip: H256) -> Result<Option<u64>, ReorgError> {
+		let staged = self.stageVoterTip(view, chain, newTip)?;
+		Ok(staged.and_then(|(retracted, applied)| {
+			retracted.iter().chain(&applied).flat_map(|(_, votes)| votes).filter_map(|proposer| view.level(proposer)).min()
+		}))
+	}
+
+	//move the main tip of voter chain `chain` to `newTip`, a plain extension or a reorg
+	pub fn setVoterTip(&mut self, view: &impl LedgerView, chain: u16, newTip: H256) -> Result<LedgerUpdate, ReorgError> {
+		//staged: every read that can fail
+		let (retractedVotes, appliedVotes) = match self.stageVoterTip(view, chain, newTip)? {
+			Some(votes) => votes,
+			None => return Ok(LedgerUpdate::default()),
+		};
+
+		//committed: nothing below fails
+		self.voterTips[chain as usize] = newTip;
+		self.changes.voterChains.insert(chain);
+		let mut touched = HashSet::new();
+		for (voterLevel, votes) in retractedVotes {
+			for proposer in votes {
+				if let Some(entries) = self.votes.get_mut(&proposer) {
+					entries.retain(|entry| *entry != (chain, voterLevel));
+				}
+				if let Some(count) = self.voteCount.get_mut(&proposer) {
+					*count = count.saturating_sub(1);
+				}
+				touched.insert(proposer);
+			}
+		}
+		for (voterLevel, votes) in appliedVotes {
+			for proposer in votes {
+				self.votes.entry(proposer).or_default().push((chain, voterLevel));
+				*self.voteCount.entry(proposer).or_insert(0) += 1;
+				touched.insert(proposer);
+			}
+		}
+		self.changes.proposers.extend(touched.iter().copied());
+		let mut levels: Vec<u64> = touched.iter().filter_map(|proposer| view.level(proposer)).collect();
+		levels.sort();
+		levels.dedup();
+		let mut firstChanged = None;
+		for level in levels {
+			if level == 0 {
+				continue;
+			}
+			let leader = self.electLeader(view, level);
+			if leader != self.leaders.get(&level).copied() {
+				match leader {
+					Some(leader) => self.leaders.insert(level, leader),
+					None => self.leaders.remove(&level),
+				};
+				self.changes.leaderLevels.insert(level);
+				firstChanged.get_or_insert(level);
+			}
+		}
+		let mut update = LedgerUpdate::default();
+		if let Some(level) = firstChanged {
+			update.removed = self.rollback(level);
+		}
+		update.added = self.extend(view);
+		Ok(update)
+	}
+
+	//the proposer block at `level` a majority of the voter chains votes for, ties go to the lowest
+	//hash whatever order the level lists its blocks in
+	fn electLeader(&self, view: &impl LedgerView, level: u64) -> Option<H256> {
+		view.proposerLevel(level)
+			.into_iter()
+			.map(|proposer| (self.voteCount(&proposer), proposer))
+			.max_by_key(|(count, proposer)| (*count, Reverse(*proposer)))
+			.filter(|(count, _)| count * 2 > self.voterChains as u64)
+			.map(|(_, proposer)| proposer)
+	}
+
+	//drop the ledger from `level` up, the genesis level stays
+	fn rollback(&mut self, level: u64) -> LedgerEntries {
+		let removed: LedgerEntries = self.ledger.split_off(&level.max(1)).into_iter().collect();
+		for (_, blocks) in &removed {
+			for block in blocks {
+				self.confirmed.remove(block);
+			}
+		}
+		removed
+	}
+
+	//confirm levels after the tip for as long as they have a leader
+	fn extend(&mut self, view: &impl LedgerView) -> LedgerEntries {
+		let mut added = Vec::new();
+		let mut level = self.proposerLedgerTip() + 1;
+		while let Some(leader) = self.leaders.get(&level).copied() {
+			let mut order = Vec::new();
+			self.confirm(view, leader, &mut order);
+			self.ledger.insert(level, order.clone());
+			added.push((level, order));
+			level += 1;
+		}
+		added
+	}
+
+	//depth first, the blocks `proposer` refers to before itself. Reference chains are as long as
+	//the proposer chain, so the walk keeps its own stack instead of recursing
+	fn confirm(&mut self, view: &impl LedgerView, proposer: H256, order: &mut Vec<H256>) {
+		if !self.confirmed.insert(proposer) {
+			return;
+		}
+		//blocks being confirmed with the references still to visit, the last one on top
+		let references = |block: &H256| view.proposerReferences(block).unwrap_or_default().into_iter();
+		let mut stack = vec![(proposer, references(&proposer))];
+		while let Some((block, pending)) = stack.last_mut() {
+			let block = *block;
+			match pending.next() {
+				Some(reference) => {
+					if self.confirmed.insert(reference) {
+						stack.push((reference, references(&reference)));
+					}
+				},
+				None => {
+					order.push(block);
+					stack.pop();
+				},
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Default)]
+	struct View {
+		levels: HashMap<H256, u64>,
+		voterParents: HashMap<H256, H256>,
+		votes: HashMap<H256, Vec<H256>>,
+		references: HashMap<H256, Vec<H256>>,
+		proposerLevels: HashMap<u64, Vec<H256>>,
+	}
+
+	impl LedgerView for View {
+		fn level(&self, hash: &H256) -> Option<u64> {
+			self.levels.get(hash).copied()
+		}
+		fn voterParent(&self, hash: &H256) -> Option<H256> {
+			self.voterParents.get(hash).copied()
+		}
+		fn votes(&self, hash: &H256) -> Option<Vec<H256>> {
+			self.votes.get(hash).cloned()
+		}
+		fn proposerReferences(&self, hash: &H256) -> Option<Vec<H256>> {
+			self.references.get(hash).cloned()
+		}
+		fn proposerLevel(&self, level: u64) -> Vec<H256> {
+			self.proposerLevels.get(&level).cloned().unwrap_or_default()
+		}
+	}
+
+	impl View {
+		fn proposer(&mut self, id: u8, level: u64, references: Vec<H256>) -> H256 {
+			let hash = H256::repeat_byte(id);
+			self.levels.insert(hash, level);
+			self.references.insert(hash, references);
+			self.proposerLevels.entry(level).or_default().push(hash);
+			hash
+		}
+
+		fn voter(&mut self, id: u8, parent: H256, votes: Vec<H256>) -> H256 {
+			let hash = H256::repeat_byte(id);
+			let level = if parent.is_zero() { 0 } else { self.levels[&parent] + 1 };
+			self.levels.insert(hash, level);
+			self.voterParents.insert(hash, parent);
+			self.votes.insert(hash, votes);
+			hash
+		}
+	}
+
+	//three voter chains, a genesis proposer and two competing proposers at level 1
+	fn setup() -> (View, Ledger, [H256; 3], [H256; 3]) {
+		let mut view = View::default();
+		let genesis = view.proposer(1, 0, Vec::new());
+		let a = view.proposer(2, 1, Vec::new());
+		let b = view.proposer(3, 1, Vec::new());
+		let voters = [view.voter(10, H256::zero(), Vec::new()), view.voter(11, H256::zero(), Vec::new()), view.voter(12, H256::zero(), Vec::new())];
+		let ledger = Ledger::new(genesis, voters.to_vec());
+		(view, ledger, [genesis, a, b], voters)
+	}
+
+	#[test]
+	fn majorityConfirmsLevel() {
+		let (mut view, mut ledger, [_, a, _], voters) = setup();
+		let first = view.voter(20, voters[0], vec![a]);
+		assert_eq!(ledger.setVoterTip(&view, 0, first).unwrap(), LedgerUpdate::default());
+		assert_eq!(ledger.voteCount(&a), 1);
+		let second = view.voter(21, voters[1], vec![a]);
+		let update = ledger.setVoterTip(&view, 1, second).unwrap();
+		assert_eq!(update, LedgerUpdate { removed: Vec::new(), added: vec![(1, vec![a])] });
+		assert_eq!(ledger.leader(1), Some(a));
+		assert_eq!(ledger.proposerLedgerTip(), 1);
+		assert_eq!(ledger.votesOn(&a), &[(0, 1), (1, 1)]);
+	}
+
+	#[test]
+	fn reorgRetractsVotesAndRollsBackLedger() {
+		let (mut view, mut ledger, [_, a, b], voters) = setup();
+		let onA = view.voter(20, voters[0], vec![a]);
+		ledger.setVoterTip(&view, 0, onA).unwrap();
+		let onA1 = view.voter(21, voters[1], vec![a]);
+		ledger.setVoterTip(&view, 1, onA1).unwrap();
+		assert!(ledger.isConfirmed(&a));
+		ledger.takeChanges();
+
+		//chain 0 switches to a longer fork voting for b
+		let onB = view.voter(22, voters[0], vec![b]);
+		let tip = view.voter(23, onB, Vec::new());
+		let update = ledger.setVoterTip(&view, 0, tip).unwrap();
+		assert_eq!(update, LedgerUpdate { removed: vec![(1, vec![a])], added: Vec::new() });
+		assert_eq!(ledger.voteCount(&a), 1);
+		assert_eq!(ledger.voteCount(&b), 1);
+		assert_eq!(ledger.leader(1), None);
+		assert_eq!(ledger.proposerLedgerTip(), 0);
+		assert!(!ledger.isConfirmed(&a));
+		assert_eq!(ledger.voterTips()[0], tip);
+		let changes = ledger.takeChanges();
+		assert_eq!(changes.proposers, HashSet::from([a, b]));
+		assert_eq!(changes.leaderLevels, BTreeSet::from([1]));
+		assert_eq!(changes.voterChains, BTreeSet::from([0]));
+
+		let onB2 = view.voter(24, voters[2], vec![b]);
+		let update = ledger.setVoterTip(&view, 2, onB2).unwrap();
+		assert_eq!(update, LedgerUpdate { removed: Vec::new(), added: vec![(1, vec![b])] });
+	}
+
+	#[test]
+	fn ledgerOrdersReferencesBeforeLeader() {
+		let (mut view, mut ledger, [_, a, b], voters) = setup();
+		let c = view.proposer(4, 2, vec![b]);
+		let tips: Vec<H256> = (0..2u8).map(|chain| view.voter(30 + chain, voters[chain as usize], vec![a, c])).collect();
+		ledger.setVoterTip(&view, 0, tips[0]).unwrap();
+		let update = ledger.setVoterTip(&view, 1, tips[1]).unwrap();
+		assert_eq!(update.added, vec![(1, vec![a]), (2, vec![b, c])]);
+	}
+
+	#[test]
+	fn tieGoesToTheLowestHash() {
+		let (mut view, mut ledger, [_, a, b], voters) = setup();
+		//listed after b, a still wins the tie by its lower hash
+		view.proposerLevels.insert(1, vec![b, a]);
+		for chain in 0..2u8 {
+			let tip = view.voter(20 + chain, voters[chain as usize], vec![a, b]);
+			ledger.setVoterTip(&view, chain as u16, tip).unwrap();
+		}
+		assert_eq!(ledger.leader(1), Some(a.min(b)));
+	}
+
+	#[test]
+	fn deepReferenceChainConfirmed() {
+		let (mut view, mut ledger, [_, a, _], voters) = setup();
+		//each block refers to the previous one, deeper than a recursive walk's stack
+		let depth = 200_000u64;
+		let deep: Vec<H256> = (1..=depth).map(|i| H256::from_low_u64_be(1 << 32 | i)).collect();
+		for (i, block) in deep.iter().enumerate() {
+			view.references.insert(*block, deep[..i].last().copied().into_iter().collect());
+		}
+		view.references.insert(a, vec![deep[deep.len() - 1]]);
+		for chain in 0..2u8 {
+			let tip = view.voter(20 + chain, voters[chain as usize], vec![a]);
+			ledger.setVoterTip(&view, chain as u16, tip).unwrap();
+		}
+		let mut expected = deep;
+		expected.push(a);
+		assert_eq!(ledger.ledger[&1], expected);
+	}
+
+	#[test]
+	fn failedReorgChangesNothing() {
+		let (mut view, mut ledger, [_, a, _], voters) = setup();
+		let onA = view.voter(20, voters[0], vec![a]);
+		ledger.setVoterTip(&view, 0, onA).unwrap();
+		let before = ledger.clone();
+		let unknown = H256::repeat_byte(99);
+		assert_eq!(ledger.setVoterTip(&view, 0, unknown), Err(ReorgError::UnknownBlock(unknown)));
+		//a block of chain 1 has another genesis than chain 0's tip
+		let other = view.voter(21, voters[1], Vec::new());
+		assert_eq!(ledger.setVoterTip(&view, 0, other), Err(ReorgError::Disjoint));
+		assert_eq!(ledger.setVoterTip(&view, 7, other), Err(ReorgError::UnknownChain(7)));
+		assert_eq!(ledger.voterTips(), before.voterTips());
+		assert_eq!(ledger.voteCount(&a), before.voteCount(&a));
+		assert_eq!(ledger.takeChanges(), before.clone().takeChanges());
+	}
+
+	#[test]
+	fn stateRoundTrip() {
+		let (mut view, mut ledger, [_, a, _], voters) = setup();
+		for chain in 0..2u8 {
+			let tip = view.voter(20 + chain, voters[chain as usize], vec![a]);
+			ledger.setVoterTip(&view, chain as u16, tip).unwrap();
+		}
+		let state = ledger.state();
+		let mut restored = Ledger::fromState(state.clone()).unwrap();
+		assert_eq!(restored.state(), state);
+		assert_eq!(restored.voteCount(&a), 2);
+		assert!(restored.isConfirmed(&a));
+		assert_eq!(restored.proposerLedgerTip(), 1);
+		let changes = restored.takeChanges();
+		assert_eq!(changes.proposers, HashSet::from([a]));
+		assert_eq!(changes.voterChains, BTreeSet::from([0, 1, 2]));
+		assert!(Ledger::fromState(LedgerState::default()).is_none());
+	}
+}
@@ -0,0 +1,175 @@
+//Schema versions of the store and the migrations between them.
+//
+//The schema version is kept in META_CF. A store written before versions existed has none and is
+//version 1, an empty store is stamped with the current version when it is first used. Whenever the
+//column family layout changes SCHEMA_VERSION goes up by one and a migration to it is added to
+//MIGRATIONS, so an older store is brought up to date on startup instead of being synced again. A
+//store of a newer version than this node knows is refused.
+//
+//Each migration reads one consistent view of the store and writes what it changes in bounded
+//batches, so its memory does not grow with the store, and its new version after the last of them.
+//A migration only writes what running it again would write, so an interrupted one leaves the old
+//version behind and runs again from the start.
+use codec::Decode;
+use super::Batch::ImportBatch;
+use super::Graph;
+use super::Store::{ReadStore, Store, StoreError};
+use super::*;
+
+pub const SCHEMA_VERSION: u32 = 2;
+pub const SCHEMA_VERSION_KEY: &[u8] = b"schemaVersion";
+
+//the version of stores written before there were versions
+const UNVERSIONED: u32 = 1;
+
+//how far a migration got, in blocks
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MigrationProgress {
+	pub done: u64,
+	pub total: u64,
+}
+
+//takes a batch of a migration to write and how far the migration got with it
+pub type MigrationWrite<'a> = dyn FnMut(ImportBatch, MigrationProgress) -> Result<()> + 'a;
+
+pub struct Migration {
+	//the version the migration leaves the store at, it runs on stores of the version before
+	pub to: u32,
+	pub description: &'static str,
+	//hands `write` its batches, the first one empty with nothing done and the total
+	migrate: fn(&dyn ReadStore, &mut MigrationWrite) -> Result<()>,
+}
+
+//in order, one for every version after UNVERSIONED
+pub const MIGRATIONS: &[Migration] = &[
+	Migration { to: 2, description: "index the blocks pointing to each block", migrate: Graph::rebuildReferrers },
+];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum MigrationError {
+	Storage(StoreError),
+	Undecodable,
+	//the store was written by a newer node
+	Newer { found: u32, supported: u32 },
+}
+
+impl core::fmt::Display for MigrationError {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		match self {
+			MigrationError::Storage(error) => write!(f, "storage error: {}", error),
+			MigrationError::Undecodable => write!(f, "the stored schema version does not decode"),
+			MigrationError::Newer { found, supported } => write!(f, "schema version {} is newer than the supported {}", found, supported),
+		}
+	}
+}
+
+impl From<StoreError> for MigrationError {
+	fn from(error: StoreError) -> Self {
+		MigrationError::Storage(error)
+	}
+}
+
+//the version the store is at, the current one for an empty store
+pub fn schemaVersion(store: &dyn ReadStore) -> std::result::Result<u32, MigrationError> {
+	match store.get(META_CF, SCHEMA_VERSION_KEY)? {
+		Some(bytes) => u32::decode(&mut &bytes[..]).map_err(|_| MigrationError::Undecodable),
+		None if store.isEmpty(BLOCK_CF)? => Ok(SCHEMA_VERSION),
+		None => Ok(UNVERSIONED),
+	}
+}
+
+//add the current version to a batch writing a new store
+pub fn stamp(batch: &mut ImportBatch) {
+	batch.put(META_CF, SCHEMA_VERSION_KEY, &SCHEMA_VERSION);
+}
+
+//bring the store to SCHEMA_VERSION, calling `progress` as each migration starts and after each of
+//its batches is written. Returns the version the store was at
+pub fn migrate(store: &impl Store, mut progress: impl FnMut(&Migration, MigrationProgress)) -> std::result::Result<u32, MigrationError> {
+	let found = schemaVersion(store)?;
+	if found > SCHEMA_VERSION {
+		return Err(MigrationError::Newer { found, supported: SCHEMA_VERSION });
+	}
+	for migration in MIGRATIONS.iter().filter(|migration| migration.to > found) {
+		store.consistent(|view| (migration.migrate)(view, &mut |batch: ImportBatch, done| {
+			if !batch.ops().is_empty() {
+				store.write(batch.ops().to_vec())?;
+			}
+			progress(migration, done);
+			Ok(())
+		}))?;
+		let mut version = ImportBatch::new();
+		version.put(META_CF, SCHEMA_VERSION_KEY, &migration.to);
+		store.write(version.ops().to_vec())?;
+	}
+	//empty and unstamped
+	if store.get(META_CF, SCHEMA_VERSION_KEY)?.is_none() {
+		let mut batch = ImportBatch::new();
+		stamp(&mut batch);
+		store.write(batch.ops().to_vec())?;
+	}
+	Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::Graph::Referrer;
+	use super::super::Prune::PruningMode;
+	use super::super::Store::MemoryStore;
+	use crate::Genesis::PrismGenesis;
+	use codec::Encode;
+
+	#[test]
+	fn migrationsAreInOrder() {
+		let versions: Vec<u32> = MIGRATIONS.iter().map(|migration| migration.to).collect();
+		let expected: Vec<u32> = (UNVERSIONED + 1..=SCHEMA_VERSION).collect();
+		assert_eq!(versions, expected);
+	}
+
+	#[test]
+	fn emptyStoreIsStamped() {
+		let store = MemoryStore::new();
+		assert_eq!(migrate(&store, |_, _| panic!("nothing to migrate")), Ok(SCHEMA_VERSION));
+		assert_eq!(store.get(META_CF, SCHEMA_VERSION_KEY), Ok(Some(SCHEMA_VERSION.encode())));
+	}
+
+	#[test]
+	fn unversionedStoreGetsItsReferrers() {
+		let genesis = PrismGenesis { voterChains: 2, timestamp: 1, difficulty: H256::repeat_byte(0xff) }.blocks().unwrap();
+		let tree = BlockTree::new(MemoryStore::new(), &genesis, PruningMode::Archive).unwrap();
+		let store = tree.store();
+		let proposer = genesis.proposerHash();
+		let written = Graph::referrers(store, &proposer).unwrap();
+		assert_eq!(written.len(), 2);
+
+		//as written before versions and referrers
+		let mut old = ImportBatch::new();
+		old.delete(META_CF, SCHEMA_VERSION_KEY);
+		for (key, _) in store.scanPrefix(REFERRER_NEIGHBOR_CF, proposer.as_bytes()).unwrap() {
+			old.delete(REFERRER_NEIGHBOR_CF, key);
+		}
+		store.write(old.ops().to_vec()).unwrap();
+		assert_eq!(schemaVersion(store), Ok(UNVERSIONED));
+
+		let blocks = store.scan(PARENT_NEIGHBOR_CF).unwrap().len() as u64;
+		let mut ran = Vec::new();
+		assert_eq!(migrate(store, |migration, progress| ran.push((migration.to, progress.done, progress.total))), Ok(UNVERSIONED));
+		assert_eq!(ran, vec![(2, 0, blocks), (2, blocks, blocks)]);
+		let mut rebuilt = Graph::referrers(store, &proposer).unwrap();
+		rebuilt.sort_by_key(|referrer: &Referrer| referrer.block);
+		let mut expected = written;
+		expected.sort_by_key(|referrer| referrer.block);
+		assert_eq!(rebuilt, expected);
+		assert_eq!(schemaVersion(store), Ok(SCHEMA_VERSION));
+	}
+
+	#[test]
+	fn newerStoreIsRefused() {
+		let store = MemoryStore::new();
+		let mut newer = ImportBatch::new();
+		newer.put(META_CF, SCHEMA_VERSION_KEY, &(SCHEMA_VERSION + 1));
+		store.write(newer.ops().to_vec()).unwrap();
+		assert_eq!(migrate(&store, |_, _| ()), Err(MigrationError::Newer { found: SCHEMA_VERSION + 1, supported: SCHEMA_VERSION }));
+	}
+}
@@ -0,0 +1,128 @@
+//Pruning of transaction block contents.
+//
+//Transaction blocks hold almost all of the data, and once the ledger has confirmed them and their
+//extrinsics are executed they are only read again to serve peers syncing from far behind. Unless
+//the node is an archive, the content of a transaction block is dropped once the ledger level that
+//confirmed it is more than `keep` levels below the ledger tip and final, see FINALITY_DEPTH. Its
+//header stays, as do all proposer and voter blocks and the whole ledger order, so the DAG can still
+//be walked and every pruned block checked against its content root.
+//
+//Only final levels are pruned, so no reorg a node follows needs a pruned block again and fork
+//choice is the same whatever a node prunes. An insert prunes at most MAX_PRUNED_LEVELS_PER_INSERT
+//levels, a node far behind catches up over the following inserts.
+use sp_core::H256;
+use std::collections::HashSet;
+use crate::Block::Header::BlockHeader;
+use super::Batch::ImportBatch;
+use super::*;
+
+pub const DEFAULT_KEEP_LEVELS: u64 = 256;
+
+//ledger levels one insert prunes at most, which bounds its write batch and how long it holds the
+//tree's locks
+pub const MAX_PRUNED_LEVELS_PER_INSERT: u64 = 16;
+
+const PRUNED_LEVEL_KEY: &[u8] = b"prunedLevel";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PruningMode {
+	//keep every transaction block
+	Archive,
+	//keep the contents of transaction blocks confirmed by the last `keep` ledger levels
+	KeepLevels(u64),
+}
+
+impl Default for PruningMode {
+	fn default() -> Self {
+		PruningMode::KeepLevels(DEFAULT_KEEP_LEVELS)
+	}
+}
+
+impl PruningMode {
+	//the highest ledger level whose transaction blocks can go with the ledger at `ledgerTip`
+	pub fn pruneTo(&self, ledgerTip: u64) -> Option<u64> {
+		match self {
+			PruningMode::Archive => None,
+			PruningMode::KeepLevels(keep) => ledgerTip.checked_sub(*keep),
+		}
+	}
+}
+
+//transaction blocks confirmed by the ledger levels `from..=to`, each once, in ledger order.
+//`levels` gives the proposer blocks a level confirms and `references` their transaction references
+pub fn confirmedTransactions(
+	levels: impl Fn(u64) -> Option<Vec<H256>>,
+	references: impl Fn(&H256) -> Result<Vec<H256>>,
+	from: u64,
+	to: u64,
+) -> Result<Vec<H256>> {
+	let mut seen = HashSet::new();
+	let mut transactions = Vec::new();
+	for level in from..=to {
+		for proposer in levels(level).unwrap_or_default() {
+			for transaction in references(&proposer)? {
+				if seen.insert(transaction) {
+					transactions.push(transaction);
+				}
+			}
+		}
+	}
+	Ok(transactions)
+}
+
+//drop the content of a transaction block, keeping its header
+pub fn pruneBlock(batch: &mut ImportBatch, hash: H256, header: &BlockHeader) {
+	batch.delete(BLOCK_CF, hash);
+	batch.put(PRUNED_HEADER_CF, hash, header);
+}
+
+//record that the ledger levels up to `level` are pruned
+pub fn prunedLevel(batch: &mut ImportBatch, level: u64) {
+	batch.put(META_CF, PRUNED_LEVEL_KEY, &level);
+}
+
+//the ledger levels up to which `store` is pruned, 0 if it never was
+pub fn readPrunedLevel(store: &dyn ReadStore) -> Result<u64> {
+	store.get(META_CF, PRUNED_LEVEL_KEY)?.map(|level| decodeValue(META_CF, &level)).transpose().map(Option::unwrap_or_default)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use std::collections::HashMap;
+
+	#[test]
+	fn archiveNeverPrunes() {
+		assert_eq!(PruningMode::Archive.pruneTo(1_000_000), None);
+		assert_eq!(PruningMode::KeepLevels(10).pruneTo(9), None);
+		assert_eq!(PruningMode::KeepLevels(10).pruneTo(25), Some(15));
+		assert_eq!(PruningMode::default().pruneTo(DEFAULT_KEEP_LEVELS), Some(0));
+	}
+
+	#[test]
+	fn transactionsListedOnceInLedgerOrder() {
+		let (p1, p2, p3) = (H256::repeat_byte(1), H256::repeat_byte(2), H256::repeat_byte(3));
+		let (t1, t2, t3) = (H256::repeat_byte(11), H256::repeat_byte(12), H256::repeat_byte(13));
+		let levels = HashMap::from([(1, vec![p1]), (2, vec![p2, p3])]);
+		let references = HashMap::from([(p1, vec![t1, t2]), (p2, vec![t2, t3]), (p3, Vec::new())]);
+		let confirmed = |from, to| {
+			confirmedTransactions(|level| levels.get(&level).cloned(), |proposer| Ok(references[proposer].clone()), from, to)
+		};
+		assert_eq!(confirmed(1, 2), Ok(vec![t1, t2, t3]));
+		assert_eq!(confirmed(2, 2), Ok(vec![t2, t3]));
+		assert_eq!(confirmed(3, 5), Ok(Vec::new()));
+	}
+
+	#[test]
+	fn prunedBlockKeepsItsHeader() {
+		let hash = H256::repeat_byte(1);
+		let header = BlockHeader::new(H256::zero(), 1, 0, H256::zero(), Default::default(), H256::zero());
+		let mut batch = ImportBatch::new();
+		pruneBlock(&mut batch, hash, &header);
+		prunedLevel(&mut batch, 4);
+		assert_eq!(batch.get(BLOCK_CF, hash), Some(None));
+		assert_eq!(batch.get(PRUNED_HEADER_CF, hash), Some(Some(&header.encode()[..])));
+		assert_eq!(batch.get(META_CF, PRUNED_LEVEL_KEY), Some(Some(&4u64.encode()[..])));
+	}
+}
@@ -0,0 +1,586 @@
+//Snapshots of the block tree, to start a node without syncing the DAG from genesis.
+//
+//A snapshot is taken at the ledger tip from a consistent view of the database and holds:
+//  every proposer block, the proposer tree is small and the ledger refers to all of it
+//  the main chain of every voter chain down to `voterHistory` blocks below its tip, the oldest
+//    one an anchor whose parent is left out, so reorgs up to that depth can still be applied
+//  the transaction blocks the ledger has not confirmed yet, those before are executed already
+//  the ledger: voter tips, main chain votes, leaders and ledger order
+//The manifest commits to each of these with a hash and names the genesis it builds on. The file
+//only vouches for itself, anyone can edit it and seal a new manifest, so importing takes the
+//manifest hash from somewhere the node trusts, the operator who got it from a node they trust.
+//Before anything is written it checks that hash, every hash in the manifest, the parent links and
+//the work and miner seal of every block, then writes the whole snapshot as one batch along with
+//the manifest hash under its own META key.
+use codec::{Decode, DecodeAll, Encode};
+use sp_core::{H256, U256};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use crate::Block::{Block, BlockKind};
+use crate::Genesis::GenesisBlocks;
+use crate::Hashing::{hashWithContext, SNAPSHOT_CONTEXT};
+use super::Batch::{ImportBatch, PROPOSER_LEDGER_TIP_KEY, VOTER_LEDGER_TIPS_KEY};
+use super::Index::{IndexError, TreeIndex};
+use super::Ledger::{Ledger, LedgerState, LedgerUpdate};
+use super::Migration;
+use super::Prune;
+use super::Store::{ReadStore, Store, StoreError};
+use super::{
+	decodeValue, BLOCK_CF, META_CF, PROPOSER_LEADER_SEQUENCE_CF, PROPOSER_LEDGER_ORDER_CF, PROPOSER_NODE_VOTE_CF,
+	PROPOSER_TREE_LEVEL_CF, UNREFERRED_TRANSACTION_CF, VOTER_NODE_LEVEL_CF, VOTER_NODE_VOTED_LEVEL_CF,
+};
+
+pub const SNAPSHOT_VERSION: u8 = 1;
+pub const DEFAULT_VOTER_HISTORY: u64 = 128;
+
+//hash of the manifest of the snapshot the store was bootstrapped from
+const SNAPSHOT_MANIFEST_KEY: &[u8] = b"snapshotManifest";
+
+#[derive(Clone, PartialEq, Eq, Debug, Encode, Decode)]
+pub struct SnapshotManifest {
+	pub version: u8,
+	//hash of the proposer genesis block
+	pub genesis: H256,
+	//the ledger tip the snapshot was taken at and its leader
+	pub level: u64,
+	pub leader: H256,
+	pub voterHistory: u64,
+	pub proposerRoot: H256,
+	pub voterRoot: H256,
+	pub transactionRoot: H256,
+	pub ledgerRoot: H256,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Encode, Decode)]
+pub struct VoterChainSnapshot {
+	//level of the first block, whose voter parent is not in the snapshot
+	pub anchorLevel: u64,
+	//main chain blocks up to the tip, oldest first
+	pub blocks: Vec<Block>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Encode, Decode)]
+pub struct Snapshot {
+	pub manifest: SnapshotManifest,
+	//by level, genesis first
+	pub proposers: Vec<Block>,
+	//by chain number
+	pub voterChains: Vec<VoterChainSnapshot>,
+	pub transactions: Vec<Block>,
+	pub ledger: LedgerState,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SnapshotError {
+	Storage(StoreError),
+	Io(String),
+	Undecodable,
+	UnsupportedVersion(u8),
+	//the database has no ledger to take a snapshot of
+	NoLedger,
+	MissingBlock(H256),
+	//a block is in the wrong part of the snapshot or its content does not match its header
+	InvalidBlock(H256),
+	//the part of the snapshot that does not match the manifest
+	RootMismatch(&'static str),
+	//the manifest is not the one the importing node trusts
+	UntrustedManifest { expected: H256, found: H256 },
+	//a block whose parent is not where the snapshot has to hold it
+	BrokenLink(H256),
+	//a block not mined at the chain's difficulty or with its hash above the target
+	InsufficientWork(H256),
+	//a miner seal that does not verify against the block's coinbase
+	InvalidSeal(H256),
+	GenesisMismatch { expected: H256, found: H256 },
+	//snapshots only bootstrap an empty database
+	NotEmpty,
+	Index(IndexError),
+}
+
+impl core::fmt::Display for SnapshotError {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		match self {
+			SnapshotError::Storage(error) => write!(f, "store error: {}", error),
+			SnapshotError::Io(error) => write!(f, "snapshot file: {}", error),
+			SnapshotError::Undecodable => write!(f, "undecodable snapshot or database entry"),
+			SnapshotError::UnsupportedVersion(version) => write!(f, "unsupported snapshot version {}", version),
+			SnapshotError::NoLedger => write!(f, "no ledger to take a snapshot of"),
+			SnapshotError::MissingBlock(hash) => write!(f, "block {:?} is missing", hash),
+			SnapshotError::InvalidBlock(hash) => write!(f, "block {:?} is invalid", hash),
+			SnapshotError::RootMismatch(part) => write!(f, "the {} part does not match the manifest", part),
+			SnapshotError::UntrustedManifest { expected, found } => write!(f, "manifest {:?}, expected {:?}", found, expected),
+			SnapshotError::BrokenLink(hash) => write!(f, "block {:?} does not link to its parent", hash),
+			SnapshotError::InsufficientWork(hash) => write!(f, "block {:?} lacks the chain's proof of work", hash),
+			SnapshotError::InvalidSeal(hash) => write!(f, "block {:?} has an invalid miner seal", hash),
+			SnapshotError::GenesisMismatch { expected, found } => write!(f, "snapshot of genesis {:?}, expected {:?}", found, expected),
+			SnapshotError::NotEmpty => write!(f, "the database is not empty"),
+			SnapshotError::Index(error) => write!(f, "cannot index the snapshot: {:?}", error),
+		}
+	}
+}
+
+impl From<StoreError> for SnapshotError {
+	fn from(error: StoreError) -> Self {
+		SnapshotError::Storage(error)
+	}
+}
+
+impl From<IndexError> for SnapshotError {
+	fn from(error: IndexError) -> Self {
+		SnapshotError::Index(error)
+	}
+}
+
+fn blocksRoot(part: &str, blocks: &[Block]) -> H256 {
+	hashWithContext(SNAPSHOT_CONTEXT, &(part, blocks.iter().map(Block::hash).collect::<Vec<_>>()))
+}
+
+fn voterRoot(chains: &[VoterChainSnapshot]) -> H256 {
+	let chains: Vec<(u64, Vec<H256>)> = chains.iter()
+		.map(|chain| (chain.anchorLevel, chain.blocks.iter().map(Block::hash).collect()))
+		.collect();
+	hashWithContext(SNAPSHOT_CONTEXT, &("voters", chains))
+}
+
+fn ledgerRoot(ledger: &LedgerState) -> H256 {
+	hashWithContext(SNAPSHOT_CONTEXT, &("ledger", ledger))
+}
+
+impl SnapshotManifest {
+	pub fn hash(&self) -> H256 {
+		hashWithContext(SNAPSHOT_CONTEXT, &("manifest", self))
+	}
+}
+
+impl Snapshot {
+	//a snapshot of the given parts with the manifest committing to them
+	pub fn seal(
+		proposers: Vec<Block>,
+		voterChains: Vec<VoterChainSnapshot>,
+		transactions: Vec<Block>,
+		ledger: LedgerState,
+		voterHistory: u64,
+	) -> Result<Self, SnapshotError> {
+		let genesis = proposers.first().map(Block::hash).ok_or(SnapshotError::NoLedger)?;
+		let (level, _) = ledger.ledger.last().cloned().ok_or(SnapshotError::NoLedger)?;
+		let leader = ledger.leaders.iter().find(|(leaderLevel, _)| *leaderLevel == level).map(|(_, leader)| *leader).ok_or(SnapshotError::NoLedger)?;
+		let manifest = SnapshotManifest {
+			version: SNAPSHOT_VERSION,
+			genesis,
+			level,
+			leader,
+			voterHistory,
+			proposerRoot: blocksRoot("proposers", &proposers),
+			voterRoot: voterRoot(&voterChains),
+			transactionRoot: blocksRoot("transactions", &transactions),
+			ledgerRoot: ledgerRoot(&ledger),
+		};
+		Ok(Snapshot { manifest, proposers, voterChains, transactions, ledger })
+	}
+
+	//every hash in the manifest, every block against its own header and the links between blocks
+	pub fn verify(&self) -> Result<(), SnapshotError> {
+		let manifest = &self.manifest;
+		if manifest.version != SNAPSHOT_VERSION {
+			return Err(SnapshotError::UnsupportedVersion(manifest.version));
+		}
+		if blocksRoot("proposers", &self.proposers) != manifest.proposerRoot {
+			return Err(SnapshotError::RootMismatch("proposers"));
+		}
+		if voterRoot(&self.voterChains) != manifest.voterRoot {
+			return Err(SnapshotError::RootMismatch("voters"));
+		}
+		if blocksRoot("transactions", &self.transactions) != manifest.transactionRoot {
+			return Err(SnapshotError::RootMismatch("transactions"));
+		}
+		if ledgerRoot(&self.ledger) != manifest.ledgerRoot {
+			return Err(SnapshotError::RootMismatch("ledger"));
+		}
+		let kinds = self.proposers.iter().map(|block| (block, block.kind() == BlockKind::Proposer))
+			.chain(self.transactions.iter().map(|block| (block, block.kind() == BlockKind::Transaction)))
+			.chain(self.voterChains.iter().enumerate().flat_map(|(chain, snapshot)| {
+				snapshot.blocks.iter().map(move |block| (block, block.kind() == BlockKind::Voter(chain as u16)))
+			}));
+		let voterChains = self.voterChains.len() as u16;
+		for (block, rightKind) in kinds {
+			if !rightKind || !block.commitsContent(voterChains) {
+				return Err(SnapshotError::InvalidBlock(block.hash()));
+			}
+		}
+		if self.proposers.first().map(Block::hash) != Some(manifest.genesis) {
+			return Err(SnapshotError::RootMismatch("genesis"));
+		}
+		self.verifyLinks()?;
+		let tips: Vec<H256> = self.voterChains.iter().filter_map(|chain| chain.blocks.last().map(Block::hash)).collect();
+		let level = self.ledger.ledger.last().map(|(level, _)| *level);
+		let leader = self.ledger.leaders.iter().find(|(leaderLevel, _)| Some(*leaderLevel) == level).map(|(_, leader)| *leader);
+		if tips != self.ledger.voterTips || level != Some(manifest.level) || leader != Some(manifest.leader) {
+			return Err(SnapshotError::RootMismatch("ledger"));
+		}
+		Ok(())
+	}
+
+	//proposer blocks hang off a proposer block before them, genesis off zero, every other block off
+	//a proposer block of the snapshot, and voter blocks off the block before them on their chain
+	fn verifyLinks(&self) -> Result<(), SnapshotError> {
+		let mut proposers = HashSet::new();
+		for (i, block) in self.proposers.iter().enumerate() {
+			let parent = block.header().parent;
+			if (i == 0 && !parent.is_zero()) || (i > 0 && !proposers.contains(&parent)) {
+				return Err(SnapshotError::BrokenLink(block.hash()));
+			}
+			proposers.insert(block.hash());
+		}
+		for block in self.transactions.iter().chain(self.voterChains.iter().flat_map(|chain| chain.blocks.iter())) {
+			if !proposers.contains(&block.header().parent) {
+				return Err(SnapshotError::BrokenLink(block.hash()));
+			}
+		}
+		for chain in &self.voterChains {
+			for pair in chain.blocks.windows(2) {
+				if pair[1].asVoter().map(|voter| voter.content.parent) != Some(pair[0].hash()) {
+					return Err(SnapshotError::BrokenLink(pair[1].hash()));
+				}
+			}
+		}
+		Ok(())
+	}
+
+	//every block but the genesis blocks is mined at the genesis difficulty with its hash at or below
+	//it, and carries a valid miner seal like on block import
+	pub fn verifyWork(&self, genesis: &GenesisBlocks) -> Result<(), SnapshotError> {
+		let difficulty = genesis.proposer.header().difficulty;
+		let genesisHashes: HashSet<H256> = std::iter::once(genesis.proposerHash()).chain(genesis.voterHashes()).collect();
+		let blocks = self.proposers.iter()
+			.chain(self.transactions.iter())
+			.chain(self.voterChains.iter().flat_map(|chain| chain.blocks.iter()));
+		for block in blocks.filter(|block| !genesisHashes.contains(&block.hash())) {
+			let header = block.header();
+			let hash = block.hash();
+			if header.difficulty != difficulty || U256::from_big_endian(hash.as_bytes()) > U256::from_big_endian(difficulty.as_bytes()) {
+				return Err(SnapshotError::InsufficientWork(hash));
+			}
+			header.verify_miner_seal().map_err(|_| SnapshotError::InvalidSeal(hash))?;
+		}
+		Ok(())
+	}
+
+	pub fn writeTo(&self, path: &Path) -> Result<(), SnapshotError> {
+		std::fs::write(path, self.encode()).map_err(|error| SnapshotError::Io(error.to_string()))
+	}
+
+	//read and verify a snapshot file
+	pub fn readFrom(path: &Path) -> Result<Self, SnapshotError> {
+		let bytes = std::fs::read(path).map_err(|error| SnapshotError::Io(error.to_string()))?;
+		let snapshot = Snapshot::decode_all(&mut &bytes[..]).map_err(|_| SnapshotError::Undecodable)?;
+		snapshot.verify()?;
+		Ok(snapshot)
+	}
+}
+
+fn decode<T: Decode>(bytes: &[u8]) -> Result<T, SnapshotError> {
+	T::decode(&mut &bytes[..]).map_err(|_| SnapshotError::Undecodable)
+}
+
+fn hashKey(key: &[u8]) -> Result<H256, SnapshotError> {
+	match key.len() {
+		32 => Ok(H256::from_slice(key)),
+		_ => Err(SnapshotError::Undecodable),
+	}
+}
+
+fn levelOf(key: &[u8]) -> Result<u64, SnapshotError> {
+	let mut level = [0u8; 8];
+	match key.len() {
+		8 => level.copy_from_slice(key),
+		_ => return Err(SnapshotError::Undecodable),
+	}
+	Ok(u64::from_be_bytes(level))
+}
+
+//take a snapshot at the ledger tip, reading everything from one consistent view of `store`
+pub fn export(store: &impl Store, voterHistory: u64) -> Result<Snapshot, SnapshotError> {
+	store.consistent(|view| exportFrom(view, voterHistory))
+}
+
+fn exportFrom(view: &dyn ReadStore, voterHistory: u64) -> Result<Snapshot, SnapshotError> {
+	let read = |cf: &'static str, key: &[u8]| view.get(cf, key);
+	let scan = |cf: &'static str| view.scan(cf);
+	let block = |hash: &H256| -> Result<Block, SnapshotError> {
+		decode(&read(BLOCK_CF, hash.as_bytes())?.ok_or(SnapshotError::MissingBlock(*hash))?)
+	};
+
+	let voterTips: Vec<H256> = decode(&read(META_CF, VOTER_LEDGER_TIPS_KEY)?.ok_or(SnapshotError::NoLedger)?)?;
+	let mut proposers = Vec::new();
+	for (_, hashes) in scan(PROPOSER_TREE_LEVEL_CF)? {
+		for hash in decode::<Vec<H256>>(&hashes)? {
+			proposers.push(block(&hash)?);
+		}
+	}
+	let genesis = proposers.first().map(Block::hash).ok_or(SnapshotError::NoLedger)?;
+
+	//the genesis level is where every ledger starts and is never written
+	let mut ledger = vec![(0, vec![genesis])];
+	for (level, blocks) in scan(PROPOSER_LEDGER_ORDER_CF)? {
+		ledger.push((levelOf(&level)?, decode(&blocks)?));
+	}
+	let mut leaders = vec![(0, genesis)];
+	for (level, leader) in scan(PROPOSER_LEADER_SEQUENCE_CF)? {
+		leaders.push((levelOf(&level)?, decode(&leader)?));
+	}
+	let mut votes = Vec::new();
+	for (proposer, proposerVotes) in scan(PROPOSER_NODE_VOTE_CF)? {
+		let mut proposerVotes: Vec<(u16, u64)> = decode(&proposerVotes)?;
+		proposerVotes.sort();
+		votes.push((hashKey(&proposer)?, proposerVotes));
+	}
+	votes.sort();
+	//leaders above the ledger tip wait for a lower level to get one, they stay in the snapshot
+	let ledgerTip: u64 = read(META_CF, PROPOSER_LEDGER_TIP_KEY)?.map(|tip| decode(&tip)).transpose()?.unwrap_or(0);
+	if ledger.last().map(|(level, _)| *level) != Some(ledgerTip) {
+		return Err(SnapshotError::RootMismatch("ledger"));
+	}
+
+	let mut voterChains = Vec::new();
+	for tip in &voterTips {
+		let mut blocks = Vec::new();
+		let mut hash = *tip;
+		loop {
+			let voter = block(&hash)?;
+			let parent = voter.asVoter().map(|voter| voter.content.parent).ok_or(SnapshotError::InvalidBlock(hash))?;
+			blocks.push(voter);
+			if parent.is_zero() || blocks.len() as u64 > voterHistory {
+				break;
+			}
+			hash = parent;
+		}
+		blocks.reverse();
+		let anchor = blocks[0].hash();
+		let anchorLevel = decode(&read(VOTER_NODE_LEVEL_CF, anchor.as_bytes())?.ok_or(SnapshotError::MissingBlock(anchor))?)?;
+		voterChains.push(VoterChainSnapshot { anchorLevel, blocks });
+	}
+
+	//transaction blocks referred to by proposer blocks the ledger has not confirmed, and those no
+	//proposer block refers to yet
+	let confirmed: HashSet<H256> = ledger.iter().flat_map(|(_, blocks)| blocks.iter().copied()).collect();
+	let mut pending = Vec::new();
+	for proposer in proposers.iter().filter(|proposer| !confirmed.contains(&proposer.hash())) {
+		pending.extend(proposer.transactionReferences().unwrap_or_default().iter().copied());
+	}
+	for (transaction, _) in scan(UNREFERRED_TRANSACTION_CF)? {
+		pending.push(hashKey(&transaction)?);
+	}
+	let mut seen = HashSet::new();
+	let mut transactions = Vec::new();
+	for hash in pending {
+		if seen.insert(hash) {
+			transactions.push(block(&hash)?);
+		}
+	}
+
+	let state = LedgerState { voterTips, votes, leaders, ledger };
+	Snapshot::seal(proposers, voterChains, transactions, state, voterHistory)
+}
+
+//bootstrap an empty database of the chain starting at `genesis` from a snapshot whose manifest
+//hashes to `trustedManifest`
+pub fn import(store: &impl Store, snapshot: &Snapshot, genesis: &GenesisBlocks, trustedManifest: H256) -> Result<(), SnapshotError> {
+	let found = snapshot.manifest.hash();
+	if found != trustedManifest {
+		return Err(SnapshotError::UntrustedManifest { expected: trustedManifest, found });
+	}
+	snapshot.verify()?;
+	if snapshot.manifest.genesis != genesis.proposerHash() {
+		return Err(SnapshotError::GenesisMismatch { expected: genesis.proposerHash(), found: snapshot.manifest.genesis });
+	}
+	snapshot.verifyWork(genesis)?;
+	if !store.isEmpty(BLOCK_CF)? {
+		return Err(SnapshotError::NotEmpty);
+	}
+
+	let mut index = TreeIndex::new();
+	let mut all = ImportBatch::new();
+	//the voted level of the block below each anchor, the highest level its chain voted on up to there
+	let mut anchorParents = HashMap::new();
+	for (chain, snapshot) in snapshot.voterChains.iter().enumerate() {
+		if let Some(anchor) = snapshot.blocks.first().and_then(|block| block.asVoter()) {
+			anchorParents.insert(anchor.content.parent, (chain as u16, snapshot.anchorLevel));
+		}
+	}
+
+	let blocks = snapshot.proposers.iter().chain(snapshot.transactions.iter()).map(|block| (block, None))
+		.chain(snapshot.voterChains.iter().flat_map(|chain| {
+			chain.blocks.iter().enumerate().map(move |(i, block)| (block, (i == 0).then_some(chain.anchorLevel)))
+		}));
+	for (block, anchorLevel) in blocks {
+		let indexed = match anchorLevel {
+			Some(level) => index.insertAnchor(block, level)?,
+			None => index.insert(block)?,
+		};
+		let mut one = ImportBatch::new();
+		one.block(block, &indexed, &index, |cf, key| {
+			if cf == VOTER_NODE_VOTED_LEVEL_CF && key.len() == 32 {
+				if let Some((chain, anchorLevel)) = anchorParents.get(&H256::from_slice(key)) {
+					return Ok(Some(votedLevel(snapshot, &index, *chain, *anchorLevel).encode()));
+				}
+			}
+			Ok(all.get(cf, key).flatten().map(|value| value.to_vec()))
+		})?;
+		all.append(one);
+	}
+
+	let mut ledger = Ledger::fromState(snapshot.ledger.clone()).ok_or(SnapshotError::NoLedger)?;
+	let mut changes = ledger.takeChanges();
+	//the genesis level is never written, see exportFrom
+	changes.leaderLevels.remove(&0);
+	let added = snapshot.ledger.ledger.iter().filter(|(level, _)| *level > 0).cloned().collect();
+	let update = LedgerUpdate { removed: Vec::new(), added };
+	all.ledger(&ledger, &changes, &update);
+	//no transaction block below the snapshot level is in the database
+	Prune::prunedLevel(&mut all, snapshot.manifest.level);
+	Migration::stamp(&mut all);
+	all.put(META_CF, SNAPSHOT_MANIFEST_KEY, &found);
+
+	all.write(store)?;
+	Ok(())
+}
+
+//the manifest hash of the snapshot `store` was bootstrapped from, none if it synced from genesis
+pub fn importedSnapshot(store: &dyn ReadStore) -> Result<Option<H256>, SnapshotError> {
+	Ok(store.get(META_CF, SNAPSHOT_MANIFEST_KEY)?.map(|hash| decodeValue(META_CF, &hash)).transpose()?)
+}
+
+//the highest proposer level voter chain `chain` voted on with its blocks below `anchorLevel`
+fn votedLevel(snapshot: &Snapshot, index: &TreeIndex, chain: u16, anchorLevel: u64) -> u64 {
+	snapshot.ledger.votes.iter()
+		.filter(|(_, votes)| votes.iter().any(|(voteChain, voterLevel)| *voteChain == chain && *voterLevel < anchorLevel))
+		.filter_map(|(proposer, _)| index.get(proposer).map(|proposer| proposer.level))
+		.max()
+		.unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::BlockTree;
+	use super::super::Fixtures::block;
+	use super::super::Store::MemoryStore;
+	use crate::Block::{Content, TransactionBlock};
+	use crate::Genesis::PrismGenesis;
+
+	fn snapshot() -> Snapshot {
+		let genesis = PrismGenesis { voterChains: 2, timestamp: 1, difficulty: H256::repeat_byte(0xff) }.blocks().unwrap();
+		let proposerHash = genesis.proposerHash();
+		let voterChains = genesis.voters.iter().map(|voter| VoterChainSnapshot { anchorLevel: 0, blocks: vec![voter.clone()] }).collect();
+		let ledger = LedgerState {
+			voterTips: genesis.voterHashes(),
+			votes: Vec::new(),
+			leaders: vec![(0, proposerHash)],
+			ledger: vec![(0, vec![proposerHash])],
+		};
+		Snapshot::seal(vec![genesis.proposer], voterChains, Vec::new(), ledger, DEFAULT_VOTER_HISTORY).unwrap()
+	}
+
+	#[test]
+	fn sealedSnapshotVerifies() {
+		let snapshot = snapshot();
+		assert_eq!(snapshot.manifest.level, 0);
+		assert_eq!(snapshot.manifest.leader, snapshot.manifest.genesis);
+		assert_eq!(snapshot.verify(), Ok(()));
+		let decoded = Snapshot::decode_all(&mut &snapshot.encode()[..]).unwrap();
+		assert_eq!(decoded, snapshot);
+		assert_eq!(decoded.manifest.hash(), snapshot.manifest.hash());
+	}
+
+	#[test]
+	fn tamperingIsCaught() {
+		let mut swapped = snapshot();
+		swapped.voterChains.swap(0, 1);
+		assert_eq!(swapped.verify(), Err(SnapshotError::RootMismatch("voters")));
+
+		let mut votes = snapshot();
+		votes.ledger.votes.push((H256::repeat_byte(1), vec![(0, 1)]));
+		assert_eq!(votes.verify(), Err(SnapshotError::RootMismatch("ledger")));
+
+		//a voter block passed off as a proposer block, with the manifest updated to match
+		let original = snapshot();
+		let voter = original.voterChains[0].blocks[0].clone();
+		let mut proposers = original.proposers.clone();
+		proposers.push(voter.clone());
+		let resealed = Snapshot::seal(proposers, original.voterChains, Vec::new(), original.ledger, DEFAULT_VOTER_HISTORY).unwrap();
+		assert_eq!(resealed.verify(), Err(SnapshotError::InvalidBlock(voter.hash())));
+
+		let mut version = snapshot();
+		version.manifest.version = SNAPSHOT_VERSION + 1;
+		assert_eq!(version.verify(), Err(SnapshotError::UnsupportedVersion(SNAPSHOT_VERSION + 1)));
+	}
+
+	#[test]
+	fn importedStoreExportsTheSameSnapshot() {
+		let genesis = PrismGenesis { voterChains: 2, timestamp: 1, difficulty: H256::repeat_byte(0xff) }.blocks().unwrap();
+		let tree = BlockTree::new(MemoryStore::new(), &genesis, Prune::PruningMode::Archive).unwrap();
+		let exported = export(tree.store(), DEFAULT_VOTER_HISTORY).unwrap();
+		assert_eq!(exported.verify(), Ok(()));
+
+		let trusted = exported.manifest.hash();
+
+		let fresh = MemoryStore::new();
+		let other = PrismGenesis { voterChains: 2, timestamp: 2, difficulty: H256::repeat_byte(0xff) }.blocks().unwrap();
+		assert_eq!(import(&fresh, &exported, &other, trusted), Err(SnapshotError::GenesisMismatch { expected: other.proposerHash(), found: genesis.proposerHash() }));
+		assert_eq!(importedSnapshot(&fresh), Ok(None));
+		import(&fresh, &exported, &genesis, trusted).unwrap();
+		assert_eq!(export(&fresh, DEFAULT_VOTER_HISTORY), Ok(exported.clone()));
+		assert_eq!(importedSnapshot(&fresh), Ok(Some(trusted)));
+		assert_eq!(super::super::Batch::lastImport(&fresh), Ok(None));
+		assert_eq!(import(&fresh, &exported, &genesis, trusted), Err(SnapshotError::NotEmpty));
+	}
+
+	#[test]
+	fn resealedSnapshotNeedsTheTrustedManifest() {
+		let genesis = PrismGenesis { voterChains: 2, timestamp: 1, difficulty: H256::repeat_byte(0xff) }.blocks().unwrap();
+		let original = snapshot();
+		let mut ledger = original.ledger.clone();
+		ledger.votes.push((genesis.proposerHash(), vec![(0, 0)]));
+		let resealed = Snapshot::seal(original.proposers.clone(), original.voterChains.clone(), Vec::new(), ledger, DEFAULT_VOTER_HISTORY).unwrap();
+		assert_eq!(resealed.verify(), Ok(()));
+
+		let store = MemoryStore::new();
+		assert_eq!(
+			import(&store, &resealed, &genesis, original.manifest.hash()),
+			Err(SnapshotError::UntrustedManifest { expected: original.manifest.hash(), found: resealed.manifest.hash() })
+		);
+		assert!(store.isEmpty(BLOCK_CF).unwrap());
+	}
+
+	#[test]
+	fn blocksNeedLinksAndWork() {
+		let genesis = PrismGenesis { voterChains: 2, timestamp: 1, difficulty: H256::repeat_byte(0xff) }.blocks().unwrap();
+		let original = snapshot();
+		let content = || Content::TransactionBlock(TransactionBlock::Content::new(Vec::new()));
+
+		let orphan = block(H256::repeat_byte(9), 0, content());
+		let orphaned = Snapshot::seal(original.proposers.clone(), original.voterChains.clone(), vec![orphan.clone()], original.ledger.clone(), DEFAULT_VOTER_HISTORY).unwrap();
+		assert_eq!(orphaned.verify(), Err(SnapshotError::BrokenLink(orphan.hash())));
+
+		//the fixture mines at difficulty zero, not the genesis difficulty
+		let unmined = block(genesis.proposerHash(), 0, content());
+		let unmined = Snapshot::seal(original.proposers.clone(), original.voterChains.clone(), vec![unmined.clone()], original.ledger.clone(), DEFAULT_VOTER_HISTORY).unwrap();
+		assert_eq!(unmined.verify(), Ok(()));
+		let store = MemoryStore::new();
+		assert_eq!(import(&store, &unmined, &genesis, unmined.manifest.hash()), Err(SnapshotError::InsufficientWork(unmined.transactions[0].hash())));
+		assert!(store.isEmpty(BLOCK_CF).unwrap());
+
+		let (pk, sk) = primitives::FalconHostFunctions::generate_keypair();
+		let mut header = unmined.transactions[0].header().clone()
+			.with_coinbase(sp_core::crypto::AccountId32::new(primitives::FalconHostFunctions::hash_public_key(&pk)));
+		header.difficulty = genesis.proposer.header().difficulty;
+		let unsealed = Block::fromParts(header.clone(), content(), Vec::new());
+		let unsealed = Snapshot::seal(original.proposers.clone(), original.voterChains.clone(), vec![unsealed], original.ledger.clone(), DEFAULT_VOTER_HISTORY).unwrap();
+		assert_eq!(unsealed.verifyWork(&genesis), Err(SnapshotError::InvalidSeal(header.hash())));
+
+		header.seal_with_miner_key(&pk, &sk);
+		let mined = Block::fromParts(header, content(), Vec::new());
+		let mined = Snapshot::seal(original.proposers, original.voterChains, vec![mined], original.ledger, DEFAULT_VOTER_HISTORY).unwrap();
+		assert_eq!(mined.verifyWork(&genesis), Ok(()));
+	}
+}
@@ -0,0 +1,439 @@
+//Storage behind the block tree.
+//
+//The tree only needs column families of byte keys and values, read one key at a time or scanned
+//in key order, and written in atomic batches. `RocksStore` keeps them in RocksDB, `MemoryStore` in
+//memory for tests and simulations. Both pass the same conformance suite below, so anything tested
+//on a `MemoryStore` holds on disk. A column family the tree does not declare in COLUMN_FAMILIES
+//is a bug and panics in both.
+use rocksdb::{DBRawIterator, WriteBatch, WriteOptions, DB};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use super::Batch::WriteOp;
+use super::{Result, COLUMN_FAMILIES};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct StoreError(pub String);
+
+impl From<rocksdb::Error> for StoreError {
+	fn from(error: rocksdb::Error) -> Self {
+		StoreError(error.into_string())
+	}
+}
+
+impl core::fmt::Display for StoreError {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+pub trait ReadStore {
+	fn get(&self, cf: &'static str, key: &[u8]) -> Result<Option<Vec<u8>>>;
+	//every entry of `cf`, in key order
+	fn scan(&self, cf: &'static str) -> Result<Vec<(Vec<u8>, Vec<u8>)>>;
+	//the entries of `cf` whose key starts with `prefix`, in key order
+	fn scanPrefix(&self, cf: &'static str, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>>;
+	//at most `limit` entries of `cf` from the key `from` on, in key order, to walk a column family
+	//without holding all of it
+	fn scanPage(&self, cf: &'static str, from: &[u8], limit: usize) -> Result<Vec<(Vec<u8>, Vec<u8>)>>;
+	fn isEmpty(&self, cf: &'static str) -> Result<bool>;
+}
+
+pub trait Store: ReadStore + Send + Sync {
+	//apply all of `ops` in order, or none of them, durably
+	fn write(&self, ops: Vec<WriteOp>) -> Result<()>;
+	//run `read` against a view of the store no write changes while it runs
+	fn consistent<R>(&self, read: impl FnOnce(&dyn ReadStore) -> R) -> R;
+}
+
+const MISSING_CF: &str = "every column family is created when the store is opened; qed";
+
+pub struct RocksStore {
+	db: DB,
+}
+
+impl RocksStore {
+	//open the store at `path` with every column family, creating what is missing. Column families
+	//of a newer schema are opened too, so the schema version can be read and the store refused, see
+	//Migration
+	pub fn open(path: &Path) -> Result<Self> {
+		Self::openWith(path, true)
+	}
+
+	//open the store at `path`, failing if there is none instead of creating an empty one
+	pub fn openExisting(path: &Path) -> Result<Self> {
+		Self::openWith(path, false)
+	}
+
+	fn openWith(path: &Path, createIfMissing: bool) -> Result<Self> {
+		let mut options = rocksdb::Options::default();
+		options.create_if_missing(createIfMissing);
+		options.create_missing_column_families(true);
+		let mut columns: Vec<String> = COLUMN_FAMILIES.iter().map(|cf| cf.to_string()).collect();
+		for existing in DB::list_cf(&options, path).unwrap_or_default() {
+			if !columns.contains(&existing) {
+				columns.push(existing);
+			}
+		}
+		Ok(RocksStore { db: DB::open_cf(&options, path, columns)? })
+	}
+
+	fn handle(&self, cf: &str) -> &rocksdb::ColumnFamily {
+		self.db.cf_handle(cf).expect(MISSING_CF)
+	}
+}
+
+//a raw iterator ends on the first error as well as at the end, only its status tells them apart
+fn scanAll(mut iterator: DBRawIterator) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+	let mut entries = Vec::new();
+	iterator.seek_to_first();
+	while let (Some(key), Some(value)) = (iterator.key(), iterator.value()) {
+		entries.push((key.to_vec(), value.to_vec()));
+		iterator.next();
+	}
+	iterator.status()?;
+	Ok(entries)
+}
+
+fn scanFrom(mut iterator: DBRawIterator, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+	let mut entries = Vec::new();
+	iterator.seek(prefix);
+	while let (Some(key), Some(value)) = (iterator.key(), iterator.value()) {
+		if !key.starts_with(prefix) {
+			break;
+		}
+		entries.push((key.to_vec(), value.to_vec()));
+		iterator.next();
+	}
+	iterator.status()?;
+	Ok(entries)
+}
+
+fn scanPage(mut iterator: DBRawIterator, from: &[u8], limit: usize) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+	let mut entries = Vec::new();
+	iterator.seek(from);
+	while let (Some(key), Some(value)) = (iterator.key(), iterator.value()) {
+		if entries.len() == limit {
+			break;
+		}
+		entries.push((key.to_vec(), value.to_vec()));
+		iterator.next();
+	}
+	iterator.status()?;
+	Ok(entries)
+}
+
+fn isEmpty(mut iterator: DBRawIterator) -> Result<bool> {
+	iterator.seek_to_first();
+	let empty = !iterator.valid();
+	iterator.status()?;
+	Ok(empty)
+}
+
+impl ReadStore for RocksStore {
+	fn get(&self, cf: &'static str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+		Ok(self.db.get_cf(self.handle(cf), key)?)
+	}
+
+	fn scan(&self, cf: &'static str) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+		scanAll(self.db.raw_iterator_cf(self.handle(cf)))
+	}
+
+	fn scanPrefix(&self, cf: &'static str, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+		scanFrom(self.db.raw_iterator_cf(self.handle(cf)), prefix)
+	}
+
+	fn scanPage(&self, cf: &'static str, from: &[u8], limit: usize) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+		scanPage(self.db.raw_iterator_cf(self.handle(cf)), from, limit)
+	}
+
+	fn isEmpty(&self, cf: &'static str) -> Result<bool> {
+		isEmpty(self.db.raw_iterator_cf(self.handle(cf)))
+	}
+}
+
+impl Store for RocksStore {
+	fn write(&self, ops: Vec<WriteOp>) -> Result<()> {
+		let mut batch = WriteBatch::default();
+		for op in ops {
+			match op.value {
+				Some(value) => batch.put_cf(self.handle(op.cf), op.key, value),
+				None => batch.delete_cf(self.handle(op.cf), op.key),
+			}
+		}
+		let mut options = WriteOptions::default();
+		options.set_sync(true);
+		Ok(self.db.write_opt(batch, &options)?)
+	}
+
+	fn consistent<R>(&self, read: impl FnOnce(&dyn ReadStore) -> R) -> R {
+		read(&RocksView { store: self, snapshot: self.db.snapshot() })
+	}
+}
+
+struct RocksView<'a> {
+	store: &'a RocksStore,
+	snapshot: rocksdb::Snapshot<'a>,
+}
+
+impl<'a> ReadStore for RocksView<'a> {
+	fn get(&self, cf: &'static str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+		Ok(self.snapshot.get_cf(self.store.handle(cf), key)?)
+	}
+
+	fn scan(&self, cf: &'static str) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+		scanAll(self.snapshot.raw_iterator_cf(self.store.handle(cf)))
+	}
+
+	fn scanPrefix(&self, cf: &'static str, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+		scanFrom(self.snapshot.raw_iterator_cf(self.store.handle(cf)), prefix)
+	}
+
+	fn scanPage(&self, cf: &'static str, from: &[u8], limit: usize) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+		scanPage(self.snapshot.raw_iterator_cf(self.store.handle(cf)), from, limit)
+	}
+
+	fn isEmpty(&self, cf: &'static str) -> Result<bool> {
+		isEmpty(self.snapshot.raw_iterator_cf(self.store.handle(cf)))
+	}
+}
+
+type Columns = HashMap<&'static str, BTreeMap<Vec<u8>, Vec<u8>>>;
+
+//a view shares the columns it was taken from, a write copies them only while a view is alive
+pub struct MemoryStore {
+	columns: RwLock<Arc<Columns>>,
+}
+
+impl Default for MemoryStore {
+	fn default() -> Self {
+		MemoryStore { columns: RwLock::new(Arc::new(COLUMN_FAMILIES.iter().map(|cf| (*cf, BTreeMap::new())).collect())) }
+	}
+}
+
+impl MemoryStore {
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+struct MemoryView(Arc<Columns>);
+
+fn column<'a>(columns: &'a Columns, cf: &str) -> &'a BTreeMap<Vec<u8>, Vec<u8>> {
+	columns.get(cf).expect(MISSING_CF)
+}
+
+impl ReadStore for MemoryView {
+	fn get(&self, cf: &'static str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+		Ok(column(&self.0, cf).get(key).cloned())
+	}
+
+	fn scan(&self, cf: &'static str) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+		Ok(column(&self.0, cf).iter().map(|(key, value)| (key.clone(), value.clone())).collect())
+	}
+
+	fn scanPrefix(&self, cf: &'static str, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+		Ok(column(&self.0, cf)
+			.range(prefix.to_vec()..)
+			.take_while(|(key, _)| key.starts_with(prefix))
+			.map(|(key, value)| (key.clone(), value.clone()))
+			.collect())
+	}
+
+	fn scanPage(&self, cf: &'static str, from: &[u8], limit: usize) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+		Ok(column(&self.0, cf)
+			.range(from.to_vec()..)
+			.take(limit)
+			.map(|(key, value)| (key.clone(), value.clone()))
+			.collect())
+	}
+
+	fn isEmpty(&self, cf: &'static str) -> Result<bool> {
+		Ok(column(&self.0, cf).is_empty())
+	}
+}
+
+impl MemoryStore {
+	fn view(&self) -> MemoryView {
+		MemoryView(self.columns.read().unwrap().clone())
+	}
+}
+
+impl ReadStore for MemoryStore {
+	fn get(&self, cf: &'static str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+		self.view().get(cf, key)
+	}
+
+	fn scan(&self, cf: &'static str) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+		self.view().scan(cf)
+	}
+
+	fn scanPrefix(&self, cf: &'static str, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+		self.view().scanPrefix(cf, prefix)
+	}
+
+	fn scanPage(&self, cf: &'static str, from: &[u8], limit: usize) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+		self.view().scanPage(cf, from, limit)
+	}
+
+	fn isEmpty(&self, cf: &'static str) -> Result<bool> {
+		self.view().isEmpty(cf)
+	}
+}
+
+impl Store for MemoryStore {
+	fn write(&self, ops: Vec<WriteOp>) -> Result<()> {
+		//check every column family before locking or changing anything, like a rocksdb batch
+		for op in &ops {
+			if !COLUMN_FAMILIES.contains(&op.cf) {
+				panic!("{}", MISSING_CF);
+			}
+		}
+		let mut guard = self.columns.write().unwrap();
+		let columns = Arc::make_mut(&mut guard);
+		for op in ops {
+			let column = columns.get_mut(op.cf).expect(MISSING_CF);
+			match op.value {
+				Some(value) => { column.insert(op.key, value); },
+				None => { column.remove(&op.key); },
+			}
+		}
+		Ok(())
+	}
+
+	fn consistent<R>(&self, read: impl FnOnce(&dyn ReadStore) -> R) -> R {
+		read(&self.view())
+	}
+}
+
+//the behaviour every store has to share, run against each implementation
+#[cfg(test)]
+pub mod conformance {
+	use super::*;
+	use super::super::{BLOCK_CF, META_CF};
+
+	fn put(cf: &'static str, key: &[u8], value: &[u8]) -> WriteOp {
+		WriteOp { cf, key: key.to_vec(), value: Some(value.to_vec()) }
+	}
+
+	fn delete(cf: &'static str, key: &[u8]) -> WriteOp {
+		WriteOp { cf, key: key.to_vec(), value: None }
+	}
+
+	pub fn readsWhatWasWritten(store: &impl Store) {
+		assert_eq!(store.get(META_CF, b"a").unwrap(), None);
+		assert!(store.isEmpty(META_CF).unwrap());
+		store.write(vec![put(META_CF, b"a", b"1"), put(BLOCK_CF, b"a", b"2")]).unwrap();
+		assert_eq!(store.get(META_CF, b"a").unwrap(), Some(b"1".to_vec()));
+		assert_eq!(store.get(BLOCK_CF, b"a").unwrap(), Some(b"2".to_vec()));
+		assert!(!store.isEmpty(META_CF).unwrap());
+		store.write(vec![delete(META_CF, b"a"), delete(META_CF, b"missing")]).unwrap();
+		assert_eq!(store.get(META_CF, b"a").unwrap(), None);
+		assert_eq!(store.get(BLOCK_CF, b"a").unwrap(), Some(b"2".to_vec()));
+	}
+
+	pub fn laterOpsWin(store: &impl Store) {
+		store.write(vec![put(META_CF, b"k", b"1"), delete(META_CF, b"k"), put(META_CF, b"k", b"2")]).unwrap();
+		assert_eq!(store.get(META_CF, b"k").unwrap(), Some(b"2".to_vec()));
+		store.write(vec![put(META_CF, b"k", b"3"), delete(META_CF, b"k")]).unwrap();
+		assert_eq!(store.get(META_CF, b"k").unwrap(), None);
+	}
+
+	pub fn scansInKeyOrder(store: &impl Store) {
+		let keys: [&[u8]; 4] = [&[2], &[0, 1], &[1], &[0]];
+		store.write(keys.iter().map(|key| put(META_CF, key, key)).collect()).unwrap();
+		let scanned: Vec<Vec<u8>> = store.scan(META_CF).unwrap().into_iter().map(|(key, _)| key).collect();
+		assert_eq!(scanned, vec![vec![0], vec![0, 1], vec![1], vec![2]]);
+		//big endian levels scan in level order
+		store.write(vec![put(BLOCK_CF, &256u64.to_be_bytes(), b""), put(BLOCK_CF, &1u64.to_be_bytes(), b"")]).unwrap();
+		let levels: Vec<Vec<u8>> = store.scan(BLOCK_CF).unwrap().into_iter().map(|(key, _)| key).collect();
+		assert_eq!(levels, vec![1u64.to_be_bytes().to_vec(), 256u64.to_be_bytes().to_vec()]);
+	}
+
+	pub fn scansAPrefix(store: &impl Store) {
+		let keys: [&[u8]; 5] = [&[1, 2], &[1], &[0, 9], &[1, 0, 5], &[2]];
+		store.write(keys.iter().map(|key| put(META_CF, key, key)).collect()).unwrap();
+		let scanned = |prefix: &[u8]| -> Vec<Vec<u8>> { store.scanPrefix(META_CF, prefix).unwrap().into_iter().map(|(key, _)| key).collect() };
+		assert_eq!(scanned(&[1]), vec![vec![1], vec![1, 0, 5], vec![1, 2]]);
+		assert_eq!(scanned(&[1, 0]), vec![vec![1, 0, 5]]);
+		assert_eq!(scanned(&[3]), Vec::<Vec<u8>>::new());
+		store.consistent(|view| {
+			store.write(vec![put(META_CF, &[1, 1], b"")]).unwrap();
+			assert_eq!(view.scanPrefix(META_CF, &[1]).unwrap().len(), 3);
+		});
+		assert_eq!(scanned(&[1]).len(), 4);
+	}
+
+	pub fn scansInPages(store: &impl Store) {
+		let keys: [&[u8]; 5] = [&[1, 2], &[1], &[0, 9], &[1, 0, 5], &[2]];
+		store.write(keys.iter().map(|key| put(META_CF, key, key)).collect()).unwrap();
+		let page = |from: &[u8], limit| -> Vec<Vec<u8>> { store.scanPage(META_CF, from, limit).unwrap().into_iter().map(|(key, _)| key).collect() };
+		assert_eq!(page(&[], 2), vec![vec![0, 9], vec![1]]);
+		assert_eq!(page(&[1, 0], 2), vec![vec![1, 0, 5], vec![1, 2]]);
+		assert_eq!(page(&[1, 2, 0], 5), vec![vec![2]]);
+		assert_eq!(page(&[3], 5), Vec::<Vec<u8>>::new());
+		assert_eq!(page(&[], 0), Vec::<Vec<u8>>::new());
+		store.consistent(|view| {
+			store.write(vec![put(META_CF, &[0], b"")]).unwrap();
+			assert_eq!(view.scanPage(META_CF, &[], 1).unwrap()[0].0, vec![0, 9]);
+		});
+		assert_eq!(page(&[], 1), vec![vec![0]]);
+	}
+
+	pub fn viewsAreConsistent(store: &impl Store) {
+		store.write(vec![put(META_CF, b"k", b"before")]).unwrap();
+		store.consistent(|view| {
+			store.write(vec![put(META_CF, b"k", b"after"), put(META_CF, b"new", b"")]).unwrap();
+			assert_eq!(view.get(META_CF, b"k").unwrap(), Some(b"before".to_vec()));
+			assert_eq!(view.scan(META_CF).unwrap().len(), 1);
+		});
+		assert_eq!(store.get(META_CF, b"k").unwrap(), Some(b"after".to_vec()));
+	}
+
+	pub fn unknownColumnFamilyChangesNothing(store: &impl Store) {
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| store.write(vec![put(META_CF, b"k", b"v"), put("UNKNOWN", b"k", b"v")])));
+		assert!(result.is_err());
+		assert_eq!(store.get(META_CF, b"k").unwrap(), None);
+	}
+
+	pub fn all<S: Store>(fresh: impl Fn() -> S) {
+		readsWhatWasWritten(&fresh());
+		laterOpsWin(&fresh());
+		scansInKeyOrder(&fresh());
+		scansAPrefix(&fresh());
+		scansInPages(&fresh());
+		viewsAreConsistent(&fresh());
+		unknownColumnFamilyChangesNothing(&fresh());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn memoryStoreConforms() {
+		conformance::all(MemoryStore::new);
+	}
+
+	#[test]
+	fn rocksStoreConforms() {
+		let root = std::env::temp_dir().join(format!("prism-store-{}", std::process::id()));
+		let count = std::sync::atomic::AtomicUsize::new(0);
+		conformance::all(|| {
+			let path = root.join(count.fetch_add(1, std::sync::atomic::Ordering::SeqCst).to_string());
+			RocksStore::open(&path).unwrap()
+		});
+		let _ = std::fs::remove_dir_all(&root);
+	}
+
+	#[test]
+	fn missingRocksStoreIsNotCreated() {
+		let path = std::env::temp_dir().join(format!("prism-store-missing-{}", std::process::id()));
+		assert!(RocksStore::openExisting(&path).is_err());
+		assert!(!path.exists());
+		RocksStore::open(&path).unwrap();
+		assert!(RocksStore::openExisting(&path).is_ok());
+		let _ = std::fs::remove_dir_all(&path);
+	}
+}
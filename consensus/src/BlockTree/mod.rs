@@ -0,0 +1,886 @@
+use codec::Decode;
+use sp_core::H256;
+use std::cell::RefCell;
+use std::sync::{atomic::{AtomicBool, Ordering}, mpsc::Sender, Mutex, RwLock};
+pub mod Batch;
+#[cfg(test)]
+mod Fixtures;
+pub mod Graph;
+pub mod Index;
+pub mod Ledger;
+pub mod Migration;
+pub mod Prune;
+pub mod Snapshot;
+pub mod Store;
+use Batch::ImportBatch;
+use Graph::{Edge, ReferenceClosure, Referrer};
+use Index::{IndexError, IndexedBlock, TreeId, TreeIndex};
+use Ledger::{Ledger as VoteLedger, LedgerState, LedgerUpdate, LedgerView, ReorgError};
+use Prune::PruningMode;
+use Store::{ReadStore, Store as TreeStore, StoreError};
+use crate::Block::{Block, BlockKind, ExtraContent::Deployment, Header::BlockHeader, Limits::{PayloadError, PayloadLimits}};
+use crate::Genesis::GenesisBlocks;
+use crate::Validation::ChainView;
+
+
+// Column family names for node/chain metadata
+const PROPOSER_NODE_LEVEL_CF: &str = "PROPOSER_NODE_LEVEL"; // hash to node level (u64)
+const VOTER_NODE_LEVEL_CF: &str = "VOTER_NODE_LEVEL"; // hash to node level (u64)
+const VOTER_NODE_CHAIN_CF: &str = "VOTER_NODE_CHAIN"; // hash to chain number (u16)
+const VOTER_TREE_LEVEL_COUNT_CF: &str = "VOTER_TREE_LEVEL_COUNT_CF"; // chain number and level (u16, u64) to number of blocks (u64)
+const PROPOSER_TREE_LEVEL_CF: &str = "PROPOSER_TREE_LEVEL"; // level (u64) to hashes of blocks (Vec<hash>)
+const VOTER_NODE_VOTED_LEVEL_CF: &str = "VOTER_NODE_VOTED_LEVEL"; // hash to max. voted level (u64)
+const PROPOSER_NODE_VOTE_CF: &str = "PROPOSER_NODE_VOTE"; // hash to level and chain number of main chain votes (Vec<u16, u64>)
+const PROPOSER_LEADER_SEQUENCE_CF: &str = "PROPOSER_LEADER_SEQUENCE"; // level (u64) to hash of leader block.
+const PROPOSER_LEDGER_ORDER_CF: &str = "PROPOSER_LEDGER_ORDER"; // level (u64) to the list of proposer blocks confirmed
+// by this level, including the leader itself. The list
+// is in the order that those blocks should live in the ledger.
+const PROPOSER_VOTE_COUNT_CF: &str = "PROPOSER_VOTE_COUNT"; // number of all votes on a block
+// Column family names for graph neighbors
+const PARENT_NEIGHBOR_CF: &str = "GRAPH_PARENT_NEIGHBOR"; // the proposer parent of a block
+const VOTE_NEIGHBOR_CF: &str = "GRAPH_VOTE_NEIGHBOR"; // neighbors associated by a vote
+const VOTER_PARENT_NEIGHBOR_CF: &str = "GRAPH_VOTER_PARENT_NEIGHBOR"; // the voter parent of a block
+const TRANSACTION_REF_NEIGHBOR_CF: &str = "GRAPH_TRANSACTION_REF_NEIGHBOR";
+const PROPOSER_REF_NEIGHBOR_CF: &str = "GRAPH_PROPOSER_REF_NEIGHBOR";
+const REFERRER_NEIGHBOR_CF: &str = "GRAPH_REFERRER_NEIGHBOR"; // hash, the hash of a block pointing to it and how (H256, H256, Edge) to nothing, see Graph
+// Column family names for blocks and bookkeeping
+const BLOCK_CF: &str = "BLOCK"; // hash to encoded block
+const UNREFERRED_TRANSACTION_CF: &str = "UNREFERRED_TRANSACTION"; // hash to timestamp (u128) of transaction blocks no proposer block refers to
+const UNREFERRED_PROPOSER_CF: &str = "UNREFERRED_PROPOSER"; // hash to timestamp (u128) of proposer blocks no proposer block refers to
+const PRUNED_HEADER_CF: &str = "PRUNED_HEADER"; // hash to header of transaction blocks whose content is pruned
+const META_CF: &str = "META"; // ledger tips, pruned level, the last imported block and the schema version
+
+//every column family the tree writes, to be created when the database is opened
+pub const COLUMN_FAMILIES: &[&str] = &[
+	PROPOSER_NODE_LEVEL_CF, VOTER_NODE_LEVEL_CF, VOTER_NODE_CHAIN_CF, VOTER_TREE_LEVEL_COUNT_CF,
+	PROPOSER_TREE_LEVEL_CF, VOTER_NODE_VOTED_LEVEL_CF, PROPOSER_NODE_VOTE_CF, PROPOSER_LEADER_SEQUENCE_CF,
+	PROPOSER_LEDGER_ORDER_CF, PROPOSER_VOTE_COUNT_CF, PARENT_NEIGHBOR_CF, VOTE_NEIGHBOR_CF,
+	VOTER_PARENT_NEIGHBOR_CF, TRANSACTION_REF_NEIGHBOR_CF, PROPOSER_REF_NEIGHBOR_CF, REFERRER_NEIGHBOR_CF,
+	BLOCK_CF, UNREFERRED_TRANSACTION_CF, UNREFERRED_PROPOSER_CF, PRUNED_HEADER_CF, META_CF,
+];
+
+pub type Result<T> = std::result::Result<T, StoreError>;
+
+//ledger levels this far below the ledger tip are final: a voter chain reorganisation that would
+//roll one of them back is refused by every node, archive or pruned
+pub const FINALITY_DEPTH: u64 = 64;
+
+//the highest final ledger level with the ledger at `ledgerTip`
+pub fn finalLevel(ledgerTip: u64) -> u64 {
+	ledgerTip.saturating_sub(FINALITY_DEPTH)
+}
+
+
+
+//blocks, their edges and the ledger live in `store`, see Store. Only the indexes and the ledger
+//are kept in memory as well
+pub struct BlockTree<S: TreeStore = Store::RocksStore>{
+	store: S,
+	//children, leaves, best tips and levels, kept up to date by insert
+	index: RwLock<TreeIndex>,
+	//votes, leaders, voter ledger tips and the proposer ledger, moved together on every voter tip change
+	ledger: Mutex<VoteLedger>,
+	//receivers of every non-empty ledger change, in the order the changes were applied
+	ledgerSubscribers: Mutex<Vec<Sender<LedgerUpdate>>>,
+	pruning: PruningMode,
+	//content beyond these limits is refused by insert
+	limits: PayloadLimits,
+	//ledger levels up to here have their transaction blocks pruned
+	prunedLevel: Mutex<u64>,
+	//an import failed after changing the in-memory tree, which is ahead of the database from then on
+	poisoned: AtomicBool,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum InsertError {
+	//the content is over the payload limits of its kind
+	Payload(PayloadError),
+	Index(IndexError),
+	Reorg(ReorgError),
+	Storage(StoreError),
+	//an earlier import failed half way, the tree has to be reopened from the database
+	Poisoned,
+	//the block would roll the ledger back to this level, a final one
+	BeyondFinality(u64),
+	//the store holds the tree of the proposer genesis with this hash
+	OtherGenesis(H256),
+}
+
+impl From<StoreError> for InsertError {
+	fn from(error: StoreError) -> Self {
+		InsertError::Storage(error)
+	}
+}
+
+impl From<IndexError> for InsertError {
+	fn from(error: IndexError) -> Self {
+		InsertError::Index(error)
+	}
+}
+
+impl From<ReorgError> for InsertError {
+	fn from(error: ReorgError) -> Self {
+		InsertError::Reorg(error)
+	}
+}
+
+//the ledger's view of the tree while the index is locked, `pending` is the block being inserted
+//and not written yet, with where the index places it. The ledger only sees missing blocks, the
+//first read error is kept for the caller
+struct TreeView<'a> {
+	index: &'a TreeIndex,
+	store: &'a dyn ReadStore,
+	pending: (&'a Block, IndexedBlock),
+	error: RefCell<Option<StoreError>>,
+}
+
+impl<'a> TreeView<'a> {
+	fn new(index: &'a TreeIndex, store: &'a dyn ReadStore, pending: (&'a Block, IndexedBlock)) -> Self {
+		TreeView { index, store, pending, error: RefCell::new(None) }
+	}
+
+	fn block(&self, hash: &H256) -> Option<Block> {
+		if *hash == self.pending.0.hash() {
+			return Some(self.pending.0.clone());
+		}
+		match readBlock(self.store, hash) {
+			Ok(block) => block,
+			Err(error) => {
+				self.error.borrow_mut().get_or_insert(error);
+				None
+			},
+		}
+	}
+
+	//the ledger's result, unless a read failed on the way
+	fn checked<T>(&self, result: std::result::Result<T, ReorgError>) -> std::result::Result<T, InsertError> {
+		match self.error.borrow_mut().take() {
+			Some(error) => Err(error.into()),
+			None => Ok(result?),
+		}
+	}
+
+	fn indexed(&self, hash: &H256) -> Option<IndexedBlock> {
+		if *hash == self.pending.0.hash() {
+			return Some(self.pending.1);
+		}
+		self.index.get(hash).copied()
+	}
+}
+
+fn decodeValue<T: Decode>(cf: &str, bytes: &[u8]) -> Result<T> {
+	T::decode(&mut &bytes[..]).map_err(|e| StoreError(format!("{}: {}", cf, e)))
+}
+
+//the value at `key`, which the tree always writes
+fn required<T: Decode>(store: &dyn ReadStore, cf: &'static str, key: &[u8]) -> Result<T> {
+	let bytes = store.get(cf, key)?.ok_or_else(|| StoreError(format!("{}: nothing at {:02x?}", cf, key)))?;
+	decodeValue(cf, &bytes)
+}
+
+fn hashKey(cf: &str, key: &[u8]) -> Result<H256> {
+	match key.len() {
+		32 => Ok(H256::from_slice(key)),
+		_ => Err(StoreError(format!("{}: key {:02x?} is not a hash", cf, key))),
+	}
+}
+
+fn levelOf(cf: &str, key: &[u8]) -> Result<u64> {
+	let level: [u8; 8] = key.try_into().map_err(|_| StoreError(format!("{}: key {:02x?} is not a level", cf, key)))?;
+	Ok(u64::from_be_bytes(level))
+}
+
+fn unreferred(store: &dyn ReadStore, cf: &'static str) -> Result<Vec<(H256, u128)>> {
+	store.scan(cf)?.into_iter().map(|(hash, timestamp)| Ok((hashKey(cf, &hash)?, decodeValue(cf, &timestamp)?))).collect()
+}
+
+fn readBlock(store: &dyn ReadStore, hash: &H256) -> Result<Option<Block>> {
+	store.get(BLOCK_CF, hash.as_bytes())?.map(|bytes| decodeValue(BLOCK_CF, &bytes)).transpose()
+}
+
+//the index of every block in `store`. Proposer blocks are restored level by level in the order
+//they were inserted, so parents come first and the best tip is again the first one seen at the top
+//level. The best tip of a voter chain is the one the ledger follows
+fn loadIndex(store: &dyn ReadStore, voterTips: &[H256]) -> Result<TreeIndex> {
+	let mut index = TreeIndex::new();
+	for (level, hashes) in store.scan(PROPOSER_TREE_LEVEL_CF)? {
+		let level = levelOf(PROPOSER_TREE_LEVEL_CF, &level)?;
+		for hash in decodeValue::<Vec<H256>>(PROPOSER_TREE_LEVEL_CF, &hashes)? {
+			let parent = required(store, PARENT_NEIGHBOR_CF, hash.as_bytes())?;
+			index.restore(hash, IndexedBlock { kind: BlockKind::Proposer, level, treeParent: parent });
+		}
+	}
+	let mut voters = Vec::new();
+	for (hash, level) in store.scan(VOTER_NODE_LEVEL_CF)? {
+		let hash = hashKey(VOTER_NODE_LEVEL_CF, &hash)?;
+		let level = decodeValue(VOTER_NODE_LEVEL_CF, &level)?;
+		let chain = required(store, VOTER_NODE_CHAIN_CF, hash.as_bytes())?;
+		let parent = required(store, VOTER_PARENT_NEIGHBOR_CF, hash.as_bytes())?;
+		voters.push((hash, IndexedBlock { kind: BlockKind::Voter(chain), level, treeParent: parent }));
+	}
+	voters.sort_by_key(|(hash, indexed)| (indexed.level, *hash));
+	for (hash, indexed) in voters {
+		index.restore(hash, indexed);
+	}
+	for (chain, tip) in voterTips.iter().enumerate() {
+		let level = index.get(tip).map(|tip| tip.level).ok_or_else(|| StoreError(format!("voter ledger tip {:?} is not in the store", tip)))?;
+		index.restoreBest(TreeId::Voter(chain as u16), *tip, level);
+	}
+	//every other block with a parent is a transaction block, pruned or not
+	for (hash, parent) in store.scan(PARENT_NEIGHBOR_CF)? {
+		let hash = hashKey(PARENT_NEIGHBOR_CF, &hash)?;
+		if index.contains(&hash) {
+			continue;
+		}
+		let parent: H256 = decodeValue(PARENT_NEIGHBOR_CF, &parent)?;
+		let level = index.get(&parent).map_or(0, |parent| parent.level + 1);
+		index.restore(hash, IndexedBlock { kind: BlockKind::Transaction, level, treeParent: H256::zero() });
+	}
+	Ok(index)
+}
+
+//the ledger as the last import left it in `store`
+fn loadLedger(store: &dyn ReadStore, genesis: H256, voterTips: Vec<H256>) -> Result<VoteLedger> {
+	//the genesis level is where every ledger starts and is never written
+	let mut ledger = vec![(0, vec![genesis])];
+	for (level, blocks) in store.scan(PROPOSER_LEDGER_ORDER_CF)? {
+		ledger.push((levelOf(PROPOSER_LEDGER_ORDER_CF, &level)?, decodeValue(PROPOSER_LEDGER_ORDER_CF, &blocks)?));
+	}
+	let mut leaders = vec![(0, genesis)];
+	for (level, leader) in store.scan(PROPOSER_LEADER_SEQUENCE_CF)? {
+		leaders.push((levelOf(PROPOSER_LEADER_SEQUENCE_CF, &level)?, decodeValue(PROPOSER_LEADER_SEQUENCE_CF, &leader)?));
+	}
+	let mut votes = Vec::new();
+	for (proposer, proposerVotes) in store.scan(PROPOSER_NODE_VOTE_CF)? {
+		votes.push((hashKey(PROPOSER_NODE_VOTE_CF, &proposer)?, decodeValue(PROPOSER_NODE_VOTE_CF, &proposerVotes)?));
+	}
+	let mut ledger = VoteLedger::fromState(LedgerState { voterTips, votes, leaders, ledger })
+		.ok_or_else(|| StoreError(format!("{}: the ledger does not start at level 0", PROPOSER_LEDGER_ORDER_CF)))?;
+	//all of it is in the database already
+	ledger.takeChanges();
+	Ok(ledger)
+}
+
+impl<'a> LedgerView for TreeView<'a> {
+	fn level(&self, hash: &H256) -> Option<u64> {
+		self.indexed(hash).map(|block| block.level)
+	}
+
+	fn voterParent(&self, hash: &H256) -> Option<H256> {
+		self.indexed(hash).filter(|block| matches!(TreeId::of(block.kind), Some(TreeId::Voter(_)))).map(|block| block.treeParent)
+	}
+
+	fn votes(&self, hash: &H256) -> Option<Vec<H256>> {
+		self.block(hash).and_then(|block| block.votes().map(|votes| votes.to_vec()))
+	}
+
+	fn proposerReferences(&self, hash: &H256) -> Option<Vec<H256>> {
+		self.block(hash).and_then(|block| block.proposerReferences().map(|references| references.to_vec()))
+	}
+
+	fn proposerLevel(&self, level: u64) -> Vec<H256> {
+		self.index.proposerLevel(level).to_vec()
+	}
+}
+
+impl<S: TreeStore> BlockTree<S>{
+	//a tree holding only `genesis`, on an empty store
+	pub fn new(store: S, genesis: &GenesisBlocks, pruning: PruningMode) -> std::result::Result<Self, InsertError>{
+		let tree = BlockTree {
+			store,
+			index: RwLock::new(TreeIndex::new()),
+			ledger: Mutex::new(VoteLedger::new(genesis.proposerHash(), genesis.voterHashes())),
+			ledgerSubscribers: Mutex::new(Vec::new()),
+			pruning,
+			limits: PayloadLimits::default(),
+			prunedLevel: Mutex::new(0),
+			poisoned: AtomicBool::new(false),
+		};
+		//the genesis ledger is never changed by an import, it is written with the schema version
+		let mut schema = ImportBatch::new();
+		Migration::stamp(&mut schema);
+		schema.put(META_CF, Batch::VOTER_LEDGER_TIPS_KEY, &genesis.voterHashes());
+		tree.store.write(schema.ops().to_vec())?;
+		tree.insert(genesis.proposer.clone())?;
+		for voter in &genesis.voters {
+			tree.insert(voter.clone())?;
+		}
+		Ok(tree)
+	}
+
+	//the tree an earlier run left in `store`: the index, the ledger and the pruned level are loaded
+	//from it again. A tree is created on an empty store by new
+	pub fn open(store: S, pruning: PruningMode) -> Result<Self>{
+		let (index, ledger, prunedLevel) = store.consistent(|view| -> Result<_> {
+			let voterTips: Vec<H256> = required(view, META_CF, Batch::VOTER_LEDGER_TIPS_KEY)?;
+			let index = loadIndex(view, &voterTips)?;
+			let genesis = *index.proposerLevel(0).first().ok_or_else(|| StoreError(format!("{}: no proposer genesis", PROPOSER_TREE_LEVEL_CF)))?;
+			let ledger = loadLedger(view, genesis, voterTips)?;
+			Ok((index, ledger, Prune::readPrunedLevel(view)?))
+		})?;
+		Ok(BlockTree {
+			store,
+			index: RwLock::new(index),
+			ledger: Mutex::new(ledger),
+			ledgerSubscribers: Mutex::new(Vec::new()),
+			pruning,
+			limits: PayloadLimits::default(),
+			prunedLevel: Mutex::new(prunedLevel),
+			poisoned: AtomicBool::new(false),
+		})
+	}
+
+	//the tree in `store` if there is one, see open, or a new one holding `genesis`. A tree of
+	//another genesis is refused
+	pub fn load(store: S, genesis: &GenesisBlocks, pruning: PruningMode) -> std::result::Result<Self, InsertError>{
+		if store.isEmpty(BLOCK_CF)? {
+			return Self::new(store, genesis, pruning);
+		}
+		let tree = Self::open(store, pruning)?;
+		match tree.lastFinalized() {
+			Some(stored) if stored == genesis.proposerHash() => Ok(tree),
+			stored => Err(InsertError::OtherGenesis(stored.unwrap_or_default())),
+		}
+	}
+
+	//the tree checking imported content against `limits` instead of the default ones
+	pub fn withLimits(mut self, limits: PayloadLimits) -> Self{
+		self.limits = limits;
+		self
+	}
+
+	pub fn store(&self) -> &S{
+		&self.store
+	}
+
+	//the store, to open the tree on it again
+	pub fn intoStore(self) -> S{
+		self.store
+	}
+
+	//a stored block, None as well once its content is pruned
+	pub fn block(&self, hash: &H256) -> Result<Option<Block>>{
+		readBlock(&self.store, hash)
+	}
+
+	//store a validated block and index it. A voter block that becomes its chain's best tip moves the
+	//chain's votes and the ledger with it, under the index lock so no reader sees the tip without
+	//the ledger that goes with it. The block and everything it changed reach the database in one
+	//synced write batch, see Batch. The ledger change is returned and sent to subscribers.
+	//
+	//A write that fails after the in-memory tree changed leaves it ahead of the database. The tree
+	//is poisoned then and refuses every later insert, it has to be reopened from the database.
+	pub fn insert(&self, block: Block) -> std::result::Result<LedgerUpdate, InsertError>{
+		block.checkPayload(&self.limits).map_err(InsertError::Payload)?;
+		let hash = block.hash();
+		let mut index = self.index.write().unwrap();
+		let mut ledger = self.ledger.lock().unwrap();
+		if self.poisoned.load(Ordering::SeqCst) {
+			return Err(InsertError::Poisoned);
+		}
+		//everything that can reject the block is checked before anything changes: its parents, and
+		//for a voter block that becomes its chain's best tip, the route the ledger reorganises along
+		//and that it leaves the final levels alone
+		let placed = index.place(&block)?;
+		let tree = TreeId::of(block.kind());
+		let mut newVoterTip = None;
+		if let Some(TreeId::Voter(chain)) = tree {
+			if chain as usize >= ledger.voterTips().len() {
+				return Err(ReorgError::UnknownChain(chain).into());
+			}
+			if index.best(TreeId::Voter(chain)).is_none_or(|(_, level)| placed.level > level) {
+				let view = TreeView::new(&index, &self.store, (&block, placed));
+				let checked = ledger.checkVoterTip(&view, chain, hash);
+				let lowest = view.checked(checked)?;
+				let finalLevel = finalLevel(ledger.proposerLedgerTip());
+				//only a change of votes down to the final levels can roll the ledger back that far,
+				//the move is tried on a copy of the ledger then
+				if lowest.is_some_and(|level| level <= finalLevel) {
+					let moved = ledger.clone().setVoterTip(&view, chain, hash);
+					let update = view.checked(moved)?;
+					if let Some((level, _)) = update.removed.first().filter(|(level, _)| *level <= finalLevel) {
+						return Err(InsertError::BeyondFinality(*level));
+					}
+				}
+				newVoterTip = Some(chain);
+			}
+		}
+
+		let applied = self.apply(&block, &mut index, &mut ledger, newVoterTip);
+		if applied.is_err() {
+			self.poisoned.store(true, Ordering::SeqCst);
+		}
+		let update = applied?;
+		if !update.isEmpty() {
+			self.ledgerSubscribers.lock().unwrap().retain(|subscriber| subscriber.send(update.clone()).is_ok());
+		}
+		Ok(update)
+	}
+
+	//index `block`, move the ledger and write both, once insert checked the block
+	fn apply(&self, block: &Block, index: &mut TreeIndex, ledger: &mut VoteLedger, newVoterTip: Option<u16>) -> std::result::Result<LedgerUpdate, InsertError>{
+		let hash = block.hash();
+		let indexed = index.insert(block)?;
+		let mut batch = ImportBatch::new();
+		batch.block(block, &indexed, index, |cf, key| self.store.get(cf, key))?;
+		let mut update = LedgerUpdate::default();
+		if let Some(chain) = newVoterTip {
+			let view = TreeView::new(index, &self.store, (block, indexed));
+			let moved = ledger.setVoterTip(&view, chain, hash);
+			update = view.checked(moved)?;
+		}
+		let changes = ledger.takeChanges();
+		batch.ledger(ledger, &changes, &update);
+		let mut prunedLevel = self.prunedLevel.lock().unwrap();
+		self.prune(ledger, &mut prunedLevel, &mut batch)?;
+		batch.imported(hash);
+		batch.write(&self.store)?;
+		Ok(update)
+	}
+
+	//add to `batch` the pruning of the transaction blocks the ledger confirmed more than the kept
+	//levels ago, once final, at most MAX_PRUNED_LEVELS_PER_INSERT levels of them
+	fn prune(&self, ledger: &VoteLedger, prunedLevel: &mut u64, batch: &mut ImportBatch) -> Result<()>{
+		let tip = ledger.proposerLedgerTip();
+		let to = match self.pruning.pruneTo(tip) {
+			Some(to) => to.min(finalLevel(tip)).min(*prunedLevel + Prune::MAX_PRUNED_LEVELS_PER_INSERT),
+			None => return Ok(()),
+		};
+		if to <= *prunedLevel {
+			return Ok(());
+		}
+		let transactions = Prune::confirmedTransactions(
+			|level| ledger.entry(level).map(|blocks| blocks.to_vec()),
+			|proposer| Ok(self.block(proposer)?.and_then(|block| block.transactionReferences().map(|references| references.to_vec())).unwrap_or_default()),
+			*prunedLevel + 1,
+			to,
+		)?;
+		for hash in transactions {
+			//pruned already by an earlier level
+			if let Some(block) = self.block(&hash)? {
+				Prune::pruneBlock(batch, hash, block.header());
+			}
+		}
+		Prune::prunedLevel(batch, to);
+		*prunedLevel = to;
+		Ok(())
+	}
+
+	//transaction blocks no proposer block refers to yet, with their timestamps
+	pub fn unreferredTransactions(&self) -> Result<Vec<(H256, u128)>>{
+		unreferred(&self.store, UNREFERRED_TRANSACTION_CF)
+	}
+
+	//proposer blocks no proposer block refers to yet, with their timestamps
+	pub fn unreferredProposers(&self) -> Result<Vec<(H256, u128)>>{
+		unreferred(&self.store, UNREFERRED_PROPOSER_CF)
+	}
+
+	//blocks pointing to `hash` by any edge, see Graph
+	pub fn referrers(&self, hash: &H256) -> Result<Vec<Referrer>>{
+		self.store.consistent(|view| Graph::referrers(view, hash))
+	}
+
+	//blocks `hash` reaches by at most `depth` of the given edges, nearest first
+	pub fn ancestors(&self, hash: &H256, depth: u64, edges: &[Edge]) -> Result<Vec<H256>>{
+		self.store.consistent(|view| Graph::ancestors(view, hash, depth, edges))
+	}
+
+	//blocks reaching `hash` by at most `depth` of the given edges, nearest first
+	pub fn descendants(&self, hash: &H256, depth: u64, edges: &[Edge]) -> Result<Vec<H256>>{
+		self.store.consistent(|view| Graph::descendants(view, hash, depth, edges))
+	}
+
+	pub fn referenceClosure(&self, proposer: &H256) -> Result<ReferenceClosure>{
+		self.store.consistent(|view| Graph::referenceClosure(view, proposer))
+	}
+
+	//voter blocks voting on proposer blocks of `level`, with the block each voted on
+	pub fn levelVoters(&self, level: u64) -> Result<Vec<(H256, H256)>>{
+		self.store.consistent(|view| Graph::levelVoters(view, level))
+	}
+
+	//the header of any block, pruned or not
+	pub fn header(&self, hash: &H256) -> Result<Option<BlockHeader>>{
+		if let Some(block) = self.block(hash)? {
+			return Ok(Some(block.header().clone()));
+		}
+		self.store.get(PRUNED_HEADER_CF, hash.as_bytes())?.map(|bytes| decodeValue(PRUNED_HEADER_CF, &bytes)).transpose()
+	}
+
+	//a snapshot at the ledger tip, see Snapshot
+	pub fn exportSnapshot(&self, voterHistory: u64) -> std::result::Result<Snapshot::Snapshot, Snapshot::SnapshotError>{
+		Snapshot::export(&self.store, voterHistory)
+	}
+
+	//the last block whose import reached the database
+	pub fn lastImport(&self) -> Result<Option<H256>>{
+		Batch::lastImport(&self.store)
+	}
+
+	//every later ledger change, removed entries first
+	pub fn subscribe(&self) -> std::sync::mpsc::Receiver<LedgerUpdate>{
+		let (sender, receiver) = std::sync::mpsc::channel();
+		self.ledgerSubscribers.lock().unwrap().push(sender);
+		receiver
+	}
+
+	pub fn voterLedgerTips(&self) -> Vec<H256>{
+		self.ledger.lock().unwrap().voterTips().to_vec()
+	}
+
+	pub fn proposerLedgerTip(&self) -> u64{
+		self.ledger.lock().unwrap().proposerLedgerTip()
+	}
+
+	//proposer blocks confirmed by each ledger level, in ledger order
+	pub fn ledgerEntries(&self) -> Vec<(u64, Vec<H256>)>{
+		self.ledger.lock().unwrap().entries().map(|(level, blocks)| (*level, blocks.clone())).collect()
+	}
+
+	pub fn leader(&self, level: u64) -> Option<H256>{
+		self.ledger.lock().unwrap().leader(level)
+	}
+
+	//extrinsics of a transaction block, None for other blocks and once pruned
+	pub fn body(&self, hash: &H256) -> Result<Option<Vec<Argonaut_Runtime::UncheckedExtrinsic>>>{
+		Ok(self.block(hash)?.and_then(|block| block.extrinsics().map(|extrinsics| extrinsics.to_vec())))
+	}
+
+	//proposer headers from the best proposer tip down, that signal `deployment`, out of its window
+	pub fn deploymentSignals(&self, deployment: &Deployment) -> Result<u32>{
+		let mut headers = Vec::new();
+		let mut next = self.best(TreeId::Proposer).map(|(hash, _)| hash);
+		while let Some(hash) = next.filter(|_| headers.len() < deployment.window as usize) {
+			let header = match self.header(&hash)? {
+				Some(header) => header,
+				None => break,
+			};
+			next = Some(header.parent);
+			headers.push(header);
+		}
+		Ok(deployment.count(&headers))
+	}
+
+	//whether `deployment` is locked in on the best proposer chain
+	pub fn deploymentLockedIn(&self, deployment: &Deployment) -> Result<bool>{
+		Ok(self.deploymentSignals(deployment)? >= deployment.threshold)
+	}
+
+	//Prism blocks are final once the ledger confirms them, not by justifications. None before the
+	//genesis is inserted
+	pub fn lastFinalized(&self) -> Option<H256>{
+		self.proposerLevel(0).first().copied()
+	}
+
+	//children of a block in its own tree
+	pub fn children(&self, hash: &H256) -> Vec<H256>{
+		self.index.read().unwrap().children(hash).to_vec()
+	}
+
+	pub fn treeLeaves(&self, tree: TreeId) -> Vec<H256>{
+		self.index.read().unwrap().leaves(tree).copied().collect()
+	}
+
+	//best tip of the proposer tree or of a voter chain, with its level
+	pub fn best(&self, tree: TreeId) -> Option<(H256, u64)>{
+		self.index.read().unwrap().best(tree)
+	}
+
+	//the highest proposer level the voter chain up to `voter` voted on, the votes of a child of
+	//`voter` start at the level after it
+	pub fn votedLevel(&self, voter: &H256) -> Result<Option<u64>>{
+		self.store.get(VOTER_NODE_VOTED_LEVEL_CF, voter.as_bytes())?.map(|bytes| decodeValue(VOTER_NODE_VOTED_LEVEL_CF, &bytes)).transpose()
+	}
+
+	pub fn proposerBestLevel(&self) -> Option<u64>{
+		self.index.read().unwrap().proposerBestLevel()
+	}
+
+	//proposer blocks at `level`, in the order they were inserted
+	pub fn proposerLevel(&self, level: u64) -> Vec<H256>{
+		self.index.read().unwrap().proposerLevel(level).to_vec()
+	}
+}
+
+//what validation checks blocks against before they are inserted. A block that cannot be read counts
+//as unknown, the block referring to it is rejected then
+impl<S: TreeStore> ChainView for BlockTree<S>{
+	fn header(&self, hash: &H256) -> Option<BlockHeader>{
+		BlockTree::header(self, hash).ok().flatten()
+	}
+
+	fn kind(&self, hash: &H256) -> Option<BlockKind>{
+		self.index.read().unwrap().get(hash).map(|block| block.kind)
+	}
+
+	//every block is mined at the target of the genesis, which its proposer parent carries
+	fn difficulty(&self, parent: &H256) -> H256{
+		ChainView::header(self, parent).map(|header| header.difficulty).unwrap_or_default()
+	}
+
+	fn levelOf(&self, proposer: &H256) -> Option<u64>{
+		self.index.read().unwrap().get(proposer).filter(|block| block.kind == BlockKind::Proposer).map(|block| block.level)
+	}
+
+	fn votedLevelOf(&self, voter: &H256) -> Option<u64>{
+		self.votedLevel(voter).ok().flatten()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::Fixtures::block;
+	use super::Store::MemoryStore;
+	use crate::Block::{Content, ExtraContent::ExtraContent, ProposerBlock, TransactionBlock, VoterBlock};
+	use crate::Genesis::PrismGenesis;
+
+	fn tree(voterChains: u16) -> (BlockTree<MemoryStore>, GenesisBlocks) {
+		let genesis = PrismGenesis { voterChains, timestamp: 1, difficulty: H256::repeat_byte(0xff) }.blocks().unwrap();
+		(BlockTree::new(MemoryStore::new(), &genesis, PruningMode::Archive).unwrap(), genesis)
+	}
+
+	#[test]
+	fn genesisIsStored() {
+		let (tree, genesis) = tree(2);
+		assert_eq!(tree.block(&genesis.proposerHash()), Ok(Some(genesis.proposer.clone())));
+		assert_eq!(tree.lastFinalized(), Some(genesis.proposerHash()));
+		assert_eq!(tree.voterLedgerTips(), genesis.voterHashes());
+		assert_eq!(tree.leader(0), Some(genesis.proposerHash()));
+		assert_eq!(tree.lastImport(), Ok(Some(genesis.voters[1].hash())));
+		assert_eq!(tree.unreferredTransactions(), Ok(Vec::new()));
+		assert_eq!(tree.votedLevel(&genesis.voters[0].hash()), Ok(Some(0)));
+		assert_eq!(tree.votedLevel(&H256::repeat_byte(3)), Ok(None));
+	}
+
+	#[test]
+	fn validatedAgainstTheTree() {
+		use crate::Validation::{ContentError, Sortition, Validator};
+		let (tree, genesis) = tree(1);
+		let sortition = Sortition { proposerWeight: 1, voterWeight: 1, voterChains: 1, transactionWeight: 1 };
+		let mut validator = Validator::new(sortition, PayloadLimits::default());
+		let difficulty = genesis.proposer.header().difficulty;
+		let (pk, sk) = primitives::FalconHostFunctions::generate_keypair();
+		let coinbase = sp_core::crypto::AccountId32::new(primitives::FalconHostFunctions::hash_public_key(&pk));
+		let mine = |content: &Content| {
+			let mut header = (0..).map(|nonce| BlockHeader::new(genesis.proposerHash(), 10, nonce, content.hash(), ExtraContent::default(), difficulty).with_coinbase(coinbase.clone()))
+				.find(|header| sortition.kind(&header.hash(), &difficulty) == Some(BlockKind::of(content)))
+				.unwrap();
+			header.seal_with_miner_key(&pk, &sk);
+			header
+		};
+
+		let transaction = Content::TransactionBlock(TransactionBlock::Content::new(Vec::new()));
+		let verified = validator.checkHeader(&tree, mine(&transaction), 10).unwrap();
+		let block = validator.checkContent(&tree, verified, transaction, Vec::new()).unwrap();
+		tree.insert(block.clone()).unwrap();
+
+		let proposer = Content::ProposerBlock(ProposerBlock::Content::new(vec![block.hash(), genesis.voters[0].hash()], Vec::new()));
+		let verified = validator.checkHeader(&tree, mine(&proposer), 10).unwrap();
+		assert_eq!(
+			validator.checkContent(&tree, verified, proposer, Vec::new()).map(|_| ()),
+			Err(ContentError::WrongReferenceKind(genesis.voters[0].hash()))
+		);
+	}
+
+	#[test]
+	fn contentOverTheLimitsRefused() {
+		let (tree, genesis) = tree(1);
+		let tree = tree.withLimits(PayloadLimits { maxProposerReferences: 1, ..PayloadLimits::default() });
+		let references = vec![genesis.proposerHash(); 2];
+		let proposer = block(genesis.proposerHash(), 1, Content::ProposerBlock(ProposerBlock::Content::new(Vec::new(), references)));
+		assert_eq!(tree.insert(proposer.clone()), Err(InsertError::Payload(PayloadError::TooManyProposerReferences { count: 2, limit: 1 })));
+		assert_eq!(tree.block(&proposer.hash()), Ok(None));
+		assert_eq!(tree.lastImport(), Ok(Some(genesis.voters[0].hash())));
+	}
+
+	#[test]
+	fn deploymentCountedOnTheBestProposerChain() {
+		let (tree, genesis) = tree(1);
+		let deployment = Deployment::new(2, 3, 2).unwrap();
+		let mut parent = genesis.proposerHash();
+		for (nonce, signals) in [(1, 1 << 2), (2, 0), (3, 1 << 2)] {
+			let content = Content::ProposerBlock(ProposerBlock::Content::new(Vec::new(), Vec::new()));
+			let header = BlockHeader::new(parent, 1, nonce, content.hash(), ExtraContent::new(signals, &[]).unwrap(), H256::zero());
+			let proposer = Block::fromParts(header, content, Vec::new());
+			tree.insert(proposer.clone()).unwrap();
+			parent = proposer.hash();
+			if nonce == 2 {
+				assert_eq!(tree.deploymentSignals(&deployment), Ok(1));
+				assert_eq!(tree.deploymentLockedIn(&deployment), Ok(false));
+			}
+		}
+		assert_eq!(tree.deploymentSignals(&deployment), Ok(2));
+		assert_eq!(tree.deploymentLockedIn(&deployment), Ok(true));
+	}
+
+	#[test]
+	fn voterParentOnAnotherChainRejectedBeforeAnyWrite() {
+		let (tree, genesis) = tree(2);
+		let chain0 = genesis.voters[0].hash();
+		let crossed = block(genesis.proposerHash(), 1, Content::VoterBlock(VoterBlock::Content::new(1, chain0, Vec::new())));
+		assert_eq!(tree.insert(crossed.clone()), Err(InsertError::Index(IndexError::WrongParent(chain0))));
+		assert_eq!(tree.lastImport(), Ok(Some(genesis.voters[1].hash())));
+		assert_eq!(tree.block(&crossed.hash()), Ok(None));
+		assert_eq!(tree.voterLedgerTips(), genesis.voterHashes());
+
+		//the same block on its parent's chain is taken
+		let voter = block(genesis.proposerHash(), 1, Content::VoterBlock(VoterBlock::Content::new(0, chain0, Vec::new())));
+		tree.insert(voter.clone()).unwrap();
+		assert_eq!(tree.voterLedgerTips(), vec![voter.hash(), genesis.voters[1].hash()]);
+	}
+
+	#[test]
+	fn failedImportPoisonsTheTree() {
+		let (tree, genesis) = tree(1);
+		let mut corrupt = ImportBatch::new();
+		corrupt.put(VOTER_TREE_LEVEL_COUNT_CF, Batch::voterLevelKey(0, 1), &1u8);
+		tree.store().write(corrupt.ops().to_vec()).unwrap();
+
+		let voter = block(genesis.proposerHash(), 1, Content::VoterBlock(VoterBlock::Content::new(0, genesis.voters[0].hash(), Vec::new())));
+		assert!(matches!(tree.insert(voter.clone()), Err(InsertError::Storage(_))));
+		assert_eq!(tree.block(&voter.hash()), Ok(None));
+		assert_eq!(tree.lastImport(), Ok(Some(genesis.voters[0].hash())));
+		let proposer = block(genesis.proposerHash(), 2, Content::ProposerBlock(ProposerBlock::Content::new(Vec::new(), Vec::new())));
+		assert_eq!(tree.insert(proposer), Err(InsertError::Poisoned));
+	}
+
+	#[test]
+	fn reopenedTreeContinuesWhereItStopped() {
+		let genesis = PrismGenesis { voterChains: 2, timestamp: 1, difficulty: H256::repeat_byte(0xff) }.blocks().unwrap();
+		let tree = BlockTree::new(MemoryStore::new(), &genesis, PruningMode::KeepLevels(0)).unwrap();
+		let transaction = block(genesis.proposerHash(), 1, Content::TransactionBlock(TransactionBlock::Content::new(Vec::new())));
+		let proposer = block(genesis.proposerHash(), 2, Content::ProposerBlock(ProposerBlock::Content::new(vec![transaction.hash()], Vec::new())));
+		let sibling = block(genesis.proposerHash(), 3, Content::ProposerBlock(ProposerBlock::Content::new(Vec::new(), Vec::new())));
+		let votes = |chain: u16, nonce| block(proposer.hash(), nonce, Content::VoterBlock(VoterBlock::Content::new(chain, genesis.voters[chain as usize].hash(), vec![proposer.hash()])));
+		//a fork of voter chain 0 at the level of its tip, whichever sorts first
+		let fork = block(genesis.proposerHash(), 6, Content::VoterBlock(VoterBlock::Content::new(0, genesis.voters[0].hash(), Vec::new())));
+		for block in [transaction.clone(), proposer.clone(), sibling.clone(), votes(0, 4), votes(1, 5), fork.clone()] {
+			tree.insert(block).unwrap();
+		}
+		//level 1 is not final yet, its transaction block stays
+		assert!(tree.block(&transaction.hash()).unwrap().is_some());
+
+		let sorted = |mut hashes: Vec<H256>| {
+			hashes.sort();
+			hashes
+		};
+		let state = |tree: &BlockTree<MemoryStore>| (
+			[TreeId::Proposer, TreeId::Voter(0), TreeId::Voter(1)].map(|id| tree.best(id)),
+			sorted(tree.treeLeaves(TreeId::Voter(0))),
+			sorted(tree.children(&genesis.voters[0].hash())),
+			tree.proposerLevel(1),
+			tree.voterLedgerTips(),
+			tree.ledgerEntries(),
+			tree.leader(1),
+			tree.lastImport(),
+			*tree.prunedLevel.lock().unwrap(),
+		);
+		let before = state(&tree);
+		assert_eq!(before.8, 0);
+		let reopened = BlockTree::load(tree.intoStore(), &genesis, PruningMode::KeepLevels(0)).unwrap();
+		assert_eq!(state(&reopened), before);
+
+		assert_eq!(reopened.insert(transaction), Err(InsertError::Index(IndexError::AlreadyKnown)));
+		let next = block(proposer.hash(), 7, Content::VoterBlock(VoterBlock::Content::new(0, before.4[0], Vec::new())));
+		reopened.insert(next.clone()).unwrap();
+		assert_eq!(reopened.voterLedgerTips(), vec![next.hash(), before.4[1]]);
+		assert_eq!(reopened.leader(1), Some(proposer.hash()));
+	}
+
+	#[test]
+	fn loadRefusesAnotherGenesis() {
+		let (tree, genesis) = tree(1);
+		let other = PrismGenesis { voterChains: 1, timestamp: 2, difficulty: H256::repeat_byte(0xff) }.blocks().unwrap();
+		let store = match BlockTree::load(tree.intoStore(), &other, PruningMode::Archive) {
+			Err(InsertError::OtherGenesis(stored)) => {
+				assert_eq!(stored, genesis.proposerHash());
+				MemoryStore::new()
+			},
+			other => panic!("loaded {:?}", other.map(|tree| tree.lastFinalized())),
+		};
+		assert_eq!(BlockTree::load(store, &other, PruningMode::Archive).unwrap().lastFinalized(), Some(other.proposerHash()));
+	}
+
+	#[test]
+	fn rollbackOfAFinalLevelRefused() {
+		let genesis = PrismGenesis { voterChains: 1, timestamp: 1, difficulty: H256::repeat_byte(0xff) }.blocks().unwrap();
+		let voterGenesis = genesis.voters[0].hash();
+		let levels = FINALITY_DEPTH as u32 + 1;
+		//whatever a node prunes, it follows the same fork
+		for pruning in [PruningMode::Archive, PruningMode::KeepLevels(0)] {
+			let tree = BlockTree::new(MemoryStore::new(), &genesis, pruning).unwrap();
+			let (mut proposer, mut voter) = (genesis.proposerHash(), voterGenesis);
+			for level in 1..=levels {
+				let next = block(proposer, level, Content::ProposerBlock(ProposerBlock::Content::new(Vec::new(), Vec::new())));
+				proposer = next.hash();
+				tree.insert(next).unwrap();
+				let vote = block(proposer, level, Content::VoterBlock(VoterBlock::Content::new(0, voter, vec![proposer])));
+				voter = vote.hash();
+				tree.insert(vote).unwrap();
+			}
+			assert_eq!(tree.proposerLedgerTip(), levels as u64);
+			let firstLeader = tree.leader(1);
+
+			//a longer fork without any vote would take the leader of the final level 1 away
+			let mut fork = voterGenesis;
+			for nonce in 0..levels {
+				let next = block(genesis.proposerHash(), 1000 + nonce, Content::VoterBlock(VoterBlock::Content::new(0, fork, Vec::new())));
+				fork = next.hash();
+				tree.insert(next).unwrap();
+			}
+			let longer = block(genesis.proposerHash(), 5000, Content::VoterBlock(VoterBlock::Content::new(0, fork, Vec::new())));
+			assert_eq!(tree.insert(longer.clone()), Err(InsertError::BeyondFinality(1)));
+			assert_eq!(tree.block(&longer.hash()), Ok(None));
+			assert_eq!(tree.voterLedgerTips(), vec![voter]);
+			assert_eq!(tree.leader(1), firstLeader);
+
+			//one that keeps the votes is followed
+			let extended = block(proposer, 6000, Content::VoterBlock(VoterBlock::Content::new(0, voter, Vec::new())));
+			tree.insert(extended.clone()).unwrap();
+			assert_eq!(tree.voterLedgerTips(), vec![extended.hash()]);
+		}
+	}
+
+	#[test]
+	fn pruningCappedPerInsert() {
+		let genesis = PrismGenesis { voterChains: 1, timestamp: 1, difficulty: H256::repeat_byte(0xff) }.blocks().unwrap();
+		let tree = BlockTree::new(MemoryStore::new(), &genesis, PruningMode::KeepLevels(0)).unwrap();
+		let levels = FINALITY_DEPTH + 2 * Prune::MAX_PRUNED_LEVELS_PER_INSERT + 1;
+		let mut proposers = vec![genesis.proposerHash()];
+		for level in 1..=levels {
+			let next = block(proposers[proposers.len() - 1], level as u32, Content::ProposerBlock(ProposerBlock::Content::new(Vec::new(), Vec::new())));
+			proposers.push(next.hash());
+			tree.insert(next).unwrap();
+		}
+		//one vote on every level confirms them all at once
+		let vote = block(proposers[1], 0, Content::VoterBlock(VoterBlock::Content::new(0, genesis.voters[0].hash(), proposers[1..].to_vec())));
+		tree.insert(vote).unwrap();
+		assert_eq!(tree.proposerLedgerTip(), levels);
+		let pruned = |tree: &BlockTree<MemoryStore>| *tree.prunedLevel.lock().unwrap();
+		assert_eq!(pruned(&tree), Prune::MAX_PRUNED_LEVELS_PER_INSERT);
+
+		//the following inserts catch up to the final level
+		for (nonce, expected) in [(1, 2 * Prune::MAX_PRUNED_LEVELS_PER_INSERT), (2, finalLevel(levels)), (3, finalLevel(levels))] {
+			tree.insert(block(genesis.proposerHash(), nonce, Content::TransactionBlock(TransactionBlock::Content::new(Vec::new())))).unwrap();
+			assert_eq!(pruned(&tree), expected);
+		}
+	}
+
+	#[test]
+	fn votesElectALeader() {
+		let (tree, genesis) = tree(1);
+		let receiver = tree.subscribe();
+		let proposer = block(genesis.proposerHash(), 1, Content::ProposerBlock(ProposerBlock::Content::new(Vec::new(), Vec::new())));
+		tree.insert(proposer.clone()).unwrap();
+		assert_eq!(tree.leader(1), None);
+
+		let voter = block(proposer.hash(), 2, Content::VoterBlock(VoterBlock::Content::new(0, genesis.voters[0].hash(), vec![proposer.hash()])));
+		let update = tree.insert(voter.clone()).unwrap();
+		assert_eq!(tree.leader(1), Some(proposer.hash()));
+		assert_eq!(tree.voterLedgerTips(), vec![voter.hash()]);
+		assert_eq!(receiver.try_recv(), Ok(update));
+		assert_eq!(tree.header(&voter.hash()), Ok(Some(voter.header().clone())));
+	}
+}
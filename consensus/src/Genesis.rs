@@ -0,0 +1,154 @@
+//Genesis of the Prism DAG.
+//
+//Every field of the genesis blocks comes from the chain spec: the number of voter chains, a fixed
+//timestamp and the difficulty of the first blocks. Nothing is read from the clock, so every node
+//of a network builds the same genesis proposer block and the same genesis voter block per chain,
+//and `root` lets a chain spec pin the result.
+use sp_core::H256;
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+use std::vec::Vec;
+use super::Block::{Block, Content, ExtraContent::ExtraContent, Header::BlockHeader, ProposerBlock, VoterBlock};
+use super::MerkleTree::MerkleTree;
+
+pub const DEFAULT_VOTER_CHAINS: u16 = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Encode, Decode, TypeInfo, Serialize, Deserialize)]
+pub struct PrismGenesis {
+	pub voterChains: u16,
+	//milliseconds since the unix epoch
+	pub timestamp: u128,
+	//target of the genesis blocks and of the first blocks mined on them
+	pub difficulty: H256,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GenesisError {
+	//a chain needs at least one voter chain to confirm proposer blocks
+	NoVoterChains,
+	//the genesis built from the spec is not the one the spec pins
+	RootMismatch { expected: H256, found: H256 },
+}
+
+//the roots of the DAG, the proposer tree's genesis and the first block of every voter chain
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GenesisBlocks {
+	pub proposer: Block,
+	//indexed by chain number
+	pub voters: Vec<Block>,
+}
+
+fn genesisBlock(parent: H256, params: &PrismGenesis, content: Content) -> Block {
+	let header = BlockHeader::new(parent, params.timestamp, 0, content.hash(), ExtraContent::default(), params.difficulty);
+	Block::fromParts(header, content, Vec::new())
+}
+
+impl PrismGenesis {
+	pub fn proposerGenesis(&self) -> Block {
+		genesisBlock(H256::zero(), self, Content::ProposerBlock(ProposerBlock::Content::new(Vec::new(), Vec::new())))
+	}
+
+	//voter genesis blocks hang off the proposer genesis like any block off its proposer parent,
+	//their voter parent is zero as they start their chain
+	pub fn voterGenesis(&self, chainNumber: u16, proposerGenesis: H256) -> Block {
+		genesisBlock(proposerGenesis, self, Content::VoterBlock(VoterBlock::Content::new(chainNumber, H256::zero(), Vec::new())))
+	}
+
+	pub fn blocks(&self) -> Result<GenesisBlocks, GenesisError> {
+		if self.voterChains == 0 {
+			return Err(GenesisError::NoVoterChains);
+		}
+		let proposer = self.proposerGenesis();
+		let proposerHash = proposer.hash();
+		let voters = (0..self.voterChains).map(|chain| self.voterGenesis(chain, proposerHash)).collect();
+		Ok(GenesisBlocks { proposer, voters })
+	}
+
+	//the genesis blocks, checked against the root a chain spec pins
+	pub fn checkedBlocks(&self, expected: H256) -> Result<GenesisBlocks, GenesisError> {
+		let blocks = self.blocks()?;
+		let found = blocks.root();
+		if found != expected {
+			return Err(GenesisError::RootMismatch { expected, found });
+		}
+		Ok(blocks)
+	}
+}
+
+impl GenesisBlocks {
+	pub fn proposerHash(&self) -> H256 {
+		self.proposer.hash()
+	}
+
+	pub fn voterHashes(&self) -> Vec<H256> {
+		self.voters.iter().map(Block::hash).collect()
+	}
+
+	//merkle root over the proposer genesis hash followed by the voter genesis hashes in chain order
+	pub fn root(&self) -> H256 {
+		let mut hashes = vec![self.proposerHash()];
+		hashes.extend(self.voterHashes());
+		MerkleTree::new(&hashes).root()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::Block::BlockKind;
+
+	fn params() -> PrismGenesis {
+		PrismGenesis { voterChains: 3, timestamp: 1_696_118_400_000, difficulty: H256::repeat_byte(0x0f) }
+	}
+
+	#[test]
+	fn genesisIsDeterministic() {
+		let first = params().blocks().unwrap();
+		let second = params().blocks().unwrap();
+		assert_eq!(first, second);
+		assert_eq!(first.root(), second.root());
+	}
+
+	#[test]
+	fn oneVoterGenesisPerChain() {
+		let blocks = params().blocks().unwrap();
+		assert_eq!(blocks.proposer.header().parent, H256::zero());
+		assert_eq!(blocks.proposer.header().timestamp, 1_696_118_400_000);
+		assert_eq!(blocks.voters.len(), 3);
+		for (chain, voter) in blocks.voters.iter().enumerate() {
+			assert_eq!(voter.header().parent, blocks.proposerHash());
+			assert_eq!(voter.header().contentRoot, voter.contentHash());
+			assert_eq!(voter.kind(), BlockKind::Voter(chain as u16));
+		}
+		//every chain starts from a distinct block
+		let mut hashes = blocks.voterHashes();
+		hashes.sort();
+		hashes.dedup();
+		assert_eq!(hashes.len(), 3);
+	}
+
+	#[test]
+	fn everySpecFieldChangesTheRoot() {
+		let root = params().blocks().unwrap().root();
+		let changed = [
+			PrismGenesis { voterChains: 4, ..params() },
+			PrismGenesis { timestamp: 1_696_118_400_001, ..params() },
+			PrismGenesis { difficulty: H256::repeat_byte(0x1f), ..params() },
+		];
+		for params in changed {
+			assert_ne!(params.blocks().unwrap().root(), root);
+		}
+	}
+
+	#[test]
+	fn pinnedRootChecked() {
+		let root = params().blocks().unwrap().root();
+		assert!(params().checkedBlocks(root).is_ok());
+		assert_eq!(
+			PrismGenesis { voterChains: 4, ..params() }.checkedBlocks(root).map(|_| ()),
+			Err(GenesisError::RootMismatch { expected: root, found: PrismGenesis { voterChains: 4, ..params() }.blocks().unwrap().root() })
+		);
+		assert_eq!(PrismGenesis { voterChains: 0, ..params() }.blocks(), Err(GenesisError::NoVoterChains));
+	}
+}
@@ -0,0 +1,45 @@
+//Canonical hashing of Prism headers and contents.
+//
+//Every hashed type gets its own Blake3 derive-key context and is hashed over the SCALE encoding of
+//all of its committed fields, so the hash of one type can never be passed off as the hash of
+//another with the same bytes, e.g. a voter content as a header, and no field can be changed
+//without changing the hash. The header's digest is the one field left out, it carries the miner
+//seal that signs the header hash.
+use sp_core::H256;
+use codec::Encode;
+
+pub const HEADER_CONTEXT: &str = "Argonaut 2023-10 prism block header v1";
+pub const PROPOSER_CONTENT_CONTEXT: &str = "Argonaut 2023-10 prism proposer content v1";
+pub const VOTER_CONTENT_CONTEXT: &str = "Argonaut 2023-10 prism voter content v1";
+pub const TRANSACTION_CONTENT_CONTEXT: &str = "Argonaut 2023-10 prism transaction content v1";
+pub const SNAPSHOT_CONTEXT: &str = "Argonaut 2023-10 prism snapshot v1";
+
+pub fn hashWithContext<T: Encode>(context: &str, data: &T) -> H256 {
+	let mut hasher = blake3::Hasher::new_derive_key(context);
+	data.using_encoded(|bytes| hasher.update(bytes));
+	H256::from(*hasher.finalize().as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn contextsSeparateTypes() {
+		let contexts = [HEADER_CONTEXT, PROPOSER_CONTENT_CONTEXT, VOTER_CONTENT_CONTEXT, TRANSACTION_CONTENT_CONTEXT, SNAPSHOT_CONTEXT];
+		let data = (H256::repeat_byte(1), H256::repeat_byte(2));
+		let mut hashes: Vec<H256> = contexts.iter().map(|context| hashWithContext(context, &data)).collect();
+		hashes.push(H256::from(*blake3::hash(&data.encode()).as_bytes()));
+		hashes.sort();
+		hashes.dedup();
+		assert_eq!(hashes.len(), contexts.len() + 1);
+	}
+
+	#[test]
+	fn hashCoversTheEncoding() {
+		let hash = hashWithContext(HEADER_CONTEXT, &(1u32, H256::zero()));
+		assert_eq!(hash, hashWithContext(HEADER_CONTEXT, &(1u32, H256::zero())));
+		assert_ne!(hash, hashWithContext(HEADER_CONTEXT, &(2u32, H256::zero())));
+		assert_ne!(hash, hashWithContext(HEADER_CONTEXT, &(1u32, H256::repeat_byte(1))));
+	}
+}
@@ -0,0 +1,228 @@
+use sp_core::H256;
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use std::vec::Vec;
+
+//prefix bytes separating leaf hashes from internal node hashes, without them an internal node
+//could be passed off as a leaf and a proof forged for data that was never in the tree
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+//hash of a leaf from the SCALE encoding of its item
+pub fn hashLeaf(data: &[u8]) -> H256 {
+	let mut hasher = blake3::Hasher::new();
+	hasher.update(&[LEAF_PREFIX]);
+	hasher.update(data);
+	H256::from_slice(hasher.finalize().as_bytes())
+}
+
+pub fn hashNode(left: &H256, right: &H256) -> H256 {
+	let mut hasher = blake3::Hasher::new();
+	hasher.update(&[NODE_PREFIX]);
+	hasher.update(left.as_bytes());
+	hasher.update(right.as_bytes());
+	H256::from_slice(hasher.finalize().as_bytes())
+}
+
+//Blake3 Merkle tree. A level with an odd number of nodes carries its last node up unchanged
+//rather than duplicating it, so no two different lists of items share a root. The root of an
+//empty tree is zero.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MerkleTree {
+	//levels[0] are the leaf hashes, the last level holds the root
+	levels: Vec<Vec<H256>>,
+}
+
+impl MerkleTree {
+	//build the tree over the SCALE encodings of `data`
+	pub fn new<T: Encode>(data: &[T]) -> Self {
+		let leaves = data.iter().map(|item| item.using_encoded(hashLeaf)).collect();
+		Self::fromLeaves(leaves)
+	}
+
+	//build the tree over already hashed leaves
+	pub fn fromLeaves(leaves: Vec<H256>) -> Self {
+		let mut levels = vec![leaves];
+		while levels[levels.len() - 1].len() > 1 {
+			let next = levels[levels.len() - 1]
+				.chunks(2)
+				.map(|pair| match pair {
+					[left, right] => hashNode(left, right),
+					[odd] => *odd,
+					_ => unreachable!("chunks(2) yields one or two nodes"),
+				})
+				.collect();
+			levels.push(next);
+		}
+		MerkleTree { levels }
+	}
+
+	pub fn root(&self) -> H256 {
+		match self.levels[self.levels.len() - 1].first() {
+			Some(root) => *root,
+			None => H256::zero(),
+		}
+	}
+
+	pub fn leafCount(&self) -> usize {
+		self.levels[0].len()
+	}
+
+	pub fn isEmpty(&self) -> bool {
+		self.levels[0].is_empty()
+	}
+
+	//proof that the leaf at `index` is in the tree, none if the index is out of range
+	pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+		if index >= self.leafCount() {
+			return None;
+		}
+		let mut siblings = Vec::new();
+		let mut position = index;
+		for level in &self.levels[..self.levels.len() - 1] {
+			let sibling = position ^ 1;
+			//a carried up node has no sibling on this level
+			if sibling < level.len() {
+				siblings.push(level[sibling]);
+			}
+			position /= 2;
+		}
+		Some(MerkleProof { index: index as u32, siblings })
+	}
+}
+
+//inclusion proof of one leaf, the sibling hashes from the leaf level up to below the root. The
+//leaf count is not part of the proof: which nodes have a sibling depends on it, so a prover
+//choosing it could pass off an inner node of a larger tree as a leaf. The verifier knows it.
+#[derive(Clone, PartialEq, Eq, Debug, Encode, Decode, TypeInfo)]
+pub struct MerkleProof {
+	pub index: u32,
+	pub siblings: Vec<H256>,
+}
+
+impl MerkleProof {
+	//check that `item` is the leaf at `index` of the tree of `leafCount` leaves with `root`
+	pub fn verify<T: Encode>(&self, root: &H256, leafCount: u32, item: &T) -> bool {
+		self.verifyLeaf(root, leafCount, item.using_encoded(hashLeaf))
+	}
+
+	pub fn verifyLeaf(&self, root: &H256, leafCount: u32, leaf: H256) -> bool {
+		if self.index >= leafCount {
+			return false;
+		}
+		let mut hash = leaf;
+		let mut position = self.index;
+		let mut width = leafCount;
+		let mut siblings = self.siblings.iter();
+		while width > 1 {
+			if position % 2 == 1 {
+				match siblings.next() {
+					Some(sibling) => hash = hashNode(sibling, &hash),
+					None => return false,
+				}
+			} else if position + 1 < width {
+				match siblings.next() {
+					Some(sibling) => hash = hashNode(&hash, sibling),
+					None => return false,
+				}
+			}
+			position /= 2;
+			width = width.div_ceil(2);
+		}
+		siblings.next().is_none() && hash == *root
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn items(count: u64) -> Vec<H256> {
+		(0..count).map(H256::from_low_u64_be).collect()
+	}
+
+	#[test]
+	fn emptyTree() {
+		let tree = MerkleTree::new::<H256>(&[]);
+		assert_eq!(tree.root(), H256::zero());
+		assert!(tree.isEmpty());
+		assert!(tree.proof(0).is_none());
+	}
+
+	#[test]
+	fn singleLeaf() {
+		let data = items(1);
+		let tree = MerkleTree::new(&data);
+		assert_eq!(tree.root(), hashLeaf(data[0].as_bytes()));
+		assert!(tree.proof(0).unwrap().verify(&tree.root(), 1, &data[0]));
+	}
+
+	#[test]
+	fn oddTreeCarriesLastNode() {
+		let data = items(3);
+		let tree = MerkleTree::new(&data);
+		let leaves: Vec<H256> = data.iter().map(|item| hashLeaf(item.as_bytes())).collect();
+		assert_eq!(tree.root(), hashNode(&hashNode(&leaves[0], &leaves[1]), &leaves[2]));
+	}
+
+	#[test]
+	fn proofsForEveryLeaf() {
+		for count in 1..=17 {
+			let data = items(count);
+			let tree = MerkleTree::new(&data);
+			for (index, item) in data.iter().enumerate() {
+				let proof = tree.proof(index).unwrap();
+				assert!(proof.verify(&tree.root(), count as u32, item), "leaf {} of {}", index, count);
+				let decoded = MerkleProof::decode(&mut &proof.encode()[..]).unwrap();
+				assert_eq!(decoded, proof);
+			}
+		}
+	}
+
+	#[test]
+	fn rootIsDeterministic() {
+		assert_eq!(MerkleTree::new(&items(7)).root(), MerkleTree::new(&items(7)).root());
+		assert_ne!(MerkleTree::new(&items(7)).root(), MerkleTree::new(&items(8)).root());
+	}
+
+	#[test]
+	fn wrongItemOrIndexFails() {
+		let data = items(6);
+		let tree = MerkleTree::new(&data);
+		let proof = tree.proof(2).unwrap();
+		assert!(!proof.verify(&tree.root(), 6, &data[3]));
+		let mut moved = proof.clone();
+		moved.index = 3;
+		assert!(!moved.verify(&tree.root(), 6, &data[2]));
+		let mut tampered = proof.clone();
+		tampered.siblings[0] = H256::repeat_byte(1);
+		assert!(!tampered.verify(&tree.root(), 6, &data[2]));
+		let mut extended = proof;
+		extended.siblings.push(H256::zero());
+		assert!(!extended.verify(&tree.root(), 6, &data[2]));
+	}
+
+	#[test]
+	fn internalNodeIsNotALeaf() {
+		let data = items(4);
+		let tree = MerkleTree::new(&data);
+		let leaves: Vec<H256> = data.iter().map(|item| hashLeaf(item.as_bytes())).collect();
+		//an item whose encoding is the two children of the root's left child, without the
+		//prefixes it would hash to that node and verify as a leaf of a two leaf tree
+		let forgedItem = (leaves[0], leaves[1]);
+		let forged = MerkleProof { index: 0, siblings: vec![hashNode(&leaves[2], &leaves[3])] };
+		assert!(!forged.verify(&tree.root(), 2, &forgedItem));
+	}
+
+	#[test]
+	fn leafCountComesFromTheVerifier() {
+		let data = items(3);
+		let tree = MerkleTree::new(&data);
+		let leaves: Vec<H256> = data.iter().map(|item| hashLeaf(item.as_bytes())).collect();
+		//in a tree of two leaves the carried up third leaf would be the right child of the root
+		let forged = MerkleProof { index: 1, siblings: vec![hashNode(&leaves[0], &leaves[1])] };
+		assert!(forged.verify(&tree.root(), 2, &data[2]));
+		assert!(!forged.verify(&tree.root(), 3, &data[2]));
+		assert!(tree.proof(2).unwrap().verify(&tree.root(), 3, &data[2]));
+	}
+}
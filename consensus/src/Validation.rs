@@ -0,0 +1,687 @@
+//Headers-first validation of Prism blocks.
+//
+//A block is validated in three stages so that junk from a peer is dropped as early and as
+//cheaply as possible:
+//  1. header: proof of work against the expected difficulty, a well formed extraContent, a known
+//     proposer parent, a sane timestamp, a sortition that puts the block in a kind this chain has
+//     and a miner seal signed by the coinbase's key
+//  2. fetch: the content is only downloaded for a verified header, its encoding is bounded by the
+//     payload limits of the kind before decoding and the header's contentRoot has to commit to it
+//  3. content: the content is of the kind the sortition chose, committed to as that kind, within
+//     the payload limits, and every reference and vote points at a known block of the right kind.
+//     A voter block votes on the proposer levels right after the one its parent voted on, one
+//     block per level
+//
+//A header commits either to the content of one kind, its contentRoot is the content's hash and the
+//block carries no sortition proof, or to a content of every kind through a sortition tree, see
+//Block::sortitionTree. The sortition proof shows the content is the leaf of the kind the header
+//hash falls into, so a miner cannot pick the kind of its block after the fact. Compact relay and
+//erasure coding rebuild contents a header commits to directly.
+use sp_core::{H256, U256, U512};
+use codec::DecodeAll;
+use primitives::seal::MinerSealError;
+use std::{collections::{btree_map::BTreeMap, btree_set::BTreeSet}, vec::Vec};
+use super::Block::{commitsTo, Block, BlockKind, Content, Header::BlockHeader};
+use super::Block::ExtraContent::{ExtraContentError, ParsedExtraContent, UnknownVersionPolicy};
+use super::Block::Limits::{PayloadError, PayloadLimits};
+use super::Genesis::DEFAULT_VOTER_CHAINS;
+
+//milliseconds a header's timestamp may be ahead of the local clock
+pub const MAX_FUTURE_DRIFT: u128 = 15_000;
+
+//bytes of enum variant, version and length prefixes around the payload of an encoded content
+const FRAMING_BYTES: usize = 16;
+
+//how the work below the target is split between the block kinds. The header hash's position in
+//[0, target] picks the kind: the first share goes to proposer blocks, then one share per voter
+//chain, and the rest to transaction blocks.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Sortition {
+	pub proposerWeight: u64,
+	//weight of each single voter chain
+	pub voterWeight: u64,
+	pub voterChains: u16,
+	pub transactionWeight: u64,
+}
+
+impl Default for Sortition {
+	fn default() -> Self {
+		Sortition { proposerWeight: 1, voterWeight: 1, voterChains: DEFAULT_VOTER_CHAINS, transactionWeight: 1000 }
+	}
+}
+
+impl Sortition {
+	fn totalWeight(&self) -> u64 {
+		self.proposerWeight + self.voterWeight * self.voterChains as u64 + self.transactionWeight
+	}
+
+	//kind of a block with `hash` mined against `target`, none if the hash is above the target
+	pub fn kind(&self, hash: &H256, target: &H256) -> Option<BlockKind> {
+		let hash = U256::from_big_endian(hash.as_bytes());
+		let target = U256::from_big_endian(target.as_bytes());
+		let total = self.totalWeight();
+		if hash > target || total == 0 {
+			return None;
+		}
+		//hash * total / (target + 1) is below total, in 512 bits neither side overflows
+		let position = (U512::from(hash) * U512::from(total) / (U512::from(target) + U512::one())).low_u64();
+		if position < self.proposerWeight {
+			return Some(BlockKind::Proposer);
+		}
+		let position = position - self.proposerWeight;
+		let voters = self.voterWeight * self.voterChains as u64;
+		if position < voters {
+			return Some(BlockKind::Voter((position / self.voterWeight) as u16));
+		}
+		if self.transactionWeight > 0 {
+			return Some(BlockKind::Transaction);
+		}
+		None
+	}
+}
+
+//what validation needs to know about the chain this node has
+pub trait ChainView {
+	//header of a known block
+	fn header(&self, hash: &H256) -> Option<BlockHeader>;
+	//kind of a known block
+	fn kind(&self, hash: &H256) -> Option<BlockKind>;
+	//target a child of the proposer block `parent` has to be mined against
+	fn difficulty(&self, parent: &H256) -> H256;
+	//level of a known proposer block
+	fn levelOf(&self, proposer: &H256) -> Option<u64>;
+	//highest proposer level the voter chain voted on up to a known voter block
+	fn votedLevelOf(&self, voter: &H256) -> Option<u64>;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HeaderError {
+	//the header's difficulty is not the one expected after its parent
+	WrongDifficulty,
+	//the header hash is above the difficulty target
+	InsufficientWork,
+	InvalidExtraContent(ExtraContentError),
+	UnknownParent,
+	//the parent is known but not a proposer block
+	ParentNotProposer,
+	TimestampNotAfterParent,
+	TimestampInFuture,
+	//the header hash falls into a kind this chain does not have
+	NoSortitionKind,
+	//the miner seal is missing while required, or not signed by the coinbase's key
+	MinerSeal(MinerSealError),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FetchError {
+	//the encoded content is larger than any content of the header's kind can be
+	TooLarge { size: usize, limit: usize },
+	Undecodable,
+	//the header's contentRoot does not commit to the content with the given sortition proof
+	ContentMismatch,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ContentError {
+	//the content is not of the kind the header's sortition chose
+	SortitionMismatch { expected: BlockKind, found: BlockKind },
+	//the sortition proof does not put the content at the leaf of its kind under the contentRoot
+	InvalidSortitionProof,
+	Payload(PayloadError),
+	UnknownReference(H256),
+	//a reference or vote points at a block of another kind
+	WrongReferenceKind(H256),
+	DuplicateReference(H256),
+	//the voter parent is unknown or on another voter chain
+	WrongVoterParent(H256),
+	//a vote is not for the level after the previous vote, or the parent's voted level
+	NonConsecutiveVote(H256),
+}
+
+impl HeaderError {
+	pub fn reason(&self) -> &'static str {
+		match self {
+			HeaderError::WrongDifficulty => "wrong_difficulty",
+			HeaderError::InsufficientWork => "insufficient_work",
+			HeaderError::InvalidExtraContent(_) => "invalid_extra_content",
+			HeaderError::UnknownParent => "unknown_parent",
+			HeaderError::ParentNotProposer => "parent_not_proposer",
+			HeaderError::TimestampNotAfterParent => "timestamp_not_after_parent",
+			HeaderError::TimestampInFuture => "timestamp_in_future",
+			HeaderError::NoSortitionKind => "no_sortition_kind",
+			HeaderError::MinerSeal(_) => "miner_seal",
+		}
+	}
+}
+
+impl FetchError {
+	pub fn reason(&self) -> &'static str {
+		match self {
+			FetchError::TooLarge { .. } => "too_large",
+			FetchError::Undecodable => "undecodable",
+			FetchError::ContentMismatch => "content_mismatch",
+		}
+	}
+}
+
+impl ContentError {
+	pub fn reason(&self) -> &'static str {
+		match self {
+			ContentError::SortitionMismatch { .. } => "sortition_mismatch",
+			ContentError::InvalidSortitionProof => "invalid_sortition_proof",
+			ContentError::Payload(_) => "payload_limit",
+			ContentError::UnknownReference(_) => "unknown_reference",
+			ContentError::WrongReferenceKind(_) => "wrong_reference_kind",
+			ContentError::DuplicateReference(_) => "duplicate_reference",
+			ContentError::WrongVoterParent(_) => "wrong_voter_parent",
+			ContentError::NonConsecutiveVote(_) => "non_consecutive_vote",
+		}
+	}
+}
+
+//accepted and rejected blocks of one stage, rejections by reason
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct StageMetrics {
+	pub accepted: u64,
+	pub rejected: BTreeMap<&'static str, u64>,
+}
+
+impl StageMetrics {
+	fn record<T, E>(&mut self, result: &Result<T, E>, reason: impl Fn(&E) -> &'static str) {
+		match result {
+			Ok(_) => self.accepted += 1,
+			Err(error) => *self.rejected.entry(reason(error)).or_insert(0) += 1,
+		}
+	}
+
+	pub fn rejectedTotal(&self) -> u64 {
+		self.rejected.values().sum()
+	}
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ValidationMetrics {
+	pub header: StageMetrics,
+	pub fetch: StageMetrics,
+	pub content: StageMetrics,
+}
+
+//a header that passed the first stage, only these get their content fetched
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct VerifiedHeader {
+	header: BlockHeader,
+	hash: H256,
+	kind: BlockKind,
+	extra: ParsedExtraContent,
+}
+
+impl VerifiedHeader {
+	pub fn header(&self) -> &BlockHeader {
+		&self.header
+	}
+
+	pub fn hash(&self) -> H256 {
+		self.hash
+	}
+
+	//kind the sortition put the block in
+	pub fn kind(&self) -> BlockKind {
+		self.kind
+	}
+
+	//the parsed extraContent, what upgrade signalling is counted from
+	pub fn extra(&self) -> &ParsedExtraContent {
+		&self.extra
+	}
+}
+
+pub struct Validator {
+	pub sortition: Sortition,
+	pub limits: PayloadLimits,
+	//what to do with headers whose extraContent layout is newer than this node's
+	pub unknownExtraVersions: UnknownVersionPolicy,
+	pub metrics: ValidationMetrics,
+}
+
+impl Validator {
+	pub fn new(sortition: Sortition, limits: PayloadLimits) -> Self {
+		Validator { sortition, limits, unknownExtraVersions: UnknownVersionPolicy::default(), metrics: ValidationMetrics::default() }
+	}
+
+	//stage 1, `now` is the local time in milliseconds
+	pub fn checkHeader(&mut self, chain: &impl ChainView, header: BlockHeader, now: u128) -> Result<VerifiedHeader, HeaderError> {
+		let result = self.headerChecks(chain, header, now);
+		self.metrics.header.record(&result, HeaderError::reason);
+		result
+	}
+
+	fn headerChecks(&self, chain: &impl ChainView, header: BlockHeader, now: u128) -> Result<VerifiedHeader, HeaderError> {
+		//the work is the cheapest check and the one that costs a junk sender the most, so it goes first
+		if header.difficulty != chain.difficulty(&header.parent) {
+			return Err(HeaderError::WrongDifficulty);
+		}
+		let hash = header.hash();
+		if U256::from_big_endian(hash.as_bytes()) > U256::from_big_endian(header.difficulty.as_bytes()) {
+			return Err(HeaderError::InsufficientWork);
+		}
+		let extra = header.extraContent.check(self.unknownExtraVersions).map_err(HeaderError::InvalidExtraContent)?;
+		let parent = chain.header(&header.parent).ok_or(HeaderError::UnknownParent)?;
+		if chain.kind(&header.parent) != Some(BlockKind::Proposer) {
+			return Err(HeaderError::ParentNotProposer);
+		}
+		if header.timestamp <= parent.timestamp {
+			return Err(HeaderError::TimestampNotAfterParent);
+		}
+		if header.timestamp > now.saturating_add(MAX_FUTURE_DRIFT) {
+			return Err(HeaderError::TimestampInFuture);
+		}
+		let kind = self.sortition.kind(&hash, &header.difficulty).ok_or(HeaderError::NoSortitionKind)?;
+		//a Falcon verification is the most expensive header check, so it goes last
+		header.verify_miner_seal().map_err(HeaderError::MinerSeal)?;
+		Ok(VerifiedHeader { header, hash, kind, extra })
+	}
+
+	//largest encoded content of `kind` within the payload limits
+	fn maxEncodedSize(&self, kind: BlockKind) -> usize {
+		let limits = &self.limits;
+		FRAMING_BYTES + match kind {
+			BlockKind::Transaction => limits.maxTransactionBlockBytes as usize,
+			BlockKind::Proposer => 32 * (limits.maxTransactionReferences as usize + limits.maxProposerReferences as usize),
+			//chain number and parent
+			BlockKind::Voter(_) => 2 + 32 + 32 * limits.maxVotes as usize,
+		}
+	}
+
+	//stage 2, decode the content a peer sent for a verified header with its sortition proof
+	pub fn fetchContent(&mut self, verified: &VerifiedHeader, encoded: &[u8], sortitionProof: &[H256]) -> Result<Content, FetchError> {
+		let result = self.fetchChecks(verified, encoded, sortitionProof);
+		self.metrics.fetch.record(&result, FetchError::reason);
+		result
+	}
+
+	fn fetchChecks(&self, verified: &VerifiedHeader, encoded: &[u8], sortitionProof: &[H256]) -> Result<Content, FetchError> {
+		let limit = self.maxEncodedSize(verified.kind);
+		if encoded.len() > limit {
+			return Err(FetchError::TooLarge { size: encoded.len(), limit });
+		}
+		let content = Content::decode_all(&mut &encoded[..]).map_err(|_| FetchError::Undecodable)?;
+		if !commitsTo(&verified.header.contentRoot, verified.kind, self.sortition.voterChains, &content.hash(), sortitionProof) {
+			return Err(FetchError::ContentMismatch);
+		}
+		Ok(content)
+	}
+
+	//stage 3, the block once its content is valid
+	pub fn checkContent(&mut self, chain: &impl ChainView, verified: VerifiedHeader, content: Content, sortitionProof: Vec<H256>) -> Result<Block, ContentError> {
+		let result = self.contentChecks(chain, &verified, &content, &sortitionProof);
+		self.metrics.content.record(&result, ContentError::reason);
+		result?;
+		Ok(Block::fromParts(verified.header, content, sortitionProof))
+	}
+
+	fn contentChecks(&self, chain: &impl ChainView, verified: &VerifiedHeader, content: &Content, sortitionProof: &[H256]) -> Result<(), ContentError> {
+		let found = BlockKind::of(content);
+		if found != verified.kind {
+			return Err(ContentError::SortitionMismatch { expected: verified.kind, found });
+		}
+		if !commitsTo(&verified.header.contentRoot, found, self.sortition.voterChains, &content.hash(), sortitionProof) {
+			return Err(ContentError::InvalidSortitionProof);
+		}
+		self.limits.check(content).map_err(ContentError::Payload)?;
+		match content {
+			Content::TransactionBlock(_) => Ok(()),
+			Content::ProposerBlock(content) => {
+				references(chain, &content.transactionReferences, BlockKind::Transaction)?;
+				references(chain, &content.proposerReferences, BlockKind::Proposer)
+			},
+			Content::VoterBlock(content) => {
+				if chain.kind(&content.parent) != Some(BlockKind::Voter(content.chainNumber)) {
+					return Err(ContentError::WrongVoterParent(content.parent));
+				}
+				references(chain, &content.votes, BlockKind::Proposer)?;
+				let voted = chain.votedLevelOf(&content.parent).ok_or(ContentError::WrongVoterParent(content.parent))?;
+				for (level, vote) in (voted + 1..).zip(&content.votes) {
+					if chain.levelOf(vote) != Some(level) {
+						return Err(ContentError::NonConsecutiveVote(*vote));
+					}
+				}
+				Ok(())
+			},
+		}
+	}
+}
+
+//every hash is a distinct known block of `kind`
+fn references(chain: &impl ChainView, hashes: &[H256], kind: BlockKind) -> Result<(), ContentError> {
+	let mut seen = BTreeSet::new();
+	for hash in hashes {
+		if !seen.insert(*hash) {
+			return Err(ContentError::DuplicateReference(*hash));
+		}
+		match chain.kind(hash) {
+			None => return Err(ContentError::UnknownReference(*hash)),
+			Some(known) if known != kind => return Err(ContentError::WrongReferenceKind(*hash)),
+			Some(_) => {},
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use super::super::Block::{ProposerBlock, TransactionBlock, VoterBlock};
+	use super::super::Block::ExtraContent::{ExtraContent, EXTRA_CONTENT_VERSION};
+
+	const EASY: H256 = H256::repeat_byte(0xff);
+
+	struct Chain {
+		blocks: BTreeMap<H256, (BlockHeader, BlockKind)>,
+		//level of proposer blocks, voted level of voter blocks
+		levels: BTreeMap<H256, u64>,
+		difficulty: H256,
+		//a proposer block at level 1, the one to vote on
+		tip: H256,
+	}
+
+	impl ChainView for Chain {
+		fn header(&self, hash: &H256) -> Option<BlockHeader> {
+			self.blocks.get(hash).map(|(header, _)| header.clone())
+		}
+
+		fn kind(&self, hash: &H256) -> Option<BlockKind> {
+			self.blocks.get(hash).map(|(_, kind)| *kind)
+		}
+
+		fn difficulty(&self, _parent: &H256) -> H256 {
+			self.difficulty
+		}
+
+		fn levelOf(&self, proposer: &H256) -> Option<u64> {
+			self.levels.get(proposer).copied().filter(|_| self.kind(proposer) == Some(BlockKind::Proposer))
+		}
+
+		fn votedLevelOf(&self, voter: &H256) -> Option<u64> {
+			self.levels.get(voter).copied().filter(|_| matches!(self.kind(voter), Some(BlockKind::Voter(_))))
+		}
+	}
+
+	fn sortition() -> Sortition {
+		Sortition { proposerWeight: 1, voterWeight: 1, voterChains: 2, transactionWeight: 1 }
+	}
+
+	//a chain with a genesis proposer block, one genesis voter block per chain voting on it, one
+	//transaction block and a proposer block at level 1
+	fn chain() -> (Chain, H256) {
+		let mut blocks = BTreeMap::new();
+		let mut levels = BTreeMap::new();
+		let genesis = BlockHeader::new(H256::zero(), 1, 0, H256::zero(), ExtraContent::default(), EASY);
+		let genesisHash = genesis.hash();
+		blocks.insert(genesisHash, (genesis, BlockKind::Proposer));
+		levels.insert(genesisHash, 0);
+		for (i, kind) in [BlockKind::Voter(0), BlockKind::Voter(1), BlockKind::Transaction].into_iter().enumerate() {
+			let header = BlockHeader::new(H256::zero(), 1, i as u32 + 1, H256::zero(), ExtraContent::default(), EASY);
+			levels.insert(header.hash(), 0);
+			blocks.insert(header.hash(), (header, kind));
+		}
+		let tip = BlockHeader::new(genesisHash, 2, 0, H256::zero(), ExtraContent::default(), EASY);
+		let tipHash = tip.hash();
+		blocks.insert(tipHash, (tip, BlockKind::Proposer));
+		levels.insert(tipHash, 1);
+		(Chain { blocks, levels, difficulty: EASY, tip: tipHash }, genesisHash)
+	}
+
+	fn known(chain: &Chain, kind: BlockKind) -> H256 {
+		*chain.blocks.iter().find(|(_, (_, known))| *known == kind).unwrap().0
+	}
+
+	//grind the nonce until the sortition puts the header committing to `contentRoot` in `kind`, then
+	//seal it with the key behind its coinbase
+	fn grind(parent: H256, contentRoot: H256, extraContent: ExtraContent, kind: BlockKind) -> BlockHeader {
+		let (pk, sk) = primitives::FalconHostFunctions::generate_keypair();
+		let coinbase = sp_core::crypto::AccountId32::new(primitives::FalconHostFunctions::hash_public_key(&pk));
+		let mut header = (0..).map(|nonce| BlockHeader::new(parent, 10, nonce, contentRoot, extraContent, EASY).with_coinbase(coinbase.clone()))
+			.find(|header| sortition().kind(&header.hash(), &EASY) == Some(kind))
+			.unwrap();
+		header.seal_with_miner_key(&pk, &sk);
+		header
+	}
+
+	fn mine(parent: H256, content: &Content, kind: BlockKind) -> BlockHeader {
+		grind(parent, content.hash(), ExtraContent::default(), kind)
+	}
+
+	fn validate(chain: &Chain, validator: &mut Validator, header: BlockHeader, content: &Content) -> Result<Block, ContentError> {
+		let verified = validator.checkHeader(chain, header, 10).unwrap();
+		let content = validator.fetchContent(&verified, &content.encode(), &[]).unwrap();
+		validator.checkContent(chain, verified, content, Vec::new())
+	}
+
+	#[test]
+	fn sortitionCoversEveryKind() {
+		let sortition = sortition();
+		let target = U256::from_big_endian(EASY.as_bytes());
+		let at = |quarter: u64| {
+			let mut bytes = [0u8; 32];
+			//start of the quarter, target + 1 is 2^256
+			((target / 4 + 1) * quarter).to_big_endian(&mut bytes);
+			H256::from(bytes)
+		};
+		assert_eq!(sortition.kind(&H256::zero(), &EASY), Some(BlockKind::Proposer));
+		assert_eq!(sortition.kind(&at(1), &EASY), Some(BlockKind::Voter(0)));
+		assert_eq!(sortition.kind(&at(2), &EASY), Some(BlockKind::Voter(1)));
+		assert_eq!(sortition.kind(&at(3), &EASY), Some(BlockKind::Transaction));
+		assert_eq!(sortition.kind(&EASY, &H256::from_low_u64_be(1)), None);
+	}
+
+	#[test]
+	fn validBlocksPassEveryStage() {
+		let (chain, genesis) = chain();
+		let mut validator = Validator::new(sortition(), PayloadLimits::default());
+		let contents = vec![
+			Content::ProposerBlock(ProposerBlock::Content::new(vec![known(&chain, BlockKind::Transaction)], vec![genesis])),
+			Content::VoterBlock(VoterBlock::Content::new(1, known(&chain, BlockKind::Voter(1)), vec![chain.tip])),
+			Content::TransactionBlock(TransactionBlock::Content::new(Vec::new())),
+		];
+		for content in contents {
+			let header = mine(genesis, &content, BlockKind::of(&content));
+			let block = validate(&chain, &mut validator, header.clone(), &content).unwrap();
+			assert_eq!(block.header(), &header);
+			assert_eq!(block.intoParts().1, content);
+		}
+		assert_eq!(validator.metrics.content.accepted, 3);
+		assert_eq!(validator.metrics.content.rejectedTotal(), 0);
+	}
+
+	#[test]
+	fn sortitionProofPicksTheMinedKind() {
+		let (chain, genesis) = chain();
+		let mut validator = Validator::new(sortition(), PayloadLimits::default());
+		let contents = [
+			Content::ProposerBlock(ProposerBlock::Content::new(Vec::new(), vec![genesis])),
+			Content::VoterBlock(VoterBlock::Content::new(0, known(&chain, BlockKind::Voter(0)), vec![chain.tip])),
+			Content::VoterBlock(VoterBlock::Content::new(1, known(&chain, BlockKind::Voter(1)), vec![chain.tip])),
+			Content::TransactionBlock(TransactionBlock::Content::new(Vec::new())),
+		];
+		let tree = crate::Block::sortitionTree(&contents.iter().map(Content::hash).collect::<Vec<_>>());
+		let proof = |index: usize| tree.proof(index).unwrap().siblings;
+		//one header mined against the tree, grinding until it lands on the second voter chain
+		let header = grind(genesis, tree.root(), ExtraContent::default(), BlockKind::Voter(1));
+
+		let verified = validator.checkHeader(&chain, header.clone(), 10).unwrap();
+		let content = validator.fetchContent(&verified, &contents[2].encode(), &proof(2)).unwrap();
+		let block = validator.checkContent(&chain, verified.clone(), content, proof(2)).unwrap();
+		assert!(block.commitsContent(2));
+		assert_eq!(block.sortitionProof(), &proof(2)[..]);
+
+		//the leaf of another kind, or the right content with another leaf's proof, is refused
+		assert_eq!(validator.fetchContent(&verified, &contents[0].encode(), &proof(0)), Err(FetchError::ContentMismatch));
+		assert_eq!(validator.fetchContent(&verified, &contents[2].encode(), &proof(1)), Err(FetchError::ContentMismatch));
+		assert_eq!(
+			validator.checkContent(&chain, verified.clone(), contents[1].clone(), proof(1)).map(|_| ()),
+			Err(ContentError::SortitionMismatch { expected: BlockKind::Voter(1), found: BlockKind::Voter(0) })
+		);
+		assert_eq!(
+			validator.checkContent(&chain, verified, contents[2].clone(), Vec::new()).map(|_| ()),
+			Err(ContentError::InvalidSortitionProof)
+		);
+	}
+
+	#[test]
+	fn badHeadersRejected() {
+		let (mut chain, genesis) = chain();
+		let mut validator = Validator::new(sortition(), PayloadLimits::default());
+		let content = Content::TransactionBlock(TransactionBlock::Content::new(Vec::new()));
+		let header = mine(genesis, &content, BlockKind::Transaction);
+
+		let mut future = header.clone();
+		future.timestamp = 10 + MAX_FUTURE_DRIFT + 1;
+		assert_eq!(validator.checkHeader(&chain, future, 10), Err(HeaderError::TimestampInFuture));
+		let mut early = header.clone();
+		early.timestamp = 1;
+		assert_eq!(validator.checkHeader(&chain, early, 10), Err(HeaderError::TimestampNotAfterParent));
+		let mut orphan = header.clone();
+		orphan.parent = H256::repeat_byte(7);
+		assert_eq!(validator.checkHeader(&chain, orphan, 10), Err(HeaderError::UnknownParent));
+		let mut voterParent = header.clone();
+		voterParent.parent = known(&chain, BlockKind::Voter(0));
+		assert_eq!(validator.checkHeader(&chain, voterParent, 10), Err(HeaderError::ParentNotProposer));
+
+		let hard = H256::from_low_u64_be(1);
+		let mut lowWork = header.clone();
+		lowWork.difficulty = hard;
+		assert_eq!(validator.checkHeader(&chain, lowWork.clone(), 10), Err(HeaderError::WrongDifficulty));
+		chain.difficulty = hard;
+		assert_eq!(validator.checkHeader(&chain, lowWork, 10), Err(HeaderError::InsufficientWork));
+
+		let mut newer = [0xaa; 32];
+		newer[0] = EXTRA_CONTENT_VERSION + 1;
+		let unknownExtra = grind(genesis, content.hash(), ExtraContent::fromBytes(newer), BlockKind::Transaction);
+		chain.difficulty = EASY;
+		assert!(validator.checkHeader(&chain, unknownExtra.clone(), 10).is_ok());
+		validator.unknownExtraVersions = UnknownVersionPolicy::Reject;
+		assert_eq!(
+			validator.checkHeader(&chain, unknownExtra, 10),
+			Err(HeaderError::InvalidExtraContent(ExtraContentError::UnknownVersion(EXTRA_CONTENT_VERSION + 1)))
+		);
+		let mut legacy = [0u8; 32];
+		legacy[31] = 1;
+		let mut malformed = header.clone();
+		malformed.extraContent = ExtraContent::fromBytes(legacy);
+		assert_eq!(
+			validator.checkHeader(&chain, malformed, 10),
+			Err(HeaderError::InvalidExtraContent(ExtraContentError::NonZeroLegacy))
+		);
+
+		let metrics = &validator.metrics.header;
+		assert_eq!(metrics.accepted, 1);
+		assert_eq!(metrics.rejectedTotal(), 8);
+		assert_eq!(metrics.rejected.get("insufficient_work"), Some(&1));
+	}
+
+	#[test]
+	fn minerSealChecked() {
+		let (chain, genesis) = chain();
+		let mut validator = Validator::new(sortition(), PayloadLimits::default());
+		let content = Content::TransactionBlock(TransactionBlock::Content::new(Vec::new()));
+		let sealed = mine(genesis, &content, BlockKind::Transaction);
+		assert!(validator.checkHeader(&chain, sealed.clone(), 10).is_ok());
+
+		let mut unsealed = sealed.clone();
+		unsealed.digest = Default::default();
+		assert_eq!(validator.checkHeader(&chain, unsealed, 10), Err(HeaderError::MinerSeal(MinerSealError::Missing)));
+		let (otherPk, otherSk) = primitives::FalconHostFunctions::generate_keypair();
+		let mut otherKey = sealed;
+		otherKey.seal_with_miner_key(&otherPk, &otherSk);
+		assert_eq!(validator.checkHeader(&chain, otherKey, 10), Err(HeaderError::MinerSeal(MinerSealError::CoinbaseMismatch)));
+		assert_eq!(validator.metrics.header.rejected.get("miner_seal"), Some(&2));
+	}
+
+	#[test]
+	fn junkContentRejectedBeforeDecoding() {
+		let (chain, genesis) = chain();
+		let limits = PayloadLimits { maxVotes: 2, ..PayloadLimits::default() };
+		let mut validator = Validator::new(sortition(), limits);
+		let voterParent = known(&chain, BlockKind::Voter(0));
+		let content = Content::VoterBlock(VoterBlock::Content::new(0, voterParent, vec![genesis]));
+		let verified = validator.checkHeader(&chain, mine(genesis, &content, BlockKind::Voter(0)), 10).unwrap();
+
+		let oversized = vec![0u8; 1024];
+		assert_eq!(validator.fetchContent(&verified, &oversized, &[]), Err(FetchError::TooLarge { size: 1024, limit: FRAMING_BYTES + 2 + 32 + 64 }));
+		assert_eq!(validator.fetchContent(&verified, &[0xff; 8], &[]), Err(FetchError::Undecodable));
+		let other = Content::VoterBlock(VoterBlock::Content::new(0, voterParent, Vec::new()));
+		assert_eq!(validator.fetchContent(&verified, &other.encode(), &[]), Err(FetchError::ContentMismatch));
+		assert_eq!(validator.fetchContent(&verified, &content.encode(), &[H256::zero()]), Err(FetchError::ContentMismatch));
+		assert_eq!(validator.fetchContent(&verified, &content.encode(), &[]), Ok(content));
+		assert_eq!(validator.metrics.fetch.accepted, 1);
+		assert_eq!(validator.metrics.fetch.rejectedTotal(), 4);
+	}
+
+	#[test]
+	fn votesFollowTheParentsVotedLevel() {
+		let (mut chain, genesis) = chain();
+		let far = BlockHeader::new(chain.tip, 3, 0, H256::zero(), ExtraContent::default(), EASY);
+		let farHash = far.hash();
+		chain.blocks.insert(farHash, (far, BlockKind::Proposer));
+		chain.levels.insert(farHash, 3);
+		let mut validator = Validator::new(sortition(), PayloadLimits::default());
+		let voterParent = known(&chain, BlockKind::Voter(0));
+		let check = |validator: &mut Validator, votes: Vec<H256>| {
+			let content = Content::VoterBlock(VoterBlock::Content::new(0, voterParent, votes));
+			validate(&chain, validator, mine(genesis, &content, BlockKind::Voter(0)), &content).map(|_| ())
+		};
+
+		assert_eq!(check(&mut validator, Vec::new()), Ok(()));
+		assert_eq!(check(&mut validator, vec![chain.tip]), Ok(()));
+		//level 2 is skipped
+		assert_eq!(check(&mut validator, vec![chain.tip, farHash]), Err(ContentError::NonConsecutiveVote(farHash)));
+		assert_eq!(check(&mut validator, vec![farHash]), Err(ContentError::NonConsecutiveVote(farHash)));
+		assert_eq!(validator.metrics.content.rejected.get("non_consecutive_vote"), Some(&2));
+	}
+
+	#[test]
+	fn badContentRejected() {
+		let (chain, genesis) = chain();
+		let mut validator = Validator::new(sortition(), PayloadLimits::default());
+		let transaction = known(&chain, BlockKind::Transaction);
+		let voterParent = known(&chain, BlockKind::Voter(0));
+		let check = |validator: &mut Validator, content: Content, kind: BlockKind| {
+			let header = mine(genesis, &content, kind);
+			validate(&chain, validator, header, &content).map(|_| ())
+		};
+
+		//a proposer block content on a header sorted into the transaction kind
+		let proposer = Content::ProposerBlock(ProposerBlock::Content::new(Vec::new(), vec![genesis]));
+		assert_eq!(
+			check(&mut validator, proposer, BlockKind::Transaction),
+			Err(ContentError::SortitionMismatch { expected: BlockKind::Transaction, found: BlockKind::Proposer })
+		);
+		let voter = Content::VoterBlock(VoterBlock::Content::new(1, voterParent, Vec::new()));
+		assert_eq!(
+			check(&mut validator, voter, BlockKind::Voter(0)),
+			Err(ContentError::SortitionMismatch { expected: BlockKind::Voter(0), found: BlockKind::Voter(1) })
+		);
+		let unknown = H256::repeat_byte(9);
+		let proposer = Content::ProposerBlock(ProposerBlock::Content::new(vec![unknown], Vec::new()));
+		assert_eq!(check(&mut validator, proposer, BlockKind::Proposer), Err(ContentError::UnknownReference(unknown)));
+		let proposer = Content::ProposerBlock(ProposerBlock::Content::new(vec![genesis], Vec::new()));
+		assert_eq!(check(&mut validator, proposer, BlockKind::Proposer), Err(ContentError::WrongReferenceKind(genesis)));
+		let voter = Content::VoterBlock(VoterBlock::Content::new(0, voterParent, vec![genesis, genesis]));
+		assert_eq!(check(&mut validator, voter, BlockKind::Voter(0)), Err(ContentError::DuplicateReference(genesis)));
+		let voter = Content::VoterBlock(VoterBlock::Content::new(0, transaction, Vec::new()));
+		assert_eq!(check(&mut validator, voter, BlockKind::Voter(0)), Err(ContentError::WrongVoterParent(transaction)));
+		//the parent voted on the genesis level already
+		let voter = Content::VoterBlock(VoterBlock::Content::new(0, voterParent, vec![genesis]));
+		assert_eq!(check(&mut validator, voter, BlockKind::Voter(0)), Err(ContentError::NonConsecutiveVote(genesis)));
+
+		let content = Content::TransactionBlock(TransactionBlock::Content::new(Vec::new()));
+		let verified = validator.checkHeader(&chain, mine(genesis, &content, BlockKind::Transaction), 10).unwrap();
+		assert_eq!(
+			validator.checkContent(&chain, verified, content, vec![H256::zero()]).map(|_| ()),
+			Err(ContentError::InvalidSortitionProof)
+		);
+		assert_eq!(validator.metrics.content.rejectedTotal(), 8);
+		assert_eq!(validator.metrics.content.rejected.get("sortition_mismatch"), Some(&2));
+	}
+}
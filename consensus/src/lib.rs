@@ -0,0 +1,13 @@
+//! Prism consensus: the block types, their validation and the block tree with its store.
+//!
+//! Everything here runs in the node, not in the runtime, so the crate is std only. The runtime is
+//! only used for the Substrate types a transaction block's extrinsics are executed as.
+#![allow(non_snake_case)]
+
+pub mod Block;
+pub mod BlockTree;
+pub mod Blockchain;
+pub mod Genesis;
+pub mod Hashing;
+pub mod MerkleTree;
+pub mod Validation;
@@ -1,10 +1,16 @@
 use Argonaut_Runtime::{
-	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, Signature, SudoConfig,
-	SystemConfig, WASM_BINARY,
+	AccountId, AuraConfig, BalancesConfig, FalconMigrationConfig, GenesisConfig, GrandpaConfig,
+	SS58Prefix, Signature, SudoConfig, SystemConfig, WASM_BINARY,
 };
-use sc_service::ChainType;
+use prism_consensus::{
+	Block::Limits::PayloadLimits,
+	Genesis::{GenesisBlocks, PrismGenesis, DEFAULT_VOTER_CHAINS},
+};
+use primitives::address::DEFAULT_SS58_PREFIX;
+use sc_service::{ChainType, Properties};
+use serde::{Deserialize, Serialize};
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
-use sp_core::{sr25519, Pair, Public};
+use sp_core::{sr25519, Pair, Public, H256};
 use sp_finality_grandpa::AuthorityId as GrandpaId;
 use sp_runtime::traits::{IdentifyAccount, Verify};
 
@@ -31,6 +37,121 @@ where
 	AccountPublic::from(get_from_seed::<TPublic>(seed)).into_account()
 }
 
+/// Chain spec property holding the [`PrismGenesisSpec`].
+pub const PRISM_GENESIS_PROPERTY: &str = "prismGenesis";
+
+/// Chain spec property holding the Prism [`PayloadLimits`].
+pub const PRISM_LIMITS_PROPERTY: &str = "prismLimits";
+
+/// Timestamp of the Prism genesis blocks, 2023-10-01 00:00 UTC in milliseconds.
+pub const PRISM_GENESIS_TIMESTAMP: u128 = 1_696_118_400_000;
+
+/// The fields the Prism genesis blocks are built from.
+///
+/// Nothing of the genesis comes from the local clock or state, so every node reading the same
+/// chain spec starts from the same proposer genesis and the same voter genesis per chain. `root`
+/// optionally pins the Merkle root of those genesis hashes, a node building a different genesis
+/// from the other fields refuses it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrismGenesisSpec {
+	/// Number of voter chains, one genesis voter block each.
+	pub voter_chains: u16,
+	/// Timestamp of every genesis block, milliseconds since the unix epoch.
+	pub timestamp: u128,
+	/// Target of the genesis blocks and of the first blocks mined on them.
+	pub difficulty: H256,
+	/// Expected Merkle root of the genesis hashes.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub root: Option<H256>,
+}
+
+impl PrismGenesisSpec {
+	/// The parameters the genesis blocks are built from.
+	pub fn params(&self) -> PrismGenesis {
+		PrismGenesis {
+			voterChains: self.voter_chains,
+			timestamp: self.timestamp,
+			difficulty: self.difficulty,
+		}
+	}
+
+	/// The genesis blocks, checked against `root` when the spec pins one.
+	pub fn blocks(&self) -> Result<GenesisBlocks, String> {
+		let params = self.params();
+		match self.root {
+			Some(root) => params.checkedBlocks(root),
+			None => params.blocks(),
+		}
+		.map_err(|e| format!("Invalid {} chain spec property: {:?}", PRISM_GENESIS_PROPERTY, e))
+	}
+}
+
+/// The Prism genesis of the networks configured here.
+pub fn prism_genesis_spec() -> PrismGenesisSpec {
+	let mut difficulty = [0xff; 32];
+	difficulty[..2].copy_from_slice(&[0, 0]);
+	PrismGenesisSpec {
+		voter_chains: DEFAULT_VOTER_CHAINS,
+		timestamp: PRISM_GENESIS_TIMESTAMP,
+		difficulty: H256(difficulty),
+		root: None,
+	}
+}
+
+/// Chain spec properties, `ss58Format` is the prefix addresses of this network are encoded with,
+/// `prismGenesis` the [`PrismGenesisSpec`] and `prismLimits` the payload limits of Prism blocks.
+pub fn chain_properties() -> Properties {
+	let mut properties = Properties::new();
+	properties.insert("ss58Format".into(), SS58Prefix::get().into());
+	properties.insert(
+		PRISM_GENESIS_PROPERTY.into(),
+		serde_json::to_value(prism_genesis_spec()).expect("genesis spec serializes to JSON; qed"),
+	);
+	properties.insert(
+		PRISM_LIMITS_PROPERTY.into(),
+		serde_json::to_value(PayloadLimits::default()).expect("limits serialize to JSON; qed"),
+	);
+	properties
+}
+
+/// The Prism genesis of `spec`, from its `prismGenesis` property.
+pub fn prism_genesis(spec: &dyn sc_service::ChainSpec) -> Result<PrismGenesisSpec, String> {
+	let value = spec
+		.properties()
+		.get(PRISM_GENESIS_PROPERTY)
+		.cloned()
+		.ok_or_else(|| format!("Missing {} chain spec property", PRISM_GENESIS_PROPERTY))?;
+	let genesis: PrismGenesisSpec = serde_json::from_value(value)
+		.map_err(|e| format!("Invalid {} chain spec property: {}", PRISM_GENESIS_PROPERTY, e))?;
+	if genesis.voter_chains == 0 {
+		return Err(format!("Invalid {} chain spec property: no voter chains", PRISM_GENESIS_PROPERTY))
+	}
+	Ok(genesis)
+}
+
+/// The Prism payload limits of `spec`, from its `prismLimits` property. A spec without the
+/// property, or a limit left out of it, gets the default.
+pub fn prism_limits(spec: &dyn sc_service::ChainSpec) -> Result<PayloadLimits, String> {
+	match spec.properties().get(PRISM_LIMITS_PROPERTY) {
+		Some(value) => serde_json::from_value(value.clone())
+			.map_err(|e| format!("Invalid {} chain spec property: {}", PRISM_LIMITS_PROPERTY, e)),
+		None => Ok(PayloadLimits::default()),
+	}
+}
+
+/// The SS58 prefix of `spec`, from its `ss58Format` property.
+pub fn ss58_prefix(spec: &dyn sc_service::ChainSpec) -> Result<u16, String> {
+	match spec.properties().get("ss58Format") {
+		Some(value) => value
+			.as_u64()
+			.and_then(|prefix| u16::try_from(prefix).ok())
+			.filter(|prefix| *prefix < 16384)
+			.ok_or_else(|| format!("Invalid ss58Format chain spec property: {}", value)),
+		None => Ok(DEFAULT_SS58_PREFIX),
+	}
+}
+
 /// Generate an Aura authority key.
 pub fn authority_keys_from_seed(s: &str) -> (AuraId, GrandpaId) {
 	(get_from_seed::<AuraId>(s), get_from_seed::<GrandpaId>(s))
@@ -70,7 +191,7 @@ pub fn development_config() -> Result<ChainSpec, String> {
 		None,
 		None,
 		// Properties
-		None,
+		Some(chain_properties()),
 		// Extensions
 		None,
 	))
@@ -116,9 +237,9 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 		None,
 		// Protocol ID
 		None,
-		// Properties
-		None,
 		None,
+		// Properties
+		Some(chain_properties()),
 		// Extensions
 		None,
 	))
@@ -152,5 +273,11 @@ fn testnet_genesis(
 			key: Some(root_key),
 		},
 		transaction_payment: Default::default(),
+		falcon_migration: FalconMigrationConfig {
+			// The endowed accounts are sr25519 and have to move onto Falcon keys, governance sets
+			// the deadline once the migration is announced.
+			classical_accounts: endowed_accounts,
+			deadline: None,
+		},
 	}
 }
@@ -1,4 +1,7 @@
+use crate::falcon_key::FalconKeySubcommand;
+use prism_consensus::Block::Limits::PayloadLimits;
 use sc_cli::RunCmd;
+use std::path::PathBuf;
 
 #[derive(Debug, clap::Parser)]
 pub struct Cli {
@@ -7,6 +10,56 @@ pub struct Cli {
 
 	#[clap(flatten)]
 	pub run: RunCmd,
+
+	/// Encrypted Falcon key file holding the mining coinbase and signing key.
+	#[clap(long, value_name = "PATH")]
+	pub falcon_key_file: Option<PathBuf>,
+
+	/// File holding the password of `--falcon-key-file`, prompted for when not given.
+	#[clap(long, value_name = "PATH", requires = "falcon-key-file")]
+	pub falcon_password_filename: Option<PathBuf>,
+
+	#[clap(flatten)]
+	pub prism_limits: PrismLimitsParams,
+}
+
+/// Overrides of the Prism payload limits of the chain spec, for test networks. A node whose limits
+/// differ from its peers' rejects blocks they accept.
+#[derive(Debug, Clone, clap::Args)]
+pub struct PrismLimitsParams {
+	/// Encoded bytes of the extrinsics of a transaction block.
+	#[clap(long, value_name = "BYTES")]
+	pub prism_max_transaction_block_bytes: Option<u32>,
+
+	/// Transaction blocks a proposer block may reference.
+	#[clap(long, value_name = "COUNT")]
+	pub prism_max_transaction_references: Option<u32>,
+
+	/// Proposer blocks a proposer block may reference.
+	#[clap(long, value_name = "COUNT")]
+	pub prism_max_proposer_references: Option<u32>,
+
+	/// Votes of a voter block.
+	#[clap(long, value_name = "COUNT")]
+	pub prism_max_votes: Option<u32>,
+}
+
+impl PrismLimitsParams {
+	/// `limits` with the limits given on the command line replaced.
+	pub fn apply(&self, limits: PayloadLimits) -> PayloadLimits {
+		PayloadLimits {
+			maxTransactionBlockBytes: self
+				.prism_max_transaction_block_bytes
+				.unwrap_or(limits.maxTransactionBlockBytes),
+			maxTransactionReferences: self
+				.prism_max_transaction_references
+				.unwrap_or(limits.maxTransactionReferences),
+			maxProposerReferences: self
+				.prism_max_proposer_references
+				.unwrap_or(limits.maxProposerReferences),
+			maxVotes: self.prism_max_votes.unwrap_or(limits.maxVotes),
+		}
+	}
 }
 
 #[derive(Debug, clap::Subcommand)]
@@ -15,6 +68,10 @@ pub enum Subcommand {
 	#[clap(subcommand)]
 	Key(sc_cli::KeySubcommand),
 
+	/// Encrypted Falcon key file utilities
+	#[clap(subcommand)]
+	FalconKey(FalconKeySubcommand),
+
 	/// Build a chain specification.
 	BuildSpec(sc_cli::BuildSpecCmd),
 
@@ -50,4 +107,13 @@ pub enum Subcommand {
 
 	/// Db meta columns information.
 	ChainInfo(sc_cli::ChainInfoCmd),
+
+	/// Export a snapshot of the Prism DAG and ledger.
+	ExportSnapshot(crate::prism_snapshot::ExportSnapshotCmd),
+
+	/// Bootstrap an empty Prism store from a snapshot.
+	ImportSnapshot(crate::prism_snapshot::ImportSnapshotCmd),
+
+	/// Migrate the Prism store to the schema version of this node.
+	MigratePrism(crate::prism_migrate::MigratePrismCmd),
 }
@@ -2,7 +2,7 @@ use crate::{
 	benchmarking::{inherent_benchmark_data, RemarkBuilder, TransferKeepAliveBuilder},
 	chain_spec,
 	cli::{Cli, Subcommand},
-	service,
+	falcon_key, prism, pruning, service,
 };
 use frame_benchmarking_cli::{BenchmarkCmd, ExtrinsicFactory, SUBSTRATE_REFERENCE_HARDWARE};
 use Argonaut_Runtime::{Block, EXISTENTIAL_DEPOSIT};
@@ -36,12 +36,16 @@ impl SubstrateCli for Cli {
 	}
 
 	fn load_spec(&self, id: &str) -> Result<Box<dyn sc_service::ChainSpec>, String> {
-		Ok(match id {
+		let spec: Box<dyn sc_service::ChainSpec> = match id {
 			"dev" => Box::new(chain_spec::development_config()?),
 			"" | "local" => Box::new(chain_spec::local_testnet_config()?),
 			path =>
 				Box::new(chain_spec::ChainSpec::from_json_file(std::path::PathBuf::from(path))?),
-		})
+		};
+		// every subcommand loads its spec through here, so addresses are encoded and parsed for
+		// the network of the spec whatever the subcommand
+		primitives::address::set_network_prefix(chain_spec::ss58_prefix(spec.as_ref())?);
+		Ok(spec)
 	}
 
 	fn native_runtime_version(_: &Box<dyn ChainSpec>) -> &'static RuntimeVersion {
@@ -55,6 +59,12 @@ pub fn run() -> sc_cli::Result<()> {
 
 	match &cli.subcommand {
 		Some(Subcommand::Key(cmd)) => cmd.run(&cli),
+		Some(Subcommand::FalconKey(cmd)) => {
+			// key files hold an address, load the spec for the network prefix it is encoded with
+			let shared = &cli.run.shared_params;
+			cli.load_spec(&shared.chain_id(shared.is_dev()))?;
+			cmd.run()
+		},
 		Some(Subcommand::BuildSpec(cmd)) => {
 			let runner = cli.create_runner(cmd)?;
 			runner.sync_run(|config| cmd.run(config.chain_spec, config.network))
@@ -187,10 +197,53 @@ pub fn run() -> sc_cli::Result<()> {
 			let runner = cli.create_runner(cmd)?;
 			runner.sync_run(|config| cmd.run::<Block>(&config))
 		},
+		Some(Subcommand::ExportSnapshot(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+			runner.sync_run(|config| cmd.run(config))
+		},
+		Some(Subcommand::ImportSnapshot(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+			runner.sync_run(|config| cmd.run(config))
+		},
+		Some(Subcommand::MigratePrism(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+			runner.sync_run(|config| cmd.run(config))
+		},
 		None => {
 			let runner = cli.create_runner(&cli.run)?;
+			let genesis = chain_spec::prism_genesis(runner.config().chain_spec.as_ref())?;
+			// a spec pinning another root than its fields build is refused before anything starts
+			let root = genesis.blocks()?.root();
+			log::info!(
+				"🌱 Prism genesis: {} voter chains at {}, root {:?}{}",
+				genesis.voter_chains,
+				genesis.timestamp,
+				root,
+				if genesis.root.is_some() { " (pinned)" } else { "" },
+			);
+			let limits = cli
+				.prism_limits
+				.apply(chain_spec::prism_limits(runner.config().chain_spec.as_ref())?);
+			let prism_import = prism::open_import(
+				runner.config(),
+				pruning::prism_pruning(runner.config().state_pruning.as_ref()),
+				limits,
+			)?;
+
+			let falcon_key = match &cli.falcon_key_file {
+				Some(path) => {
+					let password = falcon_key::read_password(
+						cli.falcon_password_filename.as_deref(),
+						"Falcon key file password: ",
+					)?;
+					let pair = falcon_key::load_key_pair(path, &password)
+						.map_err(|e| sc_cli::Error::Input(e.to_string()))?;
+					Some(pair)
+				},
+				None => None,
+			};
 			runner.run_node_until_exit(|config| async move {
-				service::new_full(config).map_err(sc_cli::Error::Service)
+				service::new_full(config, falcon_key, prism_import).map_err(sc_cli::Error::Service)
 			})
 		},
 	}
@@ -0,0 +1,568 @@
+//! Encrypted on-disk Falcon key files.
+//!
+//! Falcon secret keys do not fit the keystore's JSON format, so they get a file of their own: the
+//! secret key is encrypted with XChaCha20-Poly1305 under a key derived from a password with
+//! Argon2id. The public key, the SS58 address and the KDF parameters stay in clear so a file can be
+//! inspected without the password, they are bound to the ciphertext as associated data so any
+//! tampering with them makes decryption fail.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+	aead::{Aead, KeyInit, Payload},
+	XChaCha20Poly1305, XNonce,
+};
+use pqcrypto_falcon::falcon512::{PublicKey, SecretKey};
+use pqcrypto_traits::sign::{PublicKey as _, SecretKey as _};
+use primitives::FalconHostFunctions;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sp_core::crypto::AccountId32;
+use std::{
+	fmt, fs,
+	io::{self, Write},
+	path::{Path, PathBuf},
+};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Version of the key file format.
+pub const KEY_FILE_VERSION: u32 = 1;
+
+const KDF_NAME: &str = "argon2id";
+const CIPHER_NAME: &str = "xchacha20poly1305";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// Errors while creating, reading or decrypting a key file.
+#[derive(Debug)]
+pub enum KeyFileError {
+	/// Reading or writing the file failed.
+	Io(io::Error),
+	/// The file is not a valid key file.
+	Format(String),
+	/// The file uses a format version this node does not know.
+	UnsupportedVersion(u32),
+	/// Wrong password, or the file was modified.
+	Decryption,
+	/// The decrypted secret key does not belong to the public key in the file.
+	KeyMismatch,
+	/// The address in the file is malformed, for another network or not the public key's.
+	Address(String),
+}
+
+impl fmt::Display for KeyFileError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			KeyFileError::Io(e) => write!(f, "Falcon key file I/O error: {}", e),
+			KeyFileError::Format(e) => write!(f, "Invalid Falcon key file: {}", e),
+			KeyFileError::UnsupportedVersion(v) =>
+				write!(f, "Unsupported Falcon key file version {} (expected {})", v, KEY_FILE_VERSION),
+			KeyFileError::Decryption =>
+				write!(f, "Could not decrypt Falcon key file: wrong password or corrupted file"),
+			KeyFileError::KeyMismatch =>
+				write!(f, "Falcon key file secret key does not match its public key"),
+			KeyFileError::Address(e) => write!(f, "Falcon key file address: {}", e),
+		}
+	}
+}
+
+impl std::error::Error for KeyFileError {}
+
+impl From<io::Error> for KeyFileError {
+	fn from(e: io::Error) -> Self {
+		KeyFileError::Io(e)
+	}
+}
+
+/// Argon2id parameters, stored in the file so they can be raised later without breaking old files.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KdfParams {
+	/// Always `argon2id`.
+	pub algorithm: String,
+	/// Hex encoded salt.
+	pub salt: String,
+	/// Memory cost in KiB.
+	pub memory_kib: u32,
+	/// Number of passes.
+	pub iterations: u32,
+	/// Degree of parallelism.
+	pub parallelism: u32,
+}
+
+impl KdfParams {
+	/// Parameters with a fresh random salt and the given cost.
+	pub fn with_cost(memory_kib: u32, iterations: u32, parallelism: u32) -> Self {
+		let mut salt = [0u8; SALT_LEN];
+		OsRng.fill_bytes(&mut salt);
+		KdfParams {
+			algorithm: KDF_NAME.into(),
+			salt: hex::encode(salt),
+			memory_kib,
+			iterations,
+			parallelism,
+		}
+	}
+
+	fn derive_key(&self, password: &str) -> Result<Zeroizing<[u8; 32]>, KeyFileError> {
+		if self.algorithm != KDF_NAME {
+			return Err(KeyFileError::Format(format!("unknown KDF {}", self.algorithm)))
+		}
+		let salt = hex::decode(&self.salt).map_err(|e| KeyFileError::Format(e.to_string()))?;
+		let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+			.map_err(|e| KeyFileError::Format(e.to_string()))?;
+		let mut key = Zeroizing::new([0u8; 32]);
+		Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+			.hash_password_into(password.as_bytes(), &salt, &mut key[..])
+			.map_err(|e| KeyFileError::Format(e.to_string()))?;
+		Ok(key)
+	}
+}
+
+/// Cipher parameters.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CipherParams {
+	/// Always `xchacha20poly1305`.
+	pub algorithm: String,
+	/// Hex encoded 24 byte nonce.
+	pub nonce: String,
+}
+
+/// The JSON document written to disk.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FalconKeyFile {
+	/// Format version, see [`KEY_FILE_VERSION`].
+	pub version: u32,
+	/// SS58 address of the account derived from the public key.
+	pub address: String,
+	/// Hex encoded falcon512 public key.
+	pub public_key: String,
+	/// Key derivation parameters.
+	pub kdf: KdfParams,
+	/// Cipher parameters.
+	pub cipher: CipherParams,
+	/// Hex encoded encrypted secret key, including the authentication tag.
+	pub ciphertext: String,
+}
+
+/// A decrypted Falcon key pair. The secret key is kept as bytes that are wiped when the pair is
+/// dropped and only turned into a pqcrypto [`SecretKey`] for the duration of
+/// [`Self::with_secret`].
+///
+/// `SecretKey` is `Copy`, so the compiler is free to leave copies of it on the stack or in
+/// registers that no wipe reaches. Keeping it short lived narrows that window, it does not close it.
+pub struct FalconKeyPair {
+	/// The public key.
+	pub public: PublicKey,
+	secret: Zeroizing<Vec<u8>>,
+}
+
+// Zeroes a secret key in place, `SecretKey` is a plain byte array without a `Zeroize` impl
+fn wipe(secret: &mut SecretKey) {
+	// SAFETY: `SecretKey` wraps a `[u8; N]` and nothing else, so every byte of it is initialized
+	// and all zeroes is a valid value
+	let bytes = unsafe {
+		std::slice::from_raw_parts_mut(
+			(secret as *mut SecretKey).cast::<u8>(),
+			std::mem::size_of::<SecretKey>(),
+		)
+	};
+	bytes.zeroize();
+}
+
+impl FalconKeyPair {
+	/// A freshly generated key pair.
+	pub fn generate() -> Self {
+		let (public, mut secret) = FalconHostFunctions::generate_keypair();
+		let pair = FalconKeyPair { public, secret: Zeroizing::new(secret.as_bytes().to_vec()) };
+		wipe(&mut secret);
+		pair
+	}
+
+	/// Runs `f` with the secret key, the key is wiped again once `f` returns.
+	pub fn with_secret<R>(&self, f: impl FnOnce(&SecretKey) -> R) -> R {
+		let mut secret = SecretKey::from_bytes(&self.secret)
+			.expect("the bytes were checked to form a secret key when the pair was created; qed");
+		let result = f(&secret);
+		wipe(&mut secret);
+		result
+	}
+
+	/// The account owned by this key, used as the mining coinbase.
+	pub fn account(&self) -> AccountId32 {
+		AccountId32::from(FalconHostFunctions::hash_public_key(&self.public))
+	}
+
+	/// The SS58 address of [`Self::account`].
+	pub fn address(&self) -> String {
+		FalconHostFunctions::generate_address(FalconHostFunctions::hash_public_key(&self.public))
+	}
+}
+
+impl FalconKeyFile {
+	/// Encrypt `pair` under `password` with the default Argon2id cost (64 MiB, 3 passes).
+	pub fn encrypt(pair: &FalconKeyPair, password: &str) -> Result<Self, KeyFileError> {
+		Self::encrypt_with(pair, password, KdfParams::with_cost(64 * 1024, 3, 1))
+	}
+
+	/// Encrypt `pair` under `password` using `kdf`.
+	pub fn encrypt_with(
+		pair: &FalconKeyPair,
+		password: &str,
+		kdf: KdfParams,
+	) -> Result<Self, KeyFileError> {
+		let mut nonce = [0u8; NONCE_LEN];
+		OsRng.fill_bytes(&mut nonce);
+		let mut file = FalconKeyFile {
+			version: KEY_FILE_VERSION,
+			address: pair.address(),
+			public_key: hex::encode(pair.public.as_bytes()),
+			kdf,
+			cipher: CipherParams { algorithm: CIPHER_NAME.into(), nonce: hex::encode(nonce) },
+			ciphertext: String::new(),
+		};
+		let key = file.kdf.derive_key(password)?;
+		let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+		let aad = file.associated_data();
+		let ciphertext = cipher
+			.encrypt(XNonce::from_slice(&nonce), Payload { msg: &pair.secret, aad: &aad })
+			.map_err(|_| KeyFileError::Decryption)?;
+		file.ciphertext = hex::encode(ciphertext);
+		Ok(file)
+	}
+
+	/// Decrypt the secret key with `password`.
+	pub fn decrypt(&self, password: &str) -> Result<FalconKeyPair, KeyFileError> {
+		if self.version != KEY_FILE_VERSION {
+			return Err(KeyFileError::UnsupportedVersion(self.version))
+		}
+		if self.cipher.algorithm != CIPHER_NAME {
+			return Err(KeyFileError::Format(format!("unknown cipher {}", self.cipher.algorithm)))
+		}
+		let public = self.public_key()?;
+		let nonce = hex::decode(&self.cipher.nonce).map_err(|e| KeyFileError::Format(e.to_string()))?;
+		if nonce.len() != NONCE_LEN {
+			return Err(KeyFileError::Format("nonce must be 24 bytes".into()))
+		}
+		let ciphertext =
+			hex::decode(&self.ciphertext).map_err(|e| KeyFileError::Format(e.to_string()))?;
+
+		let key = self.kdf.derive_key(password)?;
+		let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+		let aad = self.associated_data();
+		let plaintext = Zeroizing::new(
+			cipher
+				.decrypt(XNonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: &aad })
+				.map_err(|_| KeyFileError::Decryption)?,
+		);
+		let mut secret = SecretKey::from_bytes(&plaintext).map_err(|_| KeyFileError::KeyMismatch)?;
+
+		//a signature made with the secret key has to verify under the public key in the file
+		let probe = FalconHostFunctions::detached_signature(b"argonaut/key-file-check", &secret);
+		wipe(&mut secret);
+		FalconHostFunctions::verify(&probe, b"argonaut/key-file-check", &public)
+			.map_err(|_| KeyFileError::KeyMismatch)?;
+		self.check_address(&public)?;
+		Ok(FalconKeyPair { public, secret: plaintext })
+	}
+
+	/// Re-encrypt the file under `new_password` with a fresh salt and nonce, keeping the KDF cost.
+	pub fn change_password(
+		&self,
+		old_password: &str,
+		new_password: &str,
+	) -> Result<Self, KeyFileError> {
+		let pair = self.decrypt(old_password)?;
+		let kdf = KdfParams::with_cost(self.kdf.memory_kib, self.kdf.iterations, self.kdf.parallelism);
+		Self::encrypt_with(&pair, new_password, kdf)
+	}
+
+	/// The public key stored in clear.
+	pub fn public_key(&self) -> Result<PublicKey, KeyFileError> {
+		let bytes = hex::decode(&self.public_key).map_err(|e| KeyFileError::Format(e.to_string()))?;
+		PublicKey::from_bytes(&bytes).map_err(|_| KeyFileError::Format("invalid public key".into()))
+	}
+
+	/// Whether the address in the file is the one of `public` on this node's network.
+	pub fn check_address(&self, public: &PublicKey) -> Result<(), KeyFileError> {
+		let account = FalconHostFunctions::decode_address(&self.address)
+			.map_err(|e| KeyFileError::Address(e.to_string()))?;
+		if account != FalconHostFunctions::hash_public_key(public) {
+			return Err(KeyFileError::Address("not the address of the public key".into()))
+		}
+		Ok(())
+	}
+
+	/// Read a key file from `path`.
+	pub fn load(path: &Path) -> Result<Self, KeyFileError> {
+		let contents = fs::read_to_string(path)?;
+		serde_json::from_str(&contents).map_err(|e| KeyFileError::Format(e.to_string()))
+	}
+
+	/// Write the key file to a new file at `path`, readable by the owner only where the platform
+	/// allows it. The file is created with those permissions and an existing one is never
+	/// overwritten.
+	pub fn save(&self, path: &Path) -> Result<(), KeyFileError> {
+		let contents =
+			serde_json::to_string_pretty(self).map_err(|e| KeyFileError::Format(e.to_string()))?;
+		let mut options = fs::OpenOptions::new();
+		options.write(true).create_new(true);
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::OpenOptionsExt;
+			options.mode(0o600);
+		}
+		let mut file = options.open(path)?;
+		file.write_all(contents.as_bytes())?;
+		file.sync_all()?;
+		Ok(())
+	}
+
+	// Everything stored in clear except the ciphertext itself
+	fn associated_data(&self) -> Vec<u8> {
+		let clear = (
+			self.version,
+			&self.address,
+			&self.public_key,
+			&self.kdf,
+			&self.cipher.algorithm,
+			&self.cipher.nonce,
+		);
+		serde_json::to_vec(&clear).expect("serializing plain strings and integers cannot fail; qed")
+	}
+}
+
+/// Load and decrypt the key file at `path`.
+pub fn load_key_pair(path: &Path, password: &str) -> Result<FalconKeyPair, KeyFileError> {
+	FalconKeyFile::load(path)?.decrypt(password)
+}
+
+/// Where to read a key file password from.
+#[derive(Debug, Clone, clap::Args)]
+pub struct PasswordParams {
+	/// File holding the password, prompted for interactively when not given.
+	#[clap(long, value_name = "PATH")]
+	pub password_filename: Option<PathBuf>,
+}
+
+impl PasswordParams {
+	fn read(&self, prompt: &str) -> sc_cli::Result<String> {
+		read_password(self.password_filename.as_deref(), prompt)
+	}
+}
+
+/// Read a password from `filename`, or prompt for it on the terminal.
+pub fn read_password(filename: Option<&Path>, prompt: &str) -> sc_cli::Result<String> {
+	match filename {
+		Some(path) => Ok(fs::read_to_string(path)?.trim_end_matches(&['\r', '\n'][..]).to_string()),
+		None => Ok(rpassword::prompt_password(prompt)?),
+	}
+}
+
+/// Falcon key file utilities.
+#[derive(Debug, clap::Subcommand)]
+pub enum FalconKeySubcommand {
+	/// Generate a new Falcon key pair into an encrypted key file.
+	Generate(GenerateCmd),
+	/// Print the public key and address of a key file, without decrypting it.
+	Inspect(InspectCmd),
+	/// Re-encrypt a key file under a new password.
+	ChangePassword(ChangePasswordCmd),
+}
+
+impl FalconKeySubcommand {
+	/// Run the subcommand.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		match self {
+			FalconKeySubcommand::Generate(cmd) => cmd.run(),
+			FalconKeySubcommand::Inspect(cmd) => cmd.run(),
+			FalconKeySubcommand::ChangePassword(cmd) => cmd.run(),
+		}
+	}
+}
+
+/// The `falcon-key generate` command.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct GenerateCmd {
+	/// Where to write the key file, an existing file is never overwritten.
+	#[clap(long, short, value_name = "PATH")]
+	pub output: PathBuf,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub password: PasswordParams,
+}
+
+impl GenerateCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		if self.output.exists() {
+			return Err(format!("{} already exists", self.output.display()).into())
+		}
+		let password = self.password.read("New key file password: ")?;
+		if self.password.password_filename.is_none() &&
+			rpassword::prompt_password("Repeat password: ")? != password
+		{
+			return Err("Passwords do not match".into())
+		}
+		let pair = FalconKeyPair::generate();
+		let file = FalconKeyFile::encrypt(&pair, &password).map_err(to_cli_error)?;
+		file.save(&self.output).map_err(to_cli_error)?;
+		println!("Address:    {}", file.address);
+		println!("Key file:   {}", self.output.display());
+		Ok(())
+	}
+}
+
+/// The `falcon-key inspect` command.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct InspectCmd {
+	/// The key file to inspect.
+	#[clap(value_name = "PATH")]
+	pub file: PathBuf,
+}
+
+impl InspectCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let file = FalconKeyFile::load(&self.file).map_err(to_cli_error)?;
+		let public = file.public_key().map_err(to_cli_error)?;
+		let account = AccountId32::from(FalconHostFunctions::hash_public_key(&public));
+		println!("Version:      {}", file.version);
+		match file.check_address(&public) {
+			Ok(()) => println!("Address:      {}", file.address),
+			Err(e) => println!("Address:      {} ({})", file.address, e),
+		}
+		println!("Account id:   0x{}", hex::encode(account.as_ref() as &[u8]));
+		println!("Public key:   0x{}", file.public_key);
+		println!(
+			"KDF:          {} ({} KiB, {} passes, parallelism {})",
+			file.kdf.algorithm, file.kdf.memory_kib, file.kdf.iterations, file.kdf.parallelism
+		);
+		println!("Cipher:       {}", file.cipher.algorithm);
+		Ok(())
+	}
+}
+
+/// The `falcon-key change-password` command.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct ChangePasswordCmd {
+	/// The key file to re-encrypt in place.
+	#[clap(value_name = "PATH")]
+	pub file: PathBuf,
+
+	/// File holding the current password, prompted for interactively when not given.
+	#[clap(long, value_name = "PATH")]
+	pub old_password_filename: Option<PathBuf>,
+
+	/// File holding the new password, prompted for interactively when not given.
+	#[clap(long, value_name = "PATH")]
+	pub new_password_filename: Option<PathBuf>,
+}
+
+impl ChangePasswordCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let file = FalconKeyFile::load(&self.file).map_err(to_cli_error)?;
+		let old = read_password(self.old_password_filename.as_deref(), "Current password: ")?;
+		let new = read_password(self.new_password_filename.as_deref(), "New password: ")?;
+		if self.new_password_filename.is_none() &&
+			rpassword::prompt_password("Repeat new password: ")? != new
+		{
+			return Err("Passwords do not match".into())
+		}
+		let updated = file.change_password(&old, &new).map_err(to_cli_error)?;
+		//write next to the original first so a crash never leaves a half written key file
+		let tmp = self.file.with_extension("tmp");
+		updated.save(&tmp).map_err(to_cli_error)?;
+		fs::rename(&tmp, &self.file)?;
+		println!("Password changed for {}", file.address);
+		Ok(())
+	}
+}
+
+fn to_cli_error(e: KeyFileError) -> sc_cli::Error {
+	sc_cli::Error::Input(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// the real cost only slows the tests down
+	fn encrypt(pair: &FalconKeyPair, password: &str) -> FalconKeyFile {
+		FalconKeyFile::encrypt_with(pair, password, KdfParams::with_cost(64, 1, 1)).unwrap()
+	}
+
+	#[test]
+	fn round_trip() {
+		let pair = FalconKeyPair::generate();
+		let file = encrypt(&pair, "hunter2");
+		let decrypted = file.decrypt("hunter2").unwrap();
+		assert_eq!(decrypted.public.as_bytes(), pair.public.as_bytes());
+		assert_eq!(decrypted.secret, pair.secret);
+		assert_eq!(file.address, pair.address());
+	}
+
+	#[test]
+	fn wrong_password() {
+		let pair = FalconKeyPair::generate();
+		let file = encrypt(&pair, "hunter2");
+		assert!(matches!(file.decrypt("hunter3"), Err(KeyFileError::Decryption)));
+	}
+
+	#[test]
+	fn tampered_metadata() {
+		let pair = FalconKeyPair::generate();
+		let mut file = encrypt(&pair, "hunter2");
+		file.address = FalconKeyPair::generate().address();
+		assert!(matches!(file.decrypt("hunter2"), Err(KeyFileError::Decryption)));
+	}
+
+	#[test]
+	fn secret_key_wiped() {
+		let pair = FalconKeyPair::generate();
+		let mut secret = pair.with_secret(|secret| *secret);
+		assert!(secret.as_bytes().iter().any(|byte| *byte != 0));
+		wipe(&mut secret);
+		assert!(secret.as_bytes().iter().all(|byte| *byte == 0));
+		assert_eq!(secret.as_bytes().len(), std::mem::size_of::<SecretKey>());
+	}
+
+	#[test]
+	fn change_password() {
+		let pair = FalconKeyPair::generate();
+		let file = encrypt(&pair, "hunter2").change_password("hunter2", "correct horse").unwrap();
+		assert!(matches!(file.decrypt("hunter2"), Err(KeyFileError::Decryption)));
+		assert_eq!(file.decrypt("correct horse").unwrap().secret, pair.secret);
+		assert_eq!(file.kdf.memory_kib, 64);
+	}
+
+	#[test]
+	fn address_of_another_network() {
+		let pair = FalconKeyPair::generate();
+		let mut file = encrypt(&pair, "hunter2");
+		assert!(file.check_address(&pair.public).is_ok());
+		file.address = primitives::address::encode(pair.account().into(), 0);
+		let err = file.check_address(&pair.public).unwrap_err();
+		assert!(err.to_string().contains("polkadot"));
+	}
+
+	#[test]
+	fn save_never_overwrites() {
+		let path = std::env::temp_dir().join(format!("falcon-key-{}.json", std::process::id()));
+		let _ = fs::remove_file(&path);
+		let file = encrypt(&FalconKeyPair::generate(), "hunter2");
+		file.save(&path).unwrap();
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::PermissionsExt;
+			assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+		}
+		let other = encrypt(&FalconKeyPair::generate(), "hunter2");
+		assert!(matches!(other.save(&path), Err(KeyFileError::Io(_))));
+		assert_eq!(FalconKeyFile::load(&path).unwrap(), file);
+		fs::remove_file(&path).unwrap();
+	}
+}
@@ -0,0 +1,277 @@
+//! Parallel verification of Falcon signatures.
+//!
+//! Falcon verification is far slower than sr25519, so instead of letting the runtime check every
+//! hybrid or plain Falcon signature one after the other, the transaction pool and block import
+//! hand them to a shared [`BatchVerifier`] first. Whatever fails is rejected against the exact
+//! extrinsic before the runtime runs. The verifier is registered with the externalities of every
+//! runtime call through [`FalconVerifiedFactory`], so the `falcon_crypto::verify` host function
+//! finds a signature verified here in the verifier's cache instead of verifying it again.
+
+use crate::service::FullClient;
+
+use codec::{Decode, Encode};
+use futures::{channel::oneshot, future::BoxFuture, FutureExt};
+use primitives::{
+	batch::{BatchVerifier, FalconCheck},
+	signature::Signature,
+	FalconVerifiedExt,
+};
+use Argonaut_Runtime::{self as runtime, opaque::Block, BlockNumber};
+use sc_client_api::execution_extensions::ExtensionsFactory;
+use sc_consensus::{BlockCheckParams, BlockImport, BlockImportParams, ImportResult};
+use sc_transaction_pool::{ChainApi, FullChainApi};
+use sc_transaction_pool_api::error::Error as PoolError;
+use sp_api::TransactionFor;
+use sp_blockchain::{HeaderBackend, HeaderMetadata};
+use sp_consensus::{CacheKeyId, Error as ConsensusError};
+use sp_core::offchain::Capabilities;
+use sp_externalities::Extensions;
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, Header as HeaderT, NumberFor},
+	transaction_validity::{InvalidTransaction, TransactionSource, TransactionValidity},
+	OpaqueExtrinsic,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// Extracts the Falcon checks out of extrinsics and runs them on the batch verifier.
+pub struct FalconChecker {
+	client: Arc<FullClient>,
+	verifier: Arc<BatchVerifier>,
+}
+
+impl FalconChecker {
+	/// Creates a new [`Self`] checking against the state of `client`.
+	pub fn new(client: Arc<FullClient>, verifier: Arc<BatchVerifier>) -> Self {
+		Self { client, verifier }
+	}
+
+	/// The Falcon check of `extrinsic` if it carries a hybrid or plain Falcon signature. `parent`
+	/// is the block the extrinsic is applied on top of, `number` the number of the block
+	/// including it.
+	fn check_for(
+		&self,
+		extrinsic: &OpaqueExtrinsic,
+		parent: <Block as BlockT>::Hash,
+		number: BlockNumber,
+	) -> Option<FalconCheck> {
+		let xt = runtime::UncheckedExtrinsic::decode(&mut &extrinsic.encode()[..]).ok()?;
+		let (_, signature, extra) = xt.signature.as_ref()?;
+		let (falcon_signature, falcon_public) = match signature {
+			Signature::Hybrid(hybrid) => (&hybrid.falcon_signature, &hybrid.falcon_public),
+			Signature::Falcon(falcon) => (&falcon.signature, &falcon.public),
+			_ => return None,
+		};
+
+		//rebuild what `SignedExtra::additional_signed` yields inside the runtime
+		let version = self.client.runtime_version_at(&BlockId::Hash(parent)).ok()?;
+		let genesis = self.client.hash(0).ok().flatten()?;
+		let birth = runtime::extrinsic_era(extra).birth(number as u64) as BlockNumber;
+		let era_hash = self.ancestor(parent, birth)?;
+		let payload = runtime::signed_payload(
+			xt.function.clone(),
+			extra.clone(),
+			version.spec_version,
+			version.transaction_version,
+			genesis,
+			era_hash,
+		);
+		let message = payload.using_encoded(|bytes| bytes.to_vec());
+		Some(FalconCheck::new(falcon_signature.clone(), message, falcon_public.clone()))
+	}
+
+	/// The hash of the ancestor of `block` (inclusive) at `number`. `block` may be on a fork, so
+	/// its ancestry is walked until it joins the canonical chain.
+	fn ancestor(
+		&self,
+		block: <Block as BlockT>::Hash,
+		number: BlockNumber,
+	) -> Option<<Block as BlockT>::Hash> {
+		let mut hash = block;
+		loop {
+			let meta = self.client.header_metadata(hash).ok()?;
+			if meta.number < number {
+				return None
+			}
+			if meta.number == number {
+				return Some(hash)
+			}
+			if self.client.hash(meta.number).ok().flatten() == Some(hash) {
+				return self.client.hash(number).ok().flatten()
+			}
+			hash = meta.parent;
+		}
+	}
+
+	/// Verifies every Falcon signature in a block body, returning the index of the first invalid
+	/// extrinsic on failure.
+	pub fn verify_body(
+		&self,
+		body: &[OpaqueExtrinsic],
+		parent: <Block as BlockT>::Hash,
+		number: BlockNumber,
+	) -> Result<(), usize> {
+		let (indices, checks): (Vec<usize>, Vec<FalconCheck>) = body
+			.iter()
+			.enumerate()
+			.filter_map(|(index, xt)| self.check_for(xt, parent, number).map(|check| (index, check)))
+			.unzip();
+		self.verifier.verify(&checks).map_err(|invalid| indices[invalid[0]])
+	}
+}
+
+/// Registers the batch verifier with the externalities of every runtime call the client makes.
+pub struct FalconVerifiedFactory(pub Arc<BatchVerifier>);
+
+impl ExtensionsFactory for FalconVerifiedFactory {
+	fn extensions_for(&self, _: Capabilities) -> Extensions {
+		let mut extensions = Extensions::new();
+		extensions.register(FalconVerifiedExt(self.0.clone()));
+		extensions
+	}
+}
+
+/// Block import that verifies the Falcon signatures of a block body in parallel before handing
+/// the block to the inner import.
+pub struct FalconBatchImport<I> {
+	inner: I,
+	checker: Arc<FalconChecker>,
+}
+
+impl<I> FalconBatchImport<I> {
+	/// Wraps `inner`, which only sees blocks whose Falcon signatures verified.
+	pub fn new(inner: I, checker: Arc<FalconChecker>) -> Self {
+		Self { inner, checker }
+	}
+}
+
+impl<I: Clone> Clone for FalconBatchImport<I> {
+	fn clone(&self) -> Self {
+		Self { inner: self.inner.clone(), checker: self.checker.clone() }
+	}
+}
+
+#[async_trait::async_trait]
+impl<I> BlockImport<Block> for FalconBatchImport<I>
+where
+	I: BlockImport<Block, Transaction = TransactionFor<FullClient, Block>> + Send + Sync,
+	I::Error: Into<ConsensusError>,
+{
+	type Error = ConsensusError;
+	type Transaction = TransactionFor<FullClient, Block>;
+
+	async fn check_block(
+		&mut self,
+		block: BlockCheckParams<Block>,
+	) -> Result<ImportResult, Self::Error> {
+		self.inner.check_block(block).await.map_err(Into::into)
+	}
+
+	async fn import_block(
+		&mut self,
+		block: BlockImportParams<Block, Self::Transaction>,
+		cache: HashMap<CacheKeyId, Vec<u8>>,
+	) -> Result<ImportResult, Self::Error> {
+		if let Some(body) = &block.body {
+			let number = *block.header.number();
+			self.checker.verify_body(body, *block.header.parent_hash(), number).map_err(
+				|index| {
+					ConsensusError::ClientImport(format!(
+						"Invalid Falcon signature in extrinsic {} of block #{}",
+						index, number
+					))
+				},
+			)?;
+		}
+		self.inner.import_block(block, cache).await.map_err(Into::into)
+	}
+}
+
+/// Transaction pool API that checks the Falcon signature of a transaction on the verifier's
+/// thread pool before running the runtime validation. Transactions submitted together are
+/// validated concurrently, so they end up verified in parallel on that pool.
+pub struct FalconPoolApi {
+	inner: FullChainApi<FullClient, Block>,
+	checker: Arc<FalconChecker>,
+	verifier: Arc<BatchVerifier>,
+}
+
+impl FalconPoolApi {
+	/// Wraps the regular full-node pool API.
+	pub fn new(
+		inner: FullChainApi<FullClient, Block>,
+		checker: Arc<FalconChecker>,
+		verifier: Arc<BatchVerifier>,
+	) -> Self {
+		Self { inner, checker, verifier }
+	}
+}
+
+impl ChainApi for FalconPoolApi {
+	type Block = Block;
+	type Error = PoolError;
+	type ValidationFuture = BoxFuture<'static, Result<TransactionValidity, Self::Error>>;
+	type BodyFuture = <FullChainApi<FullClient, Block> as ChainApi>::BodyFuture;
+
+	fn validate_transaction(
+		&self,
+		at: &BlockId<Self::Block>,
+		source: TransactionSource,
+		uxt: sc_transaction_pool::ExtrinsicFor<Self>,
+	) -> Self::ValidationFuture {
+		let check = match (self.inner.block_id_to_hash(at), self.inner.block_id_to_number(at)) {
+			(Ok(Some(hash)), Ok(Some(number))) => self.checker.check_for(&uxt, hash, number + 1),
+			_ => None,
+		};
+		let inner = self.inner.validate_transaction(at, source, uxt);
+		let check = match check {
+			Some(check) => check,
+			None => return inner.boxed(),
+		};
+
+		let (sender, receiver) = oneshot::channel();
+		let verifier = self.verifier.clone();
+		self.verifier.spawn(move || {
+			let _ = sender.send(verifier.verify_one(&check));
+		});
+		async move {
+			if !receiver.await.unwrap_or(false) {
+				return Ok(Err(InvalidTransaction::BadProof.into()))
+			}
+			inner.await
+		}
+		.boxed()
+	}
+
+	fn block_id_to_number(
+		&self,
+		at: &BlockId<Self::Block>,
+	) -> Result<Option<NumberFor<Self::Block>>, Self::Error> {
+		self.inner.block_id_to_number(at)
+	}
+
+	fn block_id_to_hash(
+		&self,
+		at: &BlockId<Self::Block>,
+	) -> Result<Option<<Self::Block as BlockT>::Hash>, Self::Error> {
+		self.inner.block_id_to_hash(at)
+	}
+
+	fn hash_and_length(
+		&self,
+		ex: &sc_transaction_pool::ExtrinsicFor<Self>,
+	) -> (<Self::Block as BlockT>::Hash, usize) {
+		self.inner.hash_and_length(ex)
+	}
+
+	fn block_body(&self, at: &BlockId<Self::Block>) -> Self::BodyFuture {
+		self.inner.block_body(at)
+	}
+
+	fn block_header(
+		&self,
+		at: &BlockId<Self::Block>,
+	) -> Result<Option<<Self::Block as BlockT>::Header>, Self::Error> {
+		self.inner.block_header(at)
+	}
+}
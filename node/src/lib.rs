@@ -1,3 +1,11 @@
 pub mod chain_spec;
+pub mod falcon_key;
+pub mod falcon_verifier;
+pub mod mining;
+pub mod network;
+pub mod prism;
+pub mod prism_migrate;
+pub mod prism_snapshot;
+pub mod pruning;
 pub mod rpc;
 pub mod service;
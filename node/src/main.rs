@@ -7,6 +7,14 @@ mod service;
 mod benchmarking;
 mod cli;
 mod command;
+mod falcon_key;
+mod falcon_verifier;
+mod mining;
+mod network;
+mod prism;
+mod prism_migrate;
+mod prism_snapshot;
+mod pruning;
 mod rpc;
 
 fn main() -> sc_cli::Result<()> {
@@ -0,0 +1,228 @@
+//! Prism block mining.
+//!
+//! A miner mines a content of every kind at once: a proposer content extending the best proposer
+//! block, one voter content per voter chain and a transaction content from the transaction pool.
+//! The header commits to all of them through the sortition tree, and the position of the header
+//! hash below the target decides which one the block carries, see `prism_consensus::Validation`.
+//! A found block is sealed with the Falcon key behind its coinbase over the final header hash, see
+//! [`BlockHeader::seal_with_miner_key`], imported like a block from the network and handed to
+//! the block announcer, see `crate::network::run_block_announcer`.
+
+use crate::{falcon_key::FalconKeyPair, prism::PrismImport};
+use codec::{Decode, Encode};
+use futures::{channel::mpsc, future::poll_fn};
+use prism_consensus::{
+	Block::{
+		sortitionTree, Block as PrismBlock, BlockKind, Content, ExtraContent::ExtraContent,
+		Header::BlockHeader,
+	},
+	BlockTree::Index::TreeId,
+	MerkleTree::MerkleTree,
+	Validation::ChainView,
+};
+use sc_transaction_pool_api::{InPoolTransaction, TransactionPool};
+use sp_core::H256;
+use std::{
+	collections::HashSet,
+	sync::Arc,
+	task::Poll,
+	time::{SystemTime, UNIX_EPOCH},
+};
+use Argonaut_Runtime::{opaque::Block, UncheckedExtrinsic};
+
+/// Nonces tried before the miner yields and checks whether its template is stale.
+pub const NONCES_PER_ROUND: u32 = 10_000;
+
+/// Rounds after which a template is rebuilt although no block was imported, to pick up the
+/// transactions that reached the pool meanwhile.
+pub const ROUNDS_PER_TEMPLATE: u32 = 100;
+
+/// Why the miner stopped.
+#[derive(Debug)]
+pub enum MiningError {
+	/// The block tree has no proposer block or no tip for a voter chain to mine on.
+	NoParent(TreeId),
+	/// The block tree could not be read.
+	Tree(String),
+}
+
+// A header without its nonce and the contents its contentRoot commits to
+struct Template {
+	header: BlockHeader,
+	// in sortition tree order, the proposer content first and the transaction content last
+	contents: Vec<Content>,
+	tree: MerkleTree,
+	// the last import into the tree when the template was built
+	last_import: Option<H256>,
+}
+
+impl Template {
+	// The block the first nonce of `tries` after `nonce` yields whose hash is below the target,
+	// together with its sortition proof
+	fn grind(
+		&mut self,
+		nonce: &mut u32,
+		tries: u32,
+		kind: impl Fn(&H256, &H256) -> Option<BlockKind>,
+		voter_chains: u16,
+	) -> Option<(BlockHeader, Content, Vec<H256>)> {
+		for _ in 0..tries {
+			*nonce = nonce.wrapping_add(1);
+			self.header.nonce = *nonce;
+			let found = match kind(&self.header.hash(), &self.header.difficulty) {
+				Some(found) => found,
+				None => continue,
+			};
+			let leaf = found.sortitionLeaf(voter_chains)? as usize;
+			let proof = self.tree.proof(leaf)?.siblings;
+			return Some((self.header.clone(), self.contents[leaf].clone(), proof))
+		}
+		None
+	}
+}
+
+/// Mines Prism blocks on the block tree of an import, paying the account of a Falcon key.
+pub struct Miner<P> {
+	import: Arc<PrismImport>,
+	pool: Arc<P>,
+	key: FalconKeyPair,
+	announce: mpsc::Sender<PrismBlock>,
+}
+
+impl<P> Miner<P>
+where
+	P: TransactionPool<Block = Block>,
+{
+	/// Miner extending the tree of `import` with transactions from `pool`, the blocks it finds
+	/// pay `key`'s account, carry its seal and are sent to `announce` once imported.
+	pub fn new(
+		import: Arc<PrismImport>,
+		pool: Arc<P>,
+		key: FalconKeyPair,
+		announce: mpsc::Sender<PrismBlock>,
+	) -> Self {
+		Miner { import, pool, key, announce }
+	}
+
+	/// Mine until the node shuts down. A round is a bounded amount of work and the miner yields
+	/// after each one, so it is dropped on shutdown and switches to a new template once another
+	/// block was imported.
+	pub async fn run(mut self) -> Result<(), MiningError> {
+		let mut nonce = rand::random::<u32>();
+		loop {
+			let mut template = self.template()?;
+			for _ in 0..ROUNDS_PER_TEMPLATE {
+				let sortition = self.import.sortition();
+				let found = template.grind(
+					&mut nonce,
+					NONCES_PER_ROUND,
+					|hash, target| sortition.kind(hash, target),
+					sortition.voterChains,
+				);
+				if let Some((header, content, proof)) = found {
+					self.submit(header, content, proof);
+					break
+				}
+				yield_now().await;
+				if self.import.tree().lastImport().map_err(tree_error)? != template.last_import {
+					break
+				}
+			}
+		}
+	}
+
+	// Seal a found block, import it and hand it to the announcer
+	fn submit(&mut self, mut header: BlockHeader, content: Content, proof: Vec<H256>) {
+		self.key.with_secret(|secret| header.seal_with_miner_key(&self.key.public, secret));
+		let hash = header.hash();
+		let kind = BlockKind::of(&content);
+		let block = PrismBlock::fromParts(header.clone(), content.clone(), proof.clone());
+		match self.import.import(header, content, proof) {
+			Ok(_) => log::info!(target: "prism", "⛏  Mined {:?} block {}", kind, hash),
+			Err(e) => {
+				log::warn!(target: "prism", "⛏  Mined block {} not imported: {:?}", hash, e);
+				return
+			},
+		}
+		// the announcer keeps up with the block rate, a full queue means it stopped
+		if let Err(e) = self.announce.try_send(block) {
+			log::warn!(target: "prism", "⛏  Mined block {} not announced: {:?}", hash, e);
+		}
+	}
+
+	// The contents of every kind on the current tips of the tree, and the header committing to them
+	fn template(&self) -> Result<Template, MiningError> {
+		let tree = self.import.tree();
+		let sortition = self.import.sortition();
+		let limits = self.import.limits();
+		let last_import = tree.lastImport().map_err(tree_error)?;
+		let (parent, parent_level) =
+			tree.best(TreeId::Proposer).ok_or(MiningError::NoParent(TreeId::Proposer))?;
+		let parent_header = ChainView::header(&**tree, &parent)
+			.ok_or(MiningError::NoParent(TreeId::Proposer))?;
+
+		// references oldest first, the next proposer block picks up what does not fit
+		let oldest_first = |mut blocks: Vec<(H256, u128)>| {
+			blocks.sort_by_key(|(hash, timestamp)| (*timestamp, *hash));
+			blocks.into_iter().map(|(hash, _)| hash)
+		};
+		let transactions = oldest_first(tree.unreferredTransactions().map_err(tree_error)?).collect();
+		// a proposer block at the level of the one being mined is a sibling, not a reference
+		let siblings: HashSet<H256> = tree.proposerLevel(parent_level + 1).into_iter().collect();
+		let proposers = oldest_first(tree.unreferredProposers().map_err(tree_error)?)
+			.filter(|hash| !siblings.contains(hash))
+			.collect();
+		let mut contents = vec![Content::ProposerBlock(limits.buildProposerBlock(transactions, proposers))];
+
+		// every chain votes on the levels after those it voted on, for the first block of each
+		let best_level = tree.proposerBestLevel().unwrap_or(parent_level);
+		for chain in 0..sortition.voterChains {
+			let (voter_parent, _) =
+				tree.best(TreeId::Voter(chain)).ok_or(MiningError::NoParent(TreeId::Voter(chain)))?;
+			let voted = tree.votedLevel(&voter_parent).map_err(tree_error)?.unwrap_or_default();
+			let votes = (voted + 1..=best_level)
+				.map_while(|level| tree.proposerLevel(level).first().copied())
+				.collect();
+			contents.push(Content::VoterBlock(limits.buildVoterBlock(chain, voter_parent, votes)));
+		}
+
+		// the pool stores opaque extrinsics, re-decode them as runtime extrinsics
+		let ready = self.pool.ready().map(|tx| tx.data().clone());
+		let candidates = ready.filter_map(|xt| UncheckedExtrinsic::decode(&mut &xt.encode()[..]).ok());
+		contents.push(Content::TransactionBlock(limits.buildTransactionBlock(candidates)));
+
+		let sortition_tree = sortitionTree(&contents.iter().map(Content::hash).collect::<Vec<_>>());
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|elapsed| elapsed.as_millis())
+			.unwrap_or_default();
+		let header = BlockHeader::new(
+			parent,
+			now.max(parent_header.timestamp + 1),
+			0,
+			sortition_tree.root(),
+			ExtraContent::default(),
+			ChainView::difficulty(&**tree, &parent),
+		)
+		.with_coinbase(self.key.account());
+		Ok(Template { header, contents, tree: sortition_tree, last_import })
+	}
+}
+
+fn tree_error(e: impl std::fmt::Debug) -> MiningError {
+	MiningError::Tree(format!("{:?}", e))
+}
+
+// Returns to the executor once, so that a task mining on a blocking thread can be cancelled
+async fn yield_now() {
+	let mut yielded = false;
+	poll_fn(|cx| {
+		if yielded {
+			return Poll::Ready(())
+		}
+		yielded = true;
+		cx.waker().wake_by_ref();
+		Poll::Pending
+	})
+	.await
+}
@@ -0,0 +1,636 @@
+//! Compact relay of Prism blocks.
+//!
+//! Proposer and voter blocks are small and announced whole as a [`RelayMessage::Block`].
+//! A producer announces a transaction block as a [`CompactTransactionBlock`], the header and the
+//! content hash it commits to. The receiver starts collecting the block's gossiped symbols, see
+//! [`super::SymbolGossip`], fills the block from its transaction pool, asks the sender for whatever
+//! is missing and falls back to the full block when the rebuilt content does not match the
+//! announced hash. The block is complete once the relay or the symbols rebuild it.
+//!
+//! Every message is a request on [`COMPACT_RELAY_PROTOCOL_NAME`] and its response is the message
+//! the sender has to answer next, empty when there is none: the producer drives an announcement
+//! with [`announce_block`], the receiver answers with [`CompactRelay::respond`]. Blocks waiting for
+//! an answer are bounded per peer and in total and given up on after [`PENDING_TIMEOUT`].
+
+use super::SymbolGossip;
+use codec::{Decode, DecodeAll, Encode};
+use futures::{
+	channel::mpsc,
+	future::{self, AbortHandle, Either},
+	stream::FuturesUnordered,
+	Future, FutureExt, StreamExt,
+};
+use rand::RngCore;
+use sc_network::{
+	config::{IncomingRequest, OutgoingResponse, RequestResponseConfig},
+	PeerId, ReputationChange,
+};
+use sc_transaction_pool_api::{InPoolTransaction, TransactionPool};
+use sp_core::H256;
+use std::{
+	collections::{HashMap, VecDeque},
+	sync::Arc,
+	time::{Duration, Instant},
+};
+use prism_consensus::Block::{
+	Block as PrismBlock,
+	BlockOf,
+	Compact::{CompactError, CompactTransactionBlock, PartialTransactionBlock},
+	Content as PrismContent,
+	Header::BlockHeader,
+	TransactionBlock::Content,
+};
+use Argonaut_Runtime::{opaque::Block, UncheckedExtrinsic};
+
+/// Name of the request-response protocol compact relay messages are exchanged on.
+pub const COMPACT_RELAY_PROTOCOL_NAME: &str = "/argonaut/compact-relay/1";
+
+/// Blocks a single peer may have waiting for an answer.
+pub const MAX_PENDING_PER_PEER: usize = 8;
+
+/// Blocks waiting for an answer across all peers.
+pub const MAX_PENDING: usize = 256;
+
+/// How long a block waits for the answer of its peer before it is dropped.
+pub const PENDING_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Messages an announcement takes at most: the announcement, the missing extrinsics and the full
+// block
+const ANNOUNCE_ROUNDS: usize = 3;
+
+// A full block response is the largest message, a transaction block's content and its header
+const MAX_MESSAGE_SIZE: u64 = 8 * 1024 * 1024;
+
+// Inbound requests queued before the network starts refusing them
+const INBOUND_QUEUE: usize = 64;
+
+const MISBEHAVED: ReputationChange = ReputationChange::new(-(1 << 12), "Invalid compact relay message");
+
+/// Config of the compact relay protocol, to be pushed into
+/// `config.network.request_response_protocols`, and the queue its requests arrive on.
+pub fn compact_relay_config() -> (RequestResponseConfig, mpsc::Receiver<IncomingRequest>) {
+	let (tx, rx) = mpsc::channel(INBOUND_QUEUE);
+	let config = RequestResponseConfig {
+		name: COMPACT_RELAY_PROTOCOL_NAME.into(),
+		fallback_names: Vec::new(),
+		max_request_size: MAX_MESSAGE_SIZE,
+		max_response_size: MAX_MESSAGE_SIZE,
+		request_timeout: PENDING_TIMEOUT,
+		inbound_queue: Some(tx),
+	};
+	(config, rx)
+}
+
+/// A message of the compact relay protocol.
+#[derive(Clone, PartialEq, Eq, Debug, Encode, Decode)]
+pub enum RelayMessage {
+	/// Announcement of a new transaction block.
+	Compact(CompactTransactionBlock),
+	/// Request for the extrinsics at `indexes` of a block.
+	GetTransactions { block_hash: H256, indexes: Vec<u32> },
+	/// The extrinsics asked for, in the order of the request.
+	Transactions { block_hash: H256, extrinsics: Vec<UncheckedExtrinsic> },
+	/// Request for the full content of a block.
+	GetBlock { block_hash: H256 },
+	/// A full block, the announcement of a proposer or voter block or the answer to `GetBlock`.
+	Block(PrismBlock),
+}
+
+/// The announcement of a transaction block this node has, with a fresh short id nonce.
+pub fn announcement(header: BlockHeader, content: &Content, sortition_proof: Vec<H256>) -> RelayMessage {
+	let nonce = rand::thread_rng().next_u64();
+	RelayMessage::Compact(CompactTransactionBlock::new(header, content, sortition_proof, nonce))
+}
+
+/// Answer a request of a peer for a transaction block this node has, `None` if it asks for
+/// another block or is not a request.
+pub fn serve(request: &RelayMessage, block: &BlockOf<Content>) -> Option<RelayMessage> {
+	match request {
+		RelayMessage::GetTransactions { block_hash, indexes } if *block_hash == block.header.hash() => {
+			let extrinsics: Option<Vec<UncheckedExtrinsic>> = indexes
+				.iter()
+				.map(|index| block.content.extrinsics.get(*index as usize).cloned())
+				.collect();
+			Some(RelayMessage::Transactions { block_hash: *block_hash, extrinsics: extrinsics? })
+		},
+		RelayMessage::GetBlock { block_hash } if *block_hash == block.header.hash() =>
+			Some(RelayMessage::Block(PrismBlock::Transaction(block.clone()))),
+		_ => None,
+	}
+}
+
+/// Why announcing a block to a peer ended before the peer had it.
+#[derive(Debug)]
+pub enum AnnounceError<E> {
+	/// A request to the peer failed.
+	Request(E),
+	/// The peer answered with something other than a request for the block.
+	Unexpected,
+}
+
+/// Announce a block this node has to a peer and, for a transaction block, answer the peer's
+/// requests until it has the block. `send` sends a message to the peer and resolves to its
+/// response.
+pub async fn announce_block<S, F, E>(block: &PrismBlock, mut send: S) -> Result<(), AnnounceError<E>>
+where
+	S: FnMut(Vec<u8>) -> F,
+	F: Future<Output = Result<Vec<u8>, E>>,
+{
+	let mut message = match block {
+		PrismBlock::Transaction(block) =>
+			announcement(block.header.clone(), &block.content, block.sortitionProof.clone()),
+		block => RelayMessage::Block(block.clone()),
+	};
+	for _ in 0..ANNOUNCE_ROUNDS {
+		let response = send(message.encode()).await.map_err(AnnounceError::Request)?;
+		if response.is_empty() {
+			return Ok(())
+		}
+		let request = RelayMessage::decode_all(&mut &response[..]).map_err(|_| AnnounceError::Unexpected)?;
+		let transaction = block.asTransaction().ok_or(AnnounceError::Unexpected)?;
+		message = serve(&request, transaction).ok_or(AnnounceError::Unexpected)?;
+	}
+	Err(AnnounceError::Unexpected)
+}
+
+/// Where the relay looks up the extrinsics of announced blocks.
+pub trait ExtrinsicSource {
+	/// The extrinsics ready for inclusion in a block.
+	fn ready_extrinsics(&self) -> Vec<UncheckedExtrinsic>;
+}
+
+impl<P> ExtrinsicSource for P
+where
+	P: TransactionPool<Block = Block>,
+{
+	fn ready_extrinsics(&self) -> Vec<UncheckedExtrinsic> {
+		// the pool stores opaque extrinsics, re-decode them as runtime extrinsics
+		self.ready()
+			.filter_map(|tx| UncheckedExtrinsic::decode(&mut &tx.data().encode()[..]).ok())
+			.collect()
+	}
+}
+
+/// What to do after handling a message.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RelayOutcome {
+	/// The block is complete.
+	Complete(PrismBlock),
+	/// Send this request back to the peer the message came from.
+	Request(RelayMessage),
+	/// Nothing to do, e.g. a response for a block that is not pending.
+	Ignored,
+	/// The peer sent something invalid.
+	Misbehaved,
+}
+
+// A block waiting for the answer of the peer that announced it
+struct Waiting<T> {
+	peer: PeerId,
+	since: Instant,
+	value: T,
+}
+
+// Blocks waiting for an answer, bounded per peer and in total
+struct WaitingBlocks<T> {
+	blocks: HashMap<H256, Waiting<T>>,
+}
+
+impl<T> WaitingBlocks<T> {
+	fn new() -> Self {
+		WaitingBlocks { blocks: HashMap::new() }
+	}
+
+	fn contains(&self, block_hash: &H256) -> bool {
+		self.blocks.contains_key(block_hash)
+	}
+
+	// Drops blocks that waited too long, then the peer's oldest block if it has too many and the
+	// oldest block overall if there are too many
+	fn insert(&mut self, peer: PeerId, block_hash: H256, value: T, now: Instant) {
+		self.blocks.retain(|_, waiting| now.duration_since(waiting.since) < PENDING_TIMEOUT);
+		if self.blocks.values().filter(|waiting| waiting.peer == peer).count() >= MAX_PENDING_PER_PEER {
+			self.evict_oldest(|waiting| waiting.peer == peer);
+		}
+		if self.blocks.len() >= MAX_PENDING {
+			self.evict_oldest(|_| true);
+		}
+		self.blocks.insert(block_hash, Waiting { peer, since: now, value });
+	}
+
+	fn evict_oldest(&mut self, filter: impl Fn(&Waiting<T>) -> bool) {
+		let oldest = self
+			.blocks
+			.iter()
+			.filter(|(_, waiting)| filter(waiting))
+			.min_by_key(|(_, waiting)| waiting.since)
+			.map(|(block_hash, _)| *block_hash);
+		if let Some(block_hash) = oldest {
+			self.blocks.remove(&block_hash);
+		}
+	}
+
+	// The block, if `peer` is the one it waits for and it has not timed out
+	fn take(&mut self, peer: &PeerId, block_hash: &H256, now: Instant) -> Option<T> {
+		match self.blocks.get(block_hash) {
+			Some(waiting) if waiting.peer == *peer => {},
+			_ => return None,
+		}
+		let waiting = self.blocks.remove(block_hash)?;
+		(now.duration_since(waiting.since) < PENDING_TIMEOUT).then_some(waiting.value)
+	}
+
+	fn len(&self) -> usize {
+		self.blocks.len()
+	}
+}
+
+// A block whose full content was requested, with what its announcement committed to
+#[derive(Debug)]
+struct Fallback {
+	header: BlockHeader,
+	content_hash: H256,
+	sortition_proof: Vec<H256>,
+}
+
+/// Rebuilds announced transaction blocks from the local pool.
+pub struct CompactRelay<P> {
+	pool: Arc<P>,
+	voter_chains: u16,
+	pending: WaitingBlocks<PartialTransactionBlock>,
+	// blocks whose full content was requested, only their `Block` responses are accepted
+	fallback: WaitingBlocks<Fallback>,
+	// the last `MAX_PENDING` blocks completed, announcements of them are ignored
+	complete: VecDeque<H256>,
+}
+
+impl<P> CompactRelay<P>
+where
+	P: ExtrinsicSource,
+{
+	/// Relay filling blocks from `pool`, whose headers commit to the contents of `voter_chains`
+	/// voter chains next to the proposer and transaction content.
+	pub fn new(pool: Arc<P>, voter_chains: u16) -> Self {
+		CompactRelay {
+			pool,
+			voter_chains,
+			pending: WaitingBlocks::new(),
+			fallback: WaitingBlocks::new(),
+			complete: VecDeque::new(),
+		}
+	}
+
+	/// Whether the block is waiting for an answer or was completed lately.
+	pub fn has_seen(&self, block_hash: &H256) -> bool {
+		self.pending.contains(block_hash) ||
+			self.fallback.contains(block_hash) ||
+			self.complete.contains(block_hash)
+	}
+
+	/// Record a block that was completed, by the relay or otherwise.
+	pub fn mark_complete(&mut self, block_hash: H256) {
+		if self.complete.contains(&block_hash) {
+			return
+		}
+		if self.complete.len() >= MAX_PENDING {
+			self.complete.pop_front();
+		}
+		self.complete.push_back(block_hash);
+	}
+
+	/// Whether the header of an announcement commits to its content hash, only then are the
+	/// block's symbols worth collecting.
+	pub fn commits_content(&self, compact: &CompactTransactionBlock) -> bool {
+		compact.commitsContent(self.voter_chains)
+	}
+
+	/// Blocks waiting for missing extrinsics or for their full content.
+	pub fn waiting(&self) -> usize {
+		self.pending.len() + self.fallback.len()
+	}
+
+	/// The response to a message from `peer`, the next request or empty when there is nothing
+	/// left to ask, `Err` if the peer misbehaved. Also returns the block the message completed.
+	pub fn respond(&mut self, peer: PeerId, message: RelayMessage) -> (Result<Vec<u8>, ()>, Option<PrismBlock>) {
+		match self.on_message(peer, message) {
+			RelayOutcome::Request(next) => (Ok(next.encode()), None),
+			RelayOutcome::Complete(block) => (Ok(Vec::new()), Some(block)),
+			RelayOutcome::Ignored => (Ok(Vec::new()), None),
+			RelayOutcome::Misbehaved => (Err(()), None),
+		}
+	}
+
+	/// Handle a message from `peer`.
+	pub fn on_message(&mut self, peer: PeerId, message: RelayMessage) -> RelayOutcome {
+		let now = Instant::now();
+		let outcome = match message {
+			RelayMessage::Compact(compact) => self.on_compact(peer, compact, now),
+			RelayMessage::Transactions { block_hash, extrinsics } =>
+				self.on_transactions(peer, block_hash, extrinsics, now),
+			RelayMessage::Block(block) => self.on_block(peer, block, now),
+			RelayMessage::GetTransactions { .. } | RelayMessage::GetBlock { .. } =>
+				RelayOutcome::Ignored,
+		};
+		if let RelayOutcome::Complete(block) = &outcome {
+			self.mark_complete(block.hash());
+		}
+		outcome
+	}
+
+	fn on_compact(&mut self, peer: PeerId, compact: CompactTransactionBlock, now: Instant) -> RelayOutcome {
+		let block_hash = compact.header.hash();
+		if self.has_seen(&block_hash) {
+			return RelayOutcome::Ignored
+		}
+		if !self.commits_content(&compact) {
+			return RelayOutcome::Misbehaved
+		}
+		let partial = PartialTransactionBlock::new(compact, self.pool.ready_extrinsics());
+		let missing = partial.missing();
+		if missing.is_empty() {
+			return self.finish(peer, block_hash, partial, now)
+		}
+		self.pending.insert(peer, block_hash, partial, now);
+		RelayOutcome::Request(RelayMessage::GetTransactions { block_hash, indexes: missing })
+	}
+
+	fn on_transactions(
+		&mut self,
+		peer: PeerId,
+		block_hash: H256,
+		extrinsics: Vec<UncheckedExtrinsic>,
+		now: Instant,
+	) -> RelayOutcome {
+		let mut partial = match self.pending.take(&peer, &block_hash, now) {
+			Some(partial) => partial,
+			None => return RelayOutcome::Ignored,
+		};
+		match partial.fill(extrinsics) {
+			Ok(()) => self.finish(peer, block_hash, partial, now),
+			Err(_) => RelayOutcome::Misbehaved,
+		}
+	}
+
+	fn finish(
+		&mut self,
+		peer: PeerId,
+		block_hash: H256,
+		partial: PartialTransactionBlock,
+		now: Instant,
+	) -> RelayOutcome {
+		let fallback = Fallback {
+			header: partial.header.clone(),
+			content_hash: partial.contentHash,
+			sortition_proof: partial.sortitionProof.clone(),
+		};
+		match partial.finish() {
+			Ok(content) => RelayOutcome::Complete(PrismBlock::fromParts(
+				fallback.header,
+				PrismContent::TransactionBlock(content),
+				fallback.sortition_proof,
+			)),
+			// a short id matched the wrong pool extrinsic, the full block settles it
+			Err(CompactError::RootMismatch) | Err(CompactError::Incomplete) => {
+				self.fallback.insert(peer, block_hash, fallback, now);
+				RelayOutcome::Request(RelayMessage::GetBlock { block_hash })
+			},
+			Err(CompactError::WrongTransactions) => RelayOutcome::Misbehaved,
+		}
+	}
+
+	fn on_block(&mut self, peer: PeerId, block: PrismBlock, now: Instant) -> RelayOutcome {
+		let block_hash = block.hash();
+		let block = match block {
+			PrismBlock::Transaction(block) => block,
+			// the validator checks what the header of a proposer or voter block commits to
+			_ if self.has_seen(&block_hash) => return RelayOutcome::Ignored,
+			block => return RelayOutcome::Complete(block),
+		};
+		let expected = match self.fallback.take(&peer, &block_hash, now) {
+			Some(expected) if expected.header == block.header => expected,
+			_ => return RelayOutcome::Ignored,
+		};
+		if block.content.hash() != expected.content_hash {
+			return RelayOutcome::Misbehaved
+		}
+		RelayOutcome::Complete(PrismBlock::Transaction(BlockOf {
+			sortitionProof: expected.sortition_proof,
+			..block
+		}))
+	}
+}
+
+/// Answers the requests of the compact relay protocol until the network shuts down. An
+/// announcement also starts collecting the block's symbols from `symbols`, and whichever of the
+/// two rebuilds the block first sends it to `completed`. Completed blocks are dropped if
+/// `completed` is full.
+pub async fn run_compact_relay<P>(
+	mut relay: CompactRelay<P>,
+	mut requests: mpsc::Receiver<IncomingRequest>,
+	symbols: SymbolGossip,
+	mut completed: mpsc::Sender<PrismBlock>,
+) where
+	P: ExtrinsicSource,
+{
+	let mut collecting = FuturesUnordered::new();
+	// blocks whose symbols are being collected, to stop collecting once the relay completed them
+	let mut collections: HashMap<H256, AbortHandle> = HashMap::new();
+	let mut deliver = |block: PrismBlock| {
+		if completed.try_send(block).is_err() {
+			log::warn!(target: "prism", "Dropped a relayed block, the importer is behind");
+		}
+	};
+	loop {
+		let request = if collecting.is_empty() {
+			requests.next().await
+		} else {
+			match future::select(requests.next(), collecting.next()).await {
+				Either::Left((request, _)) => request,
+				Either::Right((Some((block_hash, result)), _)) => {
+					collections.remove(&block_hash);
+					match result {
+						Ok(Ok(block)) => {
+							relay.mark_complete(block_hash);
+							deliver(block)
+						},
+						Ok(Err(e)) =>
+							log::debug!(target: "prism", "Symbols of block {} not collected: {:?}", block_hash, e),
+						// the relay completed the block first
+						Err(future::Aborted) => {},
+					}
+					continue
+				},
+				Either::Right((None, _)) => continue,
+			}
+		};
+		let request = match request {
+			Some(request) => request,
+			None => return,
+		};
+		let (result, reputation_changes) = match RelayMessage::decode_all(&mut &request.payload[..]) {
+			Ok(message) => {
+				// the announcement carries the header, collect the symbols it commits to
+				if let RelayMessage::Compact(compact) = &message {
+					let block_hash = compact.header.hash();
+					if relay.commits_content(compact) &&
+						!relay.has_seen(&block_hash) &&
+						!collections.contains_key(&block_hash) &&
+						collections.len() < MAX_PENDING
+					{
+						let (header, proof) = (compact.header.clone(), compact.sortitionProof.clone());
+						let collect = symbols.collect(block_hash, compact.contentHash).map(|result| {
+							result.map(|content| {
+								PrismBlock::fromParts(header, PrismContent::TransactionBlock(content), proof)
+							})
+						});
+						let (collect, abort) = future::abortable(collect);
+						collections.insert(block_hash, abort);
+						collecting.push(collect.map(move |result| (block_hash, result)));
+					}
+				}
+				let (response, complete) = relay.respond(request.peer, message);
+				if let Some(block) = complete {
+					if let Some(abort) = collections.remove(&block.hash()) {
+						abort.abort();
+					}
+					deliver(block);
+				}
+				match response {
+					Ok(response) => (Ok(response), Vec::new()),
+					Err(()) => (Err(()), vec![MISBEHAVED]),
+				}
+			},
+			Err(_) => (Err(()), vec![MISBEHAVED]),
+		};
+		let _ = request
+			.pending_response
+			.send(OutgoingResponse { result, reputation_changes, sent_feedback: None });
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::executor::block_on;
+	use prism_consensus::Block::{ExtraContent::ExtraContent, VoterBlock};
+
+	struct Pool(Vec<UncheckedExtrinsic>);
+
+	impl ExtrinsicSource for Pool {
+		fn ready_extrinsics(&self) -> Vec<UncheckedExtrinsic> {
+			self.0.clone()
+		}
+	}
+
+	fn extrinsic(i: u32) -> UncheckedExtrinsic {
+		UncheckedExtrinsic::new_unsigned(Argonaut_Runtime::SystemCall::remark { remark: i.encode() }.into())
+	}
+
+	// Sends the messages of `peer` to `relay`, collecting the blocks it completes
+	fn link<'a>(
+		relay: &'a mut CompactRelay<Pool>,
+		peer: PeerId,
+		completed: &'a mut Vec<PrismBlock>,
+	) -> impl FnMut(Vec<u8>) -> future::Ready<Result<Vec<u8>, ()>> + 'a {
+		move |payload| {
+			let (response, complete) = match RelayMessage::decode_all(&mut &payload[..]) {
+				Ok(message) => relay.respond(peer, message),
+				Err(_) => (Err(()), None),
+			};
+			completed.extend(complete);
+			future::ready(response)
+		}
+	}
+
+	#[test]
+	fn announced_block_relayed_twice() {
+		let content = Content::new((0..10).map(extrinsic).collect());
+		// the header commits to the transaction content alone, without a sortition proof
+		let header = BlockHeader::new(H256::zero(), 1, 1, content.hash(), ExtraContent::default(), H256::zero());
+		let block = BlockOf { header, content, sortitionProof: Vec::new() };
+		let expected = PrismBlock::Transaction(block.clone());
+		let (a, b) = (PeerId::random(), PeerId::random());
+
+		// b misses some extrinsics and asks a for them
+		let pool = Pool((0..10).filter(|i| i % 3 != 0).map(extrinsic).collect());
+		let mut relay_b = CompactRelay::new(Arc::new(pool), 1);
+		let mut at_b = Vec::new();
+		block_on(announce_block(&expected, link(&mut relay_b, a, &mut at_b))).unwrap();
+		assert_eq!(at_b, vec![expected.clone()]);
+		// another announcement of the block is answered without asking for anything
+		let mut again = Vec::new();
+		block_on(announce_block(&expected, link(&mut relay_b, a, &mut again))).unwrap();
+		assert!(again.is_empty());
+
+		// b announces the block it completed to c, which has every extrinsic
+		let relayed = at_b[0].clone();
+		let mut relay_c = CompactRelay::new(Arc::new(Pool((0..10).map(extrinsic).collect())), 1);
+		let mut at_c = Vec::new();
+		block_on(announce_block(&relayed, link(&mut relay_c, b, &mut at_c))).unwrap();
+		assert_eq!(at_c, vec![expected]);
+	}
+
+	#[test]
+	fn announcement_stops_at_requests_for_other_blocks() {
+		let content = Content::new(vec![extrinsic(0)]);
+		let header = BlockHeader::new(H256::zero(), 1, 1, content.hash(), ExtraContent::default(), H256::zero());
+		let block = PrismBlock::Transaction(BlockOf { header, content, sortitionProof: Vec::new() });
+		let other = RelayMessage::GetBlock { block_hash: H256::repeat_byte(1) }.encode();
+		let result = block_on(announce_block(&block, |_| future::ready(Ok::<_, ()>(other.clone()))));
+		assert!(matches!(result, Err(AnnounceError::Unexpected)));
+	}
+
+	#[test]
+	fn waiting_blocks_bounded_per_peer() {
+		let mut waiting = WaitingBlocks::new();
+		let (flooder, honest) = (PeerId::random(), PeerId::random());
+		let start = Instant::now();
+		waiting.insert(honest, H256::repeat_byte(0xff), (), start);
+		for i in 0..(MAX_PENDING_PER_PEER as u64 * 2) {
+			waiting.insert(flooder, H256::from_low_u64_be(i), (), start + Duration::from_millis(i));
+		}
+		assert_eq!(waiting.len(), MAX_PENDING_PER_PEER + 1);
+		// the flooder's oldest blocks went, the honest peer's block stays
+		assert!(!waiting.contains(&H256::from_low_u64_be(0)));
+		assert!(waiting.contains(&H256::repeat_byte(0xff)));
+	}
+
+	#[test]
+	fn waiting_blocks_bounded_in_total() {
+		let mut waiting = WaitingBlocks::new();
+		let start = Instant::now();
+		for i in 0..(MAX_PENDING as u64 + 1) {
+			waiting.insert(PeerId::random(), H256::from_low_u64_be(i), (), start + Duration::from_millis(i));
+		}
+		assert_eq!(waiting.len(), MAX_PENDING);
+		assert!(!waiting.contains(&H256::from_low_u64_be(0)));
+	}
+
+	#[test]
+	fn waiting_blocks_expire() {
+		let mut waiting = WaitingBlocks::new();
+		let peer = PeerId::random();
+		let start = Instant::now();
+		waiting.insert(peer, H256::zero(), 1u8, start);
+		waiting.insert(peer, H256::repeat_byte(1), 2u8, start);
+		assert_eq!(waiting.take(&PeerId::random(), &H256::zero(), start), None);
+		assert_eq!(waiting.take(&peer, &H256::zero(), start), Some(1));
+		assert_eq!(waiting.take(&peer, &H256::repeat_byte(1), start + PENDING_TIMEOUT), None);
+
+		waiting.insert(peer, H256::zero(), 1u8, start);
+		waiting.insert(peer, H256::repeat_byte(2), 3u8, start + PENDING_TIMEOUT);
+		assert_eq!(waiting.len(), 1);
+	}
+
+	#[test]
+	fn voter_block_announced_whole() {
+		let content = PrismContent::VoterBlock(VoterBlock::Content::new(0, H256::zero(), Vec::new()));
+		let header = BlockHeader::new(H256::zero(), 1, 1, content.hash(), ExtraContent::default(), H256::zero());
+		let block = PrismBlock::fromParts(header, content, Vec::new());
+		let mut relay = CompactRelay::new(Arc::new(Pool(Vec::new())), 1);
+		let mut completed = Vec::new();
+		block_on(announce_block(&block, link(&mut relay, PeerId::random(), &mut completed))).unwrap();
+		block_on(announce_block(&block, link(&mut relay, PeerId::random(), &mut completed))).unwrap();
+		assert_eq!(completed, vec![block]);
+	}
+}
@@ -0,0 +1,252 @@
+//! Gossip of the RaptorQ symbols of transaction blocks.
+//!
+//! Instead of sending a large transaction block whole to every peer, its producer gossips the
+//! block's symbols (see `prism_consensus::Block::ErasureCode`) next to announcing it through the
+//! [`compact`] relay, and every peer relays the symbols it receives. A peer receiving the
+//! announcement, the header and the content hash it commits to, collects symbols until it can
+//! rebuild the content, whichever of the two completes the block first. Only symbols that verify
+//! against the content hash of an announced block are kept and relayed, so a symbol of another
+//! block or coding dies at the first honest peer.
+
+pub mod compact;
+
+use codec::{DecodeAll, Encode};
+use futures::{channel::mpsc, future, stream::FuturesUnordered, FutureExt, StreamExt};
+use futures_timer::Delay;
+use parking_lot::{Mutex, RwLock};
+use sc_network::{config::NonDefaultSetConfig, PeerId};
+use sc_network_common::{
+	protocol::event::Event,
+	request_responses::IfDisconnected,
+	service::{NetworkEventStream, NetworkRequest},
+};
+use sc_network_gossip::{GossipEngine, Network, ValidationResult, Validator, ValidatorContext};
+use sp_core::H256;
+use sp_runtime::traits::{BlakeTwo256, Hash};
+use std::{
+	collections::{HashMap, HashSet, VecDeque},
+	sync::Arc,
+	time::Duration,
+};
+use substrate_prometheus_endpoint::Registry;
+use prism_consensus::Block::{
+	Block as PrismBlock,
+	ErasureCode::{Coding, Symbol, SymbolCollector, SymbolError},
+	TransactionBlock::Content,
+};
+use Argonaut_Runtime::opaque::Block;
+
+/// Name of the notifications protocol symbols are gossiped on.
+pub const SYMBOL_PROTOCOL_NAME: &str = "/argonaut/transaction-symbols/1";
+
+// A symbol, its proof and the roots stay well below this with `SYMBOL_SIZE` payloads
+const MAX_SYMBOL_MESSAGE_SIZE: u64 = 16 * 1024;
+
+/// Blocks whose symbols are kept and relayed, the oldest is forgotten first.
+pub const MAX_EXPECTED_BLOCKS: usize = 256;
+
+/// How long the symbols of an announced block are collected before giving up on them.
+pub const COLLECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Gossip topic of the symbols of the block with `block_hash`.
+pub fn symbol_topic(block_hash: &H256) -> H256 {
+	BlakeTwo256::hash_of(&(b"argonaut/symbols", block_hash))
+}
+
+/// Peer set of the symbol protocol, to be pushed into `config.network.extra_sets`.
+pub fn symbol_peers_set_config() -> NonDefaultSetConfig {
+	let mut config = NonDefaultSetConfig::new(SYMBOL_PROTOCOL_NAME.into(), MAX_SYMBOL_MESSAGE_SIZE);
+	config.allow_non_reserved(25, 25);
+	config
+}
+
+/// Why collecting the symbols of a block failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectError {
+	/// The gossip engine shut down before enough symbols arrived.
+	Closed,
+	/// Not enough symbols arrived within [`COLLECT_TIMEOUT`].
+	TimedOut,
+	/// The coding the block commits to does not rebuild its content, the block is invalid.
+	Inconsistent,
+}
+
+// Block hashes to the content hash their header commits to, in the order they were announced
+#[derive(Default)]
+struct Expected {
+	content_hashes: HashMap<H256, H256>,
+	order: VecDeque<H256>,
+}
+
+/// Keeps and relays only symbols of announced blocks that verify against the block's content
+/// hash.
+#[derive(Default)]
+pub struct SymbolValidator {
+	expected: RwLock<Expected>,
+}
+
+impl SymbolValidator {
+	/// Accept symbols of the block with `block_hash`, whose header commits to the transaction
+	/// content with `content_hash`. Beyond [`MAX_EXPECTED_BLOCKS`] the oldest block is forgotten
+	/// and its stored symbols expire.
+	pub fn expect(&self, block_hash: H256, content_hash: H256) {
+		let mut expected = self.expected.write();
+		if expected.content_hashes.insert(block_hash, content_hash).is_some() {
+			return
+		}
+		expected.order.push_back(block_hash);
+		if expected.order.len() > MAX_EXPECTED_BLOCKS {
+			if let Some(oldest) = expected.order.pop_front() {
+				expected.content_hashes.remove(&oldest);
+			}
+		}
+	}
+}
+
+impl Validator<Block> for SymbolValidator {
+	fn validate(
+		&self,
+		_context: &mut dyn ValidatorContext<Block>,
+		_sender: &PeerId,
+		data: &[u8],
+	) -> ValidationResult<H256> {
+		let symbol = match Symbol::decode_all(&mut &data[..]) {
+			Ok(symbol) => symbol,
+			Err(_) => return ValidationResult::Discard,
+		};
+		match self.expected.read().content_hashes.get(&symbol.blockHash) {
+			Some(content_hash) if symbol.verify(content_hash) =>
+				ValidationResult::ProcessAndKeep(symbol_topic(&symbol.blockHash)),
+			_ => ValidationResult::Discard,
+		}
+	}
+
+	fn message_expired<'a>(&'a self) -> Box<dyn FnMut(H256, &[u8]) -> bool + 'a> {
+		let expected = self.expected.read();
+		Box::new(move |_topic, data| match Symbol::decode_all(&mut &data[..]) {
+			Ok(symbol) => !expected.content_hashes.contains_key(&symbol.blockHash),
+			Err(_) => true,
+		})
+	}
+}
+
+/// Publishes and collects transaction block symbols over gossip.
+#[derive(Clone)]
+pub struct SymbolGossip {
+	engine: Arc<Mutex<GossipEngine<Block>>>,
+	validator: Arc<SymbolValidator>,
+}
+
+impl SymbolGossip {
+	/// Starts gossiping on [`SYMBOL_PROTOCOL_NAME`], [`Self::run`] has to be spawned to drive it.
+	pub fn new<N>(network: N, registry: Option<&Registry>) -> Self
+	where
+		N: Network<Block> + Send + Clone + 'static,
+	{
+		let validator = Arc::new(SymbolValidator::default());
+		let engine = GossipEngine::new(network, SYMBOL_PROTOCOL_NAME, validator.clone(), registry);
+		SymbolGossip { engine: Arc::new(Mutex::new(engine)), validator }
+	}
+
+	/// Gossip every symbol of a transaction block this node produced.
+	pub fn publish(&self, block_hash: H256, content: &Content) {
+		let coding = Coding::new(content);
+		self.validator.expect(block_hash, coding.contentRoot());
+		let topic = symbol_topic(&block_hash);
+		let mut engine = self.engine.lock();
+		for symbol in coding.symbols(block_hash) {
+			engine.gossip_message(topic, symbol.encode(), false);
+		}
+	}
+
+	/// Collect the symbols of an announced block until its transaction content, the one with
+	/// `content_hash`, can be rebuilt.
+	pub fn collect(
+		&self,
+		block_hash: H256,
+		content_hash: H256,
+	) -> impl future::Future<Output = Result<Content, CollectError>> {
+		self.validator.expect(block_hash, content_hash);
+		let mut messages = self.engine.lock().messages_for(symbol_topic(&block_hash));
+		let collect = async move {
+			let mut collector = SymbolCollector::new(content_hash);
+			loop {
+				let notification = match messages.next().await {
+					Some(notification) => notification,
+					None => return Err(CollectError::Closed),
+				};
+				let symbol = match Symbol::decode_all(&mut &notification.message[..]) {
+					Ok(symbol) => symbol,
+					Err(_) => continue,
+				};
+				match collector.push(symbol) {
+					Ok(Some(content)) => return Ok(content),
+					Err(SymbolError::Inconsistent) => return Err(CollectError::Inconsistent),
+					Ok(None) | Err(SymbolError::Duplicate) | Err(SymbolError::Forged) => continue,
+				}
+			}
+		};
+		future::select(Box::pin(collect), Delay::new(COLLECT_TIMEOUT)).map(|either| match either {
+			future::Either::Left((result, _)) => result,
+			future::Either::Right(((), _)) => Err(CollectError::TimedOut),
+		})
+	}
+
+	/// Drives the gossip engine until the network shuts down.
+	pub async fn run(self) {
+		future::poll_fn(|cx| self.engine.lock().poll_unpin(cx)).await
+	}
+}
+
+/// Sends the blocks this node mined or completed from its peers out to the peers it is connected
+/// to: every block is announced to every peer through the [`compact`] relay, and the symbols of a
+/// transaction block are gossiped besides.
+pub async fn run_block_announcer<N>(symbols: SymbolGossip, network: N, mut blocks: mpsc::Receiver<PrismBlock>)
+where
+	N: NetworkRequest + NetworkEventStream + Clone + Send + Sync + 'static,
+{
+	let mut events = network.event_stream("prism-block-announcer").fuse();
+	let mut peers = HashSet::new();
+	let mut announcements = FuturesUnordered::new();
+	loop {
+		futures::select! {
+			block = blocks.next() => {
+				let block = match block {
+					Some(block) => block,
+					None => return,
+				};
+				let block_hash = block.hash();
+				if let Some(transaction) = block.asTransaction() {
+					symbols.publish(block_hash, &transaction.content);
+				}
+				let block = Arc::new(block);
+				for peer in peers.iter().copied() {
+					let (network, block) = (network.clone(), block.clone());
+					announcements.push(async move {
+						let send = |payload| {
+							let network = network.clone();
+							async move {
+								let protocol = compact::COMPACT_RELAY_PROTOCOL_NAME.into();
+								network.request(peer, protocol, payload, IfDisconnected::ImmediateError).await
+							}
+						};
+						if let Err(e) = compact::announce_block(&block, send).await {
+							log::debug!(target: "prism", "Block {} not announced to {}: {:?}", block_hash, peer, e);
+						}
+					});
+				}
+			},
+			event = events.next() => match event {
+				Some(Event::SyncConnected { remote }) => {
+					peers.insert(remote);
+				},
+				Some(Event::SyncDisconnected { remote }) => {
+					peers.remove(&remote);
+				},
+				Some(_) => {},
+				None => return,
+			},
+			() = announcements.select_next_some() => {},
+		}
+	}
+}
@@ -0,0 +1,256 @@
+//! The node's Prism block tree.
+//!
+//! The tree lives in the Prism store next to the Substrate database. On startup the store is
+//! migrated to the schema this node writes, see `prism_migrate`, and the tree is loaded from it, or
+//! created holding the chain spec's genesis blocks on an empty store. Blocks the network completes
+//! go through the headers-first validation of `prism_consensus::Validation` before they are
+//! inserted, and the validation counters are exported to Prometheus.
+
+use crate::{
+	chain_spec, prism_migrate::open_prism_store, prism_snapshot::prism_db_path, pruning,
+};
+use futures::{channel::mpsc, StreamExt};
+use parking_lot::Mutex;
+use prism_consensus::{
+	Block::{Block, Content, Header::BlockHeader, Limits::PayloadLimits},
+	BlockTree::{
+		BlockTree, InsertError, Ledger::LedgerUpdate, Prune::PruningMode, Store::RocksStore,
+	},
+	Genesis::GenesisBlocks,
+	Validation::{ContentError, HeaderError, Sortition, ValidationMetrics, Validator},
+};
+use sc_service::Configuration;
+use sp_core::H256;
+use std::{
+	sync::Arc,
+	time::{SystemTime, UNIX_EPOCH},
+};
+use substrate_prometheus_endpoint::{register, CounterVec, Opts, PrometheusError, Registry, U64};
+
+/// The Prism block tree of a node, on its Prism store.
+pub type PrismTree = BlockTree<RocksStore>;
+
+fn tree_error(e: impl std::fmt::Debug) -> sc_cli::Error {
+	sc_cli::Error::Input(format!("Prism block tree: {:?}", e))
+}
+
+/// The Prism genesis blocks of the chain spec in `config`, checked against the root it pins.
+pub fn genesis_blocks(config: &Configuration) -> sc_cli::Result<GenesisBlocks> {
+	Ok(chain_spec::prism_genesis(config.chain_spec.as_ref())?.blocks()?)
+}
+
+/// Open the Prism block tree of the node configured by `config`, pruned as `pruning` says, and
+/// the import validating blocks for it within `limits`. A node without an on-disk database has no
+/// Prism store and gets `None`.
+pub fn open_import(
+	config: &Configuration,
+	pruning: PruningMode,
+	limits: PayloadLimits,
+) -> sc_cli::Result<Option<Arc<PrismImport>>> {
+	if config.database.path().is_none() {
+		return Ok(None)
+	}
+	let spec = chain_spec::prism_genesis(config.chain_spec.as_ref())?;
+	let genesis = spec.blocks()?;
+	let path = prism_db_path(config)?;
+	let tree = BlockTree::load(open_prism_store(&path)?, &genesis, pruning)
+		.map_err(tree_error)?
+		.withLimits(limits);
+	log::info!(
+		"🌳 Prism block tree at {}: ledger level {}, pruning: {}",
+		path.display(),
+		tree.proposerLedgerTip(),
+		pruning::describe(pruning),
+	);
+	let validator = Validator::new(
+		Sortition { voterChains: spec.voter_chains, ..Sortition::default() },
+		limits,
+	);
+	let import = PrismImport::new(Arc::new(tree), validator, config.prometheus_registry())
+		.map_err(|e| sc_cli::Error::Input(format!("Prism validation metrics: {}", e)))?;
+	Ok(Some(Arc::new(import)))
+}
+
+/// Why a block was not imported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+	/// The header failed validation.
+	Header(HeaderError),
+	/// The content failed validation.
+	Content(ContentError),
+	/// The tree refused the validated block.
+	Insert(InsertError),
+}
+
+// Prism validation counters on the node's Prometheus endpoint
+struct ValidationCounters {
+	accepted: CounterVec<U64>,
+	rejected: CounterVec<U64>,
+}
+
+impl ValidationCounters {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(ValidationCounters {
+			accepted: register(
+				CounterVec::new(
+					Opts::new(
+						"prism_validation_accepted_total",
+						"Prism blocks that passed a validation stage",
+					),
+					&["stage"],
+				)?,
+				registry,
+			)?,
+			rejected: register(
+				CounterVec::new(
+					Opts::new(
+						"prism_validation_rejected_total",
+						"Prism blocks a validation stage rejected, by reason",
+					),
+					&["stage", "reason"],
+				)?,
+				registry,
+			)?,
+		})
+	}
+
+	// add what `current` counted since `exported`, which it becomes
+	fn export(&self, current: &ValidationMetrics, exported: &mut ValidationMetrics) {
+		let stages = [
+			("header", &current.header, &exported.header),
+			("fetch", &current.fetch, &exported.fetch),
+			("content", &current.content, &exported.content),
+		];
+		for (stage, now, before) in stages {
+			self.accepted.with_label_values(&[stage]).inc_by(now.accepted - before.accepted);
+			for (reason, count) in &now.rejected {
+				let added = count - before.rejected.get(reason).copied().unwrap_or(0);
+				if added > 0 {
+					self.rejected.with_label_values(&[stage, reason]).inc_by(added);
+				}
+			}
+		}
+		*exported = current.clone();
+	}
+}
+
+// the validator and the metrics the counters were last brought up to
+struct ValidationState {
+	validator: Validator,
+	exported: ValidationMetrics,
+}
+
+/// Validates Prism blocks against the block tree and inserts those that pass.
+pub struct PrismImport {
+	tree: Arc<PrismTree>,
+	state: Mutex<ValidationState>,
+	counters: Option<ValidationCounters>,
+}
+
+impl PrismImport {
+	/// Import into `tree` what `validator` accepts, counting on `registry` if there is one.
+	pub fn new(
+		tree: Arc<PrismTree>,
+		validator: Validator,
+		registry: Option<&Registry>,
+	) -> Result<Self, PrometheusError> {
+		let counters = registry.map(ValidationCounters::register).transpose()?;
+		let state = ValidationState { validator, exported: ValidationMetrics::default() };
+		Ok(PrismImport { tree, state: Mutex::new(state), counters })
+	}
+
+	/// The tree blocks are imported into.
+	pub fn tree(&self) -> &Arc<PrismTree> {
+		&self.tree
+	}
+
+	/// How the work below the target is split between the block kinds.
+	pub fn sortition(&self) -> Sortition {
+		self.state.lock().validator.sortition
+	}
+
+	/// The payload limits blocks are validated against.
+	pub fn limits(&self) -> PayloadLimits {
+		self.state.lock().validator.limits
+	}
+
+	/// Validate a block whose content was rebuilt against its header, then insert it. Blocks on
+	/// the Falcon verification and the synced write of the Prism store.
+	pub fn import(
+		&self,
+		header: BlockHeader,
+		content: Content,
+		sortition_proof: Vec<H256>,
+	) -> Result<LedgerUpdate, ImportError> {
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|elapsed| elapsed.as_millis())
+			.unwrap_or_default();
+		let mut state = self.state.lock();
+		let validated = state
+			.validator
+			.checkHeader(&*self.tree, header, now)
+			.map_err(ImportError::Header)
+			.and_then(|verified| {
+				state
+					.validator
+					.checkContent(&*self.tree, verified, content, sortition_proof)
+					.map_err(ImportError::Content)
+			});
+		if let Some(counters) = &self.counters {
+			let ValidationState { validator, exported } = &mut *state;
+			counters.export(&validator.metrics, exported);
+		}
+		drop(state);
+		self.tree.insert(validated?).map_err(ImportError::Insert)
+	}
+}
+
+/// Import the blocks completed by the compact relay or from their symbols, and pass those imported
+/// on to `announce`. Imports write to the Prism store and check Falcon seals, so this has to run
+/// as a blocking task.
+pub async fn import_relayed_blocks(
+	import: Arc<PrismImport>,
+	mut blocks: mpsc::Receiver<Block>,
+	mut announce: mpsc::Sender<Block>,
+) {
+	while let Some(block) = blocks.next().await {
+		let hash = block.hash();
+		let (header, content, sortition_proof) = block.clone().intoParts();
+		match import.import(header, content, sortition_proof) {
+			Ok(_) => {
+				log::debug!(target: "prism", "Imported relayed block {}", hash);
+				if announce.try_send(block).is_err() {
+					log::debug!(target: "prism", "Relayed block {} not announced, the announcer is behind", hash);
+				}
+			},
+			Err(e) => log::debug!(target: "prism", "Relayed block {} not imported: {:?}", hash, e),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn counters_follow_the_validator() {
+		let registry = Registry::new();
+		let counters = ValidationCounters::register(&registry).unwrap();
+		let mut exported = ValidationMetrics::default();
+		let mut current = ValidationMetrics::default();
+		current.header.accepted = 3;
+		current.header.rejected.insert("insufficient_work", 2);
+		counters.export(&current, &mut exported);
+		current.header.accepted = 4;
+		current.header.rejected.insert("insufficient_work", 5);
+		current.content.rejected.insert("payload_limit", 1);
+		counters.export(&current, &mut exported);
+
+		assert_eq!(exported, current);
+		assert_eq!(counters.accepted.with_label_values(&["header"]).get(), 4);
+		assert_eq!(counters.accepted.with_label_values(&["content"]).get(), 0);
+		assert_eq!(counters.rejected.with_label_values(&["header", "insufficient_work"]).get(), 5);
+		assert_eq!(counters.rejected.with_label_values(&["content", "payload_limit"]).get(), 1);
+	}
+}
@@ -0,0 +1,81 @@
+//! Schema migrations of the Prism store.
+//!
+//! Every time the Prism store is opened it is brought up to the schema version this node writes,
+//! see `prism_consensus::BlockTree::Migration`. A node started on an older store migrates it before
+//! its block tree is loaded from it, see `prism::open_import`, and `migrate-prism` does the same
+//! offline to a store that must already exist. A store written by a newer node is refused rather
+//! than opened.
+
+use crate::prism_snapshot::prism_db_path;
+use prism_consensus::BlockTree::{
+	Migration::{self, SCHEMA_VERSION},
+	Store::{RocksStore, StoreError},
+};
+use sc_cli::{CliConfiguration, SharedParams};
+use sc_service::Configuration;
+use std::path::Path;
+
+/// Open the Prism store at `path`, creating it if there is none, migrated to the current schema.
+pub fn open_prism_store(path: &Path) -> sc_cli::Result<RocksStore> {
+	migrated(path, RocksStore::open(path))
+}
+
+/// Open the Prism store at `path` migrated to the current schema, an error rather than a new store
+/// if there is none.
+pub fn open_existing_prism_store(path: &Path) -> sc_cli::Result<RocksStore> {
+	migrated(path, RocksStore::openExisting(path))
+}
+
+fn migrated(path: &Path, store: Result<RocksStore, StoreError>) -> sc_cli::Result<RocksStore> {
+	let store = store.map_err(|e| migration_error(path, e))?;
+	let found = Migration::migrate(&store, |migration, progress| {
+		if progress.done == 0 {
+			log::info!(
+				"⬆️  Migrating the Prism store to schema version {}: {} ({} blocks)",
+				migration.to,
+				migration.description,
+				progress.total,
+			);
+		} else {
+			log::info!(
+				"⬆️  Schema version {}: {}/{} blocks migrated",
+				migration.to,
+				progress.done,
+				progress.total,
+			);
+		}
+	})
+	.map_err(|e| migration_error(path, e))?;
+	if found < SCHEMA_VERSION {
+		log::info!("⬆️  Prism store migrated from schema version {} to {}", found, SCHEMA_VERSION);
+	}
+	Ok(store)
+}
+
+fn migration_error(path: &Path, e: impl std::fmt::Display) -> sc_cli::Error {
+	sc_cli::Error::Input(format!("Prism store at {}: {}", path.display(), e))
+}
+
+/// Migrate the Prism store to the schema version of this node without starting it.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct MigratePrismCmd {
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl MigratePrismCmd {
+	/// Run the command.
+	pub fn run(&self, config: Configuration) -> sc_cli::Result<()> {
+		let path = prism_db_path(&config)?;
+		open_existing_prism_store(&path)?;
+		log::info!("✅ Prism store at {} is at schema version {}", path.display(), SCHEMA_VERSION);
+		Ok(())
+	}
+}
+
+impl CliConfiguration for MigratePrismCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+}
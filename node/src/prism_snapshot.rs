@@ -0,0 +1,110 @@
+//! `export-snapshot` and `import-snapshot`: moving the Prism DAG and ledger between nodes.
+//!
+//! A snapshot is taken at the ledger tip of an existing Prism store and written to one file whose
+//! manifest commits to everything in it. The node owning the store has to be stopped first, its
+//! database is locked while it runs. A fresh node imports it into its empty store and
+//! continues from there, see `prism_consensus::BlockTree::Snapshot` for what a snapshot holds.
+//!
+//! The manifest only vouches for the file it is in, so `import-snapshot` also takes the manifest
+//! hash `export-snapshot` logged, obtained from a node the operator trusts.
+
+use crate::{
+	prism,
+	prism_migrate::{open_existing_prism_store, open_prism_store},
+};
+use prism_consensus::BlockTree::Snapshot::{self, DEFAULT_VOTER_HISTORY};
+use sc_cli::{CliConfiguration, SharedParams};
+use sc_service::Configuration;
+use sp_core::H256;
+use std::path::PathBuf;
+
+/// Directory of the Prism store, next to the Substrate database.
+pub fn prism_db_path(config: &Configuration) -> sc_cli::Result<PathBuf> {
+	let database = config
+		.database
+		.path()
+		.ok_or_else(|| sc_cli::Error::Input("The Prism store needs an on-disk database".into()))?;
+	Ok(database.parent().unwrap_or(database).join("prism"))
+}
+
+fn snapshot_error(e: impl std::fmt::Display) -> sc_cli::Error {
+	sc_cli::Error::Input(format!("Prism snapshot: {}", e))
+}
+
+/// Export a snapshot of the Prism DAG and ledger at the ledger tip.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct ExportSnapshotCmd {
+	/// File to write the snapshot to.
+	#[clap(value_name = "PATH")]
+	pub output: PathBuf,
+
+	/// Voter blocks kept below each voter chain tip, the deepest voter chain reorg the importing
+	/// node can follow.
+	#[clap(long, value_name = "COUNT", default_value_t = DEFAULT_VOTER_HISTORY)]
+	pub voter_history: u64,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl ExportSnapshotCmd {
+	/// Run the command.
+	pub fn run(&self, config: Configuration) -> sc_cli::Result<()> {
+		let store = open_existing_prism_store(&prism_db_path(&config)?)?;
+		let snapshot = Snapshot::export(&store, self.voter_history).map_err(snapshot_error)?;
+		snapshot.writeTo(&self.output).map_err(snapshot_error)?;
+		log::info!(
+			"📦 Exported Prism snapshot at ledger level {} to {}, import it with --manifest {:?}",
+			snapshot.manifest.level,
+			self.output.display(),
+			snapshot.manifest.hash(),
+		);
+		Ok(())
+	}
+}
+
+impl CliConfiguration for ExportSnapshotCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+}
+
+/// Bootstrap an empty Prism store from a snapshot.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct ImportSnapshotCmd {
+	/// Snapshot file to import.
+	#[clap(value_name = "PATH")]
+	pub input: PathBuf,
+
+	/// Hash of the snapshot's manifest, as logged by the exporting node. The import is refused
+	/// unless the file's manifest hashes to it.
+	#[clap(long, value_name = "HASH")]
+	pub manifest: H256,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl ImportSnapshotCmd {
+	/// Run the command.
+	pub fn run(&self, config: Configuration) -> sc_cli::Result<()> {
+		let genesis = prism::genesis_blocks(&config)?;
+		let snapshot = Snapshot::Snapshot::readFrom(&self.input).map_err(snapshot_error)?;
+		let store = open_prism_store(&prism_db_path(&config)?)?;
+		Snapshot::import(&store, &snapshot, &genesis, self.manifest).map_err(snapshot_error)?;
+		log::info!(
+			"📦 Imported Prism snapshot at ledger level {}, manifest {:?}",
+			snapshot.manifest.level,
+			snapshot.manifest.hash(),
+		);
+		Ok(())
+	}
+}
+
+impl CliConfiguration for ImportSnapshotCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+}
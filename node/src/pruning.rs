@@ -0,0 +1,43 @@
+//! Pruning of the Prism store.
+//!
+//! `--pruning` covers both Substrate state and Prism transaction blocks. A node keeping the state of
+//! the last `n` blocks keeps the transaction block contents of the last `n` confirmed ledger levels,
+//! and of every level that is not final yet (`prism_consensus::BlockTree::FINALITY_DEPTH`), and an
+//! archive node keeps every transaction block. Headers, proposer and voter blocks and the
+//! ledger order are never pruned, see `prism_consensus::BlockTree::Prune`.
+
+use prism_consensus::BlockTree::Prune::{PruningMode as PrismPruning, DEFAULT_KEEP_LEVELS};
+use sc_service::PruningMode;
+
+/// The Prism pruning matching the state pruning a node runs with. Without a number the node keeps
+/// `DEFAULT_KEEP_LEVELS` levels, the same as Substrate's default for state.
+pub fn prism_pruning(state_pruning: Option<&PruningMode>) -> PrismPruning {
+	match state_pruning {
+		Some(PruningMode::ArchiveAll) | Some(PruningMode::ArchiveCanonical) => PrismPruning::Archive,
+		Some(PruningMode::Constrained(constraints)) => PrismPruning::KeepLevels(
+			constraints.max_blocks.map(u64::from).unwrap_or(DEFAULT_KEEP_LEVELS),
+		),
+		None => PrismPruning::KeepLevels(DEFAULT_KEEP_LEVELS),
+	}
+}
+
+/// `pruning` as the logs show it.
+pub fn describe(pruning: PrismPruning) -> String {
+	match pruning {
+		PrismPruning::Archive => "archive".into(),
+		PrismPruning::KeepLevels(levels) => format!("keep {} confirmed levels", levels),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn follows_state_pruning() {
+		assert_eq!(prism_pruning(None), PrismPruning::KeepLevels(DEFAULT_KEEP_LEVELS));
+		assert_eq!(prism_pruning(Some(&PruningMode::ArchiveAll)), PrismPruning::Archive);
+		assert_eq!(prism_pruning(Some(&PruningMode::ArchiveCanonical)), PrismPruning::Archive);
+		assert_eq!(prism_pruning(Some(&PruningMode::blocks_pruning(1000))), PrismPruning::KeepLevels(1000));
+	}
+}
@@ -1,7 +1,20 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
+use crate::{
+	chain_spec,
+	falcon_key::FalconKeyPair,
+	falcon_verifier::{FalconBatchImport, FalconChecker, FalconPoolApi, FalconVerifiedFactory},
+	mining::Miner,
+	network::{
+		self,
+		compact::{self, CompactRelay},
+		SymbolGossip,
+	},
+	prism::{self, PrismImport},
+};
 use Argonaut_Runtime::{self, opaque::Block, RuntimeApi};
-use sc_client_api::{BlockBackend, ExecutorProvider};
+use primitives::batch::{BatchVerifier, DEFAULT_BATCH_SIZE};
+use sc_client_api::{BlockBackend, ExecutorProvider, UsageProvider};
 use sc_consensus_aura::{ImportQueueParams, SlotProportion, StartAuraParams};
 pub use sc_executor::NativeElseWasmExecutor;
 use sc_finality_grandpa::SharedVoterState;
@@ -11,16 +24,23 @@ use sc_telemetry::{Telemetry, TelemetryWorker};
 use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
 use std::{sync::Arc, time::Duration};
 
+// Blocks waiting for the announcer
+const ANNOUNCE_QUEUE: usize = 64;
+
 // Our native executor instance.
 pub struct ExecutorDispatch;
 
 impl sc_executor::NativeExecutionDispatch for ExecutorDispatch {
 	/// Only enable the benchmarking host functions when we actually want to benchmark.
 	#[cfg(feature = "runtime-benchmarks")]
-	type ExtendHostFunctions = frame_benchmarking::benchmarking::HostFunctions;
-	/// Otherwise we only use the default Substrate host functions.
+	type ExtendHostFunctions = (
+		frame_benchmarking::benchmarking::HostFunctions,
+		primitives::falcon_crypto::HostFunctions,
+	);
+	/// Otherwise the default Substrate host functions and the Falcon verification the runtime
+	/// calls out to.
 	#[cfg(not(feature = "runtime-benchmarks"))]
-	type ExtendHostFunctions = ();
+	type ExtendHostFunctions = primitives::falcon_crypto::HostFunctions;
 
 	fn dispatch(method: &str, data: &[u8]) -> Option<Vec<u8>> {
 		Argonaut_Runtime::api::dispatch(method, data)
@@ -35,6 +55,7 @@ pub(crate) type FullClient =
 	sc_service::TFullClient<Block, RuntimeApi, NativeElseWasmExecutor<ExecutorDispatch>>;
 type FullBackend = sc_service::TFullBackend<Block>;
 type FullSelectChain = sc_consensus::LongestChain<FullBackend, Block>;
+pub(crate) type FullPool = sc_transaction_pool::BasicPool<FalconPoolApi, Block>;
 
 pub fn new_partial(
 	config: &Configuration,
@@ -44,7 +65,7 @@ pub fn new_partial(
 		FullBackend,
 		FullSelectChain,
 		sc_consensus::DefaultImportQueue<Block, FullClient>,
-		sc_transaction_pool::FullPool<Block, FullClient>,
+		FullPool,
 		(
 			sc_finality_grandpa::GrandpaBlockImport<
 				FullBackend,
@@ -95,12 +116,30 @@ pub fn new_partial(
 
 	let select_chain = sc_consensus::LongestChain::new(backend.clone());
 
-	let transaction_pool = sc_transaction_pool::BasicPool::new_full(
+	// Falcon signatures are checked in parallel on one shared pool, by both the transaction pool
+	// and block import, and the runtime looks up what was verified there.
+	let falcon_verifier = Arc::new(
+		BatchVerifier::new(0, DEFAULT_BATCH_SIZE)
+			.map_err(|e| ServiceError::Other(format!("Falcon verifier thread pool: {}", e)))?,
+	);
+	client
+		.execution_extensions()
+		.set_extensions_factory(Box::new(FalconVerifiedFactory(falcon_verifier.clone())));
+	let falcon_checker = Arc::new(FalconChecker::new(client.clone(), falcon_verifier.clone()));
+
+	let pool_api = sc_transaction_pool::FullChainApi::new(
+		client.clone(),
+		config.prometheus_registry(),
+		&task_manager.spawn_essential_handle(),
+	);
+	let transaction_pool = sc_transaction_pool::BasicPool::with_revalidation_type(
 		config.transaction_pool.clone(),
 		config.role.is_authority().into(),
+		Arc::new(FalconPoolApi::new(pool_api, falcon_checker.clone(), falcon_verifier)),
 		config.prometheus_registry(),
+		sc_transaction_pool::RevalidationType::Full,
 		task_manager.spawn_essential_handle(),
-		client.clone(),
+		client.usage_info().chain.best_number,
 	);
 
 	let (grandpa_block_import, grandpa_link) = sc_finality_grandpa::block_import(
@@ -114,7 +153,7 @@ pub fn new_partial(
 
 	let import_queue =
 		sc_consensus_aura::import_queue::<AuraPair, _, _, _, _, _, _>(ImportQueueParams {
-			block_import: grandpa_block_import.clone(),
+			block_import: FalconBatchImport::new(grandpa_block_import.clone(), falcon_checker),
 			justification_import: Some(Box::new(grandpa_block_import.clone())),
 			client: client.clone(),
 			create_inherent_data_providers: move |_, ()| async move {
@@ -156,8 +195,14 @@ fn remote_keystore(_url: &String) -> Result<Arc<LocalKeystore>, &'static str> {
 	Err("Remote Keystore not supported.")
 }
 
-/// Builds a new service for a full client.
-pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError> {
+/// Builds a new service for a full client. `falcon_key` is the decrypted `--falcon-key-file`, its
+/// account is the mining coinbase and it signs the miner seal. `prism_import` validates the Prism
+/// blocks the network completes and inserts them into the node's block tree.
+pub fn new_full(
+	mut config: Configuration,
+	falcon_key: Option<FalconKeyPair>,
+	prism_import: Option<Arc<PrismImport>>,
+) -> Result<TaskManager, ServiceError> {
 	let sc_service::PartialComponents {
 		client,
 		backend,
@@ -188,6 +233,9 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		.network
 		.extra_sets
 		.push(sc_finality_grandpa::grandpa_peers_set_config(grandpa_protocol_name.clone()));
+	config.network.extra_sets.push(network::symbol_peers_set_config());
+	let (compact_relay_config, compact_relay_requests) = compact::compact_relay_config();
+	config.network.request_response_protocols.push(compact_relay_config);
 	let warp_sync = Arc::new(sc_finality_grandpa::warp_proof::NetworkProvider::new(
 		backend.clone(),
 		grandpa_link.shared_authority_set().clone(),
@@ -205,6 +253,45 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 			warp_sync: Some(warp_sync),
 		})?;
 
+	// transaction block symbols are relayed by every node, whether it mines or not
+	let symbol_gossip = SymbolGossip::new(network.clone(), config.prometheus_registry());
+	task_manager.spawn_handle().spawn("prism-symbol-gossip", None, symbol_gossip.clone().run());
+	let voter_chains =
+		chain_spec::prism_genesis(config.chain_spec.as_ref()).map_err(ServiceError::Other)?.voter_chains;
+	let (relayed_blocks, mut relayed) = futures::channel::mpsc::channel(compact::MAX_PENDING);
+	task_manager.spawn_handle().spawn(
+		"prism-compact-relay",
+		None,
+		compact::run_compact_relay(
+			CompactRelay::new(transaction_pool.clone(), voter_chains),
+			compact_relay_requests,
+			symbol_gossip.clone(),
+			relayed_blocks,
+		),
+	);
+	// mined blocks and those imported from the relay are announced to every peer
+	let (announced_blocks, announced) = futures::channel::mpsc::channel(ANNOUNCE_QUEUE);
+	task_manager.spawn_handle().spawn(
+		"prism-block-announcer",
+		None,
+		network::run_block_announcer(symbol_gossip, network.clone(), announced),
+	);
+	let mining_import = prism_import.clone();
+	match prism_import {
+		// an import blocks on Falcon verification and the synced store write, keep it off the executor
+		Some(import) => task_manager.spawn_handle().spawn_blocking(
+			"prism-relayed-blocks",
+			None,
+			prism::import_relayed_blocks(import, relayed, announced_blocks.clone()),
+		),
+		// without a Prism store relayed blocks are only logged
+		None => task_manager.spawn_handle().spawn("prism-relayed-blocks", None, async move {
+			while let Some(block) = futures::StreamExt::next(&mut relayed).await {
+				log::debug!(target: "prism", "Relayed block {}", block.hash());
+			}
+		}),
+	}
+
 	if config.offchain_worker.enabled {
 		sc_service::build_offchain_workers(
 			&config,
@@ -215,10 +302,10 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 	}
 
 	let role = config.role.clone();
-	let force_authoring = config.force_authoring;
-	let backoff_authoring_blocks: Option<()> = None;
 	let name = config.network.node_name.clone();
 	let enable_grandpa = !config.disable_grandpa;
+	let force_authoring = config.force_authoring;
+	let backoff_authoring_blocks: Option<()> = None;
 	let prometheus_registry = config.prometheus_registry().cloned();
 
 	let rpc_extensions_builder = {
@@ -245,6 +332,30 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		telemetry: telemetry.as_mut(),
 	})?;
 
+	// mined Prism blocks pay the Falcon key's account and carry its seal, there is no mining
+	// without one
+	match (falcon_key, mining_import) {
+		(Some(key), Some(import)) if role.is_authority() => {
+			log::info!("⛏  Mining coinbase: {}", key.address());
+			let miner = Miner::new(import, transaction_pool.clone(), key, announced_blocks);
+			// the miner is considered essential, i.e. if it fails we take down the service with it.
+			task_manager.spawn_essential_handle().spawn_blocking(
+				"prism-miner",
+				Some("block-authoring"),
+				async move {
+					if let Err(e) = miner.run().await {
+						log::error!(target: "prism", "⛏  Miner stopped: {:?}", e);
+					}
+				},
+			);
+		},
+		(None, _) if role.is_authority() =>
+			log::warn!("⛏  No --falcon-key-file given, this node does not mine"),
+		(Some(_), None) if role.is_authority() =>
+			log::warn!("⛏  No Prism store, this node does not mine"),
+		_ => {},
+	}
+
 	if role.is_authority() {
 		let proposer_factory = sc_basic_authorship::ProposerFactory::new(
 			task_manager.spawn_handle(),
@@ -0,0 +1,313 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Migration of classical (sr25519) accounts onto Falcon accounts.
+//!
+//! A classical account signs a `migrate` extrinsic carrying a Falcon public key and a Falcon
+//! signature over the migration payload. That proves ownership of both keys, after which the
+//! free balance and the nonce of the classical account are swept onto the Falcon key's account,
+//! the one a `Signature::Falcon` by that key signs for (see [`primitives::falcon::falcon_account`]),
+//! and the classical account is retired. Reserved and locked funds belong to whichever pallet
+//! reserved or locked them, so an account holding any has to release them before it migrates.
+//! Governance sets a deadline, past it any classical account that has not migrated is frozen by
+//! [`CheckMigrated`].
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+use codec::{Decode, Encode};
+use frame_support::weights::DispatchInfo;
+use scale_info::TypeInfo;
+use sp_runtime::{
+	traits::{DispatchInfoOf, Dispatchable, SignedExtension},
+	transaction_validity::{
+		InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction,
+	},
+};
+use sp_std::{fmt::Debug, marker::PhantomData};
+
+// Domain tag of the payload the Falcon key signs when migrating
+pub const MIGRATION_CONTEXT: &[u8] = b"argonaut/falcon-migration/v1";
+
+// Custom `InvalidTransaction` codes returned by `CheckMigrated`
+pub const FROZEN_ACCOUNT: u8 = 1;
+pub const RETIRED_ACCOUNT: u8 = 2;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use frame_support::{
+		pallet_prelude::*,
+		traits::{Currency, ExistenceRequirement, ReservableCurrency, WithdrawReasons},
+	};
+	use frame_system::pallet_prelude::*;
+	use primitives::{falcon::falcon_account, FalconHostFunctions};
+	use sp_runtime::traits::Zero;
+	use sp_std::prelude::*;
+
+	pub(crate) type BalanceOf<T> =
+		<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching event type.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		/// The currency holding the balances that get swept.
+		type Currency: ReservableCurrency<Self::AccountId>;
+		/// Origin allowed to set the deadline and register classical accounts.
+		type GovernanceOrigin: EnsureOrigin<Self::Origin>;
+	}
+
+	/// Block after which classical accounts that have not migrated are frozen.
+	#[pallet::storage]
+	#[pallet::getter(fn deadline)]
+	pub type Deadline<T: Config> = StorageValue<_, T::BlockNumber, OptionQuery>;
+
+	/// Classical accounts that still have to migrate.
+	#[pallet::storage]
+	pub type Pending<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, (), OptionQuery>;
+
+	/// Classical accounts that have migrated, mapped to the Falcon account they moved to.
+	#[pallet::storage]
+	#[pallet::getter(fn migrated)]
+	pub type Migrated<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, T::AccountId, OptionQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		/// Classical accounts present at genesis, e.g. the endowed testnet accounts.
+		pub classical_accounts: Vec<T::AccountId>,
+		pub deadline: Option<T::BlockNumber>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self { classical_accounts: Vec::new(), deadline: None }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+		fn build(&self) {
+			for account in &self.classical_accounts {
+				Pending::<T>::insert(account, ());
+			}
+			if let Some(deadline) = self.deadline {
+				Deadline::<T>::put(deadline);
+			}
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A classical account moved its balance and nonce onto a Falcon account.
+		AccountMigrated { classical: T::AccountId, falcon: T::AccountId, amount: BalanceOf<T> },
+		/// The migration deadline was changed. [deadline]
+		DeadlineSet { deadline: Option<T::BlockNumber> },
+		/// Classical accounts were added to the set that has to migrate. [count]
+		ClassicalAccountsRegistered { count: u32 },
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The Falcon signature does not verify against the given public key.
+		InvalidFalconProof,
+		/// The Falcon public key does not derive a valid account id.
+		InvalidFalconKey,
+		/// The account has already been migrated.
+		AlreadyMigrated,
+		/// The Falcon account is the migrating account itself.
+		SameAccount,
+		/// The deadline has passed and the account is frozen.
+		Frozen,
+		/// The account has reserved funds, they have to be unreserved before migrating.
+		ReservedBalance,
+		/// Part of the free balance is locked, the lock has to be removed before migrating.
+		LockedBalance,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Prove ownership of `falcon_public` and sweep the caller's balance and nonce onto the
+		/// account derived from it. `falcon_signature` is a detached Falcon signature over
+		/// [`Pallet::migration_payload`] for the caller.
+		// Falcon verification dominates, it is far heavier than a regular signature check
+		#[pallet::weight(50_000_000 + T::DbWeight::get().reads_writes(6, 5))]
+		pub fn migrate(
+			origin: OriginFor<T>,
+			falcon_public: Vec<u8>,
+			falcon_signature: Vec<u8>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(!Migrated::<T>::contains_key(&who), Error::<T>::AlreadyMigrated);
+			ensure!(!Self::is_frozen(&who), Error::<T>::Frozen);
+
+			let payload = Self::migration_payload(&who);
+			ensure!(
+				FalconHostFunctions::verify_bytes(&falcon_signature, &payload, &falcon_public),
+				Error::<T>::InvalidFalconProof
+			);
+			let account = falcon_account(&falcon_public).encode();
+			let falcon = T::AccountId::decode(&mut &account[..])
+				.map_err(|_| Error::<T>::InvalidFalconKey)?;
+			ensure!(falcon != who, Error::<T>::SameAccount);
+
+			ensure!(T::Currency::reserved_balance(&who).is_zero(), Error::<T>::ReservedBalance);
+			let amount = T::Currency::free_balance(&who);
+			if !amount.is_zero() {
+				T::Currency::ensure_can_withdraw(&who, amount, WithdrawReasons::all(), Zero::zero())
+					.map_err(|_| Error::<T>::LockedBalance)?;
+			}
+
+			//read the nonce first, sweeping the whole balance reaps the classical account
+			let nonce = frame_system::Pallet::<T>::account_nonce(&who);
+			if !amount.is_zero() {
+				T::Currency::transfer(&who, &falcon, amount, ExistenceRequirement::AllowDeath)?;
+			}
+			//the falcon account must never be able to replay anything signed at a lower nonce. Only
+			//an account that exists gets it, writing a nonce alone would create one without providers
+			if frame_system::Pallet::<T>::account_exists(&falcon) {
+				frame_system::Account::<T>::mutate(&falcon, |account| {
+					if account.nonce < nonce {
+						account.nonce = nonce;
+					}
+				});
+			}
+
+			Pending::<T>::remove(&who);
+			Migrated::<T>::insert(&who, &falcon);
+			Self::deposit_event(Event::AccountMigrated { classical: who, falcon, amount });
+			Ok(())
+		}
+
+		/// Set or clear the block after which unmigrated classical accounts are frozen.
+		#[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
+		pub fn set_deadline(origin: OriginFor<T>, deadline: Option<T::BlockNumber>) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+			match deadline {
+				Some(deadline) => Deadline::<T>::put(deadline),
+				None => Deadline::<T>::kill(),
+			}
+			Self::deposit_event(Event::DeadlineSet { deadline });
+			Ok(())
+		}
+
+		/// Mark classical accounts created after genesis as having to migrate.
+		#[pallet::weight(10_000 + T::DbWeight::get().writes(accounts.len() as u64))]
+		pub fn register_classical(origin: OriginFor<T>, accounts: Vec<T::AccountId>) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+			let count = accounts.len() as u32;
+			for account in accounts {
+				if !Migrated::<T>::contains_key(&account) {
+					Pending::<T>::insert(&account, ());
+				}
+			}
+			Self::deposit_event(Event::ClassicalAccountsRegistered { count });
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The bytes the Falcon key signs to migrate `who`. Binding the genesis hash keeps a proof
+		/// from being replayed on another network.
+		pub fn migration_payload(who: &T::AccountId) -> Vec<u8> {
+			let genesis = frame_system::Pallet::<T>::block_hash(T::BlockNumber::zero());
+			(MIGRATION_CONTEXT, genesis, who).encode()
+		}
+
+		/// Whether `who` is a classical account that missed the deadline.
+		pub fn is_frozen(who: &T::AccountId) -> bool {
+			match Deadline::<T>::get() {
+				Some(deadline) =>
+					frame_system::Pallet::<T>::block_number() > deadline &&
+						Pending::<T>::contains_key(who),
+				None => false,
+			}
+		}
+
+		/// Whether `who` is a classical account that already moved to a Falcon key.
+		pub fn is_retired(who: &T::AccountId) -> bool {
+			Migrated::<T>::contains_key(who)
+		}
+	}
+}
+
+/// Rejects transactions from classical accounts that have been retired by a migration or that
+/// are frozen because they missed the deadline.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct CheckMigrated<T: Config + Send + Sync>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> CheckMigrated<T> {
+	pub fn new() -> Self {
+		Self(PhantomData)
+	}
+}
+
+impl<T: Config + Send + Sync> Default for CheckMigrated<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: Config + Send + Sync> Debug for CheckMigrated<T> {
+	#[cfg(feature = "std")]
+	fn fmt(&self, f: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+		write!(f, "CheckMigrated")
+	}
+
+	#[cfg(not(feature = "std"))]
+	fn fmt(&self, _: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+		Ok(())
+	}
+}
+
+impl<T: Config + Send + Sync> SignedExtension for CheckMigrated<T>
+where
+	<T as frame_system::Config>::Call: Dispatchable<Info = DispatchInfo>,
+{
+	const IDENTIFIER: &'static str = "CheckMigrated";
+	type AccountId = T::AccountId;
+	type Call = <T as frame_system::Config>::Call;
+	type AdditionalSigned = ();
+	type Pre = ();
+
+	fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+		Ok(())
+	}
+
+	fn validate(
+		&self,
+		who: &Self::AccountId,
+		_call: &Self::Call,
+		_info: &DispatchInfoOf<Self::Call>,
+		_len: usize,
+	) -> TransactionValidity {
+		if Pallet::<T>::is_retired(who) {
+			return Err(InvalidTransaction::Custom(RETIRED_ACCOUNT).into())
+		}
+		if Pallet::<T>::is_frozen(who) {
+			return Err(InvalidTransaction::Custom(FROZEN_ACCOUNT).into())
+		}
+		Ok(ValidTransaction::default())
+	}
+
+	fn pre_dispatch(
+		self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		info: &DispatchInfoOf<Self::Call>,
+		len: usize,
+	) -> Result<(), TransactionValidityError> {
+		self.validate(who, call, info, len).map(|_| ())
+	}
+}
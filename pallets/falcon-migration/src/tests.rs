@@ -0,0 +1,196 @@
+use crate::{mock::*, CheckMigrated, Error, Event as MigrationEvent, FROZEN_ACCOUNT, RETIRED_ACCOUNT};
+use codec::Encode;
+use frame_support::{
+	assert_noop, assert_ok,
+	traits::{LockableCurrency, ReservableCurrency, WithdrawReasons},
+	weights::DispatchInfo,
+};
+use pqcrypto_traits::sign::{DetachedSignature as _, PublicKey as _};
+use primitives::{falcon::FalconSignature, signature::Signature, FalconHostFunctions};
+use sp_core::crypto::AccountId32;
+use sp_runtime::{
+	traits::{Dispatchable, SignedExtension, Verify},
+	transaction_validity::InvalidTransaction,
+};
+
+// Falcon key and proof that migrate `who` onto the key's account
+fn falcon_proof(who: &AccountId32) -> (Vec<u8>, Vec<u8>, AccountId32) {
+	let (pk, sk) = FalconHostFunctions::generate_keypair();
+	let payload = FalconMigration::migration_payload(who);
+	let signature = FalconHostFunctions::detached_signature(&payload, &sk);
+	let account = AccountId32::from(FalconHostFunctions::hash_public_key(&pk));
+	(pk.as_bytes().to_vec(), signature.as_bytes().to_vec(), account)
+}
+
+#[test]
+fn migrate_sweeps_balance_and_nonce() {
+	new_test_ext().execute_with(|| {
+		frame_system::Pallet::<Test>::inc_account_nonce(&ALICE);
+		frame_system::Pallet::<Test>::inc_account_nonce(&ALICE);
+		let (public, signature, falcon) = falcon_proof(&ALICE);
+
+		assert_ok!(FalconMigration::migrate(Origin::signed(ALICE), public, signature));
+
+		assert_eq!(Balances::free_balance(&ALICE), 0);
+		assert_eq!(Balances::free_balance(&falcon), INITIAL_BALANCE);
+		assert_eq!(System::account_nonce(&falcon), 2);
+		assert_eq!(FalconMigration::migrated(&ALICE), Some(falcon.clone()));
+		System::assert_last_event(
+			MigrationEvent::AccountMigrated { classical: ALICE, falcon, amount: INITIAL_BALANCE }
+				.into(),
+		);
+	});
+}
+
+#[test]
+fn nothing_created_without_funds() {
+	new_test_ext().execute_with(|| {
+		//an account that never held anything has nothing to sweep and no account to give a nonce
+		let carol = AccountId32::new([3u8; 32]);
+		let (public, signature, falcon) = falcon_proof(&carol);
+
+		assert_ok!(FalconMigration::migrate(Origin::signed(carol.clone()), public, signature));
+
+		assert!(!System::account_exists(&falcon));
+		assert!(!frame_system::Account::<Test>::contains_key(&falcon));
+		assert_eq!(FalconMigration::migrated(&carol), Some(falcon));
+	});
+}
+
+#[test]
+fn reserved_balance_is_rejected() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Balances::reserve(&ALICE, 1_000));
+		let (public, signature, falcon) = falcon_proof(&ALICE);
+
+		assert_noop!(
+			FalconMigration::migrate(Origin::signed(ALICE), public, signature),
+			Error::<Test>::ReservedBalance
+		);
+		assert_eq!(Balances::reserved_balance(&ALICE), 1_000);
+		assert!(!System::account_exists(&falcon));
+	});
+}
+
+#[test]
+fn locked_balance_is_rejected() {
+	new_test_ext().execute_with(|| {
+		Balances::set_lock(*b"staking ", &ALICE, 1_000, WithdrawReasons::all());
+		let (public, signature, falcon) = falcon_proof(&ALICE);
+
+		assert_noop!(
+			FalconMigration::migrate(Origin::signed(ALICE), public.clone(), signature.clone()),
+			Error::<Test>::LockedBalance
+		);
+		assert!(!System::account_exists(&falcon));
+
+		Balances::remove_lock(*b"staking ", &ALICE);
+		assert_ok!(FalconMigration::migrate(Origin::signed(ALICE), public, signature));
+		assert_eq!(Balances::free_balance(&falcon), INITIAL_BALANCE);
+	});
+}
+
+#[test]
+fn migrated_account_spends_with_its_falcon_key() {
+	new_test_ext().execute_with(|| {
+		let (pk, sk) = FalconHostFunctions::generate_keypair();
+		let proof = FalconHostFunctions::detached_signature(&FalconMigration::migration_payload(&ALICE), &sk);
+		assert_ok!(FalconMigration::migrate(
+			Origin::signed(ALICE),
+			pk.as_bytes().to_vec(),
+			proof.as_bytes().to_vec()
+		));
+		let falcon = FalconMigration::migrated(&ALICE).unwrap();
+
+		//the chain's signature type accepts the Falcon key's signature for the account the funds
+		//were swept to, and for no other
+		let transfer = Call::Balances(pallet_balances::Call::transfer { dest: BOB, value: 1_000 });
+		let payload = transfer.encode();
+		let signature = Signature::from(FalconSignature::sign(&payload, &pk, &sk));
+		assert!(signature.verify(&payload[..], &falcon));
+		assert!(!signature.verify(&payload[..], &ALICE));
+
+		assert_ok!(transfer.dispatch(Origin::signed(falcon.clone())));
+		assert_eq!(Balances::free_balance(&falcon), INITIAL_BALANCE - 1_000);
+		assert_eq!(Balances::free_balance(&BOB), INITIAL_BALANCE + 1_000);
+	});
+}
+
+#[test]
+fn proof_for_another_account_is_rejected() {
+	new_test_ext().execute_with(|| {
+		let (public, signature, _) = falcon_proof(&BOB);
+		assert_noop!(
+			FalconMigration::migrate(Origin::signed(ALICE), public, signature),
+			Error::<Test>::InvalidFalconProof
+		);
+	});
+}
+
+#[test]
+fn cannot_migrate_twice() {
+	new_test_ext().execute_with(|| {
+		let (public, signature, _) = falcon_proof(&ALICE);
+		assert_ok!(FalconMigration::migrate(Origin::signed(ALICE), public.clone(), signature.clone()));
+		assert_noop!(
+			FalconMigration::migrate(Origin::signed(ALICE), public, signature),
+			Error::<Test>::AlreadyMigrated
+		);
+	});
+}
+
+#[test]
+fn deadline_freezes_unmigrated_accounts() {
+	new_test_ext().execute_with(|| {
+		let (public, signature, _) = falcon_proof(&ALICE);
+		assert_ok!(FalconMigration::migrate(Origin::signed(ALICE), public, signature));
+
+		System::set_block_number(101);
+		assert!(FalconMigration::is_frozen(&BOB));
+		assert!(!FalconMigration::is_frozen(&ALICE));
+
+		let (public, signature, _) = falcon_proof(&BOB);
+		assert_noop!(
+			FalconMigration::migrate(Origin::signed(BOB), public, signature),
+			Error::<Test>::Frozen
+		);
+	});
+}
+
+#[test]
+fn only_governance_sets_the_deadline() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			FalconMigration::set_deadline(Origin::signed(ALICE), None),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_ok!(FalconMigration::set_deadline(Origin::root(), Some(10)));
+		assert_eq!(FalconMigration::deadline(), Some(10));
+		assert_ok!(FalconMigration::set_deadline(Origin::root(), None));
+		assert_eq!(FalconMigration::deadline(), None);
+	});
+}
+
+#[test]
+fn signed_extension_rejects_retired_and_frozen_accounts() {
+	new_test_ext().execute_with(|| {
+		let call = Call::System(frame_system::Call::remark { remark: vec![] });
+		let info = DispatchInfo::default();
+		let check = CheckMigrated::<Test>::new();
+		assert!(check.validate(&BOB, &call, &info, 0).is_ok());
+
+		let (public, signature, falcon) = falcon_proof(&ALICE);
+		assert_ok!(FalconMigration::migrate(Origin::signed(ALICE), public, signature));
+		assert_eq!(
+			check.validate(&ALICE, &call, &info, 0),
+			Err(InvalidTransaction::Custom(RETIRED_ACCOUNT).into())
+		);
+		assert!(check.validate(&falcon, &call, &info, 0).is_ok());
+
+		System::set_block_number(101);
+		assert_eq!(
+			check.validate(&BOB, &call, &info, 0),
+			Err(InvalidTransaction::Custom(FROZEN_ACCOUNT).into())
+		);
+	});
+}
@@ -0,0 +1,436 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! m-of-n multisig accounts whose members are Falcon public keys.
+//!
+//! The account of a multisig is derived from its threshold and the sorted blake3 hashes of its
+//! members' Falcon keys (see [`primitives::multisig::multisig_account`]), so it can be computed
+//! off-chain before it is registered. A call is proposed against the multisig, then each member
+//! submits a Falcon signature over the approval payload of the call hash. Approvals prove
+//! membership on their own, anyone can relay them. The call is dispatched from the multisig
+//! account as soon as the threshold is reached.
+//!
+//! Proposing reserves `ProposalDeposit` from the proposer until the proposal is executed,
+//! cancelled, withdrawn or removed after it expired, and a multisig has at most `MaxProposals`
+//! open at a time.
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+// Domain tags of the payloads members sign
+pub const APPROVAL_CONTEXT: &[u8] = b"argonaut/falcon-multisig/approve/v1";
+pub const CANCEL_CONTEXT: &[u8] = b"argonaut/falcon-multisig/cancel/v1";
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::{APPROVAL_CONTEXT, CANCEL_CONTEXT};
+	use codec::{Decode, Encode};
+	use frame_support::{
+		dispatch::{DispatchResult, GetDispatchInfo, PostDispatchInfo},
+		pallet_prelude::*,
+		traits::{Currency, ReservableCurrency},
+		weights::extract_actual_weight,
+		CloneNoBound, EqNoBound, PartialEqNoBound, RuntimeDebugNoBound,
+	};
+	use frame_system::{pallet_prelude::*, RawOrigin};
+	use primitives::{multisig, FalconHostFunctions};
+	use scale_info::TypeInfo;
+	use sp_runtime::traits::{Dispatchable, Hash, Saturating, Zero};
+	use sp_std::prelude::*;
+
+	// Weight of an approval without the call it may dispatch, Falcon verification dominates
+	const APPROVE_WEIGHT: Weight = 50_000_000;
+	// Weight `create` adds per member, for sorting them and hashing them into the account
+	const CREATE_MEMBER_WEIGHT: Weight = 1_000_000;
+
+	pub(crate) type BalanceOf<T> =
+		<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
+	/// Members and threshold of a registered multisig.
+	#[derive(
+		CloneNoBound, Encode, Decode, PartialEqNoBound, EqNoBound, RuntimeDebugNoBound, TypeInfo, MaxEncodedLen,
+	)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct MultisigInfo<T: Config> {
+		/// Sorted blake3 hashes of the members' Falcon public keys.
+		pub members: BoundedVec<[u8; 32], T::MaxMembers>,
+		/// Number of approvals a call needs.
+		pub threshold: u16,
+	}
+
+	/// A call waiting for approvals, stored under its multisig, proposer and call hash.
+	#[derive(
+		CloneNoBound, Encode, Decode, PartialEqNoBound, EqNoBound, RuntimeDebugNoBound, TypeInfo, MaxEncodedLen,
+	)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct Proposal<T: Config> {
+		/// Index of the proposal within its multisig, signed along with the call hash so an
+		/// approval can never be replayed onto a later proposal of the same call.
+		pub index: u32,
+		/// The encoded call.
+		pub call: BoundedVec<u8, T::MaxCallSize>,
+		/// Deposit reserved from the proposer, returned when the proposal is closed.
+		pub deposit: BalanceOf<T>,
+		/// Last block the proposal can be approved in.
+		pub expiry: T::BlockNumber,
+		/// Members that approved so far.
+		pub approvals: BoundedVec<[u8; 32], T::MaxMembers>,
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching event type.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		/// The calls a multisig can dispatch.
+		type Call: Parameter
+			+ Dispatchable<Origin = Self::Origin, PostInfo = PostDispatchInfo>
+			+ GetDispatchInfo
+			+ From<frame_system::Call<Self>>;
+		/// The currency proposal deposits are reserved in.
+		type Currency: ReservableCurrency<Self::AccountId>;
+		/// Maximum number of members of a multisig.
+		#[pallet::constant]
+		type MaxMembers: Get<u32>;
+		/// Maximum size of an encoded proposed call.
+		#[pallet::constant]
+		type MaxCallSize: Get<u32>;
+		/// Maximum number of open proposals of one multisig.
+		#[pallet::constant]
+		type MaxProposals: Get<u32>;
+		/// Deposit reserved from the proposer while a proposal is open.
+		#[pallet::constant]
+		type ProposalDeposit: Get<BalanceOf<Self>>;
+		/// Number of blocks a proposal can be approved for.
+		#[pallet::constant]
+		type ProposalLifetime: Get<Self::BlockNumber>;
+	}
+
+	/// Registered multisigs by account.
+	#[pallet::storage]
+	#[pallet::getter(fn multisig)]
+	pub type Multisigs<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, MultisigInfo<T>, OptionQuery>;
+
+	/// Open proposals of a multisig, by proposer and call hash.
+	#[pallet::storage]
+	#[pallet::getter(fn proposal)]
+	pub type Proposals<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		(T::AccountId, T::Hash),
+		Proposal<T>,
+		OptionQuery,
+	>;
+
+	/// Number of open proposals of a multisig.
+	#[pallet::storage]
+	#[pallet::getter(fn open_proposals)]
+	pub type OpenProposals<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
+	/// Index the next proposal of a multisig gets.
+	#[pallet::storage]
+	pub type NextProposalIndex<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A multisig was registered.
+		MultisigCreated { multisig: T::AccountId, threshold: u16, members: u32 },
+		/// A call was proposed to a multisig.
+		Proposed {
+			multisig: T::AccountId,
+			proposer: T::AccountId,
+			call_hash: T::Hash,
+			index: u32,
+			expiry: T::BlockNumber,
+		},
+		/// A member approved a proposal.
+		Approved { multisig: T::AccountId, call_hash: T::Hash, member: [u8; 32], approvals: u32 },
+		/// A proposal reached its threshold and was dispatched.
+		Executed { multisig: T::AccountId, call_hash: T::Hash, result: DispatchResult },
+		/// A member cancelled a proposal.
+		Cancelled { multisig: T::AccountId, call_hash: T::Hash, member: [u8; 32] },
+		/// The proposer withdrew a proposal.
+		Withdrawn { multisig: T::AccountId, proposer: T::AccountId, call_hash: T::Hash },
+		/// An expired proposal was removed.
+		Expired { multisig: T::AccountId, proposer: T::AccountId, call_hash: T::Hash },
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// More members than `MaxMembers`.
+		TooManyMembers,
+		/// The threshold is zero or above the number of members.
+		InvalidThreshold,
+		/// The derived account id is not a valid `AccountId`.
+		InvalidAccount,
+		/// The multisig is already registered.
+		AlreadyExists,
+		/// No multisig is registered under this account.
+		UnknownMultisig,
+		/// The encoded call is larger than `MaxCallSize`.
+		CallTooLarge,
+		/// The proposer already proposed the same call.
+		ProposalExists,
+		/// The multisig already has `MaxProposals` open proposals.
+		TooManyProposals,
+		/// No such proposal.
+		UnknownProposal,
+		/// The proposal expired and can only be removed.
+		ProposalExpired,
+		/// The proposal has not expired yet.
+		ProposalNotExpired,
+		/// The Falcon key is not a member of the multisig.
+		NotMember,
+		/// The member already approved this proposal.
+		AlreadyApproved,
+		/// The Falcon signature does not verify against the given public key.
+		InvalidFalconSignature,
+		/// The call weighs more than the `max_weight` the final approval allows.
+		MaxWeightTooLow,
+		/// The stored call could not be decoded.
+		UndecodableCall,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Register the multisig of `threshold` out of `members`, each the blake3 hash of a
+		/// Falcon public key. Member order and duplicates do not matter, more than `MaxMembers`
+		/// entries do not even decode.
+		#[pallet::weight(
+			10_000 +
+				CREATE_MEMBER_WEIGHT.saturating_mul(members.len() as Weight) +
+				T::DbWeight::get().reads_writes(1, 1)
+		)]
+		pub fn create(
+			origin: OriginFor<T>,
+			members: BoundedVec<[u8; 32], T::MaxMembers>,
+			threshold: u16,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
+			let members: BoundedVec<_, T::MaxMembers> = multisig::normalize_members(members.into_inner())
+				.try_into()
+				.map_err(|_| Error::<T>::TooManyMembers)?;
+			ensure!(threshold > 0 && threshold as usize <= members.len(), Error::<T>::InvalidThreshold);
+
+			let account = multisig::multisig_account(&members, threshold);
+			let account = T::AccountId::decode(&mut &account[..]).map_err(|_| Error::<T>::InvalidAccount)?;
+			ensure!(!Multisigs::<T>::contains_key(&account), Error::<T>::AlreadyExists);
+
+			let count = members.len() as u32;
+			Multisigs::<T>::insert(&account, MultisigInfo { members, threshold });
+			Self::deposit_event(Event::MultisigCreated { multisig: account, threshold, members: count });
+			Ok(())
+		}
+
+		/// Propose `call` to be dispatched from `multisig` once enough members approve it. Reserves
+		/// `ProposalDeposit` from the proposer, the proposal can be approved for
+		/// `ProposalLifetime` blocks.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(5, 4))]
+		pub fn propose(
+			origin: OriginFor<T>,
+			multisig: T::AccountId,
+			call: Box<<T as Config>::Call>,
+		) -> DispatchResult {
+			let proposer = ensure_signed(origin)?;
+			ensure!(Multisigs::<T>::contains_key(&multisig), Error::<T>::UnknownMultisig);
+			let call: BoundedVec<u8, T::MaxCallSize> =
+				call.encode().try_into().map_err(|_| Error::<T>::CallTooLarge)?;
+			let call_hash = T::Hashing::hash(&call);
+			let key = (proposer.clone(), call_hash);
+			ensure!(!Proposals::<T>::contains_key(&multisig, &key), Error::<T>::ProposalExists);
+			ensure!(
+				OpenProposals::<T>::get(&multisig) < T::MaxProposals::get(),
+				Error::<T>::TooManyProposals
+			);
+
+			let deposit = T::ProposalDeposit::get();
+			T::Currency::reserve(&proposer, deposit)?;
+			let expiry = frame_system::Pallet::<T>::block_number().saturating_add(T::ProposalLifetime::get());
+			let index = NextProposalIndex::<T>::mutate(&multisig, |next| {
+				let index = *next;
+				*next = next.wrapping_add(1);
+				index
+			});
+			OpenProposals::<T>::mutate(&multisig, |open| *open += 1);
+			Proposals::<T>::insert(
+				&multisig,
+				&key,
+				Proposal { index, call, deposit, expiry, approvals: BoundedVec::default() },
+			);
+			Self::deposit_event(Event::Proposed { multisig, proposer, call_hash, index, expiry });
+			Ok(())
+		}
+
+		/// Add the approval of the member owning `falcon_public` to the proposal of `call_hash`
+		/// by `proposer`. `falcon_signature` is a detached Falcon signature over
+		/// [`Pallet::approval_payload`]. The approval reaching the threshold dispatches the call,
+		/// which may weigh at most `max_weight`.
+		#[pallet::weight(
+			APPROVE_WEIGHT + T::DbWeight::get().reads_writes(3, 3) + *max_weight
+		)]
+		pub fn approve(
+			origin: OriginFor<T>,
+			multisig: T::AccountId,
+			proposer: T::AccountId,
+			call_hash: T::Hash,
+			falcon_public: Vec<u8>,
+			falcon_signature: Vec<u8>,
+			max_weight: Weight,
+		) -> DispatchResultWithPostInfo {
+			ensure_signed(origin)?;
+			let info = Multisigs::<T>::get(&multisig).ok_or(Error::<T>::UnknownMultisig)?;
+			let key = (proposer, call_hash);
+			let mut proposal =
+				Proposals::<T>::get(&multisig, &key).ok_or(Error::<T>::UnknownProposal)?;
+			ensure!(
+				frame_system::Pallet::<T>::block_number() <= proposal.expiry,
+				Error::<T>::ProposalExpired
+			);
+			let member = Self::check_member(&info, &falcon_public)?;
+			ensure!(!proposal.approvals.contains(&member), Error::<T>::AlreadyApproved);
+			let payload = Self::approval_payload(&multisig, proposal.index, &call_hash);
+			ensure!(
+				FalconHostFunctions::verify_bytes(&falcon_signature, &payload, &falcon_public),
+				Error::<T>::InvalidFalconSignature
+			);
+
+			// a member is approving at most once, so there is room for every member
+			proposal.approvals.try_push(member).map_err(|_| Error::<T>::TooManyMembers)?;
+			let approvals = proposal.approvals.len() as u32;
+			Self::deposit_event(Event::Approved {
+				multisig: multisig.clone(),
+				call_hash,
+				member,
+				approvals,
+			});
+			let base_weight = APPROVE_WEIGHT + T::DbWeight::get().reads_writes(3, 3);
+			if approvals < info.threshold as u32 {
+				Proposals::<T>::insert(&multisig, &key, proposal);
+				return Ok(Some(base_weight).into())
+			}
+
+			let call = <T as Config>::Call::decode(&mut &proposal.call[..])
+				.map_err(|_| Error::<T>::UndecodableCall)?;
+			let dispatch_info = call.get_dispatch_info();
+			ensure!(dispatch_info.weight <= max_weight, Error::<T>::MaxWeightTooLow);
+			Self::close(&multisig, &key, &proposal);
+
+			let result = call.dispatch(RawOrigin::Signed(multisig.clone()).into());
+			let actual = extract_actual_weight(&result, &dispatch_info);
+			Self::deposit_event(Event::Executed {
+				multisig,
+				call_hash,
+				result: result.map(|_| ()).map_err(|e| e.error),
+			});
+			Ok(Some(base_weight.saturating_add(actual)).into())
+		}
+
+		/// Drop the proposal of `call_hash` by `proposer`, signed by any member with a detached
+		/// Falcon signature over [`Pallet::cancel_payload`].
+		#[pallet::weight(APPROVE_WEIGHT + T::DbWeight::get().reads_writes(3, 3))]
+		pub fn cancel(
+			origin: OriginFor<T>,
+			multisig: T::AccountId,
+			proposer: T::AccountId,
+			call_hash: T::Hash,
+			falcon_public: Vec<u8>,
+			falcon_signature: Vec<u8>,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
+			let info = Multisigs::<T>::get(&multisig).ok_or(Error::<T>::UnknownMultisig)?;
+			let key = (proposer, call_hash);
+			let proposal = Proposals::<T>::get(&multisig, &key).ok_or(Error::<T>::UnknownProposal)?;
+			let member = Self::check_member(&info, &falcon_public)?;
+			let payload = Self::cancel_payload(&multisig, proposal.index, &call_hash);
+			ensure!(
+				FalconHostFunctions::verify_bytes(&falcon_signature, &payload, &falcon_public),
+				Error::<T>::InvalidFalconSignature
+			);
+
+			Self::close(&multisig, &key, &proposal);
+			Self::deposit_event(Event::Cancelled { multisig, call_hash, member });
+			Ok(())
+		}
+
+		/// Withdraw a proposal of the caller's, returning its deposit.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 3))]
+		pub fn withdraw(origin: OriginFor<T>, multisig: T::AccountId, call_hash: T::Hash) -> DispatchResult {
+			let proposer = ensure_signed(origin)?;
+			let key = (proposer.clone(), call_hash);
+			let proposal = Proposals::<T>::get(&multisig, &key).ok_or(Error::<T>::UnknownProposal)?;
+
+			Self::close(&multisig, &key, &proposal);
+			Self::deposit_event(Event::Withdrawn { multisig, proposer, call_hash });
+			Ok(())
+		}
+
+		/// Remove a proposal past its expiry, anyone can call this. The deposit goes back to the
+		/// proposer.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 3))]
+		pub fn remove_expired(
+			origin: OriginFor<T>,
+			multisig: T::AccountId,
+			proposer: T::AccountId,
+			call_hash: T::Hash,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
+			let key = (proposer.clone(), call_hash);
+			let proposal = Proposals::<T>::get(&multisig, &key).ok_or(Error::<T>::UnknownProposal)?;
+			ensure!(
+				frame_system::Pallet::<T>::block_number() > proposal.expiry,
+				Error::<T>::ProposalNotExpired
+			);
+
+			Self::close(&multisig, &key, &proposal);
+			Self::deposit_event(Event::Expired { multisig, proposer, call_hash });
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The bytes a member signs to approve proposal `index` of `call_hash` on `multisig`.
+		/// Binding the genesis hash keeps an approval from being replayed on another network.
+		pub fn approval_payload(multisig: &T::AccountId, index: u32, call_hash: &T::Hash) -> Vec<u8> {
+			Self::payload(APPROVAL_CONTEXT, multisig, index, call_hash)
+		}
+
+		/// The bytes a member signs to cancel proposal `index` of `call_hash` on `multisig`.
+		pub fn cancel_payload(multisig: &T::AccountId, index: u32, call_hash: &T::Hash) -> Vec<u8> {
+			Self::payload(CANCEL_CONTEXT, multisig, index, call_hash)
+		}
+
+		fn payload(context: &[u8], multisig: &T::AccountId, index: u32, call_hash: &T::Hash) -> Vec<u8> {
+			let genesis = frame_system::Pallet::<T>::block_hash(T::BlockNumber::zero());
+			(context, genesis, multisig, index, call_hash).encode()
+		}
+
+		fn check_member(info: &MultisigInfo<T>, falcon_public: &[u8]) -> Result<[u8; 32], Error<T>> {
+			let member = FalconHostFunctions::hash_public_key_bytes(falcon_public);
+			if info.members.binary_search(&member).is_ok() {
+				Ok(member)
+			} else {
+				Err(Error::<T>::NotMember)
+			}
+		}
+
+		// Removes an open proposal and returns the deposit to its proposer
+		fn close(multisig: &T::AccountId, key: &(T::AccountId, T::Hash), proposal: &Proposal<T>) {
+			Proposals::<T>::remove(multisig, key);
+			OpenProposals::<T>::mutate(multisig, |open| *open = open.saturating_sub(1));
+			T::Currency::unreserve(&key.0, proposal.deposit);
+		}
+	}
+}
@@ -0,0 +1,289 @@
+use crate::{mock::*, Error, Event as MultisigEvent};
+use codec::{Decode, Encode};
+use frame_support::{assert_noop, assert_ok, dispatch::GetDispatchInfo, BoundedVec};
+use pqcrypto_falcon::falcon512::{PublicKey, SecretKey};
+use pqcrypto_traits::sign::{DetachedSignature as _, PublicKey as _};
+use primitives::{multisig::multisig_account, FalconHostFunctions};
+use sp_core::{crypto::AccountId32, H256};
+use sp_runtime::traits::{BlakeTwo256, Hash};
+
+const MAX_WEIGHT: u64 = 1_000_000_000;
+
+struct Member {
+	public: PublicKey,
+	secret: SecretKey,
+}
+
+impl Member {
+	fn new() -> Self {
+		let (public, secret) = FalconHostFunctions::generate_keypair();
+		Member { public, secret }
+	}
+
+	fn hash(&self) -> [u8; 32] {
+		FalconHostFunctions::hash_public_key(&self.public)
+	}
+
+	fn sign(&self, payload: &[u8]) -> (Vec<u8>, Vec<u8>) {
+		let signature = FalconHostFunctions::detached_signature(payload, &self.secret);
+		(self.public.as_bytes().to_vec(), signature.as_bytes().to_vec())
+	}
+
+	// approves ALICE's proposal of `call_hash`
+	fn approve(&self, multisig: &AccountId32, call_hash: H256) -> frame_support::dispatch::DispatchResultWithPostInfo {
+		let index = FalconMultisig::proposal(multisig, (ALICE, call_hash)).map(|p| p.index).unwrap_or_default();
+		let (public, signature) = self.sign(&FalconMultisig::approval_payload(multisig, index, &call_hash));
+		FalconMultisig::approve(Origin::signed(BOB), multisig.clone(), ALICE, call_hash, public, signature, MAX_WEIGHT)
+	}
+}
+
+fn bounded(hashes: &[[u8; 32]]) -> BoundedVec<[u8; 32], <Test as crate::Config>::MaxMembers> {
+	hashes.to_vec().try_into().unwrap()
+}
+
+// Registers a 2-of-3 multisig funded by ALICE
+fn two_of_three() -> (Vec<Member>, AccountId32) {
+	let members = vec![Member::new(), Member::new(), Member::new()];
+	let hashes: Vec<[u8; 32]> = members.iter().map(Member::hash).collect();
+	assert_ok!(FalconMultisig::create(Origin::signed(ALICE), bounded(&hashes), 2));
+	let multisig = AccountId32::from(multisig_account(&hashes, 2));
+	assert_ok!(Balances::transfer(Origin::signed(ALICE), multisig.clone(), 10_000));
+	(members, multisig)
+}
+
+fn transfer(amount: u128) -> Call {
+	Call::Balances(pallet_balances::Call::transfer { dest: BOB, value: amount })
+}
+
+// ALICE proposes a transfer of `amount` from the multisig to BOB
+fn propose_transfer(multisig: &AccountId32, amount: u128) -> H256 {
+	let call = transfer(amount);
+	let call_hash = BlakeTwo256::hash(&call.encode());
+	assert_ok!(FalconMultisig::propose(Origin::signed(ALICE), multisig.clone(), Box::new(call)));
+	call_hash
+}
+
+#[test]
+fn executes_at_threshold() {
+	new_test_ext().execute_with(|| {
+		let (members, multisig) = two_of_three();
+		let call_hash = propose_transfer(&multisig, 1_000);
+
+		assert_ok!(members[0].approve(&multisig, call_hash));
+		assert_eq!(Balances::free_balance(&BOB), INITIAL_BALANCE);
+
+		assert_ok!(members[2].approve(&multisig, call_hash));
+		assert_eq!(Balances::free_balance(&BOB), INITIAL_BALANCE + 1_000);
+		assert_eq!(Balances::free_balance(&multisig), 9_000);
+		assert!(FalconMultisig::proposal(&multisig, (ALICE, call_hash)).is_none());
+		assert_eq!(Balances::reserved_balance(&ALICE), 0);
+		assert_eq!(FalconMultisig::open_proposals(&multisig), 0);
+		System::assert_last_event(
+			MultisigEvent::Executed { multisig, call_hash, result: Ok(()) }.into(),
+		);
+	});
+}
+
+#[test]
+fn member_order_gives_the_same_account() {
+	new_test_ext().execute_with(|| {
+		let members = vec![Member::new(), Member::new()];
+		let mut hashes: Vec<[u8; 32]> = members.iter().map(Member::hash).collect();
+		assert_ok!(FalconMultisig::create(Origin::signed(ALICE), bounded(&hashes), 2));
+		hashes.reverse();
+		assert_noop!(
+			FalconMultisig::create(Origin::signed(ALICE), bounded(&hashes), 2),
+			Error::<Test>::AlreadyExists
+		);
+	});
+}
+
+#[test]
+fn too_many_members_do_not_decode() {
+	let members: BoundedVec<[u8; 32], <Test as crate::Config>::MaxMembers> = bounded(&[[1u8; 32]; 5]);
+	let call = crate::Call::<Test>::create { members, threshold: 2 };
+	let encoded = call.encode();
+	assert!(crate::Call::<Test>::decode(&mut &encoded[..]).is_ok());
+	//the same call with a sixth member
+	let oversized = (encoded[0], vec![[1u8; 32]; 6], 2u16).encode();
+	assert!(crate::Call::<Test>::decode(&mut &oversized[..]).is_err());
+}
+
+#[test]
+fn create_weight_scales_with_members() {
+	let weight = |count: usize| {
+		crate::Call::<Test>::create { members: bounded(&vec![[1u8; 32]; count]), threshold: 1 }
+			.get_dispatch_info()
+			.weight
+	};
+	assert!(weight(1) < weight(2));
+	assert!(weight(2) < weight(5));
+}
+
+#[test]
+fn invalid_threshold() {
+	new_test_ext().execute_with(|| {
+		let hashes = vec![Member::new().hash(), Member::new().hash()];
+		assert_noop!(
+			FalconMultisig::create(Origin::signed(ALICE), bounded(&hashes), 0),
+			Error::<Test>::InvalidThreshold
+		);
+		assert_noop!(
+			FalconMultisig::create(Origin::signed(ALICE), bounded(&hashes), 3),
+			Error::<Test>::InvalidThreshold
+		);
+	});
+}
+
+#[test]
+fn outsider_cannot_approve() {
+	new_test_ext().execute_with(|| {
+		let (_, multisig) = two_of_three();
+		let call_hash = propose_transfer(&multisig, 1_000);
+		assert_noop!(Member::new().approve(&multisig, call_hash), Error::<Test>::NotMember);
+	});
+}
+
+#[test]
+fn signature_over_another_call_is_rejected() {
+	new_test_ext().execute_with(|| {
+		let (members, multisig) = two_of_three();
+		let call_hash = propose_transfer(&multisig, 1_000);
+		let other = propose_transfer(&multisig, 2_000);
+		let (public, signature) = members[0].sign(&FalconMultisig::approval_payload(&multisig, 1, &other));
+		assert_noop!(
+			FalconMultisig::approve(Origin::signed(BOB), multisig, ALICE, call_hash, public, signature, MAX_WEIGHT),
+			Error::<Test>::InvalidFalconSignature
+		);
+	});
+}
+
+#[test]
+fn cannot_approve_twice() {
+	new_test_ext().execute_with(|| {
+		let (members, multisig) = two_of_three();
+		let call_hash = propose_transfer(&multisig, 1_000);
+		assert_ok!(members[1].approve(&multisig, call_hash));
+		assert_noop!(members[1].approve(&multisig, call_hash), Error::<Test>::AlreadyApproved);
+	});
+}
+
+#[test]
+fn approvals_do_not_carry_over_to_a_new_proposal() {
+	new_test_ext().execute_with(|| {
+		let (members, multisig) = two_of_three();
+		let call_hash = propose_transfer(&multisig, 1_000);
+		let old = members[0].sign(&FalconMultisig::approval_payload(&multisig, 0, &call_hash));
+		assert_ok!(members[0].approve(&multisig, call_hash));
+		assert_ok!(members[1].approve(&multisig, call_hash));
+
+		// same call again, the approval signed for the first proposal must not count
+		assert_eq!(propose_transfer(&multisig, 1_000), call_hash);
+		assert_noop!(
+			FalconMultisig::approve(Origin::signed(BOB), multisig, ALICE, call_hash, old.0, old.1, MAX_WEIGHT),
+			Error::<Test>::InvalidFalconSignature
+		);
+	});
+}
+
+#[test]
+fn member_can_cancel() {
+	new_test_ext().execute_with(|| {
+		let (members, multisig) = two_of_three();
+		let call_hash = propose_transfer(&multisig, 1_000);
+		let (public, signature) = members[2].sign(&FalconMultisig::cancel_payload(&multisig, 0, &call_hash));
+		assert_ok!(FalconMultisig::cancel(Origin::signed(BOB), multisig.clone(), ALICE, call_hash, public, signature));
+		assert!(FalconMultisig::proposal(&multisig, (ALICE, call_hash)).is_none());
+		assert_eq!(Balances::reserved_balance(&ALICE), 0);
+		assert_noop!(members[0].approve(&multisig, call_hash), Error::<Test>::UnknownProposal);
+	});
+}
+
+#[test]
+fn proposing_reserves_a_deposit() {
+	new_test_ext().execute_with(|| {
+		let (_, multisig) = two_of_three();
+		let call_hash = propose_transfer(&multisig, 1_000);
+		assert_eq!(Balances::reserved_balance(&ALICE), PROPOSAL_DEPOSIT);
+		assert_eq!(FalconMultisig::proposal(&multisig, (ALICE, call_hash)).unwrap().deposit, PROPOSAL_DEPOSIT);
+
+		// an account that cannot cover the deposit cannot propose
+		let poor = AccountId32::new([3u8; 32]);
+		assert_ok!(Balances::transfer(Origin::signed(ALICE), poor.clone(), PROPOSAL_DEPOSIT - 1));
+		assert!(FalconMultisig::propose(Origin::signed(poor), multisig, Box::new(transfer(1))).is_err());
+	});
+}
+
+#[test]
+fn proposals_are_keyed_by_proposer() {
+	new_test_ext().execute_with(|| {
+		let (members, multisig) = two_of_three();
+		let call_hash = propose_transfer(&multisig, 1_000);
+		// BOB proposing the same call neither collides with nor takes over ALICE's proposal
+		assert_ok!(FalconMultisig::propose(Origin::signed(BOB), multisig.clone(), Box::new(transfer(1_000))));
+		assert_noop!(
+			FalconMultisig::propose(Origin::signed(ALICE), multisig.clone(), Box::new(transfer(1_000))),
+			Error::<Test>::ProposalExists
+		);
+		assert_eq!(FalconMultisig::proposal(&multisig, (BOB, call_hash)).unwrap().index, 1);
+
+		assert_ok!(members[0].approve(&multisig, call_hash));
+		assert_ok!(members[1].approve(&multisig, call_hash));
+		assert!(FalconMultisig::proposal(&multisig, (ALICE, call_hash)).is_none());
+		assert!(FalconMultisig::proposal(&multisig, (BOB, call_hash)).unwrap().approvals.is_empty());
+		assert_eq!(Balances::reserved_balance(&BOB), PROPOSAL_DEPOSIT);
+	});
+}
+
+#[test]
+fn open_proposals_are_bounded() {
+	new_test_ext().execute_with(|| {
+		let (_, multisig) = two_of_three();
+		for amount in 1..=3 {
+			propose_transfer(&multisig, amount);
+		}
+		assert_noop!(
+			FalconMultisig::propose(Origin::signed(ALICE), multisig.clone(), Box::new(transfer(4))),
+			Error::<Test>::TooManyProposals
+		);
+		// withdrawing one makes room again
+		assert_ok!(FalconMultisig::withdraw(Origin::signed(ALICE), multisig.clone(), BlakeTwo256::hash(&transfer(1).encode())));
+		assert_eq!(Balances::reserved_balance(&ALICE), 2 * PROPOSAL_DEPOSIT);
+		propose_transfer(&multisig, 4);
+	});
+}
+
+#[test]
+fn only_the_proposer_withdraws() {
+	new_test_ext().execute_with(|| {
+		let (_, multisig) = two_of_three();
+		let call_hash = propose_transfer(&multisig, 1_000);
+		assert_noop!(
+			FalconMultisig::withdraw(Origin::signed(BOB), multisig.clone(), call_hash),
+			Error::<Test>::UnknownProposal
+		);
+		assert_ok!(FalconMultisig::withdraw(Origin::signed(ALICE), multisig.clone(), call_hash));
+		assert_eq!(Balances::reserved_balance(&ALICE), 0);
+		System::assert_last_event(MultisigEvent::Withdrawn { multisig, proposer: ALICE, call_hash }.into());
+	});
+}
+
+#[test]
+fn expired_proposals_are_removed() {
+	new_test_ext().execute_with(|| {
+		let (members, multisig) = two_of_three();
+		let call_hash = propose_transfer(&multisig, 1_000);
+		assert_ok!(members[0].approve(&multisig, call_hash));
+		assert_noop!(
+			FalconMultisig::remove_expired(Origin::signed(BOB), multisig.clone(), ALICE, call_hash),
+			Error::<Test>::ProposalNotExpired
+		);
+
+		System::set_block_number(1 + PROPOSAL_LIFETIME + 1);
+		assert_noop!(members[1].approve(&multisig, call_hash), Error::<Test>::ProposalExpired);
+		assert_ok!(FalconMultisig::remove_expired(Origin::signed(BOB), multisig.clone(), ALICE, call_hash));
+		assert!(FalconMultisig::proposal(&multisig, (ALICE, call_hash)).is_none());
+		assert_eq!(Balances::reserved_balance(&ALICE), 0);
+		assert_eq!(Balances::free_balance(&BOB), INITIAL_BALANCE);
+	});
+}
@@ -0,0 +1,50 @@
+//! Serial `FalconHostFunctions::verify` against `BatchVerifier` on a transaction block's worth of
+//! signatures. Run with `cargo bench -p primitives`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use pqcrypto_falcon::falcon512::{DetachedSignature, PublicKey};
+use pqcrypto_traits::sign::{DetachedSignature as _, PublicKey as _};
+use primitives::batch::{BatchVerifier, FalconCheck, DEFAULT_BATCH_SIZE};
+use primitives::FalconHostFunctions;
+
+fn signatures(count: usize) -> Vec<(DetachedSignature, Vec<u8>, PublicKey)> {
+    (0..count)
+        .map(|i| {
+            let (pk, sk) = FalconHostFunctions::generate_keypair();
+            let message = format!("extrinsic payload {}", i).into_bytes();
+            (FalconHostFunctions::detached_signature(&message, &sk), message, pk)
+        })
+        .collect()
+}
+
+fn verification(c: &mut Criterion) {
+    let mut group = c.benchmark_group("falcon_verify");
+    let verifier = BatchVerifier::new(0, DEFAULT_BATCH_SIZE).unwrap();
+    for count in [64usize, 256, 1024] {
+        let signed = signatures(count);
+        let checks: Vec<FalconCheck> = signed
+            .iter()
+            .map(|(signature, message, pk)| {
+                FalconCheck::new(signature.as_bytes().to_vec(), message.clone(), pk.as_bytes().to_vec())
+            })
+            .collect();
+        group.throughput(Throughput::Elements(count as u64));
+
+        group.bench_with_input(BenchmarkId::new("serial", count), &signed, |b, signed| {
+            b.iter(|| {
+                for (signature, message, pk) in signed {
+                    assert!(FalconHostFunctions::verify(signature, message, pk).is_ok());
+                }
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("batch", count), &checks, |b, checks| {
+            // the verified cache would answer every iteration after the first one
+            b.iter(|| assert_eq!(verifier.verify_uncached(checks), Ok(())))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, verification);
+criterion_main!(benches);
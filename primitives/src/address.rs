@@ -0,0 +1,171 @@
+use core::fmt;
+
+use sp_core::crypto::{
+    default_ss58_version, set_default_ss58_version, AccountId32, Ss58AddressFormat, Ss58AddressFormatRegistry,
+    Ss58Codec,
+};
+use sp_core::hashing::blake2_512;
+
+// Prefix used until the chain spec says otherwise
+pub use crate::DEFAULT_SS58_PREFIX;
+
+// Preimage prefix of the SS58 checksum
+const CHECKSUM_PREFIX: &[u8] = b"SS58PRE";
+const CHECKSUM_LEN: usize = 2;
+const ACCOUNT_LEN: usize = 32;
+
+// Why an address could not be decoded into an account hash
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AddressError {
+    // Not base58 at all, usually a typo or a truncated paste
+    BadBase58,
+    // Decodes, but not to a 32 byte account
+    BadLength,
+    // The first byte is not a valid SS58 prefix encoding
+    InvalidPrefix,
+    // The checksum does not match, at least one character is wrong
+    BadChecksum,
+    // A valid address, but for another network
+    WrongNetwork { expected: u16, found: u16 },
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AddressError::BadBase58 => write!(f, "address is not valid base58"),
+            AddressError::BadLength => write!(f, "address does not encode a 32 byte account"),
+            AddressError::InvalidPrefix => write!(f, "address has an invalid SS58 prefix"),
+            AddressError::BadChecksum => write!(f, "address checksum mismatch, check it for typos"),
+            AddressError::WrongNetwork { expected, found } => write!(
+                f,
+                "address is for {} (prefix {}), this network is {} (prefix {})",
+                network_name(*found),
+                found,
+                network_name(*expected),
+                expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+// Registered name of an SS58 prefix, or a generic description for unregistered ones
+pub fn network_name(prefix: u16) -> String {
+    match Ss58AddressFormatRegistry::try_from(Ss58AddressFormat::custom(prefix)) {
+        Ok(registered) => registered.to_string(),
+        Err(_) => String::from("an unregistered network"),
+    }
+}
+
+// Makes `prefix` the one every address of this process is encoded with and checked against.
+// The node calls it once at startup with the `ss58Format` chain spec property.
+pub fn set_network_prefix(prefix: u16) {
+    set_default_ss58_version(Ss58AddressFormat::custom(prefix));
+}
+
+pub fn network_prefix() -> u16 {
+    default_ss58_version().prefix()
+}
+
+pub fn encode(account: [u8; 32], prefix: u16) -> String {
+    AccountId32::from(account).to_ss58check_with_version(Ss58AddressFormat::custom(prefix))
+}
+
+// Decodes `address` back into the 32 byte account hash, without looking at the prefix
+pub fn decode_any(address: &str) -> Result<([u8; 32], u16), AddressError> {
+    let data = bs58::decode(address).into_vec().map_err(|_| AddressError::BadBase58)?;
+    if data.is_empty() {
+        return Err(AddressError::BadLength);
+    }
+    let (prefix_len, prefix) = match data[0] {
+        0..=63 => (1, data[0] as u16),
+        64..=127 => {
+            if data.len() < 2 {
+                return Err(AddressError::BadLength);
+            }
+            // two byte form, the lower six bits of the prefix are spread over both bytes
+            let lower = (data[0] << 2) | (data[1] >> 6);
+            let upper = data[1] & 0b0011_1111;
+            (2, (lower as u16) | ((upper as u16) << 8))
+        }
+        _ => return Err(AddressError::InvalidPrefix),
+    };
+    if data.len() != prefix_len + ACCOUNT_LEN + CHECKSUM_LEN {
+        return Err(AddressError::BadLength);
+    }
+
+    let body = prefix_len + ACCOUNT_LEN;
+    if checksum(&data[..body])[..CHECKSUM_LEN] != data[body..] {
+        return Err(AddressError::BadChecksum);
+    }
+    let mut account = [0u8; 32];
+    account.copy_from_slice(&data[prefix_len..body]);
+    Ok((account, prefix))
+}
+
+// Decodes `address` and checks it belongs to the network with `expected` prefix
+pub fn decode(address: &str, expected: u16) -> Result<[u8; 32], AddressError> {
+    let (account, found) = decode_any(address)?;
+    if found != expected {
+        return Err(AddressError::WrongNetwork { expected, found });
+    }
+    Ok(account)
+}
+
+fn checksum(data: &[u8]) -> [u8; 64] {
+    let mut preimage = Vec::with_capacity(CHECKSUM_PREFIX.len() + data.len());
+    preimage.extend_from_slice(CHECKSUM_PREFIX);
+    preimage.extend_from_slice(data);
+    blake2_512(&preimage)
+}
+
+#[cfg(test)]
+mod address_tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let account = [7u8; 32];
+        let address = encode(account, DEFAULT_SS58_PREFIX);
+        assert_eq!(decode(&address, DEFAULT_SS58_PREFIX), Ok(account));
+    }
+
+    #[test]
+    fn two_byte_prefix_round_trip() {
+        let account = [9u8; 32];
+        let address = encode(account, 1234);
+        assert_eq!(decode_any(&address), Ok((account, 1234)));
+    }
+
+    #[test]
+    fn matches_sp_core() {
+        let account = [3u8; 32];
+        let address = encode(account, 0);
+        let (decoded, format) = AccountId32::from_ss58check_with_version(&address).unwrap();
+        assert_eq!(decoded, AccountId32::from(account));
+        assert_eq!(format.prefix(), 0);
+    }
+
+    #[test]
+    fn mistyped_character() {
+        let mut address = encode([5u8; 32], DEFAULT_SS58_PREFIX).into_bytes();
+        let last = address.len() - 1;
+        address[last] = if address[last] == b'a' { b'b' } else { b'a' };
+        let address = String::from_utf8(address).unwrap();
+        assert_eq!(decode(&address, DEFAULT_SS58_PREFIX), Err(AddressError::BadChecksum));
+    }
+
+    #[test]
+    fn wrong_network() {
+        let address = encode([1u8; 32], 0);
+        let err = decode(&address, DEFAULT_SS58_PREFIX).unwrap_err();
+        assert_eq!(err, AddressError::WrongNetwork { expected: DEFAULT_SS58_PREFIX, found: 0 });
+        assert!(err.to_string().contains("polkadot"));
+    }
+
+    #[test]
+    fn not_base58() {
+        assert_eq!(decode("0OIl", DEFAULT_SS58_PREFIX), Err(AddressError::BadBase58));
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+use crate::FalconHostFunctions;
+
+// Number of verified (signature, message, key) triples remembered by the cache
+pub const VERIFIED_CACHE_CAPACITY: usize = 1 << 16;
+
+// Default number of signatures a worker verifies before picking up more work
+pub const DEFAULT_BATCH_SIZE: usize = 16;
+
+// One Falcon signature to check, everything as raw bytes the way it sits in an extrinsic
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FalconCheck {
+    pub signature: Vec<u8>,
+    pub message: Vec<u8>,
+    pub public_key: Vec<u8>,
+}
+
+impl FalconCheck {
+    pub fn new(signature: Vec<u8>, message: Vec<u8>, public_key: Vec<u8>) -> Self {
+        FalconCheck { signature, message, public_key }
+    }
+
+    fn cache_key(&self) -> [u8; 32] {
+        cache_key(&self.signature, &self.message, &self.public_key)
+    }
+}
+
+fn cache_key(signature: &[u8], message: &[u8], public_key: &[u8]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    for part in [signature, message, public_key] {
+        hasher.update(&(part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+// Bounded set of signatures that already verified on one `BatchVerifier`, keyed on the whole
+// (signature, message, key) triple. The transaction pool and block import share a verifier, so a
+// transaction checked when it entered the pool is a lookup when its block is imported. Only
+// successes are stored, a failed signature is always checked again.
+#[derive(Default)]
+struct VerifiedCache {
+    entries: HashSet<[u8; 32]>,
+    order: VecDeque<[u8; 32]>,
+}
+
+impl VerifiedCache {
+    fn contains(&self, key: &[u8; 32]) -> bool {
+        self.entries.contains(key)
+    }
+
+    fn insert(&mut self, key: [u8; 32]) {
+        if !self.entries.insert(key) {
+            return;
+        }
+        self.order.push_back(key);
+        if self.order.len() > VERIFIED_CACHE_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+// Verifies Falcon signatures in parallel batches on its own thread pool
+pub struct BatchVerifier {
+    pool: ThreadPool,
+    batch_size: usize,
+    verified: Mutex<VerifiedCache>,
+}
+
+impl BatchVerifier {
+    // `threads == 0` lets rayon pick one thread per core
+    pub fn new(threads: usize, batch_size: usize) -> Result<Self, ThreadPoolBuildError> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|index| format!("falcon-verify-{}", index))
+            .build()?;
+        Ok(BatchVerifier { pool, batch_size: batch_size.max(1), verified: Mutex::new(VerifiedCache::default()) })
+    }
+
+    // Whether this verifier already verified `check`
+    pub fn is_cached(&self, check: &FalconCheck) -> bool {
+        self.is_verified(&check.signature, &check.message, &check.public_key)
+    }
+
+    // `is_cached` for the raw parts, what the `falcon_crypto` host function looks up
+    pub fn is_verified(&self, signature: &[u8], message: &[u8], public_key: &[u8]) -> bool {
+        self.verified.lock().is_ok_and(|cache| cache.contains(&cache_key(signature, message, public_key)))
+    }
+
+    fn remember(&self, keys: impl IntoIterator<Item = [u8; 32]>) {
+        if let Ok(mut cache) = self.verified.lock() {
+            for key in keys {
+                cache.insert(key);
+            }
+        }
+    }
+
+    // Checks every signature and returns the indices of the invalid ones in ascending order, so
+    // a failure can be reported against the exact extrinsic it came from
+    pub fn verify(&self, checks: &[FalconCheck]) -> Result<(), Vec<usize>> {
+        self.run(checks, true)
+    }
+
+    // `verify` without reading or filling the verified cache, e.g. to measure raw throughput
+    pub fn verify_uncached(&self, checks: &[FalconCheck]) -> Result<(), Vec<usize>> {
+        self.run(checks, false)
+    }
+
+    fn run(&self, checks: &[FalconCheck], use_cache: bool) -> Result<(), Vec<usize>> {
+        let batch_size = self.batch_size;
+        let results: Vec<(usize, Option<[u8; 32]>)> = self.pool.install(|| {
+            checks
+                .par_iter()
+                .enumerate()
+                .with_min_len(batch_size)
+                .map(|(index, check)| {
+                    let valid = (use_cache && self.is_cached(check)) || verify_uncached(check);
+                    (index, if valid { Some(check.cache_key()) } else { None })
+                })
+                .collect()
+        });
+
+        let mut invalid = Vec::new();
+        let mut verified = Vec::with_capacity(results.len());
+        for (index, key) in results {
+            match key {
+                Some(key) => verified.push(key),
+                None => invalid.push(index),
+            }
+        }
+        if use_cache {
+            self.remember(verified);
+        }
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid)
+        }
+    }
+
+    // Runs `job` on the verifier's thread pool
+    pub fn spawn<F: FnOnce() + Send + 'static>(&self, job: F) {
+        self.pool.spawn(job)
+    }
+
+    // Same as `verify` for a single signature
+    pub fn verify_one(&self, check: &FalconCheck) -> bool {
+        self.verify(core::slice::from_ref(check)).is_ok()
+    }
+}
+
+// Natively, not through the host function, whose cache lookup would find nothing new here
+fn verify_uncached(check: &FalconCheck) -> bool {
+    FalconHostFunctions::verify_native(&check.signature, &check.message, &check.public_key)
+}
+
+#[cfg(test)]
+mod batch_tests {
+    use super::*;
+    use pqcrypto_traits::sign::{DetachedSignature as _, PublicKey as _};
+
+    fn checks(count: usize) -> Vec<FalconCheck> {
+        let (pk, sk) = FalconHostFunctions::generate_keypair();
+        (0..count)
+            .map(|i| {
+                let message = format!("extrinsic {}", i).into_bytes();
+                let signature = FalconHostFunctions::detached_signature(&message, &sk);
+                FalconCheck::new(signature.as_bytes().to_vec(), message, pk.as_bytes().to_vec())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid_batch() {
+        let verifier = BatchVerifier::new(4, 2).unwrap();
+        assert_eq!(verifier.verify(&checks(10)), Ok(()));
+    }
+
+    #[test]
+    fn reports_exact_invalid_indices() {
+        let verifier = BatchVerifier::new(4, 2).unwrap();
+        let mut checks = checks(10);
+        checks[3].message = b"tampered".to_vec();
+        checks[7].signature.truncate(10);
+        assert_eq!(verifier.verify(&checks), Err(vec![3, 7]));
+    }
+
+    #[test]
+    fn batch_fills_the_cache() {
+        let verifier = BatchVerifier::new(2, 1).unwrap();
+        let checks = checks(3);
+        let check = &checks[1];
+        assert!(!verifier.is_cached(check));
+        assert_eq!(verifier.verify(&checks), Ok(()));
+        assert!(verifier.is_cached(check));
+        //the cache belongs to the verifier, another one checks the signature again
+        assert!(!BatchVerifier::new(1, 1).unwrap().is_cached(check));
+        assert_eq!(verifier.verify_uncached(&checks), Ok(()));
+    }
+
+    #[test]
+    fn cache_keyed_on_the_whole_check() {
+        let verifier = BatchVerifier::new(1, 1).unwrap();
+        let checks = checks(2);
+        assert_eq!(verifier.verify(&checks), Ok(()));
+        //a verified signature does not vouch for another message or key
+        let other_message = FalconCheck { message: checks[1].message.clone(), ..checks[0].clone() };
+        let other_key = FalconCheck { public_key: vec![0; checks[0].public_key.len()], ..checks[0].clone() };
+        assert!(!verifier.is_cached(&other_message));
+        assert_eq!(verifier.verify(&[other_message, other_key]), Err(vec![0, 1]));
+    }
+
+    #[test]
+    fn host_function_reads_the_registered_cache() {
+        let verifier = std::sync::Arc::new(BatchVerifier::new(1, 1).unwrap());
+        let mut checks = checks(1);
+        checks[0].message = b"tampered".to_vec();
+        //only a cache hit accepts a signature of another message, a verification would not
+        verifier.remember([checks[0].cache_key()]);
+        let check = &checks[0];
+        let verify = || crate::falcon_crypto::verify(&check.signature, &check.message, &check.public_key);
+
+        let mut ext = sp_io::TestExternalities::default();
+        assert!(!ext.execute_with(verify));
+        ext.register_extension(crate::FalconVerifiedExt(verifier));
+        assert!(ext.execute_with(verify));
+        assert!(!verify());
+    }
+
+    #[test]
+    fn empty_batch() {
+        let verifier = BatchVerifier::new(1, DEFAULT_BATCH_SIZE).unwrap();
+        assert_eq!(verifier.verify(&[]), Ok(()));
+    }
+}
@@ -0,0 +1,73 @@
+use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use pqcrypto_falcon::falcon512::{PublicKey, SecretKey};
+#[cfg(feature = "std")]
+use pqcrypto_traits::sign::{DetachedSignature as _, PublicKey as _};
+use scale_info::TypeInfo;
+use sp_core::crypto::AccountId32;
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+
+use crate::FalconHostFunctions;
+
+// A Falcon signature on its own. The public key travels with the signature, the signer is the
+// account it hashes to, so an account is controlled by one Falcon key and nothing else.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, TypeInfo, RuntimeDebug)]
+pub struct FalconSignature {
+    // falcon512 public key bytes
+    pub public: Vec<u8>,
+    // falcon512 detached signature bytes
+    pub signature: Vec<u8>,
+}
+
+impl FalconSignature {
+    #[cfg(feature = "std")]
+    pub fn sign(message: &[u8], public_key: &PublicKey, secret_key: &SecretKey) -> Self {
+        FalconSignature {
+            public: public_key.as_bytes().to_vec(),
+            signature: FalconHostFunctions::detached_signature(message, secret_key).as_bytes().to_vec(),
+        }
+    }
+
+    // The account controlled by the public key carried in this signature
+    pub fn account(&self) -> AccountId32 {
+        falcon_account(&self.public)
+    }
+
+    pub fn verify(&self, message: &[u8], signer: &AccountId32) -> bool {
+        self.account() == *signer && FalconHostFunctions::verify_bytes(&self.signature, message, &self.public)
+    }
+}
+
+// Account of a Falcon public key, the blake3 hash of its bytes (`FalconHostFunctions::hash_public_key`).
+// Coinbases, migrations to Falcon and multisig members all use this account.
+pub fn falcon_account(public_key: &[u8]) -> AccountId32 {
+    AccountId32::from(FalconHostFunctions::hash_public_key_bytes(public_key))
+}
+
+#[cfg(test)]
+mod falcon_signature_tests {
+    use super::*;
+
+    #[test]
+    fn sign_and_verify() {
+        let (pk, sk) = FalconHostFunctions::generate_keypair();
+        let message = b"Hello, world!";
+        let signature = FalconSignature::sign(message, &pk, &sk);
+        assert_eq!(signature.account(), AccountId32::from(FalconHostFunctions::hash_public_key(&pk)));
+        assert!(signature.verify(message, &signature.account()));
+        assert!(!signature.verify(b"something else", &signature.account()));
+    }
+
+    #[test]
+    fn other_account_fails() {
+        let (pk, sk) = FalconHostFunctions::generate_keypair();
+        let (other_pk, _) = FalconHostFunctions::generate_keypair();
+        let message = b"Hello, world!";
+        let signature = FalconSignature::sign(message, &pk, &sk);
+        assert!(!signature.verify(message, &falcon_account(other_pk.as_bytes())));
+        //a key that does not hash to the signer cannot be swapped in
+        let swapped = FalconSignature { public: other_pk.as_bytes().to_vec(), ..signature.clone() };
+        assert!(!swapped.verify(message, &signature.account()));
+    }
+}
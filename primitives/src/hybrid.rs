@@ -0,0 +1,166 @@
+use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use pqcrypto_falcon::falcon512::{PublicKey, SecretKey};
+#[cfg(feature = "std")]
+use pqcrypto_traits::sign::{DetachedSignature as _, PublicKey as _};
+use scale_info::TypeInfo;
+use sp_core::{crypto::AccountId32, ed25519, sr25519};
+#[cfg(feature = "std")]
+use sp_core::Pair;
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+
+use crate::FalconHostFunctions;
+
+// Blake3 derive-key context for accounts owned by a classical key and a Falcon key together.
+// It keeps a hybrid account from ever colliding with a plain Falcon account (blake3 of the key).
+pub const HYBRID_ACCOUNT_CONTEXT: &str = "Argonaut 2023-10 hybrid account v1";
+
+// The classical half of a hybrid signature, the public key travels with the signature so that
+// the account can be recomputed from it during verification
+#[derive(Clone, PartialEq, Eq, Encode, Decode, TypeInfo, RuntimeDebug)]
+pub enum ClassicalSignature {
+    Sr25519(sr25519::Public, sr25519::Signature),
+    Ed25519(ed25519::Public, ed25519::Signature),
+}
+
+impl ClassicalSignature {
+    // Tag byte followed by the raw public key, this is what goes into the account derivation
+    pub fn public_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(33);
+        match self {
+            ClassicalSignature::Sr25519(public, _) => {
+                bytes.push(0);
+                bytes.extend_from_slice(public.as_ref());
+            }
+            ClassicalSignature::Ed25519(public, _) => {
+                bytes.push(1);
+                bytes.extend_from_slice(public.as_ref());
+            }
+        }
+        bytes
+    }
+
+    pub fn verify(&self, message: &[u8]) -> bool {
+        match self {
+            ClassicalSignature::Sr25519(public, signature) => sp_io::crypto::sr25519_verify(signature, message, public),
+            ClassicalSignature::Ed25519(public, signature) => sp_io::crypto::ed25519_verify(signature, message, public),
+        }
+    }
+}
+
+// An sr25519 or ed25519 signature paired with a Falcon detached signature over the same payload.
+// It is only valid if both halves verify and the signer is the account derived from both keys.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, TypeInfo, RuntimeDebug)]
+pub struct HybridSignature {
+    pub classical: ClassicalSignature,
+    // falcon512 public key bytes
+    pub falcon_public: Vec<u8>,
+    // falcon512 detached signature bytes
+    pub falcon_signature: Vec<u8>,
+}
+
+impl HybridSignature {
+    #[cfg(feature = "std")]
+    pub fn sign_sr25519(message: &[u8], pair: &sr25519::Pair, falcon_public: &PublicKey, falcon_secret: &SecretKey) -> Self {
+        HybridSignature {
+            classical: ClassicalSignature::Sr25519(pair.public(), pair.sign(message)),
+            falcon_public: falcon_public.as_bytes().to_vec(),
+            falcon_signature: FalconHostFunctions::detached_signature(message, falcon_secret).as_bytes().to_vec(),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn sign_ed25519(message: &[u8], pair: &ed25519::Pair, falcon_public: &PublicKey, falcon_secret: &SecretKey) -> Self {
+        HybridSignature {
+            classical: ClassicalSignature::Ed25519(pair.public(), pair.sign(message)),
+            falcon_public: falcon_public.as_bytes().to_vec(),
+            falcon_signature: FalconHostFunctions::detached_signature(message, falcon_secret).as_bytes().to_vec(),
+        }
+    }
+
+    // The account controlled by the two public keys carried in this signature
+    pub fn account(&self) -> AccountId32 {
+        hybrid_account(&self.classical, &self.falcon_public)
+    }
+
+    pub fn verify(&self, message: &[u8], signer: &AccountId32) -> bool {
+        if self.account() != *signer {
+            return false;
+        }
+        self.classical.verify(message)
+            && FalconHostFunctions::verify_bytes(&self.falcon_signature, message, &self.falcon_public)
+    }
+}
+
+// Derives the account of a hybrid key pair from both public keys
+pub fn hybrid_account(classical: &ClassicalSignature, falcon_public: &[u8]) -> AccountId32 {
+    hybrid_account_from_bytes(&classical.public_bytes(), falcon_public)
+}
+
+pub fn hybrid_account_from_bytes(classical_public: &[u8], falcon_public: &[u8]) -> AccountId32 {
+    let mut hasher = blake3::Hasher::new_derive_key(HYBRID_ACCOUNT_CONTEXT);
+    hasher.update(&(classical_public.len() as u32).to_le_bytes());
+    hasher.update(classical_public);
+    hasher.update(falcon_public);
+    let hash: [u8; 32] = hasher.finalize().into();
+    AccountId32::from(hash)
+}
+
+#[cfg(test)]
+mod hybrid_tests {
+    use super::*;
+
+    fn alice() -> sr25519::Pair {
+        sr25519::Pair::from_string("//Alice", None).unwrap()
+    }
+
+    #[test]
+    fn sign_and_verify_sr25519() {
+        let (pk, sk) = FalconHostFunctions::generate_keypair();
+        let message = b"Hello, world!";
+        let signature = HybridSignature::sign_sr25519(message, &alice(), &pk, &sk);
+        assert!(signature.verify(message, &signature.account()));
+    }
+
+    #[test]
+    fn sign_and_verify_ed25519() {
+        let (pk, sk) = FalconHostFunctions::generate_keypair();
+        let pair = ed25519::Pair::from_string("//Alice", None).unwrap();
+        let message = b"Hello, world!";
+        let signature = HybridSignature::sign_ed25519(message, &pair, &pk, &sk);
+        assert!(signature.verify(message, &signature.account()));
+    }
+
+    #[test]
+    fn bad_falcon_half_fails() {
+        let (pk, sk) = FalconHostFunctions::generate_keypair();
+        let message = b"Hello, world!";
+        let mut signature = HybridSignature::sign_sr25519(message, &alice(), &pk, &sk);
+        signature.falcon_signature = FalconHostFunctions::detached_signature(b"something else", &sk).as_bytes().to_vec();
+        assert!(!signature.verify(message, &signature.account()));
+    }
+
+    #[test]
+    fn bad_classical_half_fails() {
+        let (pk, sk) = FalconHostFunctions::generate_keypair();
+        let message = b"Hello, world!";
+        let mut signature = HybridSignature::sign_sr25519(message, &alice(), &pk, &sk);
+        let pair = alice();
+        signature.classical = ClassicalSignature::Sr25519(pair.public(), pair.sign(b"something else"));
+        assert!(!signature.verify(message, &signature.account()));
+    }
+
+    #[test]
+    fn account_depends_on_both_keys() {
+        let (pk, sk) = FalconHostFunctions::generate_keypair();
+        let (other_pk, other_sk) = FalconHostFunctions::generate_keypair();
+        let message = b"Hello, world!";
+        let signature = HybridSignature::sign_sr25519(message, &alice(), &pk, &sk);
+        let other = HybridSignature::sign_sr25519(message, &alice(), &other_pk, &other_sk);
+        assert_ne!(signature.account(), other.account());
+        assert_ne!(signature.account(), AccountId32::from(FalconHostFunctions::hash_public_key(&pk)));
+        //a valid signature still fails against somebody else's account
+        assert!(!signature.verify(message, &other.account()));
+    }
+}
@@ -1,43 +1,128 @@
+// Everything the runtime needs builds without std: the signature types, verification and account
+// derivation. Key generation, signing, batch verification and addresses are for the node.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
 use pqcrypto_falcon::falcon512::{self, PublicKey, SecretKey, SignedMessage, DetachedSignature};
-use pqcrypto_traits::sign::{VerificationError, PublicKey as pubkey};
-use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+#[cfg(feature = "std")]
+use pqcrypto_traits::sign::{VerificationError, PublicKey as pubkey, DetachedSignature as detached};
+#[cfg(feature = "std")]
+use sp_externalities::ExternalitiesExt;
+use sp_runtime_interface::runtime_interface;
+#[cfg(feature = "std")]
+use sp_std::vec::Vec;
+
+// The SS58 prefix of the chain, the runtime's `SS58Prefix` and the `ss58Format` of the chain specs
+// the node builds
+pub const DEFAULT_SS58_PREFIX: u16 = 42;
+
+#[cfg(feature = "std")]
+pub mod address;
+#[cfg(feature = "std")]
+pub mod batch;
+pub mod falcon;
+pub mod hybrid;
+pub mod multisig;
+pub mod seal;
+pub mod signature;
 
 
+// Falcon verification as a host function. The pqcrypto C code is only built for the node, the wasm
+// runtime calls out to it through this interface and the node verifies natively. Nodes have to
+// register `falcon_crypto::HostFunctions` with their executor.
+#[runtime_interface]
+pub trait FalconCrypto {
+    // Whether `signature` is a falcon512 detached signature of `message` by `public_key`. A triple
+    // the batch verifier registered with the externalities already verified is a cache lookup.
+    fn verify(signature: &[u8], message: &[u8], public_key: &[u8]) -> bool {
+        let verified = sp_externalities::with_externalities(|mut ext| {
+            ext.extension::<FalconVerifiedExt>()
+                .map(|verifier| verifier.is_verified(signature, message, public_key))
+        });
+        verified.flatten().unwrap_or(false) || FalconHostFunctions::verify_native(signature, message, public_key)
+    }
+}
+
+#[cfg(feature = "std")]
+sp_externalities::decl_extension! {
+    // The node's batch verifier, registered with the externalities of every runtime call so that
+    // signatures the transaction pool or block import verified in parallel are not verified again
+    // one after the other by the runtime.
+    pub struct FalconVerifiedExt(std::sync::Arc<batch::BatchVerifier>);
+}
+
 pub struct FalconHostFunctions;
 
 impl FalconHostFunctions {
 
+    #[cfg(feature = "std")]
     pub fn generate_keypair() -> (PublicKey, SecretKey) {
         let (pk, sk) = falcon512::keypair();
         (pk, sk)
     }
 
+    #[cfg(feature = "std")]
     pub fn sign(message: &[u8], sk: &SecretKey ) -> SignedMessage {
         falcon512::sign(message, sk)
     }
 
+    #[cfg(feature = "std")]
     pub fn verify(signature: &DetachedSignature, message: &[u8], public_key: &PublicKey) -> Result<(), VerificationError> {
         falcon512::verify_detached_signature(signature, message, public_key)
     }
 
+    #[cfg(feature = "std")]
     pub fn open(signature: &SignedMessage, public_key: &PublicKey) -> Result<Vec<u8>, VerificationError> {
         falcon512::open(signature, public_key)
     }
 
+    // Verifies a detached signature given as raw bytes, malformed keys or signatures are just invalid.
+    // Goes through the `falcon_crypto` host function, so it works in the runtime as well.
+    pub fn verify_bytes(signature: &[u8], message: &[u8], public_key: &[u8]) -> bool {
+        falcon_crypto::verify(signature, message, public_key)
+    }
+
+    // `verify_bytes` done natively, what the host function runs. Always a full verification, no
+    // state is shared between callers.
+    #[cfg(feature = "std")]
+    pub fn verify_native(signature: &[u8], message: &[u8], public_key: &[u8]) -> bool {
+        let public_key = match PublicKey::from_bytes(public_key) {
+            Ok(public_key) => public_key,
+            Err(_) => return false,
+        };
+        let signature = match DetachedSignature::from_bytes(signature) {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        };
+        Self::verify(&signature, message, &public_key).is_ok()
+    }
+
+    #[cfg(feature = "std")]
     pub fn detached_signature(message: &[u8], secret_key: &SecretKey) -> DetachedSignature {
         falcon512::detached_sign(message, secret_key)
     }
 
+    #[cfg(feature = "std")]
     pub fn hash_public_key(public_key: &PublicKey) -> [u8; 32] {
+        Self::hash_public_key_bytes(public_key.as_bytes())
+    }
+
+    pub fn hash_public_key_bytes(public_key: &[u8]) -> [u8; 32] {
         let mut hasher = blake3::Hasher::new();
-        hasher.update(public_key.as_bytes());
+        hasher.update(public_key);
         hasher.finalize().into()
     }
     
+    // Encodes with the network prefix set by `address::set_network_prefix`, 42 by default
+    #[cfg(feature = "std")]
     pub fn generate_address(hashed_key: [u8; 32]) -> String {
-        let address = sp_core::crypto::AccountId32::from(hashed_key).to_ss58check_with_version(
-            sp_core::crypto::Ss58AddressFormat::custom(42));
-        address
+        address::encode(hashed_key, address::network_prefix())
+    }
+
+    // The reverse of `generate_address`, rejects addresses of other networks
+    #[cfg(feature = "std")]
+    pub fn decode_address(address: &str) -> Result<[u8; 32], address::AddressError> {
+        address::decode(address, address::network_prefix())
     }
     
 
@@ -71,6 +156,16 @@ mod falcon_tests {
         assert!(falcon512::verify_detached_signature(&signature, message, &pk).is_ok());
     }
 
+    #[test]
+    fn host_function_verifies_natively() {
+        let (pk, sk) = FalconHostFunctions::generate_keypair();
+        let message = b"Hello, world!";
+        let signature = FalconHostFunctions::detached_signature(message, &sk);
+        assert!(falcon_crypto::verify(signature.as_bytes(), message, pk.as_bytes()));
+        assert!(!falcon_crypto::verify(signature.as_bytes(), b"something else", pk.as_bytes()));
+        assert!(!falcon_crypto::verify(&signature.as_bytes()[..10], message, pk.as_bytes()));
+    }
+
     #[test]
     fn generate_address() {
         let (pk, _) = FalconHostFunctions::generate_keypair();
@@ -78,5 +173,6 @@ mod falcon_tests {
         let address = FalconHostFunctions::generate_address(hashed_key);
         println!("The substrate address generated is: {}", address);
         assert_eq!(address.len(), 48);
+        assert_eq!(FalconHostFunctions::decode_address(&address), Ok(hashed_key));
     }
 }
\ No newline at end of file
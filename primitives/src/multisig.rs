@@ -0,0 +1,65 @@
+use codec::Encode;
+use sp_std::vec::Vec;
+
+use crate::FalconHostFunctions;
+
+// Blake3 derive-key context for m-of-n accounts whose members are Falcon keys. It keeps a
+// multisig account apart from plain Falcon accounts and hybrid accounts.
+pub const MULTISIG_ACCOUNT_CONTEXT: &str = "Argonaut 2023-10 falcon multisig account v1";
+
+// Sorts and deduplicates member key hashes, the order members are listed in never matters
+pub fn normalize_members(mut members: Vec<[u8; 32]>) -> Vec<[u8; 32]> {
+    members.sort_unstable();
+    members.dedup();
+    members
+}
+
+// Account of the multisig with `threshold` out of `members`, the members being the blake3
+// hashes of their Falcon public keys (`FalconHostFunctions::hash_public_key`).
+pub fn multisig_account(members: &[[u8; 32]], threshold: u16) -> [u8; 32] {
+    let members = normalize_members(members.to_vec());
+    let mut hasher = blake3::Hasher::new_derive_key(MULTISIG_ACCOUNT_CONTEXT);
+    hasher.update(&(threshold, members).encode());
+    hasher.finalize().into()
+}
+
+// Same as `multisig_account` taking the raw Falcon public keys
+pub fn multisig_account_from_keys(public_keys: &[Vec<u8>], threshold: u16) -> [u8; 32] {
+    let members: Vec<[u8; 32]> = public_keys
+        .iter()
+        .map(|key| FalconHostFunctions::hash_public_key_bytes(key))
+        .collect();
+    multisig_account(&members, threshold)
+}
+
+#[cfg(test)]
+mod multisig_tests {
+    use super::*;
+
+    #[test]
+    fn member_order_does_not_matter() {
+        let members = [[1u8; 32], [2u8; 32], [3u8; 32]];
+        let shuffled = [[3u8; 32], [1u8; 32], [2u8; 32]];
+        assert_eq!(multisig_account(&members, 2), multisig_account(&shuffled, 2));
+    }
+
+    #[test]
+    fn threshold_changes_account() {
+        let members = [[1u8; 32], [2u8; 32], [3u8; 32]];
+        assert_ne!(multisig_account(&members, 2), multisig_account(&members, 3));
+    }
+
+    #[test]
+    fn duplicates_are_ignored() {
+        let members = [[1u8; 32], [2u8; 32]];
+        let duplicated = [[1u8; 32], [2u8; 32], [1u8; 32]];
+        assert_eq!(multisig_account(&members, 2), multisig_account(&duplicated, 2));
+    }
+
+    #[test]
+    fn not_a_plain_falcon_account() {
+        let key = vec![7u8; 897];
+        let member = FalconHostFunctions::hash_public_key_bytes(&key);
+        assert_ne!(multisig_account_from_keys(&[key], 1), member);
+    }
+}
@@ -0,0 +1,118 @@
+use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use pqcrypto_falcon::falcon512::{PublicKey, SecretKey};
+#[cfg(feature = "std")]
+use pqcrypto_traits::sign::{DetachedSignature as _, PublicKey as _};
+use scale_info::TypeInfo;
+use sp_runtime::{ConsensusEngineId, DigestItem, RuntimeDebug};
+use sp_std::vec::Vec;
+
+use crate::FalconHostFunctions;
+
+// Engine id of the miner seal item in a header digest
+pub const MINER_SEAL_ENGINE_ID: ConsensusEngineId = *b"FALC";
+
+// Domain tag prepended to the block hash before the miner signs it
+pub const MINER_SEAL_CONTEXT: &[u8] = b"argonaut/miner-seal/v1";
+
+// Signature of the block hash by the Falcon key behind the block's coinbase. Without it anybody
+// relaying a block can claim to be the miner, with it the reward is bound to the key holder.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, TypeInfo, RuntimeDebug)]
+pub struct MinerSeal {
+    // falcon512 public key, its blake3 hash is the coinbase account
+    pub public_key: Vec<u8>,
+    // detached falcon512 signature over the seal payload
+    pub signature: Vec<u8>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum MinerSealError {
+    // the header carries no miner seal but the chain requires one
+    Missing,
+    // the seal digest item could not be decoded
+    Malformed,
+    // the seal's public key does not hash to the coinbase
+    CoinbaseMismatch,
+    // the signature does not verify against the block hash
+    BadSignature,
+}
+
+impl MinerSeal {
+    #[cfg(feature = "std")]
+    pub fn sign(block_hash: &[u8; 32], public_key: &PublicKey, secret_key: &SecretKey) -> Self {
+        let signature = FalconHostFunctions::detached_signature(&Self::payload(block_hash), secret_key);
+        MinerSeal {
+            public_key: public_key.as_bytes().to_vec(),
+            signature: signature.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn payload(block_hash: &[u8; 32]) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(MINER_SEAL_CONTEXT.len() + 32);
+        payload.extend_from_slice(MINER_SEAL_CONTEXT);
+        payload.extend_from_slice(block_hash);
+        payload
+    }
+
+    pub fn verify(&self, block_hash: &[u8; 32], coinbase: &[u8; 32]) -> Result<(), MinerSealError> {
+        if FalconHostFunctions::hash_public_key_bytes(&self.public_key) != *coinbase {
+            return Err(MinerSealError::CoinbaseMismatch);
+        }
+        if !FalconHostFunctions::verify_bytes(&self.signature, &Self::payload(block_hash), &self.public_key) {
+            return Err(MinerSealError::BadSignature);
+        }
+        Ok(())
+    }
+
+    pub fn to_digest_item(&self) -> DigestItem {
+        DigestItem::Seal(MINER_SEAL_ENGINE_ID, self.encode())
+    }
+
+    // Finds and decodes the miner seal among the digest items, `Ok(None)` if there is none
+    pub fn from_digest<'a>(items: impl IntoIterator<Item = &'a DigestItem>) -> Result<Option<Self>, MinerSealError> {
+        for item in items {
+            if let DigestItem::Seal(id, data) = item {
+                if *id == MINER_SEAL_ENGINE_ID {
+                    return MinerSeal::decode(&mut &data[..]).map(Some).map_err(|_| MinerSealError::Malformed);
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod seal_tests {
+    use super::*;
+
+    #[test]
+    fn sign_and_verify() {
+        let (pk, sk) = FalconHostFunctions::generate_keypair();
+        let coinbase = FalconHostFunctions::hash_public_key(&pk);
+        let hash = [7u8; 32];
+        let seal = MinerSeal::sign(&hash, &pk, &sk);
+        assert_eq!(seal.verify(&hash, &coinbase), Ok(()));
+        assert_eq!(seal.verify(&[8u8; 32], &coinbase), Err(MinerSealError::BadSignature));
+    }
+
+    #[test]
+    fn relayer_cannot_claim_coinbase() {
+        let (pk, sk) = FalconHostFunctions::generate_keypair();
+        let (relayer_pk, _) = FalconHostFunctions::generate_keypair();
+        let hash = [7u8; 32];
+        let seal = MinerSeal::sign(&hash, &pk, &sk);
+        let relayer = FalconHostFunctions::hash_public_key(&relayer_pk);
+        assert_eq!(seal.verify(&hash, &relayer), Err(MinerSealError::CoinbaseMismatch));
+    }
+
+    #[test]
+    fn digest_round_trip() {
+        let (pk, sk) = FalconHostFunctions::generate_keypair();
+        let seal = MinerSeal::sign(&[7u8; 32], &pk, &sk);
+        let items = vec![DigestItem::Other(vec![1, 2, 3]), seal.to_digest_item()];
+        assert_eq!(MinerSeal::from_digest(&items), Ok(Some(seal)));
+        assert_eq!(MinerSeal::from_digest(&items[..1]), Ok(None));
+        let broken = vec![DigestItem::Seal(MINER_SEAL_ENGINE_ID, vec![0xff])];
+        assert_eq!(MinerSeal::from_digest(&broken), Err(MinerSealError::Malformed));
+    }
+}
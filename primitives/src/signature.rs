@@ -0,0 +1,171 @@
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_core::{crypto::AccountId32, ecdsa, ed25519, sr25519};
+use sp_runtime::{
+    traits::{IdentifyAccount, Lazy, Verify},
+    MultiSignature, MultiSigner, RuntimeDebug,
+};
+
+use sp_std::vec::Vec;
+
+use crate::falcon::{falcon_account, FalconSignature};
+use crate::hybrid::{hybrid_account_from_bytes, HybridSignature};
+
+// The chain's transaction signature. It is `MultiSignature` with extra hybrid and Falcon variants,
+// the classical variants keep their indices so existing encodings stay valid.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, TypeInfo, RuntimeDebug)]
+pub enum Signature {
+    Ed25519(ed25519::Signature),
+    Sr25519(sr25519::Signature),
+    Ecdsa(ecdsa::Signature),
+    Hybrid(HybridSignature),
+    Falcon(FalconSignature),
+}
+
+// The signer matching `Signature`
+#[derive(Clone, PartialEq, Eq, Encode, Decode, TypeInfo, RuntimeDebug)]
+pub enum Signer {
+    Ed25519(ed25519::Public),
+    Sr25519(sr25519::Public),
+    Ecdsa(ecdsa::Public),
+    // classical public key bytes (tagged) and falcon512 public key bytes
+    Hybrid(Vec<u8>, Vec<u8>),
+    // falcon512 public key bytes
+    Falcon(Vec<u8>),
+}
+
+impl Verify for Signature {
+    type Signer = Signer;
+
+    fn verify<L: Lazy<[u8]>>(&self, mut msg: L, signer: &AccountId32) -> bool {
+        let classical = match self {
+            Signature::Ed25519(signature) => MultiSignature::Ed25519(signature.clone()),
+            Signature::Sr25519(signature) => MultiSignature::Sr25519(signature.clone()),
+            Signature::Ecdsa(signature) => MultiSignature::Ecdsa(signature.clone()),
+            Signature::Hybrid(signature) => return signature.verify(msg.get(), signer),
+            Signature::Falcon(signature) => return signature.verify(msg.get(), signer),
+        };
+        classical.verify(msg, signer)
+    }
+}
+
+impl IdentifyAccount for Signer {
+    type AccountId = AccountId32;
+
+    fn into_account(self) -> AccountId32 {
+        match self {
+            Signer::Ed25519(public) => MultiSigner::Ed25519(public).into_account(),
+            Signer::Sr25519(public) => MultiSigner::Sr25519(public).into_account(),
+            Signer::Ecdsa(public) => MultiSigner::Ecdsa(public).into_account(),
+            Signer::Hybrid(classical, falcon) => hybrid_account_from_bytes(&classical, &falcon),
+            Signer::Falcon(falcon) => falcon_account(&falcon),
+        }
+    }
+}
+
+impl From<ed25519::Signature> for Signature {
+    fn from(signature: ed25519::Signature) -> Self {
+        Signature::Ed25519(signature)
+    }
+}
+
+impl From<sr25519::Signature> for Signature {
+    fn from(signature: sr25519::Signature) -> Self {
+        Signature::Sr25519(signature)
+    }
+}
+
+impl From<ecdsa::Signature> for Signature {
+    fn from(signature: ecdsa::Signature) -> Self {
+        Signature::Ecdsa(signature)
+    }
+}
+
+impl From<HybridSignature> for Signature {
+    fn from(signature: HybridSignature) -> Self {
+        Signature::Hybrid(signature)
+    }
+}
+
+impl From<FalconSignature> for Signature {
+    fn from(signature: FalconSignature) -> Self {
+        Signature::Falcon(signature)
+    }
+}
+
+impl From<ed25519::Public> for Signer {
+    fn from(public: ed25519::Public) -> Self {
+        Signer::Ed25519(public)
+    }
+}
+
+impl From<sr25519::Public> for Signer {
+    fn from(public: sr25519::Public) -> Self {
+        Signer::Sr25519(public)
+    }
+}
+
+impl From<ecdsa::Public> for Signer {
+    fn from(public: ecdsa::Public) -> Self {
+        Signer::Ecdsa(public)
+    }
+}
+
+impl From<&HybridSignature> for Signer {
+    fn from(signature: &HybridSignature) -> Self {
+        Signer::Hybrid(signature.classical.public_bytes(), signature.falcon_public.clone())
+    }
+}
+
+impl From<&FalconSignature> for Signer {
+    fn from(signature: &FalconSignature) -> Self {
+        Signer::Falcon(signature.public.clone())
+    }
+}
+
+#[cfg(test)]
+mod signature_tests {
+    use super::*;
+    use crate::FalconHostFunctions;
+    use sp_core::Pair;
+
+    #[test]
+    fn classical_signatures_still_verify() {
+        let pair = sr25519::Pair::from_string("//Alice", None).unwrap();
+        let message = b"Hello, world!";
+        let signature = Signature::from(pair.sign(message));
+        let account = Signer::from(pair.public()).into_account();
+        assert!(signature.verify(&message[..], &account));
+    }
+
+    #[test]
+    fn hybrid_signer_matches_signature_account() {
+        let pair = sr25519::Pair::from_string("//Alice", None).unwrap();
+        let (pk, sk) = FalconHostFunctions::generate_keypair();
+        let message = b"Hello, world!";
+        let hybrid = HybridSignature::sign_sr25519(message, &pair, &pk, &sk);
+        let account = Signer::from(&hybrid).into_account();
+        assert_eq!(account, hybrid.account());
+        assert!(Signature::from(hybrid).verify(&message[..], &account));
+    }
+
+    #[test]
+    fn falcon_signer_matches_signature_account() {
+        let (pk, sk) = FalconHostFunctions::generate_keypair();
+        let message = b"Hello, world!";
+        let falcon = FalconSignature::sign(message, &pk, &sk);
+        let account = Signer::from(&falcon).into_account();
+        assert_eq!(account, AccountId32::from(FalconHostFunctions::hash_public_key(&pk)));
+        assert!(Signature::from(falcon.clone()).verify(&message[..], &account));
+        //a hybrid account is not the plain Falcon account of the same key
+        let pair = sr25519::Pair::from_string("//Alice", None).unwrap();
+        assert_ne!(HybridSignature::sign_sr25519(message, &pair, &pk, &sk).account(), account);
+    }
+
+    #[test]
+    fn classical_encoding_is_unchanged() {
+        let pair = sr25519::Pair::from_string("//Alice", None).unwrap();
+        let raw = pair.sign(b"Hello, world!");
+        assert_eq!(Signature::from(raw.clone()).encode(), MultiSignature::Sr25519(raw).encode());
+    }
+}
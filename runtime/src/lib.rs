@@ -16,7 +16,7 @@ use sp_runtime::{
 	create_runtime_str, generic, impl_opaque_keys,
 	traits::{AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor, Verify},
 	transaction_validity::{TransactionSource, TransactionValidity},
-	ApplyExtrinsicResult, MultiSignature,
+	ApplyExtrinsicResult,
 };
 use sp_std::prelude::*;
 #[cfg(feature = "std")]
@@ -47,8 +47,11 @@ pub use sp_runtime::{Perbill, Permill};
 /// An index to a block.
 pub type BlockNumber = u32;
 
-/// Alias to 512-bit hash when used in the context of a transaction signature on the chain.
-pub type Signature = MultiSignature;
+/// Transaction signature on the chain: the classical sr25519/ed25519/ecdsa signatures, the hybrid
+/// classical-plus-Falcon signature used while accounts move to post-quantum keys, and a plain
+/// Falcon signature for accounts that are controlled by a Falcon key alone. Falcon signatures are
+/// verified by the node through the `falcon_crypto` host function.
+pub type Signature = primitives::signature::Signature;
 
 /// Some way of identifying an account on the chain. We intentionally make it equivalent
 /// to the public key of our transaction signing scheme.
@@ -138,7 +141,7 @@ parameter_types! {
 	//Maximum Blocklength which is currently 5 mb
 	pub BlockLength: frame_system::limits::BlockLength = frame_system::limits::BlockLength
 		::max_with_normal_ratio(5 * 1024 * 1024, NORMAL_DISPATCH_RATIO);
-	pub const SS58Prefix: u8 = 42;
+	pub const SS58Prefix: u16 = primitives::DEFAULT_SS58_PREFIX;
 }
 
 // Configure FRAME pallets to include in runtime.
@@ -262,6 +265,24 @@ impl pallet_sudo::Config for Runtime {
 	type Call = Call;
 }
 
+impl pallet_falcon_migration::Config for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	/// Until there is on-chain governance the deadline is set through sudo.
+	type GovernanceOrigin = frame_system::EnsureRoot<AccountId>;
+}
+
+impl pallet_falcon_multisig::Config for Runtime {
+	type Event = Event;
+	type Call = Call;
+	type Currency = Balances;
+	type MaxMembers = ConstU32<32>;
+	type MaxCallSize = ConstU32<{ 16 * 1024 }>;
+	type MaxProposals = ConstU32<64>;
+	type ProposalDeposit = ConstU128<{ 1_000 * EXISTENTIAL_DEPOSIT }>;
+	type ProposalLifetime = ConstU32<{ 7 * DAYS }>;
+}
+
 
 /* 
  Replacing Runtime config with Argonaut Config a WIP
@@ -294,6 +315,8 @@ construct_runtime!(pub struct Runtime where
 		Balances: pallet_balances,
 		TransactionPayment: pallet_transaction_payment,
 		Sudo: pallet_sudo,
+		FalconMigration: pallet_falcon_migration,
+		FalconMultisig: pallet_falcon_multisig,
 	}
 );
 
@@ -313,11 +336,37 @@ pub type SignedExtra = (
 	frame_system::CheckNonce<Runtime>,
 	frame_system::CheckWeight<Runtime>,
 	pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	pallet_falcon_migration::CheckMigrated<Runtime>,
 );
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic = generic::UncheckedExtrinsic<Address, Call, Signature, SignedExtra>;
 /// The payload being signed in transactions.
 pub type SignedPayload = generic::SignedPayload<Call, SignedExtra>;
+
+/// The era of a transaction, taken from the `CheckEra` extension of its `SignedExtra`.
+pub fn extrinsic_era(extra: &SignedExtra) -> generic::Era {
+	let (_, _, _, _, check_era, ..) = extra;
+	let check_era: &frame_system::CheckEra<Runtime> = check_era;
+	codec::Decode::decode(&mut &codec::Encode::encode(check_era)[..])
+		.unwrap_or(generic::Era::Immortal)
+}
+
+/// The payload signed for `call` with `extra`, built outside of the runtime from the values
+/// `SignedExtra::additional_signed` reads from the chain state.
+pub fn signed_payload(
+	call: Call,
+	extra: SignedExtra,
+	spec_version: u32,
+	transaction_version: u32,
+	genesis_hash: Hash,
+	era_hash: Hash,
+) -> SignedPayload {
+	SignedPayload::from_raw(
+		call,
+		extra,
+		((), spec_version, transaction_version, genesis_hash, era_hash, (), (), (), ()),
+	)
+}
 /// Executive: handles dispatch to the various modules.
 pub type Executive = frame_executive::Executive<
 	Runtime,